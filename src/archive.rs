@@ -0,0 +1,124 @@
+//! Member listings of zip and tar archives, so they can be described by their contents.
+
+use crate::content::human_bytes;
+use flate2::read::GzDecoder;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use tracing::debug;
+
+/// Most members listed from one archive.
+pub(crate) const MAX_ARCHIVE_MEMBERS: usize = 50;
+
+/// The first members of an archive.
+#[derive(Debug, Clone)]
+pub(crate) struct ArchiveInfo {
+    /// Archive format, e.g. "zip" or "tar.gz".
+    pub format: &'static str,
+    /// `(path, uncompressed size)` of the first [`MAX_ARCHIVE_MEMBERS`] files, in archive order.
+    pub members: Vec<(String, u64)>,
+    /// Total number of files when the format records it (zip); `None` for tar streams.
+    pub total: Option<usize>,
+}
+
+impl ArchiveInfo {
+    /// One member per line, e.g. `assets/icon.png (12.0 KB)`, ending with a note when cut short.
+    pub(crate) fn listing(&self) -> String {
+        let mut out: Vec<String> = self
+            .members
+            .iter()
+            .map(|(path, size)| format!("{path} ({})", human_bytes(*size)))
+            .collect();
+        match self.total {
+            Some(total) if total > self.members.len() => {
+                out.push(format!("… and {} more", total - self.members.len()))
+            }
+            None if self.members.len() == MAX_ARCHIVE_MEMBERS => out.push("… and more".to_string()),
+            _ => {}
+        }
+        out.join("\n")
+    }
+}
+
+/// List the first members of a zip, tar or gzip-compressed tar archive.
+///
+/// Formats are recognized by their magic bytes, so `.jar`, `.whl` and other zip-based
+/// files are listed too. Directories are skipped.
+///
+/// Parameters:
+/// - `path`: The file to inspect.
+///
+/// Returns:
+/// - The listing, or `None` for other files and archives that can't be read.
+pub(crate) fn archive_info(path: &Path) -> Option<ArchiveInfo> {
+    let mut magic = [0u8; 262];
+    let n = fs::File::open(path)
+        .and_then(|mut f| f.read(&mut magic))
+        .ok()?;
+    let magic = &magic[..n];
+
+    let result = if magic.starts_with(b"PK\x03\x04") || magic.starts_with(b"PK\x05\x06") {
+        zip_members(path)
+    } else if magic.starts_with(&[0x1F, 0x8B]) {
+        let file = fs::File::open(path).ok()?;
+        // Only tarballs are listed; a plain .gz has nothing to list
+        tar_members(GzDecoder::new(io::BufReader::new(file)), "tar.gz")
+    } else if magic.get(257..262) == Some(&b"ustar"[..]) {
+        let file = fs::File::open(path).ok()?;
+        tar_members(io::BufReader::new(file), "tar")
+    } else {
+        return None;
+    };
+
+    match result {
+        Ok(info) if !info.members.is_empty() => Some(info),
+        Ok(_) => None,
+        Err(e) => {
+            debug!(path=%path.display(), %e, "Could not list archive members");
+            None
+        }
+    }
+}
+
+/// Read member names and sizes from a zip's central directory (nothing is decompressed).
+fn zip_members(path: &Path) -> anyhow::Result<ArchiveInfo> {
+    let mut zip = zip::ZipArchive::new(io::BufReader::new(fs::File::open(path)?))?;
+    let mut members = Vec::new();
+    let mut total = 0;
+    for i in 0..zip.len() {
+        let entry = zip.by_index_raw(i)?;
+        if entry.is_dir() {
+            continue;
+        }
+        total += 1;
+        if members.len() < MAX_ARCHIVE_MEMBERS {
+            members.push((entry.name().to_string(), entry.size()));
+        }
+    }
+    Ok(ArchiveInfo {
+        format: "zip",
+        members,
+        total: Some(total),
+    })
+}
+
+/// Read the first member headers of a tar stream.
+fn tar_members(reader: impl Read, format: &'static str) -> anyhow::Result<ArchiveInfo> {
+    let mut tar = tar::Archive::new(reader);
+    let mut members = Vec::new();
+    for entry in tar.entries()? {
+        let entry = entry?;
+        if entry.header().entry_type().is_dir() {
+            continue;
+        }
+        members.push((entry.path()?.display().to_string(), entry.size()));
+        if members.len() == MAX_ARCHIVE_MEMBERS {
+            break;
+        }
+    }
+    Ok(ArchiveInfo {
+        format,
+        members,
+        total: None,
+    })
+}
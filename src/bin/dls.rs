@@ -1,22 +1,41 @@
-use chrono::{DateTime, Utc};
-use chrono_humanize::{Accuracy, HumanTime, Tense};
-use clap::Parser;
+use anyhow::Context;
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser, ValueEnum, ValueHint};
+use dirdocs::completions::{self, Shell};
+use dirdocs::view::{
+    DirdocsRoot, FileDocInfo, Node, SUPPORTED_SCHEMA_VERSION, Theme, as_emoji_presentation,
+    file_hash, find_project_root, humanize, hyperlink, load_descriptions, load_descriptions_db,
+    nuon_string, parse_descriptions, prefer_db, read_bundle, read_cache, read_cache_at,
+    read_config, rel_str, tree_from_paths, try_load_nu_theme, xattr_info,
+};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use humansize::{DECIMAL, format_size};
+use ignore::WalkBuilder;
 use lscolors::LsColors;
 use nu_ansi_term::{Color, Style};
 use nu_table::{NuTable, TableTheme, TextStyle};
-use serde::Deserialize;
+use regex::{Regex, RegexBuilder};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Serialize, Serializer};
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::fmt::Write as _;
 use std::fs;
-use std::io::IsTerminal;
+use std::io::{self, IsTerminal, Write};
+use std::iter::Peekable;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
+use std::str::Chars;
+use std::time::{Duration, Instant};
 use tabled::grid::records::vec_records::Text;
-use terminal_size::{Width as TermWidth, terminal_size};
-use walkdir::WalkDir;
+use terminal_size::{Height as TermHeight, Width as TermWidth, terminal_size};
 
 #[derive(Parser, Debug)]
 #[clap(
+    name = "dls",
     author,
     version,
     about = "dls — Nushell-style `ls` + description from .dirdocs.nuon"
@@ -24,75 +43,782 @@ use walkdir::WalkDir;
 
 /// Command-line arguments for the dls tool. Contains options to specify a directory, recurse into subdirectories, and show additional file information.
 struct Args {
-    /// Directory to search (default is current directory).
-    #[clap(default_value = ".")]
-    directory: String,
+    /// Directories to list (default is current directory), or glob patterns such as
+    /// `'src/**/*.rs'` to list only the matching paths below the current directory.
+    #[clap(default_value = ".", value_hint = ValueHint::AnyPath)]
+    paths: Vec<String>,
     /// If set, show all files (not just regular ones).
     #[clap(long, short = 'a')]
     all: bool,
     /// If set, include subdirectories and contents of directories.
     #[clap(long, short = 'R')]
     recursive: bool,
+    /// With `-R`, list at most this many levels of directories (1 is just the listed one).
+    #[clap(
+        long,
+        short = 'L',
+        value_name = "N",
+        requires = "recursive",
+        value_parser = clap::value_parser!(u16).range(1..)
+    )]
+    depth: Option<u16>,
+    /// Show the total size of everything below each directory instead of 0 B.
+    #[clap(long)]
+    du: bool,
+    /// Also list files and directories excluded by `.gitignore`, `.ignore` and git's
+    /// exclude files (hidden ones still need `-a`).
+    #[clap(long)]
+    no_ignore: bool,
     /// Show additional information about the files (personality and joy rating).
     #[clap(long)]
     fun: bool,
+    /// Columns to show, in order, e.g. `name,size,description` or `name,hash,updated-at`
+    /// (default: name, type, size, modified and description, plus personality and joy
+    /// with `--fun`).
+    #[clap(long, value_enum, value_delimiter = ',', value_name = "COLUMNS")]
+    columns: Vec<Column>,
+    /// Prefix names with Nerd Font icons by file type (needs a Nerd Font in the terminal).
+    #[clap(long)]
+    icons: bool,
+    /// Render tables this many characters wide instead of the terminal's width (which is
+    /// unknown, and taken as 80, when output is redirected).
+    #[clap(
+        long,
+        short = 'w',
+        value_name = "COLS",
+        value_parser = clap::value_parser!(u16).range(4..)
+    )]
+    width: Option<u16>,
+
+    /// Show descriptions as they were committed at this git ref (e.g. `HEAD~10`, `v0.2.0`).
+    #[clap(long, value_name = "GIT_REF")]
+    at: Option<String>,
+
+    /// List a file written by `dirdocs bundle` instead of the filesystem; the directory
+    /// is then a path inside the bundle.
+    #[clap(long, value_name = "FILE", conflicts_with = "at")]
+    bundle: Option<PathBuf>,
+    /// List the project's cache tree (`.dirdocs.nuon`) instead of the filesystem, so the
+    /// listing (and `-R` recursion) matches exactly what was documented.
+    #[clap(long, conflicts_with = "bundle")]
+    cached: bool,
+
+    /// Keep only rows whose name or description matches this regex, e.g.
+    /// `dls -R --where 'retr(y|ies)'` to find the file that handles retries.
+    #[clap(long = "where", visible_alias = "grep", value_name = "REGEX")]
+    where_: Option<String>,
+    /// Match `--where` case-insensitively.
+    #[clap(long, short = 'i', requires = "where_")]
+    ignore_case: bool,
+
+    /// Show only directories.
+    #[clap(long, conflicts_with = "files_only")]
+    dirs_only: bool,
+    /// Show only files.
+    #[clap(long)]
+    files_only: bool,
+    /// Show only files of these types: extensions (`toml`, `.yaml`) or MIME types
+    /// (`application/json`, `text/*`), comma-separated or repeated.
+    #[clap(
+        long,
+        value_delimiter = ',',
+        value_name = "TYPES",
+        conflicts_with = "dirs_only"
+    )]
+    mime: Vec<String>,
+
+    /// Show only files without a description in the cache.
+    #[clap(long)]
+    undocumented: bool,
+    /// Show only files whose content changed since they were described (re-hashes each file).
+    #[clap(long, conflicts_with = "bundle")]
+    stale: bool,
+    /// Re-hash documented files and mark the ones changed since they were described
+    /// (adds the `stale` column).
+    #[clap(long, conflicts_with = "bundle")]
+    verify: bool,
+    /// Show only files whose joy rating (1 to 10) is at least this.
+    #[clap(long, value_name = "N", value_parser = clap::value_parser!(u8).range(1..=10))]
+    min_joy: Option<u8>,
+
+    /// When to show the output in a pager (`$PAGER`, or `less`): `auto` pages output
+    /// taller than the terminal.
+    #[clap(long, value_enum, default_value_t = Paging::Auto, value_name = "WHEN")]
+    paging: Paging,
+    /// When to make names clickable `file://` links (OSC 8, like `ls --hyperlink`); a bare
+    /// `--hyperlink` means `always`.
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = Hyperlink::Auto,
+        default_missing_value = "always",
+        num_args = 0..=1,
+        require_equals = true,
+        value_name = "WHEN"
+    )]
+    hyperlink: Hyperlink,
+
+    /// End each listing with a line of totals: files, directories, combined size and how
+    /// many files are documented. Not available with JSON or NUON output.
+    #[clap(long, conflicts_with_all = ["json", "nuon"])]
+    summary: bool,
+
+    /// Print the rows as a JSON array instead of a table (for `jq` and other tools).
+    #[clap(long, conflicts_with = "nuon")]
+    json: bool,
+    /// Print the rows as a NUON table instead of a rendered one (for `open`/`from nuon` in Nushell).
+    #[clap(long)]
+    nuon: bool,
+
+    /// Output format; `markdown` prints a GitHub-flavored table (name, size and
+    /// description unless `--columns` is given) for pasting into PRs and wikis.
+    #[clap(long, value_enum, value_name = "FORMAT", conflicts_with_all = ["json", "nuon"])]
+    format: Option<Format>,
+
+    /// Ignore the defaults in `dls.toml`.
+    #[clap(long)]
+    no_config: bool,
+
+    /// Print a shell completion script for `dls` and exit, e.g. `dls --completions fish`.
+    #[clap(long, value_enum, value_name = "SHELL", exclusive = true)]
+    completions: Option<Shell>,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(tag = "kind", rename_all = "lowercase")]
-enum Node {
-    Dir(DirEntry),
-    File(FileEntry),
-}
-
-/// A directory entry containing a list of nodes. Used to represent files and subdirectories in the file system.
-#[derive(Debug, Deserialize)]
-struct DirEntry {
-    /// A vector of `Node` instances that contain the actual content.
-    entries: Vec<Node>,
-}
-
-/// A data structure representing a file entry with its path and documentation.
-#[derive(Debug, Deserialize)]
-struct FileEntry {
-    /// The path of the file entry, uniquely identified in its tree.
-    path: String,
-    /// The documentation associated with this file entry, initialized to an empty doc.
-    #[serde(default)]
-    doc: Doc,
-}
-
-/// A struct representing a document's metadata, including description.
-#[allow(non_snake_case)]
-#[derive(Debug, Deserialize, Default)]
-struct Doc {
-    /// The file's description.",
-    #[serde(default)]
-    fileDescription: String,
-    /// Represents how much joy the file brings, with a default value. This field can be aliased as `howMuchJoyDoesThisFileBringYou`.
-    #[serde(default, alias = "howMuchJoyDoesThisFileBringYou")]
-    joyThisFileBrings: serde_json::Value,
-    /// The personality emoji of the file, with a default value. This field can be aliased as `emojiThatExpressesThisFilesPersonality`.
-    #[serde(default, alias = "emojiThatExpressesThisFilesPersonality")]
-    personalityEmoji: String,
-}
-
-/// DirdocsRoot holds all the description docs in a directory.
-#[derive(Debug, Deserialize)]
-struct DirdocsRoot {
-    /// Vec of child docs (each is a Node).
-    entries: Vec<Node>,
-}
-
-/// Represents metadata about a file for documentation purposes.
-#[derive(Debug, Default, Clone)]
-struct FileDocInfo {
-    /// The brief description of the file, typically from its README.
-    description: String,
-    /// The personality of the file, such as "Acutely Perceptive".
-    personality: String,
-    /// The joy associated with the file, such as "Enthusiastically".
-    joy: String,
+/// File name of the `dls` defaults inside the dirdocs config directory.
+const DEFAULTS_FILE: &str = "dls.toml";
+
+/// Defaults for `dls` flags, read from [`DEFAULTS_FILE`]; flags given on the command
+/// line win. Keys are the long flag names, e.g. `icons = true` or
+/// `columns = ["name", "size", "description"]`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct Defaults {
+    all: Option<bool>,
+    no_ignore: Option<bool>,
+    fun: Option<bool>,
+    columns: Option<Vec<Column>>,
+    icons: Option<bool>,
+    width: Option<u16>,
+    du: Option<bool>,
+    summary: Option<bool>,
+    paging: Option<Paging>,
+    hyperlink: Option<Hyperlink>,
+    format: Option<Format>,
+}
+
+/// How the listing is printed, selected with `--format`.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum Format {
+    /// Rendered tables, one per directory.
+    Table,
+    /// A JSON array of rows (same as `--json`).
+    Json,
+    /// A NUON table of rows (same as `--nuon`).
+    Nuon,
+    /// A GitHub-flavored markdown table.
+    Markdown,
+}
+
+/// When output goes through a pager, selected with `--paging`.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum Paging {
+    /// When stdout is a terminal and the output is taller than it.
+    Auto,
+    /// Always, even for short output.
+    Always,
+    /// Never.
+    Never,
+}
+
+/// When names are wrapped in terminal hyperlinks, selected with `--hyperlink`.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum Hyperlink {
+    /// When stdout is a terminal (other than `TERM=dumb`).
+    Auto,
+    /// Always, even when piped.
+    Always,
+    /// Never.
+    Never,
+}
+
+/// A listing, built up before it's printed or paged.
+#[derive(Default)]
+struct Output {
+    /// Rendered tables and directory headings.
+    text: String,
+    /// Rows collected for one combined table (glob patterns) or `--json`/`--nuon`.
+    rows: Vec<RowRaw>,
+    /// What the tables rendered since the last `--summary` footer contain.
+    totals: Totals,
+    /// Directory sizes computed for `--du`.
+    du: DirSizes,
+}
+
+impl Output {
+    /// Render `rows` as a table, counting them towards the `--summary` footer.
+    fn table(&mut self, rows: &[RowRaw], args: &Args) -> String {
+        self.totals.add(rows);
+        let hyperlinks = match args.hyperlink {
+            Hyperlink::Always => true,
+            Hyperlink::Never => false,
+            Hyperlink::Auto => {
+                io::stdout().is_terminal() && std::env::var("TERM").map_or(true, |t| t != "dumb")
+            }
+        };
+        render_nu_table(
+            rows,
+            &args.columns(),
+            args.icons,
+            hyperlinks,
+            args.width.map(usize::from),
+        )
+    }
+}
+
+/// Recursive directory sizes for `--du`, memoized so a `-R` listing walks each
+/// directory once however many tables it appears in.
+#[derive(Default)]
+struct DirSizes {
+    /// Total size of every directory walked so far.
+    sizes: HashMap<PathBuf, u64>,
+    /// When the current `fill` started; the spinner only shows for slow ones.
+    started: Option<Instant>,
+    /// When the progress spinner was last drawn, if it has been.
+    drawn: Option<Instant>,
+    /// Spinner frame to draw next.
+    frame: usize,
+}
+
+impl DirSizes {
+    /// Replace the size of each directory row with the size of everything below it.
+    ///
+    /// Rows that aren't on disk (bundles) keep their placeholder. While walking, a
+    /// spinner is shown on stderr when it's a terminal.
+    fn fill(&mut self, rows: &mut [RowRaw]) {
+        self.started = Some(Instant::now());
+        for r in rows
+            .iter_mut()
+            .filter(|r| r.ty == "dir" && r.size.is_some())
+        {
+            let size = self.size_of(&r.path);
+            r.size = Some(size);
+            r.size_h = format_size(size, DECIMAL);
+        }
+        if self.drawn.take().is_some() {
+            eprint!("\r\x1b[2K");
+        }
+    }
+
+    /// Total size of the files below `dir`; symlinks aren't followed.
+    fn size_of(&mut self, dir: &Path) -> u64 {
+        if let Some(size) = self.sizes.get(dir) {
+            return *size;
+        }
+        self.tick(dir);
+        let mut total = 0;
+        for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                total += self.size_of(&entry.path());
+            } else if meta.is_file() {
+                total += meta.len();
+            }
+        }
+        self.sizes.insert(dir.to_path_buf(), total);
+        total
+    }
+
+    /// Redraw the spinner every 100 ms once a walk has taken longer than 300 ms.
+    fn tick(&mut self, dir: &Path) {
+        const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+        let since = |t: Option<Instant>, ms: u64| {
+            t.is_some_and(|t| t.elapsed() < Duration::from_millis(ms))
+        };
+        if !io::stderr().is_terminal() || since(self.started, 300) || since(self.drawn, 100) {
+            return;
+        }
+        let name: String = dir.to_string_lossy().chars().take(60).collect();
+        eprint!(
+            "\r\x1b[2K{} sizing {name}",
+            FRAMES[self.frame % FRAMES.len()]
+        );
+        self.frame += 1;
+        self.drawn = Some(Instant::now());
+    }
+}
+
+/// Counts for the `--summary` footer of a listing.
+#[derive(Default)]
+struct Totals {
+    /// Files listed.
+    files: usize,
+    /// Directories listed.
+    dirs: usize,
+    /// Combined size of the files, in bytes (unknown sizes count as 0).
+    bytes: u64,
+    /// Files with a description.
+    documented: usize,
+}
+
+impl Totals {
+    /// Count the rows of one table.
+    fn add(&mut self, rows: &[RowRaw]) {
+        for r in rows {
+            if r.ty == "dir" {
+                self.dirs += 1;
+                continue;
+            }
+            self.files += 1;
+            self.bytes += r.size.unwrap_or(0);
+            if !r.description.is_empty() {
+                self.documented += 1;
+            }
+        }
+    }
+
+    /// The footer line, e.g. `12 files, 3 dirs, 48.2 kB, 9 of 12 files documented (75%)`.
+    fn footer(&self) -> String {
+        let plural = |n: usize, one: &str| {
+            if n == 1 {
+                format!("1 {one}")
+            } else {
+                format!("{n} {one}s")
+            }
+        };
+        let coverage = if self.files == 0 {
+            100
+        } else {
+            self.documented * 100 / self.files
+        };
+        format!(
+            "{}, {}, {}, {} of {} documented ({coverage}%)",
+            plural(self.files, "file"),
+            plural(self.dirs, "dir"),
+            format_size(self.bytes, DECIMAL),
+            self.documented,
+            plural(self.files, "file"),
+        )
+    }
+}
+
+/// A column of the listing, selected with `--columns`.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum Column {
+    /// File or directory name (the path with `-R --json` or glob patterns).
+    Name,
+    /// `file` or `dir`.
+    Type,
+    /// Size on disk.
+    Size,
+    /// Last modification time.
+    Modified,
+    /// The cached description.
+    Description,
+    /// Personality emoji from the cache.
+    Personality,
+    /// Joy rating from the cache.
+    Joy,
+    /// Content hash recorded when the file was described.
+    Hash,
+    /// When the cache entry was written.
+    UpdatedAt,
+    /// Whether the file changed since it was described (re-hashes it; see `--verify`).
+    Stale,
+}
+
+impl Column {
+    /// Header in the table, and key in `--json`/`--nuon` output.
+    fn header(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Type => "type",
+            Self::Size => "size",
+            Self::Modified => "modified",
+            Self::Description => "description",
+            Self::Personality => "personality",
+            Self::Joy => "joy",
+            Self::Hash => "hash",
+            Self::UpdatedAt => "updated_at",
+            Self::Stale => "stale",
+        }
+    }
+
+    /// Raw value for `--json` (bytes, RFC 3339 times, `null` when unknown).
+    fn value(self, r: &RowRaw) -> serde_json::Value {
+        use serde_json::Value;
+        let time = |t: Option<DateTime<Utc>>| {
+            t.map_or(Value::Null, |dt| {
+                Value::String(dt.to_rfc3339_opts(SecondsFormat::Secs, true))
+            })
+        };
+        match self {
+            Self::Name => r.name.clone().into(),
+            Self::Type => r.ty.clone().into(),
+            Self::Size => r.size.map_or(Value::Null, Value::from),
+            Self::Modified => time(r.modified),
+            Self::Description => r.description.clone().into(),
+            Self::Personality => r.personality.clone().into(),
+            Self::Joy => r.joy.clone().into(),
+            Self::Hash => r.hash.clone().into(),
+            Self::UpdatedAt => time(r.updated_at),
+            Self::Stale => is_stale(r).map_or(Value::Null, Value::from),
+        }
+    }
+}
+
+impl Args {
+    /// Parse the command line, taking flags it doesn't give from [`DEFAULTS_FILE`].
+    ///
+    /// Errors:
+    /// - A defaults file that can't be read, isn't valid TOML or has unknown keys.
+    fn load() -> anyhow::Result<Self> {
+        let matches = Self::command().get_matches();
+        let mut args = Self::from_arg_matches(&matches)?;
+        // `conflicts_with_all` only covers the `--json`/`--nuon` shorthands
+        if args.summary && matches!(args.format, Some(Format::Json | Format::Nuon)) {
+            Self::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "the argument '--summary' cannot be used with '--format json' or '--format nuon'",
+                )
+                .exit();
+        }
+        if args.no_config || args.completions.is_some() {
+            return Ok(args);
+        }
+        let Some(defaults) = read_config::<Defaults>(DEFAULTS_FILE)? else {
+            return Ok(args);
+        };
+
+        let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+        macro_rules! default {
+            ($field:ident) => {
+                if let Some(value) = defaults.$field {
+                    if unset(stringify!($field)) {
+                        args.$field = value;
+                    }
+                }
+            };
+            ($field:ident, Some) => {
+                if let Some(value) = defaults.$field {
+                    if unset(stringify!($field)) {
+                        args.$field = Some(value);
+                    }
+                }
+            };
+        }
+        default!(all);
+        default!(no_ignore);
+        default!(fun);
+        default!(columns);
+        default!(icons);
+        default!(width, Some);
+        default!(du);
+        default!(summary);
+        default!(paging);
+        default!(hyperlink);
+        // `--json`/`--nuon` pick the format too
+        if !args.json && !args.nuon {
+            default!(format, Some);
+        }
+        Ok(args)
+    }
+
+    /// The columns to show: `--columns`, or the default set.
+    fn columns(&self) -> Vec<Column> {
+        if !self.columns.is_empty() {
+            let mut columns = self.columns.clone();
+            if self.verify && !columns.contains(&Column::Stale) {
+                columns.push(Column::Stale);
+            }
+            return columns;
+        }
+        let mut columns = vec![
+            Column::Name,
+            Column::Type,
+            Column::Size,
+            Column::Modified,
+            Column::Description,
+        ];
+        if self.fun {
+            columns.extend([Column::Personality, Column::Joy]);
+        }
+        if self.verify {
+            columns.push(Column::Stale);
+        }
+        columns
+    }
+
+    /// The output format: `--format`, or its `--json`/`--nuon` shorthands.
+    fn format(&self) -> Format {
+        if self.json {
+            Format::Json
+        } else if self.nuon {
+            Format::Nuon
+        } else {
+            self.format.unwrap_or(Format::Table)
+        }
+    }
+
+    /// Whether rows are collected into one list (`--json`, `--nuon`, `--format markdown`)
+    /// rather than rendered as tables.
+    fn structured(&self) -> bool {
+        self.format() != Format::Table
+    }
+
+    /// What the positional arguments ask to list.
+    ///
+    /// Arguments without glob characters that are all directories (or a single argument,
+    /// or any with `--bundle`) are listed one after the other. Anything else is a set of
+    /// patterns matched against paths relative to the current directory; plain paths (as
+    /// a shell expands an unquoted glob into) match just themselves.
+    ///
+    /// Errors:
+    /// - An invalid glob pattern or `--where` regex.
+    fn target(&self) -> anyhow::Result<Target> {
+        let matcher = self
+            .where_
+            .as_deref()
+            .map(|re| {
+                RegexBuilder::new(re)
+                    .case_insensitive(self.ignore_case)
+                    .build()
+                    .map_err(|e| anyhow::anyhow!("invalid --where pattern {re:?}: {e}"))
+            })
+            .transpose()?;
+
+        let plain = self.paths.iter().all(|p| !is_glob(p));
+        let target = match self.paths.as_slice() {
+            [] => Target::directories(vec![".".to_string()]),
+            [dir] if plain => Target::directories(vec![dir.clone()]),
+            dirs if plain
+                && (self.bundle.is_some() || dirs.iter().all(|d| Path::new(d).is_dir())) =>
+            {
+                Target::directories(dirs.to_vec())
+            }
+            patterns => {
+                let mut builder = GlobSetBuilder::new();
+                for p in patterns {
+                    let p = p.trim_start_matches("./");
+                    // `*` stays within one directory, like in a shell
+                    let glob = GlobBuilder::new(p)
+                        .literal_separator(true)
+                        .build()
+                        .with_context(|| format!("invalid pattern {p:?}"))?;
+                    builder.add(glob);
+                }
+                Target {
+                    directories: vec![".".to_string()],
+                    walk_root: common_literal_prefix(patterns),
+                    patterns: Some(builder.build()?),
+                    matcher: None,
+                    kind: None,
+                    mimes: Vec::new(),
+                    undocumented: false,
+                    stale: false,
+                    min_joy: None,
+                }
+            }
+        };
+        let kind = match (self.dirs_only, self.files_only || !self.mime.is_empty()) {
+            (true, _) => Some("dir"),
+            (_, true) => Some("file"),
+            _ => None,
+        };
+        Ok(Target {
+            matcher,
+            kind,
+            mimes: self
+                .mime
+                .iter()
+                .map(|m| m.trim().to_ascii_lowercase())
+                .collect(),
+            undocumented: self.undocumented,
+            stale: self.stale,
+            min_joy: self.min_joy,
+            ..target
+        })
+    }
+}
+
+/// The directories to list and, when glob patterns were given, the paths to keep.
+struct Target {
+    /// Directories listed, in order; patterns are relative to the (only) one.
+    directories: Vec<String>,
+    /// Directory below the listed one that contains every possible match, so only it is walked.
+    walk_root: PathBuf,
+    /// Glob patterns; matching rows from all directories below `walk_root` form one table.
+    patterns: Option<GlobSet>,
+    /// The `--where` regex; only rows whose name or description matches are kept.
+    matcher: Option<Regex>,
+    /// `--dirs-only` (`dir`) or `--files-only` (`file`, also implied by `--mime`).
+    kind: Option<&'static str>,
+    /// `--mime`: lowercased extensions and MIME types, of which a file must match one.
+    mimes: Vec<String>,
+    /// `--undocumented`: keep files without a description.
+    undocumented: bool,
+    /// `--stale`: keep files whose hash no longer matches their cache entry.
+    stale: bool,
+    /// `--min-joy`: keep only files rated at least this joyful.
+    min_joy: Option<u8>,
+}
+
+impl Target {
+    /// List directories (or, with `-R`, everything below them).
+    fn directories(dirs: Vec<String>) -> Self {
+        Self {
+            directories: dirs,
+            walk_root: PathBuf::new(),
+            patterns: None,
+            matcher: None,
+            kind: None,
+            mimes: Vec::new(),
+            undocumented: false,
+            stale: false,
+            min_joy: None,
+        }
+    }
+
+    /// Whether rows are filtered by `--where`, type, `--min-joy`, `--undocumented` or `--stale`.
+    fn filtering(&self) -> bool {
+        self.matcher.is_some()
+            || self.kind.is_some()
+            || self.min_joy.is_some()
+            || self.undocumented
+            || self.stale
+    }
+
+    /// Whether a row passes the `--where`, type, `--min-joy`, `--undocumented` and `--stale`
+    /// filters.
+    ///
+    /// With `--undocumented` and `--stale` together, a file showing up in either is kept,
+    /// so the listing is everything the next `dirdocs run` would describe.
+    fn keeps(&self, row: &RowRaw) -> bool {
+        if self.kind.is_some_and(|kind| row.ty != kind) {
+            return false;
+        }
+        if !self.mimes.is_empty() && !self.mimes.iter().any(|m| mime_matches(m, &row.name)) {
+            return false;
+        }
+        if let Some(re) = &self.matcher {
+            if !re.is_match(&row.name) && !re.is_match(&row.description) {
+                return false;
+            }
+        }
+        if let Some(min) = self.min_joy {
+            let joyful = row.ty == "file" && joy_score(&row.joy).is_some_and(|j| j >= min as f64);
+            if !joyful {
+                return false;
+            }
+        }
+        if !self.undocumented && !self.stale {
+            return true;
+        }
+        if row.ty != "file" {
+            return false;
+        }
+        (self.undocumented && row.description.is_empty())
+            || (self.stale && is_stale(row) == Some(true))
+    }
+}
+
+/// The numeric joy rating in a cache entry (`7`, `"7"`, `"7/10"`), if it has one.
+fn joy_score(joy: &str) -> Option<f64> {
+    let joy = joy.trim().trim_matches('"');
+    let end = joy
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(joy.len());
+    joy[..end].parse().ok()
+}
+
+/// Red-to-green (through yellow) color for a joy rating from 1 to 10.
+fn joy_color(score: f64) -> Color {
+    let t = ((score - 1.0) / 9.0).clamp(0.0, 1.0);
+    let red = (2.0 - 2.0 * t).min(1.0) * 230.0;
+    let green = (2.0 * t).min(1.0) * 200.0;
+    Color::Rgb(red as u8, green as u8, 60)
+}
+
+/// `⚠` for a file changed since it was described, `✓` for an unchanged one, else nothing.
+fn stale_mark(row: &RowRaw) -> &'static str {
+    match is_stale(row) {
+        Some(true) => "⚠",
+        Some(false) => "✓",
+        None => "",
+    }
+}
+
+/// Whether a file's content changed since its cache entry was written.
+///
+/// Returns:
+/// - `None` for directories, files without a recorded hash, and rows that aren't on
+///   disk (bundles carry no sizes) or can't be read.
+fn is_stale(row: &RowRaw) -> Option<bool> {
+    if row.ty != "file" || row.hash.is_empty() || row.size.is_none() {
+        return None;
+    }
+    file_hash(&row.path).ok().map(|h| h != row.hash)
+}
+
+/// Whether a file name matches a `--mime` filter.
+///
+/// Parameters:
+/// - `filter`: A lowercased extension (`toml`, `.yaml`) or MIME type, where `text/*`
+///   matches every subtype.
+/// - `name`: The file's name or relative path; its MIME type is guessed from the extension.
+fn mime_matches(filter: &str, name: &str) -> bool {
+    if let Some((ty, sub)) = filter.split_once('/') {
+        return mime_guess::from_path(name)
+            .iter()
+            .any(|m| m.type_().as_str() == ty && (sub == "*" || m.subtype().as_str() == sub));
+    }
+    Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case(filter.trim_start_matches('.')))
+}
+
+/// Whether a path argument contains glob syntax.
+fn is_glob(s: &str) -> bool {
+    s.contains(['*', '?', '[', '{'])
+}
+
+/// The deepest directory shared by the literal (glob-free) leading components of `patterns`,
+/// e.g. `src` for `src/**/*.rs` and `src/bin/*.rs`.
+fn common_literal_prefix(patterns: &[String]) -> PathBuf {
+    let literal = |p: &str| -> Vec<String> {
+        let mut parts: Vec<String> = p
+            .trim_start_matches("./")
+            .split('/')
+            .take_while(|c| !is_glob(c))
+            .map(str::to_string)
+            .collect();
+        // A fully literal pattern names an entry listed by its parent directory
+        if !is_glob(p) {
+            parts.pop();
+        }
+        parts
+    };
+    let mut common = literal(&patterns[0]);
+    for p in &patterns[1..] {
+        let other = literal(p);
+        let shared = common
+            .iter()
+            .zip(&other)
+            .take_while(|(a, b)| a == b)
+            .count();
+        common.truncate(shared);
+    }
+    common
+        .iter()
+        .filter(|c| !c.is_empty() && *c != ".")
+        .collect()
 }
 
 /// Represents raw data for a file or directory entry.
@@ -106,314 +832,262 @@ struct RowRaw {
     ty: String,
     /// Size in bytes, as a string.
     size_h: String,
+    /// Size in bytes, when known (not for bundles).
+    size: Option<u64>,
     /// Last modified time in human-readable format.
     modified_h: String,
+    /// Last modified time, when known (not for bundles).
+    modified: Option<DateTime<Utc>>,
     /// Detailed description of the item.
     description: String,
     /// Personality trait assigned to this item;
     personality: String,
     /// A measure of joy associated with this item;
     joy: String,
+    /// Content hash recorded in the cache (empty without an entry).
+    hash: String,
+    /// When the cache entry was written (`None` without an entry).
+    updated_at: Option<DateTime<Utc>>,
 }
 
-/// A theme for the "tree" view. This data structure encapsulates all styles and configuration options required to render a tree in the terminal.
-#[derive(Clone)]
-struct Theme {
-    /// Content of the file or directory name, with a "/" prefix added.
-    header: Style,
-    /// Style for the directory (used as a default if no matching file found).
-    dir: Style,
-    /// Style for the file name.
-    filesize: Style,
-    /// Style for the date of the file or directory.
-    date: Style,
-    /// Style for the index of file or directory.
-    index: Style,
-    /// Whether to enable the theme; disabled by default.
-    enabled: bool,
-}
-
-impl Theme {
-    /// Handles enabling or disabling the default theme in a directory listing system.
-    ///
-    /// This function constructs an instance of `Theme` with styling applied to various
-    /// elements, and sets the `enabled` flag based on input.
-    ///
-    /// Parameters:
-    /// - `enabled`: A boolean indicating whether to enable the default theme.
-    ///
-    /// Returns:
-    /// - `Self`: An instance of `Theme` with styling and state configured.
-    ///
-    /// Notes:
-    /// - The styles for headers, directories, file sizes, dates, and indexes are
-    ///   predefined using color codes from the `Color::` enum.
-    /// - This function is intended to be used internally by other parts of the system
-    ///   and should not be called directly.
-    fn default_enabled(enabled: bool) -> Self {
-        Self {
-            header: Style::new().fg(Color::Green).bold(),
-            dir: Style::new().fg(Color::Cyan),
-            filesize: Style::new().fg(Color::Cyan),
-            date: Style::new().fg(Color::Purple),
-            index: Style::new(),
-            enabled,
-        }
-    }
-}
-/// Load and parse a `nu` theme configuration file.
-///
-/// This function executes a shell command to get color settings from an environment variable,
-/// parses the resulting JSON, and constructs a `Theme` struct based on resolved values.
-///
-/// Parameters:
-/// - None
-///
-/// Returns:
-/// - `Some(Theme)` if the theme is successfully loaded and parsed.
-/// - `None` on failure (e.g., if command fails or JSON parse error).
-///
-/// Errors:
-/// - I/O errors from executing the `nu` command.
-/// - JSON parsing errors.
-///
-/// Notes:
-/// - Uses `serde_json` to parse the output of a shell command.
-/// - Defaults to using environment variables and fallback strategies for missing keys.
-///
-/// References:
-/// - `Command::new`
-/// - `serde_json::from_slice
-fn try_load_nu_theme() -> Option<Theme> {
-    let out = Command::new("nu")
-        .args(["-c", "$env.config.color_config | to json"])
-        .output()
-        .ok()?;
-
-    if !out.status.success() {
-        return None;
+/// Handle the main entry point for the `dls` command-line tool.
+/// This function parses command-line arguments, locates a project root directory, and collects descriptions
+/// from files in that directory. It supports recursive traversal of directories and prints a formatted table
+/// of found descriptions based on the provided arguments. On success, it returns `Ok(())`.
+fn main() -> anyhow::Result<()> {
+    let args = Args::load()?;
+    if let Some(shell) = args.completions {
+        completions::print(shell, &mut Args::command());
+        return Ok(());
     }
+    let target = args.target()?;
 
-    let json = serde_json::from_slice::<serde_json::Value>(&out.stdout).ok()?;
-
-    let resolve = |key: &str| -> Option<Style> { value_to_style(json.get(key)?) };
-
-    let header = resolve("header").or_else(|| resolve("shape_table"));
-    let dir = resolve("shape_directory")
-        .or_else(|| resolve("shape_filepath"))
-        .or_else(|| resolve("string"));
-    let filesize = resolve("filesize").or_else(|| resolve("int"));
-    let date = resolve("date").or_else(|| resolve("shape_datetime"));
-    let index = resolve("row_index").or_else(|| header.clone());
+    let bundle = args.bundle.as_deref().map(Bundle::load).transpose()?;
+    let mut descriptions = Descriptions::default();
+    let mut out = Output::default();
 
-    Some(Theme {
-        header: header.unwrap_or_else(|| Style::new().fg(Color::Green).bold()),
-        dir: dir.unwrap_or_else(|| Style::new().fg(Color::Cyan)),
-        filesize: filesize.unwrap_or_else(|| Style::new().fg(Color::Cyan)),
-        date: date.unwrap_or_else(|| Style::new().fg(Color::Purple)),
-        index: index.unwrap_or_else(Style::new),
-        enabled: true,
-    })
-}
-
-/// Handle conversion of JSON value to a `Style` object.
-///
-/// Parses a serde_json::Value into an optional Style object, using
-/// custom formatting rules. Supports string and object formats.
-///
-/// Parameters:
-/// - `v`: A reference to a serde_json::Value
-///
-/// Returns:
-/// - An Option<Style> containing parsed style or None if parsing fails.
-///
-/// Errors:
-/// - Returns None in case of invalid input format
-///
-/// Notes:
-/// - The object format expects specific keys: "fg", "bg" for colors,
-///   and "attr" for text attributes.
-fn value_to_style(v: &serde_json::Value) -> Option<Style> {
-    match v {
-        serde_json::Value::String(s) => parse_style_string(s),
-        serde_json::Value::Object(map) => {
-            let mut st = Style::new();
-            if let Some(fg) = map.get("fg").and_then(|x| x.as_str()) {
-                if let Some(c) = parse_color(fg) {
-                    st = st.fg(c);
-                }
+    // Several directories each get a `dir:` label, like `ls a b`
+    let labeled = target.directories.len() > 1;
+    let tables = !args.structured() && target.patterns.is_none();
+    for (i, dir) in target.directories.iter().enumerate() {
+        let first_row = out.rows.len();
+        if labeled && tables && !args.recursive {
+            let _ = writeln!(out.text, "{dir}:");
+        }
+        match &bundle {
+            Some(bundle) => list_bundle(bundle, dir, &args, &target, &mut out)?,
+            None if args.cached => {
+                let (cache, rel) = Bundle::cached(Path::new(dir), args.at.as_deref())?;
+                list_bundle(&cache, &rel, &args, &target, &mut out)?
             }
-            if let Some(bg) = map.get("bg").and_then(|x| x.as_str()) {
-                if let Some(c) = parse_color(bg) {
-                    st = st.on(c);
-                }
+            None => list_dir(dir, &args, &target, &mut descriptions, &mut out)?,
+        }
+        if args.summary && tables {
+            let totals = std::mem::take(&mut out.totals);
+            let _ = writeln!(out.text, "{}", totals.footer());
+        }
+        if labeled {
+            let dir = dir.trim_end_matches('/');
+            for r in &mut out.rows[first_row..] {
+                r.name = format!("{dir}/{}", r.name);
             }
-            if let Some(attr) = map.get("attr").and_then(|x| x.as_str()) {
-                let a = attr.to_lowercase();
-                if a.contains('b') {
-                    st = st.bold();
-                }
-                if a.contains('u') {
-                    st = st.underline();
-                }
-                if a.contains('r') {
-                    st = st.reverse();
-                }
+            if tables && !args.recursive && i + 1 < target.directories.len() {
+                out.text.push('\n');
             }
-            Some(st)
         }
-        _ => None,
     }
+
+    if args.structured() {
+        out.text = render_structured(&out.rows, &args)?;
+        if args.summary && args.format() == Format::Markdown {
+            out.totals.add(&out.rows);
+            let _ = writeln!(out.text, "\n{}", out.totals.footer());
+        }
+    } else if target.patterns.is_some() {
+        let rows = std::mem::take(&mut out.rows);
+        out.text = out.table(&rows, &args) + "\n";
+        if args.summary {
+            let _ = writeln!(out.text, "{}", out.totals.footer());
+        }
+    }
+    print_paged(&out.text, args.paging);
+    Ok(())
 }
 
-/// Parses a style string and returns an optional `Style` object.
-///
-/// This function takes a string input, trims it, and converts to lowercase
-/// before attempting to parse either an RGB hex color or a style configuration
-/// consisting of key-value pairs separated by underscores. It supports common
-/// syntax such as "bold", "underline", "reverse", and color names or hex codes.
+/// Lists a directory of the filesystem (or, with glob patterns, the matching paths).
 ///
 /// Parameters:
-/// - `s`: A string slice representing the style configuration to parse.
-///
-/// Returns:
-/// - `Option<Style>`: An optional `Style` object if parsing succeeds, or `None`
-///   if the input is invalid or unsupported.
+/// - `dir`: The directory to list.
+/// - `args`: The parsed arguments.
+/// - `target`: What to list.
+/// - `descriptions`: Description maps already loaded by earlier directories.
+/// - `out`: Receives the rendered tables, or the rows when they're combined into one
+///   table or printed as data.
 ///
 /// Errors:
-/// - This function does not directly return errors but may panic if it encounters
-///   invalid input or unsupported syntax. Use `?` operator in caller to handle panics.
-///
-/// Notes:
-/// - The input string is case-insensitive due to `to_lowercase`.
-/// - Supported style keywords include: bold, underline (u), reverse (r).
-/// - Supported color values can be named or specified in hex format.
-fn parse_style_string(s: &str) -> Option<Style> {
-    let s = s.trim().to_lowercase();
-    if let Some(rgb) = parse_hex_rgb(&s) {
-        return Some(Style::new().fg(rgb));
-    }
-    let mut st = Style::new();
-    let parts: Vec<&str> = s.split('_').collect();
-    for p in parts {
-        match p {
-            "bold" => st = st.bold(),
-            "underline" | "u" => st = st.underline(),
-            "reverse" | "r" => st = st.reverse(),
-            other => {
-                if let Some(c) = parse_color(other) {
-                    st = st.fg(c);
+/// - When descriptions can't be read at the `--at` ref, or the cache doesn't parse.
+fn list_dir(
+    dir: &str,
+    args: &Args,
+    target: &Target,
+    descriptions: &mut Descriptions,
+    out: &mut Output,
+) -> anyhow::Result<()> {
+    let start = PathBuf::from(dir)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(dir));
+    let (project_root, desc_map) = descriptions.for_dir(&start, args.at.as_deref())?;
+
+    // With --json/--nuon, every directory's rows go into one flat list
+    if let Some(globs) = &target.patterns {
+        let walk_root = start.join(&target.walk_root);
+        for dir_path in walk_dirs(&walk_root, args.all, args.no_ignore, None) {
+            let rows = collect_rows_for_dir(
+                &dir_path,
+                project_root.as_deref(),
+                desc_map,
+                args.all,
+                args.no_ignore,
+            )?;
+            for mut r in rows {
+                r.name = rel_str(&r.path, &start);
+                if globs.is_match(&r.name) && target.keeps(&r) {
+                    if args.du {
+                        out.du.fill(std::slice::from_mut(&mut r));
+                    }
+                    out.rows.push(r);
                 }
             }
         }
-    }
-    Some(st)
-}
-/// Parses a hexadecimal RGB color string and returns an Option<Color>.
-///
-/// This function checks if the input string starts with a `#` and contains exactly 6 hexadecimal characters.
-/// If valid, it parses the red (`r`), green (`g`), and blue (`b`) values from the string using base-16 conversion.
-/// Returns Some(Color::Rgb(r, g, b)) on success or None otherwise.
-///
-/// Parameters:
-/// - `s`: A string slice representing the hexadecimal RGB color (e.g., "#RRGGBB").
-///
-/// Returns:
-/// - Some(Color::Rgb(r, g, b)) if the input is a valid hexadecimal RGB string.
-/// - None otherwise.
-///
-/// Errors:
-/// - Returns Option::None if the input is not a valid hexadecimal RGB string.
-fn parse_hex_rgb(s: &str) -> Option<Color> {
-    if let Some(hex) = s.strip_prefix('#') {
-        if hex.len() == 6 {
-            let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
-            let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
-            let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
-            return Some(Color::Rgb(r, g, b));
+    } else if args.recursive {
+        for dir_path in walk_dirs(
+            &start,
+            args.all,
+            args.no_ignore,
+            args.depth.map(usize::from),
+        ) {
+            let mut rows = collect_rows_for_dir(
+                &dir_path,
+                project_root.as_deref(),
+                desc_map,
+                args.all,
+                args.no_ignore,
+            )?;
+            rows.retain(|r| target.keeps(r));
+            if args.du {
+                out.du.fill(&mut rows);
+            }
+            if args.structured() {
+                for r in &mut rows {
+                    r.name = rel_str(&r.path, &start);
+                }
+                out.rows.extend(rows);
+                continue;
+            }
+            // When filtering, directories without a match are left out
+            if rows.is_empty() && target.filtering() {
+                continue;
+            }
+            let table = out.table(&rows, args);
+            let _ = writeln!(out.text, "{}\n{table}\n", dir_path.display());
+        }
+    } else {
+        let mut rows = collect_rows_for_dir(
+            &start,
+            project_root.as_deref(),
+            desc_map,
+            args.all,
+            args.no_ignore,
+        )?;
+        rows.retain(|r| target.keeps(r));
+        if args.du {
+            out.du.fill(&mut rows);
+        }
+        if args.structured() {
+            out.rows.extend(rows);
+        } else {
+            let table = out.table(&rows, args);
+            let _ = writeln!(out.text, "{table}");
         }
     }
-    None
+    Ok(())
 }
-/// Parse a color name and return the corresponding `Color` value.
-/// This function supports named colors, RGB values, and special keywords like "dark_gray" or "light_red".
-/// On failure to match a known color, it returns `None`.
+
+/// Directories below `root` (including it), in the order `-R` and glob patterns visit them.
 ///
 /// Parameters:
-/// - `name`: The name of the color to parse (e.g., "red", "cyan").
-///
-/// Returns:
-/// - `Some(Color)` if the name matches a known color.
-/// - `None` otherwise.
-///
-/// Notes:
-/// - Supports named colors, RGB values, and special keywords.
-/// - Uses `use Color::*` to simplify the match pattern.
-fn parse_color(name: &str) -> Option<Color> {
-    use Color::*;
-    Some(match name {
-        "black" => Black,
-        "red" => Red,
-        "green" => Green,
-        "yellow" => Yellow,
-        "blue" => Blue,
-        "purple" | "magenta" => Purple,
-        "cyan" => Cyan,
-        "white" => White,
-
-        "dark_gray" | "grey" | "gray" => Color::Rgb(128, 128, 128),
-        "light_black" => Color::Rgb(96, 96, 96),
-        "light_white" => Color::Rgb(240, 240, 240),
-
-        "light_red" => LightRed,
-        "light_green" => LightGreen,
-        "light_yellow" => LightYellow,
-        "light_blue" => LightBlue,
-        "light_purple" | "light_magenta" => LightPurple,
-        "light_cyan" => LightCyan,
-
-        _ => return None,
-    })
+/// - `root`: Where the walk starts.
+/// - `show_all`: Whether to descend into hidden directories.
+/// - `no_ignore`: Whether to descend into directories excluded by `.gitignore` and friends.
+/// - `depth`: `--depth`; how many levels of directories to return (1 is just `root`).
+fn walk_dirs(root: &Path, show_all: bool, no_ignore: bool, depth: Option<usize>) -> Vec<PathBuf> {
+    WalkBuilder::new(root)
+        .max_depth(depth.map(|d| d.saturating_sub(1)))
+        .standard_filters(!no_ignore)
+        .hidden(!show_all)
+        .sort_by_file_name(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()))
+        .build()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_some_and(|t| t.is_dir()))
+        .map(|e| e.into_path())
+        .collect()
 }
 
-/// Handle the main entry point for the `dls` command-line tool.
-/// This function parses command-line arguments, locates a project root directory, and collects descriptions
-/// from files in that directory. It supports recursive traversal of directories and prints a formatted table
-/// of found descriptions based on the provided arguments. On success, it returns `Ok(())`.
-fn main() -> anyhow::Result<()> {
-    let args = Args::parse();
+/// Description maps loaded so far, so directories of one project share a single load.
+#[derive(Default)]
+struct Descriptions {
+    /// Descriptions by project root (`None` for directories outside a documented project).
+    by_root: HashMap<Option<PathBuf>, HashMap<String, FileDocInfo>>,
+}
 
-    let start = PathBuf::from(&args.directory)
-        .canonicalize()
-        .unwrap_or_else(|_| PathBuf::from(&args.directory));
-
-    let project_root = find_project_root(&start);
-    let desc_map = project_root
-        .as_ref()
-        .and_then(|r| load_descriptions(r).ok())
-        .unwrap_or_default();
-
-    if args.recursive {
-        for entry in WalkDir::new(&start).min_depth(0).max_open(256) {
-            let entry = match entry {
-                Ok(e) => e,
-                Err(_) => continue,
-            };
-            if entry.file_type().is_dir() {
-                let dir_path = entry.path();
-                println!("{}", dir_path.display());
-                let rows =
-                    collect_rows_for_dir(dir_path, project_root.as_deref(), &desc_map, args.all)?;
-                print_nu_table(&rows, args.fun);
-                println!();
+impl Descriptions {
+    /// The project root of `start` and its descriptions, loading them on first use.
+    ///
+    /// Parameters:
+    /// - `start`: The (canonicalized) directory being listed.
+    /// - `at`: `--at`; read the descriptions committed at this git ref instead.
+    ///
+    /// Errors:
+    /// - When descriptions can't be read at the `--at` ref, or that cache doesn't parse.
+    ///   A current cache that can't be read only prints a warning.
+    fn for_dir(
+        &mut self,
+        start: &Path,
+        at: Option<&str>,
+    ) -> anyhow::Result<(Option<PathBuf>, &HashMap<String, FileDocInfo>)> {
+        let root = match at {
+            Some(git_ref) => {
+                let (root, cache) = read_cache_at(start, git_ref)
+                    .with_context(|| format!("cannot read descriptions at {git_ref}"))?;
+                if let Entry::Vacant(slot) = self.by_root.entry(Some(root.clone())) {
+                    slot.insert(parse_descriptions(&cache, "dls")?);
+                }
+                Some(root)
             }
-        }
-    } else {
-        let rows = collect_rows_for_dir(&start, project_root.as_deref(), &desc_map, args.all)?;
-        print_nu_table(&rows, args.fun);
+            None => {
+                let root = find_project_root(start);
+                if let Entry::Vacant(slot) = self.by_root.entry(root.clone()) {
+                    let map = match root
+                        .as_deref()
+                        .map(|r| read_cache(r, |r| load_descriptions(r, "dls")))
+                    {
+                        Some(Ok(map)) => map,
+                        Some(Err(e)) => {
+                            eprintln!(
+                                "dls: warning: could not read descriptions ({e}); listing without them"
+                            );
+                            HashMap::new()
+                        }
+                        None => HashMap::new(),
+                    };
+                    slot.insert(map);
+                }
+                root
+            }
+        };
+        let map = &self.by_root[&root];
+        Ok((root, map))
     }
-
-    Ok(())
 }
 
 /// Collects file and directory rows from a given directory, including metadata like size, modification time, and optional description.
@@ -423,6 +1097,7 @@ fn main() -> anyhow::Result<()> {
 /// - `project_root`: Optional root path for relative file paths (used in `rel_str`).
 /// - `desc_map`: A map of file names to their description and metadata (from previous runs).
 /// - `show_all`: Whether to include hidden files.
+/// - `no_ignore`: Whether to include entries excluded by `.gitignore` and friends.
 ///
 /// Returns:
 /// A Vec of `RowRaw` objects containing file/dir info, or an error.
@@ -434,6 +1109,8 @@ fn main() -> anyhow::Result<()> {
 ///
 /// Notes:
 /// - Hidden files are skipped unless `show_all` is true.
+/// - Ignored entries are skipped like `dirdocs run` and `dtree` skip them (git ignore
+///   rules of the directory and its parents, `.ignore`, git excludes) unless `no_ignore`.
 /// - The returned rows are sorted with files first, then dirs by name.
 /// - `size_h` is formatted using `format_size`.
 fn collect_rows_for_dir(
@@ -441,6 +1118,7 @@ fn collect_rows_for_dir(
     project_root: Option<&Path>,
     desc_map: &HashMap<String, FileDocInfo>,
     show_all: bool,
+    no_ignore: bool,
 ) -> anyhow::Result<Vec<RowRaw>> {
     let entries = match fs::read_dir(dir) {
         Ok(rd) => rd,
@@ -449,21 +1127,30 @@ fn collect_rows_for_dir(
             return Ok(vec![]);
         }
     };
+    let children: Vec<PathBuf> = if no_ignore {
+        entries.filter_map(|d| d.ok().map(|d| d.path())).collect()
+    } else {
+        WalkBuilder::new(dir)
+            .max_depth(Some(1))
+            .hidden(false)
+            .build()
+            .filter_map(Result::ok)
+            .filter(|d| d.depth() > 0)
+            .map(|d| d.into_path())
+            .collect()
+    };
 
     let mut rows: Vec<RowRaw> = Vec::new();
 
-    for dent in entries {
-        let dent = match dent {
-            Ok(d) => d,
-            Err(_) => continue,
+    for path in children {
+        let Some(name) = path.file_name().map(|n| n.to_os_string()) else {
+            continue;
         };
-        let name = dent.file_name();
         if !show_all && is_hidden(&name) {
             continue;
         }
 
-        let path = dent.path();
-        let meta = match dent.metadata() {
+        let meta = match fs::symlink_metadata(&path) {
             Ok(m) => m,
             Err(_) => continue,
         };
@@ -477,14 +1164,8 @@ fn collect_rows_for_dir(
             format_size(size_raw, DECIMAL)
         };
 
-        let modified_h = meta
-            .modified()
-            .ok()
-            .map(|t| {
-                let dt: DateTime<Utc> = t.into();
-                HumanTime::from(Utc::now() - dt).to_text_en(Accuracy::Rough, Tense::Past)
-            })
-            .unwrap_or_else(|| "—".to_string());
+        let modified: Option<DateTime<Utc>> = meta.modified().ok().map(Into::into);
+        let modified_h = humanize(modified);
 
         let rel_key = if let Some(root) = project_root {
             rel_str(&path, root)
@@ -492,28 +1173,356 @@ fn collect_rows_for_dir(
             rel_str(&path, dir)
         };
 
-        let doc = desc_map.get(&rel_key).cloned().unwrap_or_default();
+        // Without a cache file, fall back to descriptions stored in extended attributes
+        let doc = match desc_map.get(&rel_key) {
+            Some(doc) => doc.clone(),
+            None if project_root.is_none() => xattr_info(&path).unwrap_or_default(),
+            None => FileDocInfo::default(),
+        };
 
         rows.push(RowRaw {
             path: path.clone(),
             name: name.to_string_lossy().to_string(),
             ty,
             size_h,
+            size: Some(size_raw),
             modified_h,
+            modified,
+            description: doc.description,
+            personality: doc.personality,
+            joy: doc.joy,
+            hash: doc.hash,
+            updated_at: doc.updated_at,
+        });
+    }
+
+    sort_rows(&mut rows);
+
+    Ok(rows)
+}
+
+/// A file written by `dirdocs bundle`, loaded once for every listed directory, or the
+/// cache tree of a project listed with `--cached`.
+struct Bundle {
+    /// The bundle file, for messages.
+    path: PathBuf,
+    /// The project root the tree describes (`--cached`), for sizes and modification
+    /// times; `None` for bundle files.
+    root: Option<PathBuf>,
+    /// The merged tree.
+    tree: DirdocsRoot,
+    /// Descriptions keyed by bundle-relative path.
+    descriptions: HashMap<String, FileDocInfo>,
+}
+
+impl Bundle {
+    /// Read and parse a bundle.
+    ///
+    /// Errors:
+    /// - When the file isn't a readable dirdocs bundle.
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let cache = read_bundle(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            root: None,
+            descriptions: parse_descriptions(&cache, "dls")?,
+            tree: serde_json::from_str(&cache)?,
+        })
+    }
+
+    /// Load the cache tree of the project containing `dir`, for `--cached`.
+    ///
+    /// Parameters:
+    /// - `dir`: The directory being listed.
+    /// - `at`: `--at`; read the tree committed at this git ref instead.
+    ///
+    /// Returns:
+    /// - The tree, and the path of `dir` inside it (`.` for the project root).
+    ///
+    /// Errors:
+    /// - When `dir` isn't inside a documented project, or its cache can't be read.
+    ///
+    /// Notes:
+    /// - `.dirdocs.db` records only files, so directories are rebuilt from their paths.
+    fn cached(dir: &Path, at: Option<&str>) -> anyhow::Result<(Self, String)> {
+        let start = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        let (root, tree, descriptions) = match at {
+            Some(git_ref) => {
+                let (root, cache) = read_cache_at(&start, git_ref)
+                    .with_context(|| format!("cannot read descriptions at {git_ref}"))?;
+                let tree: DirdocsRoot = serde_json::from_str(&cache)?;
+                (root, tree, parse_descriptions(&cache, "dls")?)
+            }
+            None => {
+                let root = find_project_root(&start).with_context(|| {
+                    format!(
+                        "no .dirdocs.nuon or .dirdocs.db in or above {}",
+                        dir.display()
+                    )
+                })?;
+                let (tree, descriptions) = read_cache(&root, |root| {
+                    if prefer_db(root) {
+                        let descriptions = load_descriptions_db(&root.join(".dirdocs.db"), "dls")?;
+                        let tree = DirdocsRoot {
+                            schema_version: SUPPORTED_SCHEMA_VERSION,
+                            entries: tree_from_paths(descriptions.keys()),
+                            ..DirdocsRoot::default()
+                        };
+                        return Ok((tree, descriptions));
+                    }
+                    let cache = fs::read_to_string(root.join(".dirdocs.nuon"))?;
+                    Ok((
+                        serde_json::from_str(&cache)?,
+                        parse_descriptions(&cache, "dls")?,
+                    ))
+                })?;
+                (root, tree, descriptions)
+            }
+        };
+        let rel = match rel_str(&start, &root).as_str() {
+            "" => ".".to_string(),
+            rel => rel.replace('\\', "/"),
+        };
+        Ok((
+            Self {
+                path: root.join(".dirdocs.nuon"),
+                root: Some(root),
+                tree,
+                descriptions,
+            },
+            rel,
+        ))
+    }
+}
+
+/// Lists a directory stored in a `dirdocs bundle` file (or, with `--cached`, in the
+/// project's cache tree), without walking the filesystem.
+///
+/// Parameters:
+/// - `bundle`: The loaded bundle or cache tree.
+/// - `dir`: The directory to list, as a path inside the bundle; patterns match paths
+///   relative to it.
+/// - `args`: The parsed arguments.
+/// - `target`: What to list.
+/// - `out`: Receives the table (or, with `-R`, one table per directory), or the rows
+///   when they're combined into one table or printed as data.
+///
+/// Errors:
+/// - When the directory is not inside the bundle.
+///
+/// Notes:
+/// - Bundles carry no sizes or modification times, so those columns show `—`; a cache
+///   tree takes them from the files on disk.
+fn list_bundle(
+    bundle: &Bundle,
+    dir: &str,
+    args: &Args,
+    target: &Target,
+    out: &mut Output,
+) -> anyhow::Result<()> {
+    let rel = match dir.trim_start_matches("./").trim_end_matches('/') {
+        "." => "",
+        rel => rel,
+    };
+    let mut cur = bundle.tree.entries.as_slice();
+    for name in rel.split('/').filter(|c| !c.is_empty()) {
+        cur = cur
+            .iter()
+            .find_map(|n| match n {
+                Node::Dir(d) if d.name == name => Some(d.entries.as_slice()),
+                _ => None,
+            })
+            .with_context(|| format!("{rel} is not a directory in {}", bundle.path.display()))?;
+    }
+
+    /// Renders the table for one bundle directory, then (when recursive) its subdirectories.
+    ///
+    /// With `--json`/`--nuon` or glob patterns, the rows are collected instead, named by
+    /// their path below `base` (the listed directory).
+    fn visit(
+        nodes: &[Node],
+        rel: &str,
+        base: &str,
+        bundle: &Bundle,
+        args: &Args,
+        target: &Target,
+        out: &mut Output,
+    ) {
+        let globs = target.patterns.as_ref();
+        let recursive = args.recursive || globs.is_some();
+        let mut rows = bundle_rows(nodes, rel, bundle, args.all);
+        if args.du {
+            out.du.fill(&mut rows);
+        }
+        if args.structured() || globs.is_some() {
+            if recursive {
+                for r in &mut rows {
+                    r.name = rel_str(&Path::new(rel).join(&r.name), Path::new(base));
+                }
+            }
+            out.rows.extend(
+                rows.into_iter()
+                    .filter(|r| globs.map_or(true, |g| g.is_match(&r.name)) && target.keeps(r)),
+            );
+        } else {
+            rows.retain(|r| target.keeps(r));
+            // When filtering, directories without a match are left out
+            let shown = !rows.is_empty() || !target.filtering();
+            if shown {
+                if recursive {
+                    let _ = writeln!(out.text, "{}", if rel.is_empty() { "." } else { rel });
+                }
+                let table = out.table(&rows, args);
+                let _ = writeln!(out.text, "{table}");
+                if recursive {
+                    out.text.push('\n');
+                }
+            }
+        }
+        if !recursive {
+            return;
+        }
+        // `--depth` counts levels from the listed directory, which is level 1
+        let level = Path::new(rel).components().count() - Path::new(base).components().count();
+        if args
+            .depth
+            .is_some_and(|depth| level + 1 >= usize::from(depth))
+        {
+            return;
+        }
+        for n in nodes {
+            if let Node::Dir(d) = n {
+                let child = if rel.is_empty() {
+                    d.name.clone()
+                } else {
+                    format!("{rel}/{}", d.name)
+                };
+                visit(&d.entries, &child, base, bundle, args, target, out);
+            }
+        }
+    }
+
+    visit(cur, rel, rel, bundle, args, target, out);
+    Ok(())
+}
+
+/// Builds table rows for the entries of one directory in a bundle tree.
+///
+/// Parameters:
+/// - `nodes`: The directory's entries.
+/// - `rel`: The directory's path inside the bundle (empty for the root).
+/// - `bundle`: The bundle, for its descriptions and (for a cache tree) project root.
+/// - `show_all`: Whether to include hidden entries.
+///
+/// Returns:
+/// - Rows sorted like [`collect_rows_for_dir`]: files first, then dirs, by name.
+fn bundle_rows(nodes: &[Node], rel: &str, bundle: &Bundle, show_all: bool) -> Vec<RowRaw> {
+    let mut rows: Vec<RowRaw> = Vec::new();
+    for n in nodes {
+        let (name, ty) = match n {
+            Node::Dir(d) => (d.name.clone(), "dir"),
+            Node::File(f) => match Path::new(&f.path).file_name() {
+                Some(name) => (name.to_string_lossy().to_string(), "file"),
+                None => continue,
+            },
+            Node::Unknown => continue,
+        };
+        if !show_all && name.starts_with('.') {
+            continue;
+        }
+        let key = if rel.is_empty() {
+            name.clone()
+        } else {
+            format!("{rel}/{name}")
+        };
+        let doc = bundle.descriptions.get(&key).cloned().unwrap_or_default();
+        let path = match &bundle.root {
+            Some(root) => root.join(&key),
+            None => PathBuf::from(&key),
+        };
+        // Entries of a cache tree that are gone from disk keep the placeholders
+        let meta = bundle
+            .root
+            .as_ref()
+            .and_then(|_| fs::symlink_metadata(&path).ok());
+        let size = meta.as_ref().map(|m| if m.is_file() { m.len() } else { 0 });
+        let modified: Option<DateTime<Utc>> = meta
+            .as_ref()
+            .and_then(|m| m.modified().ok())
+            .map(Into::into);
+        rows.push(RowRaw {
+            path,
+            name,
+            ty: ty.to_string(),
+            size_h: match size {
+                Some(0) => "0 B".to_string(),
+                Some(n) => format_size(n, DECIMAL),
+                None => "—".to_string(),
+            },
+            size,
+            modified_h: if modified.is_some() {
+                humanize(modified)
+            } else {
+                "—".to_string()
+            },
+            modified,
             description: doc.description,
             personality: doc.personality,
             joy: doc.joy,
+            hash: doc.hash,
+            updated_at: doc.updated_at,
         });
     }
 
-    // sort: files first, then dirs, by name
+    sort_rows(&mut rows);
+    rows
+}
+
+/// Sort rows the way every listing shows them: files first, then dirs, each by
+/// [`natural_cmp`] of their names.
+fn sort_rows(rows: &mut [RowRaw]) {
     rows.sort_by(|a, b| match (a.ty.as_str(), b.ty.as_str()) {
-        ("file", "dir") => std::cmp::Ordering::Less,
-        ("dir", "file") => std::cmp::Ordering::Greater,
-        _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        ("file", "dir") => Ordering::Less,
+        ("dir", "file") => Ordering::Greater,
+        _ => natural_cmp(&a.name, &b.name),
     });
+}
 
-    Ok(rows)
+/// Compare names the way people count, so `file2.txt` comes before `file10.txt`.
+///
+/// Runs of digits compare by their value and everything else case-insensitively; names
+/// that are still equal (differing only in case or leading zeros) fall back to a plain
+/// comparison so the order is stable.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    fn digits(it: &mut Peekable<Chars<'_>>) -> String {
+        let mut run = String::new();
+        while let Some(c) = it.next_if(char::is_ascii_digit) {
+            run.push(c);
+        }
+        run
+    }
+
+    let (mut x, mut y) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        let ord = match (x.peek().copied(), y.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(c), Some(d)) if c.is_ascii_digit() && d.is_ascii_digit() => {
+                let (m, n) = (digits(&mut x), digits(&mut y));
+                let (m, n) = (m.trim_start_matches('0'), n.trim_start_matches('0'));
+                m.len().cmp(&n.len()).then_with(|| m.cmp(n))
+            }
+            (Some(c), Some(d)) => {
+                x.next();
+                y.next();
+                c.to_lowercase().cmp(d.to_lowercase())
+            }
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
 }
 
 /// Handle and format a table of data for the `nu` command-line tool.
@@ -525,25 +1534,34 @@ fn collect_rows_for_dir(
 ///
 /// Parameters:
 /// - `rows`: A slice of raw row data to be displayed in the table.
-/// - `fun`: A boolean flag indicating whether emoji-based personality/joy data should be included.
+/// - `columns`: The columns to show, in order (after the `#` index).
+/// - `icons`: Whether to prefix names with a Nerd Font icon (see [`icon_for`]).
+/// - `hyperlinks`: Whether to link names to their files (see [`hyperlink`]).
+/// - `width`: Table width from `--width`; the terminal's width (or 80) when `None`.
 ///
 ///
 /// Returns:
-/// - `()`: This function does not return a value; it prints the formatted table directly to stdout.
-///
-///
-/// Errors:
-/// - `()` (no return value), but errors may occur during table construction, styling, or rendering.
-///   See the function body for details on potential error sources (e.g., terminal size, color settings).
+/// - The rendered table, ANSI-styled when stdout is a color terminal, for the caller to print
+///   or page.
 ///
 /// Notes:
 /// - The function builds a table with optional headers and rows, using either theme-based or color-based
 ///   styling for visual presentation.
-/// - The `fun` parameter controls whether emoji representations of personality and joy are added to the table.
-fn print_nu_table(rows: &[RowRaw], fun: bool) {
-    // Terminal width
-    let mut width = terminal_size()
-        .map(|(TermWidth(w), _)| w as usize)
+/// - Personality emojis are forced into emoji presentation; hashes are shortened to 12 characters.
+/// - When the joy column is shown, descriptions and ratings are colored from red (1) to
+///   green (10) by joy.
+/// - The `stale` column shows `⚠` for files changed since they were described and `✓`
+///   for unchanged ones.
+fn render_nu_table(
+    rows: &[RowRaw],
+    columns: &[Column],
+    icons: bool,
+    hyperlinks: bool,
+    width: Option<usize>,
+) -> String {
+    // Forced or terminal width
+    let mut width = width
+        .or_else(|| terminal_size().map(|(TermWidth(w), _)| w as usize))
         .unwrap_or(0);
     if width < 4 {
         eprintln!("Width must be >= 4; defaulting to 80");
@@ -555,7 +1573,7 @@ fn print_nu_table(rows: &[RowRaw], fun: bool) {
 
     // Theme (for header, index, size, date)
     let theme = if color_on {
-        try_load_nu_theme().unwrap_or_else(|| Theme::default_enabled(true))
+        try_load_nu_theme("dls").unwrap_or_else(|| Theme::default_enabled(true))
     } else {
         Theme::default_enabled(false)
     };
@@ -563,12 +1581,10 @@ fn print_nu_table(rows: &[RowRaw], fun: bool) {
     // LS_COLORS for the NAME column
     let ls_colors = if color_on { LsColors::from_env() } else { None };
 
-    // Headers (conditionally add personality & joy)
-    let mut headers = vec!["#", "name", "type", "size", "modified", "description"];
-    if fun {
-        headers.push("personality");
-        headers.push("joy");
-    }
+    // Headers: our own "#" index, then the selected columns
+    let headers: Vec<&str> = std::iter::once("#")
+        .chain(columns.iter().map(|c| c.header()))
+        .collect();
     let cols = headers.len();
 
     let headers_cells: Vec<Text<String>> = headers
@@ -589,35 +1605,62 @@ fn print_nu_table(rows: &[RowRaw], fun: bool) {
 
         let idx = paint(&theme.index, &i.to_string());
 
-        // NAME: prefer LS_COLORS, fallback to theme.dir for directories
+        // NAME: prefer LS_COLORS, fallback to theme.dir for directories; the icon shares its color
+        let label = if icons {
+            format!("{} {}", icon_for(&r.name, r.ty == "dir"), r.name)
+        } else {
+            r.name.clone()
+        };
         let name = if let Some(ls) = ls_colors.as_ref() {
             if let Some(st) = ls.style_for_path(&r.path) {
-                st.to_ansi_term_style().paint(&r.name).to_string()
+                st.to_ansi_term_style().paint(&label).to_string()
             } else if r.ty == "dir" && theme.enabled {
-                paint(&theme.dir, &r.name)
+                paint(&theme.dir, &label)
             } else {
-                r.name.clone()
+                label
             }
         } else if r.ty == "dir" && theme.enabled {
-            paint(&theme.dir, &r.name)
+            paint(&theme.dir, &label)
         } else {
-            r.name.clone()
+            label
+        };
+        let name = if hyperlinks {
+            hyperlink(&r.path, &name)
+        } else {
+            name
         };
 
-        let size = paint(&theme.filesize, &r.size_h);
-        let modified = paint(&theme.date, &r.modified_h);
+        // With the joy column shown (`--fun`), descriptions take the color of their rating
+        let joy = if columns.contains(&Column::Joy) {
+            joy_score(&r.joy).map(joy_color)
+        } else {
+            None
+        };
 
-        let mut row = vec![
-            Text::new(idx),
-            Text::new(name),
-            Text::new(r.ty.clone()),
-            Text::new(size),
-            Text::new(modified),
-            Text::new(r.description.clone()),
-        ];
-        if fun {
-            row.push(Text::new(as_emoji_presentation(&r.personality)));
-            row.push(Text::new(r.joy.clone()));
+        let mut row = vec![Text::new(idx)];
+        for column in columns {
+            let cell = match column {
+                Column::Name => name.clone(),
+                Column::Type => r.ty.clone(),
+                Column::Size => paint(&theme.filesize, &r.size_h),
+                Column::Modified => paint(&theme.date, &r.modified_h),
+                Column::Description => match joy {
+                    Some(c) => paint(&Style::new().fg(c), &r.description),
+                    None => r.description.clone(),
+                },
+                Column::Personality => as_emoji_presentation(&r.personality),
+                Column::Joy => match joy {
+                    Some(c) => paint(&Style::new().fg(c), &r.joy),
+                    None => r.joy.clone(),
+                },
+                Column::Hash => r.hash.chars().take(12).collect(),
+                Column::UpdatedAt => paint(&theme.date, &humanize(r.updated_at)),
+                Column::Stale => match stale_mark(r) {
+                    "⚠" => paint(&Style::new().fg(Color::Yellow), "⚠"),
+                    mark => mark.to_string(),
+                },
+            };
+            row.push(Text::new(cell));
         }
         debug_assert_eq!(row.len(), cols);
         data_rows.push(row);
@@ -636,179 +1679,311 @@ fn print_nu_table(rows: &[RowRaw], fun: bool) {
     // We render our own "#" index; no built-in index
     table.set_structure(false, true, false);
 
-    let output = table
+    table
         .draw(width)
-        .unwrap_or_else(|| format!("Couldn't fit table into {width} columns!"));
-    println!("{output}");
+        .unwrap_or_else(|| format!("Couldn't fit table into {width} columns!"))
 }
 
-/// Checks if a file or directory is hidden by examining its name.
-/// A path is considered hidden if it starts with a dot (`.`).
-///
-/// Parameters:
-/// - `name`: A reference to an OS string representing the path.
-///
-/// Returns:
-/// - `true` if the name starts with a dot, indicating a hidden file or directory.
-/// - `false` otherwise.
-fn is_hidden(name: &std::ffi::OsStr) -> bool {
-    name.to_string_lossy().starts_with('.')
+/// One row of `--json` output: the selected columns, as raw values, in column order.
+struct RowOut<'a> {
+    /// The row to serialize.
+    row: &'a RowRaw,
+    /// The selected columns.
+    columns: &'a [Column],
 }
 
-/// Find the root of a project by searching for `.dirdocs.nuon` files.
-///
-/// This function starts at the given `start` path and recursively checks
-/// parent directories for a file named `.dirdocs.nuon`. If found, it returns
-/// the path to that directory. If no such file is found within a reasonable
-/// range, it returns `None`.
+impl Serialize for RowOut<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.columns.len()))?;
+        for column in self.columns {
+            map.serialize_entry(column.header(), &column.value(self.row))?;
+        }
+        map.end()
+    }
+}
+
+/// Render rows as a JSON array (`--json`), a NUON table (`--nuon`) or a markdown table
+/// (`--format markdown`) instead of a rendered table.
 ///
 /// Parameters:
-/// - `start`: A reference to a path from which the search begins.
-///
-/// Returns:
-/// - An `Option<PathBuf>` containing the path to the project root if
-///   a `.dirdocs.nuon` file is found; otherwise `None`.
+/// - `rows`: The rows to print, in order.
+/// - `args`: The parsed arguments; `format()` picks the format and `columns()` the fields.
 ///
 /// Errors:
-/// - This function does not return explicit errors, but may panic
-///   if `cur.parent()` returns `None` (e.g., when `cur` is `/`).
+/// - JSON serialization errors.
 ///
 /// Notes:
-/// - The search continues upwards through parent directories until
-///   either a `.dirdocs.nuon` file is found or the root of the filesystem
-///   is reached.
-/// ```
-fn find_project_root(start: &Path) -> Option<PathBuf> {
-    let mut cur = start.to_path_buf();
-    loop {
-        if cur.join(".dirdocs.nuon").exists() {
-            return Some(cur);
-        }
-        let parent = cur.parent()?.to_path_buf();
-        if parent == cur {
-            return None;
+/// - Sizes and times are raw values (bytes, RFC 3339) rather than the table's
+///   human-readable ones. In NUON they are filesize and datetime literals, so Nushell
+///   can sort and filter on them directly.
+/// - Values that aren't known (sizes and times in bundles) are `null`.
+/// - Markdown shows the table's human-readable values instead.
+fn render_structured(rows: &[RowRaw], args: &Args) -> anyhow::Result<String> {
+    let columns = args.columns();
+    match args.format() {
+        Format::Nuon => return Ok(nuon_table(rows, &columns) + "\n"),
+        Format::Markdown if args.columns.is_empty() => {
+            let mut columns = vec![Column::Name, Column::Size, Column::Description];
+            if args.verify {
+                columns.push(Column::Stale);
+            }
+            return Ok(markdown_table(rows, &columns));
         }
-        cur = parent;
+        Format::Markdown => return Ok(markdown_table(rows, &columns)),
+        Format::Table | Format::Json => {}
     }
+    let out: Vec<RowOut<'_>> = rows
+        .iter()
+        .map(|row| RowOut {
+            row,
+            columns: &columns,
+        })
+        .collect();
+    Ok(serde_json::to_string_pretty(&out)? + "\n")
 }
 
-/// Load descriptions from a directory structure and return them as `FileDocInfo` in a `HashMap`.
-///
-/// This function reads the `.dirdocs.nuon` file to parse a directory structure and extracts
-/// descriptions, personality emojis, and joy fields from each file. It builds a mapping of file paths
-/// to `FileDocInfo` objects, which contain the extracted metadata. If any required fields are empty,
-/// the file is skipped.
-///
-/// # Parameters:
-/// - `root`: The path to the directory where `.dirdocs.nuon` is located.
-///
-/// # Returns:
-/// - A `HashMap<String, FileDocInfo>` containing the parsed descriptions.
-///
-/// # Errors:
-/// - I/O errors when reading files or parsing JSON, and
-///   any errors from `serde_json::from_str`.
-///
-/// # Notes:
-/// - The `.dirdocs.nuon` file must be in the form of a JSON object with an `entries` field.
-/// - Empty fields are ignored to ensure valid output.
-fn load_descriptions(root: &Path) -> anyhow::Result<HashMap<String, FileDocInfo>> {
-    let mut map: HashMap<String, FileDocInfo> = HashMap::new();
-    let s = fs::read_to_string(root.join(".dirdocs.nuon"))?;
-    let parsed: DirdocsRoot = serde_json::from_str(&s)?;
-
-    /// Handle a JSON value and convert it into a compact string representation.
-    ///
-    /// Converts any `serde_json::Value` to a string, handling nulls by returning an empty
-    /// string, strings by cloning their contents, and other types (numbers, booleans,
-    /// arrays, objects) by calling `to_string()` on them.
-    fn v_to_joy(v: &serde_json::Value) -> String {
-        match v {
-            serde_json::Value::Null => String::new(),
-            serde_json::Value::String(s) => s.clone(),
-            // numbers, bools, arrays, objects – compact string
-            other => other.to_string(),
-        }
+/// Render rows as a NUON table literal: `[[name, type, ...]; [a.rs, file, ...], ...]`.
+fn nuon_table(rows: &[RowRaw], columns: &[Column]) -> String {
+    if rows.is_empty() {
+        return "[]".to_string();
     }
+    let headers: Vec<&str> = columns.iter().map(|c| c.header()).collect();
 
-    /// Handle visiting nodes to populate file documentation info.
-    ///
-    /// This function recursively visits directory and file nodes, extracting
-    /// descriptions, personality emojis, and joy metadata from each file.
-    /// It builds a mapping between file paths and their documentation info,
-    /// skipping any files with empty description, personality emoji, or joy data.
-    fn visit(nodes: &[Node], out: &mut HashMap<String, FileDocInfo>) {
-        for n in nodes {
-            match n {
-                Node::Dir(d) => visit(&d.entries, out),
-                Node::File(f) => {
-                    let desc = f.doc.fileDescription.trim().to_string();
-                    let personality = f.doc.personalityEmoji.trim().to_string();
-                    let joy = v_to_joy(&f.doc.joyThisFileBrings);
-                    if !(desc.is_empty() && personality.is_empty() && joy.is_empty()) {
-                        out.insert(
-                            f.path.clone(),
-                            FileDocInfo {
-                                description: desc,
-                                personality,
-                                joy,
-                            },
-                        );
-                    }
+    let mut out = format!("[[{}];\n", headers.join(", "));
+    for (i, r) in rows.iter().enumerate() {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|c| match c.value(r) {
+                serde_json::Value::Null => "null".to_string(),
+                // Filesize and datetime literals
+                serde_json::Value::Number(n) if *c == Column::Size => format!("{n}b"),
+                serde_json::Value::String(t)
+                    if matches!(c, Column::Modified | Column::UpdatedAt) =>
+                {
+                    t
                 }
-            }
-        }
+                serde_json::Value::String(t) => nuon_string(&t),
+                other => other.to_string(),
+            })
+            .collect();
+        let sep = if i + 1 == rows.len() { "" } else { "," };
+        out.push_str(&format!("  [{}]{sep}\n", cells.join(", ")));
     }
+    out.push(']');
+    out
+}
 
-    visit(&parsed.entries, &mut map);
-    Ok(map)
+/// Render rows as a GitHub-flavored markdown table, names in code spans (directories
+/// with a trailing `/`) and sizes right-aligned.
+fn markdown_table(rows: &[RowRaw], columns: &[Column]) -> String {
+    let headers: Vec<&str> = columns.iter().map(|c| c.header()).collect();
+    let rule: Vec<&str> = columns
+        .iter()
+        .map(|c| if *c == Column::Size { "---:" } else { "---" })
+        .collect();
+    let mut out = format!("| {} |\n| {} |\n", headers.join(" | "), rule.join(" | "));
+    for r in rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|c| {
+                let text = match c {
+                    Column::Name if r.ty == "dir" => format!("`{}/`", r.name),
+                    Column::Name => format!("`{}`", r.name),
+                    Column::Size if r.ty == "dir" => String::new(),
+                    Column::Size => r.size_h.clone(),
+                    Column::Modified => r.modified_h.clone(),
+                    Column::Hash => r.hash.chars().take(12).collect(),
+                    Column::UpdatedAt => humanize(r.updated_at),
+                    Column::Stale => stale_mark(r).to_string(),
+                    Column::Type => r.ty.clone(),
+                    Column::Description => r.description.clone(),
+                    Column::Personality => r.personality.clone(),
+                    Column::Joy => r.joy.clone(),
+                };
+                // A pipe would end the cell and a newline the row
+                text.replace('|', "\\|").replace(['\r', '\n'], " ")
+            })
+            .collect();
+        out.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    out
 }
 
-/// Handle relative path string comparison between `p` and `base`.
-///
-/// Computes the relative path from `base` to `p`, using the
-/// `pathdiff::diff_paths` utility. If no relative path exists,
-/// returns a copy of `p`. The result is converted to a lossy string.
+/// Print the listing, through a pager when `paging` asks for it.
 ///
 /// Parameters:
-/// - `p`: Path to resolve relative from `base`.
-/// - `base`: Base path used for computing the relative path.
+/// - `text`: The rendered listing.
+/// - `paging`: `--paging`; `auto` pages when stdout is a terminal and `text` has more
+///   lines than it.
 ///
-/// Returns:
-/// A lossy UTF-8 string representing the relative path, or a copy of `p`
-/// if no relative path exists.
+/// Notes:
+/// - Falls back to printing directly when the pager can't be started.
+fn print_paged(text: &str, paging: Paging) {
+    let page = match paging {
+        Paging::Never => false,
+        Paging::Always => true,
+        Paging::Auto => {
+            io::stdout().is_terminal()
+                && terminal_size()
+                    .is_some_and(|(_, TermHeight(h))| text.lines().count() >= h as usize)
+        }
+    };
+    if page && page_text(text).is_ok() {
+        return;
+    }
+    print!("{text}");
+}
+
+/// Pipe `text` into `$PAGER` (default `less`) and wait for it to exit.
 ///
-/// Errors:
-/// - No direct errors are returned; all failures propagate through `unwrap_or_else`.
+/// Unless `$LESS` is set, `less` runs with `-FRX`: colors pass through, short output
+/// exits at once and the listing stays on screen afterwards.
 ///
-/// Notes:
-/// The result contains only ASCII if `p` or `base` contain non-ASCII UTF-8.
-fn rel_str(p: &Path, base: &Path) -> String {
-    pathdiff::diff_paths(p, base)
-        .unwrap_or_else(|| p.to_path_buf())
-        .to_string_lossy()
-        .into()
+/// Errors:
+/// - When the pager can't be started.
+fn page_text(text: &str) -> io::Result<()> {
+    let pager = std::env::var("PAGER")
+        .ok()
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| "less".to_string());
+    let mut parts = pager.split_whitespace();
+    let mut cmd = Command::new(parts.next().unwrap_or("less"));
+    cmd.args(parts).stdin(Stdio::piped());
+    if std::env::var_os("LESS").is_none() {
+        cmd.env("LESS", "FRX");
+    }
+
+    let mut child = cmd.spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Quitting the pager early closes the pipe; that's fine
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    let _ = child.wait();
+    Ok(())
 }
 
-/// Convert a string to its emoji presentation form.
+/// Nerd Font icons for well-known file names (matched case-insensitively).
+const NAME_ICONS: &[(&str, char)] = &[
+    ("cargo.toml", '\u{e7a8}'),
+    ("cargo.lock", '\u{e7a8}'),
+    ("dockerfile", '\u{f308}'),
+    ("makefile", '\u{e779}'),
+    ("license", '\u{f02d}'),
+    ("readme.md", '\u{f48a}'),
+    (".gitignore", '\u{f1d3}'),
+    (".gitattributes", '\u{f1d3}'),
+    (".gitmodules", '\u{f1d3}'),
+    ("package.json", '\u{e71e}'),
+];
+
+/// Nerd Font icons by file extension (matched case-insensitively).
+const EXTENSION_ICONS: &[(&str, char)] = &[
+    ("rs", '\u{e7a8}'),
+    ("py", '\u{e606}'),
+    ("js", '\u{e74e}'),
+    ("mjs", '\u{e74e}'),
+    ("ts", '\u{e628}'),
+    ("tsx", '\u{e7ba}'),
+    ("jsx", '\u{e7ba}'),
+    ("go", '\u{e627}'),
+    ("c", '\u{e61e}'),
+    ("h", '\u{f0fd}'),
+    ("cpp", '\u{e61d}'),
+    ("cc", '\u{e61d}'),
+    ("hpp", '\u{f0fd}'),
+    ("java", '\u{e738}'),
+    ("kt", '\u{e634}'),
+    ("swift", '\u{e755}'),
+    ("rb", '\u{e791}'),
+    ("php", '\u{e73d}'),
+    ("lua", '\u{e620}'),
+    ("hs", '\u{e777}'),
+    ("ex", '\u{e62d}'),
+    ("exs", '\u{e62d}'),
+    ("html", '\u{e736}'),
+    ("css", '\u{e749}'),
+    ("scss", '\u{e749}'),
+    ("vue", '\u{e6a0}'),
+    ("md", '\u{f48a}'),
+    ("json", '\u{e60b}'),
+    ("nuon", '\u{e60b}'),
+    ("toml", '\u{e6b2}'),
+    ("yaml", '\u{e6a8}'),
+    ("yml", '\u{e6a8}'),
+    ("xml", '\u{f05c0}'),
+    ("sh", '\u{f489}'),
+    ("bash", '\u{f489}'),
+    ("zsh", '\u{f489}'),
+    ("fish", '\u{f489}'),
+    ("nu", '\u{f489}'),
+    ("sql", '\u{f1c0}'),
+    ("db", '\u{f1c0}'),
+    ("lock", '\u{f023}'),
+    ("txt", '\u{f15c}'),
+    ("pdf", '\u{f1c1}'),
+    ("png", '\u{f1c5}'),
+    ("jpg", '\u{f1c5}'),
+    ("jpeg", '\u{f1c5}'),
+    ("gif", '\u{f1c5}'),
+    ("svg", '\u{f1c5}'),
+    ("webp", '\u{f1c5}'),
+    ("ico", '\u{f1c5}'),
+    ("mp3", '\u{f001}'),
+    ("wav", '\u{f001}'),
+    ("flac", '\u{f001}'),
+    ("mp4", '\u{f03d}'),
+    ("mkv", '\u{f03d}'),
+    ("mov", '\u{f03d}'),
+    ("zip", '\u{f410}'),
+    ("tar", '\u{f410}'),
+    ("gz", '\u{f410}'),
+    ("xz", '\u{f410}'),
+    ("7z", '\u{f410}'),
+    ("ttf", '\u{f031}'),
+    ("otf", '\u{f031}'),
+    ("woff", '\u{f031}'),
+    ("woff2", '\u{f031}'),
+];
+
+/// Icon for directories.
+const DIR_ICON: char = '\u{f07b}';
+
+/// Icon for files without a more specific one.
+const FILE_ICON: char = '\u{f15b}';
+
+/// The Nerd Font icon for a file or directory name, as `eza --icons` and `lsd` show them.
 ///
-/// This function checks if the input string is empty or contains the emoji modifier code point (`\u{FE0F}`). If so, it returns the string unchanged.
-/// Otherwise, if the string contains exactly one codepoint (e.g., a single Unicode character), it appends the emoji modifier to force emoji presentation.
+/// Parameters:
+/// - `name`: The entry's name (or path; only the last component is used).
+/// - `is_dir`: Whether the entry is a directory.
+///
+/// Returns:
+/// - An icon for well-known file names, else one for the extension, else a generic
+///   file or folder icon.
+fn icon_for(name: &str, is_dir: bool) -> char {
+    if is_dir {
+        return DIR_ICON;
+    }
+    let file = name.rsplit('/').next().unwrap_or(name).to_lowercase();
+    if let Some((_, icon)) = NAME_ICONS.iter().find(|(n, _)| *n == file) {
+        return *icon;
+    }
+    Path::new(&file)
+        .extension()
+        .and_then(|e| e.to_str())
+        .and_then(|ext| EXTENSION_ICONS.iter().find(|(e, _)| *e == ext))
+        .map_or(FILE_ICON, |(_, icon)| *icon)
+}
+
+/// Checks if a file or directory is hidden by examining its name.
+/// A path is considered hidden if it starts with a dot (`.`).
 ///
 /// Parameters:
-/// - `s`: The input string to be converted.
+/// - `name`: A reference to an OS string representing the path.
 ///
 /// Returns:
-/// A new `String` with emoji modifier applied if necessary.
-fn as_emoji_presentation(s: &str) -> String {
-    if s.is_empty() || s.contains('\u{FE0F}') {
-        return s.to_string();
-    }
-    // cheap check: if it’s a single codepoint, force emoji presentation
-    if s.chars().count() == 1 {
-        let mut out = s.to_string();
-        out.push('\u{FE0F}'); // VS16
-        return out;
-    }
-    s.to_string()
+/// - `true` if the name starts with a dot, indicating a hidden file or directory.
+/// - `false` otherwise.
+fn is_hidden(name: &std::ffi::OsStr) -> bool {
+    name.to_string_lossy().starts_with('.')
 }
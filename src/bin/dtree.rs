@@ -1,81 +1,34 @@
+use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
-use std::process::Command;
 
-use clap::Parser;
+use anyhow::Context;
+use clap::{CommandFactory, Parser, ValueEnum, ValueHint};
+use dirdocs::completions::{self, Shell};
+use dirdocs::view::{
+    DirdocsRoot, FileDocInfo, Node, Theme, as_emoji_presentation, file_hash, find_project_root,
+    humanize, hyperlink, load_descriptions, load_descriptions_db, nuon_string, parse_descriptions,
+    prefer_db, read_bundle, read_cache, read_cache_at, read_config, rel_str, tree_from_paths,
+    try_load_nu_theme, xattr_info,
+};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use humansize::{DECIMAL, format_size};
 use ignore::WalkBuilder;
 use lscolors::LsColors;
 use nu_ansi_term::{Color, Style};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use terminal_size::{Width as TermWidth, terminal_size};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-#[derive(Debug, Deserialize)]
-#[serde(tag = "kind", rename_all = "lowercase")]
-enum Node {
-    Dir(DirEntry),
-    File(FileEntry),
-}
-
-/// Represents a directory entry in the file system.
-/// Contains information about a directory and its contents.
-#[derive(Debug, Deserialize)]
-struct DirEntry {
-    /// path is a string representing the relative path within the directory.
-    #[expect(dead_code, reason = "Field kept to match .dirdocs.nuon schema")]
-    path: String,
-    /// Recursive list of directory entries.
-    entries: Vec<Node>,
-}
-
-/// Represents a file in the cache with its path and description.
-#[derive(Debug, Deserialize)]
-struct FileEntry {
-    /// Path to the file, e.g. "/home/user/...",
-    path: String,
-    /// Textual description of the file. Default is `Doc::empty()`.
-    #[serde(default)]
-    doc: Doc,
-}
-
-/// Represents a structured document with metadata about a file, including its description.
-#[allow(non_snake_case)]
-#[derive(Debug, Deserialize, Default)]
-struct Doc {
-    /// The file's description, defaulting to empty string.
-    #[serde(default)]
-    fileDescription: String,
-    /// The file's joyfulness, defaulting to empty JSON value.
-    #[serde(default, alias = "howMuchJoyDoesThisFileBringYou")]
-    #[expect(dead_code, reason = "Field kept to match .dirdocs.nuon schema")]
-    joyThisFileBrings: serde_json::Value,
-    /// The file's personality emoji, defaulting to empty string.
-    #[serde(default, alias = "emojiThatExpressesThisFilesPersonality")]
-    #[expect(dead_code, reason = "Field kept to match .dirdocs.nuon schema")]
-    personalityEmoji: String,
-}
-
-/// A root directory structure for dirdocs documentation.
-/// Contains a placeholder `root` and managed nodes under it.
-#[derive(Debug, Deserialize)]
-struct DirdocsRoot {
-    /// `root` is a placeholder kept to match .dirdocs.nuon schema.
-    #[expect(dead_code, reason = "Field kept to match .dirdocs.nuon schema")]
-    root: String,
-    /// The collection of nodes under the root, managed by directory tree logic.
-    entries: Vec<Node>,
-}
-
-/// A container for human-readable descriptions of files and directories.
-#[derive(Debug, Default, Clone)]
-struct FileDocInfo {
-    /// This field stores a human-readable description of the file or directory.
-    description: String,
-}
+/// Narrowest description column kept by `--align` on a narrow terminal.
+const MIN_DESCRIPTION_WIDTH: usize = 30;
 
 /// Arguments for the `dtree` command.
 #[derive(Parser, Debug)]
 #[clap(
+    name = "dtree",
     author,
     version,
     about = "dtree — tree-style view + descriptions from .dirdocs.nuon"
@@ -83,433 +36,1620 @@ struct FileDocInfo {
 /// Args holds the command line arguments for the tree utility. It contains options to control directory traversal and output formatting.
 struct Args {
     /// Start directory (default: .).
-    #[clap(default_value = ".")]
+    #[clap(default_value = ".", value_hint = ValueHint::DirPath)]
     directory: String,
 
     /// Show hidden files (dotfiles).
     #[clap(long, short = 'a')]
     all: bool,
 
+    /// List directories only, for an overview of how the project is laid out.
+    #[clap(long, short = 'd')]
+    dirs_only: bool,
+
+    /// List only files whose name matches this glob, e.g. `-P '*.rs'`; `|` separates
+    /// alternatives (`-P 'Cargo.*|*.md'`). Directories left empty are pruned.
+    #[clap(short = 'P', long = "pattern", value_name = "GLOB")]
+    pattern: Vec<String>,
+
+    /// Leave out files and directories whose name matches this glob, e.g. `-I '*.lock|tests'`.
+    /// Directories left empty are pruned.
+    #[clap(short = 'I', long = "exclude", value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// List only files without a description, and the directories leading to them.
+    #[clap(long)]
+    undocumented: bool,
+
+    /// Show each described entry's personality emoji and joy rating after its name.
+    #[clap(long)]
+    fun: bool,
+
+    /// Re-hash described files and mark the ones that changed since with `*`.
+    #[clap(long, conflicts_with = "bundle")]
+    verify: bool,
+
+    /// Leave out what git ignores: `.gitignore` rules, `.git/info/exclude` and the global
+    /// excludes file (the default).
+    #[clap(long, overrides_with = "no_gitignore")]
+    gitignore: bool,
+
+    /// List everything, build artifacts included, instead of leaving out what git ignores.
+    #[clap(long, overrides_with = "gitignore")]
+    no_gitignore: bool,
+
     /// Comma-separated directory names to ignore (repeat to add more).
     #[clap(short = 'i', long = "ignore", value_delimiter = ',')]
     ignore: Vec<String>,
 
-    /// Classic tree connectors (├── └── │   ).
-    #[clap(long)]
+    /// Descend into symlinked directories (links back to a directory being listed are not
+    /// followed).
+    #[clap(short = 'l', long)]
+    follow_symlinks: bool,
+
+    /// Classic tree connectors (├── └── │   ); short for `--connectors classic`.
+    #[clap(long, conflicts_with = "connectors")]
     boring: bool,
+
+    /// Connector set: `emoji` (🪾/🍃), `classic` (├──/└──) or `ascii` (|--/`--).
+    #[clap(long, value_enum, value_name = "SET")]
+    connectors: Option<Connectors>,
+
+    /// Print each entry's path relative to the start directory instead of just its name,
+    /// e.g. `src/bin/dtree.rs`, for piping the tree into other tools.
+    #[clap(long, short = 'f')]
+    full_path: bool,
+
+    /// Show each entry's size after its name; directories show the total of what's below them.
+    #[clap(long, short = 's')]
+    size: bool,
+
+    /// Show when each entry was last modified, e.g. `3 days ago`.
+    #[clap(long, short = 'D')]
+    date: bool,
+
+    /// Order of the entries in each directory: by `name`, largest `size` first, or most
+    /// recently `modified` first.
+    #[clap(long, value_enum, value_name = "KEY", default_value_t = Sort::Name)]
+    sort: Sort,
+
+    /// Reverse the `--sort` order (directories still come first).
+    #[clap(long, short = 'r')]
+    reverse: bool,
+
+    /// List files before directories instead of after them.
+    #[clap(long)]
+    files_first: bool,
+
+    /// Start every description at the same column.
+    #[clap(long)]
+    align: bool,
+
+    /// Wrap descriptions that don't fit the terminal onto the following lines instead of
+    /// cutting them short with `…`.
+    #[clap(long)]
+    wrap: bool,
+
+    /// Don't print the closing `N directories, M files, K documented` line.
+    #[clap(long)]
+    noreport: bool,
+
+    /// Descend at most this many levels (1 is just the start directory's entries).
+    #[clap(
+        long,
+        short = 'L',
+        value_name = "N",
+        value_parser = clap::value_parser!(u16).range(1..)
+    )]
+    depth: Option<u16>,
+
+    /// In directories with more than N entries, print only the first N and a line like
+    /// `… and 342 more files` in place of the rest.
+    #[clap(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    filelimit: Option<u32>,
+
+    /// Show descriptions as they were committed at this git ref (e.g. `HEAD~10`, `v0.2.0`).
+    #[clap(long, value_name = "GIT_REF")]
+    at: Option<String>,
+
+    /// Read the tree and descriptions from a file written by `dirdocs bundle` instead of
+    /// the filesystem; the start directory is then a path inside the bundle.
+    #[clap(long, value_name = "FILE", conflicts_with = "at")]
+    bundle: Option<PathBuf>,
+
+    /// Output format; `markdown` prints a nested bullet list with descriptions, for a
+    /// README "project layout" section, `json`/`nuon` the tree as nested records, `dot`
+    /// a Graphviz graph and `html` a standalone page with collapsible directories.
+    #[clap(long, value_enum, value_name = "FORMAT", default_value_t = Format::Tree)]
+    format: Format,
+
+    /// Show the project's cache tree (`.dirdocs.nuon`) instead of the filesystem: what was
+    /// documented, including files deleted since. Combines with `--at`.
+    #[clap(long, conflicts_with = "bundle")]
+    cached: bool,
+
+    /// Write the output to this file instead of stdout, without colors unless
+    /// `--color always` is given.
+    #[clap(long, short = 'o', value_name = "FILE", value_hint = ValueHint::FilePath)]
+    output: Option<PathBuf>,
+
+    /// When to color names: `auto` (when printing to a terminal and `NO_COLOR` is unset),
+    /// `always` or `never`.
+    #[clap(long, value_enum, value_name = "WHEN", default_value_t = When::Auto)]
+    color: When,
+
+    /// When to make names clickable `file://` links (OSC 8, like `ls --hyperlink`); a bare
+    /// `--hyperlink` means `always`.
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = When::Auto,
+        default_missing_value = "always",
+        num_args = 0..=1,
+        require_equals = true,
+        value_name = "WHEN"
+    )]
+    hyperlink: When,
+
+    /// Print a shell completion script for `dtree` and exit, e.g. `dtree --completions zsh`.
+    #[clap(long, value_enum, value_name = "SHELL", exclusive = true)]
+    completions: Option<Shell>,
 }
 
-/// `Theme` represents a directory navigation theme, storing visual styles and enabled status.
-#[derive(Clone)]
-struct Theme {
-    /// A `Style` for the header, kept to match .dirdocs.nuon schema.
-    #[expect(dead_code, reason = "Field kept to match .dirdocs.nuon schema")]
-    header: Style,
-    /// A `Style` for directory names, kept to match .dirdocs.nuon schema.
-    dir: Style,
-    /// A `Style` for file names, kept to match .dirdocs.nuon schema.
-    file: Style,
-    /// Whether the theme is enabled.
-    enabled: bool,
-}
-
-impl Theme {
-    /// Handle enabling or disabling the default theme settings. This function sets up the styling for
-    /// headers, directories, and files based on the `enabled` boolean argument. If enabled,
-    /// it applies green color to headers and bold formatting; directories are shown in cyan.
-    ///
-    /// Parameters:
-    /// - `enabled`: A boolean indicating whether to enable the default theme styling.
-    ///
-    /// Returns:
-    /// - The updated `Self` instance with styled elements configured according to the
-    ///   provided `enabled` value.
-    fn default_enabled(enabled: bool) -> Self {
-        Self {
-            header: Style::new().fg(Color::Green).bold(),
-            dir: Style::new().fg(Color::Cyan),
-            file: Style::new(), // default/no color for files if LS_COLORS is absent
-            enabled,
+/// When something is turned on, selected with `--color` and `--hyperlink`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum When {
+    /// When printing to a terminal.
+    Auto,
+    /// Always, even in files and pipes.
+    Always,
+    /// Never.
+    Never,
+}
+
+/// Built-in connector sets, selected with `--connectors` or in [`CONFIG_FILE`].
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum Connectors {
+    /// 🪾 before directories, 🍃 before files.
+    Emoji,
+    /// Box-drawing lines, like `tree`.
+    Classic,
+    /// Plain ASCII lines, for fonts and terminals without box drawing.
+    Ascii,
+}
+
+impl Connectors {
+    /// The glyphs of this set.
+    fn glyphs(self) -> Glyphs {
+        let (branch, last, pipe, indent, dir, file) = match self {
+            Connectors::Emoji => ("", "", "🪾  ", "   ", "🪾 ", "🍃 "),
+            Connectors::Classic => ("├── ", "└── ", "│   ", "    ", "", ""),
+            Connectors::Ascii => ("|-- ", "`-- ", "|   ", "    ", "", ""),
+        };
+        Glyphs {
+            branch: branch.to_string(),
+            last: last.to_string(),
+            pipe: pipe.to_string(),
+            indent: indent.to_string(),
+            dir: dir.to_string(),
+            file: file.to_string(),
         }
     }
 }
 
-/// Handle loading a `nu` theme configuration and return it as an optional [`Theme`].
-///
-/// This function uses the `nu` command-line tool to fetch a theme configuration,
-/// parses it with Serde JSON, and constructs a Theme object using helper functions.
-/// Colors are assigned based on the returned map values. If errors occur during
-/// parsing or command execution, returns `None`.
-///
-/// Parameters:
-/// - None; the function is called without explicit parameters.
-///
-/// Returns:
-/// - An `Option<Theme>`: Some theme if successful, None on failure.
-///
-/// Errors:
-/// - I/O errors when running the `nu` command or parsing JSON.
-/// - Errors from Serde JSON deserialization.
-fn try_load_nu_theme() -> Option<Theme> {
-    let out = Command::new("nu")
-        .args(["-c", "$env.config.color_config | to json"])
-        .output()
-        .ok()?;
-
-    if !out.status.success() {
-        return None;
-    }
-
-    let json = serde_json::from_slice::<serde_json::Value>(&out.stdout).ok()?;
-
-    let resolve = |key: &str| -> Option<Style> { value_to_style(json.get(key)?) };
-
-    let header = resolve("header").or_else(|| resolve("shape_table"));
-    let dir = resolve("shape_directory")
-        .or_else(|| resolve("shape_filepath"))
-        .or_else(|| resolve("string"));
-    let file = resolve("shape_filepath")
-        .or_else(|| resolve("string"))
-        .or_else(|| resolve("nothing"));
-
-    Some(Theme {
-        header: header.unwrap_or_else(|| Style::new().fg(Color::Green).bold()),
-        dir: dir.unwrap_or_else(|| Style::new().fg(Color::Cyan)),
-        file: file.unwrap_or_else(Style::new),
-        enabled: true,
-    })
+/// The pieces a tree line is drawn from.
+#[derive(Debug, Clone)]
+struct Glyphs {
+    /// Before an entry that has siblings below it.
+    branch: String,
+    /// Before the last entry of a directory.
+    last: String,
+    /// Indentation under an entry that has siblings below it.
+    pipe: String,
+    /// Indentation under the last entry of a directory.
+    indent: String,
+    /// After the connector of a directory.
+    dir: String,
+    /// After the connector of a file.
+    file: String,
 }
 
-/// Handle converting a JSON value to an optional `Style` object.
-///
-/// Parses the given JSON value and returns a `Style` if it represents a valid style definition.
-/// If the input is a string, it calls `parse_style_string`.
-/// If the input is an object (dictionary), it extracts foreground (`fg`), background (`bg`), and attribute (`attr`) values,
-/// parses their colors or attributes using `parse_color`, then constructs a `Style` object with the result.
-/// Returns `None` for invalid or unsupported input types.
-///
-/// Parameters:
-/// - `v`: A reference to a `serde_json::Value` representing the style definition.
-///
-/// Returns:
-/// - An `Option<Style>` which contains the parsed style if valid, or `None` otherwise.
+/// File name of the `dtree` settings inside the dirdocs config directory.
+const CONFIG_FILE: &str = "dtree.toml";
+
+/// Settings read from [`CONFIG_FILE`], e.g. `connectors = "ascii"` and a `[glyphs]`
+/// table replacing single pieces of the chosen set.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Config {
+    /// Connector set used when `--connectors`/`--boring` aren't given.
+    connectors: Option<Connectors>,
+    /// Pieces replaced in that set.
+    glyphs: GlyphOverrides,
+}
+
+/// The `[glyphs]` table of [`CONFIG_FILE`]; keys match the fields of [`Glyphs`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct GlyphOverrides {
+    branch: Option<String>,
+    last: Option<String>,
+    pipe: Option<String>,
+    indent: Option<String>,
+    dir: Option<String>,
+    file: Option<String>,
+}
+
+impl GlyphOverrides {
+    /// `glyphs` with every piece set here replaced.
+    fn apply(self, mut glyphs: Glyphs) -> Glyphs {
+        let pieces = [
+            (self.branch, &mut glyphs.branch),
+            (self.last, &mut glyphs.last),
+            (self.pipe, &mut glyphs.pipe),
+            (self.indent, &mut glyphs.indent),
+            (self.dir, &mut glyphs.dir),
+            (self.file, &mut glyphs.file),
+        ];
+        for (value, piece) in pieces {
+            if let Some(value) = value {
+                *piece = value;
+            }
+        }
+        glyphs
+    }
+}
+
+/// The glyphs to draw with: a set named on the command line is used as is; otherwise the
+/// set from [`CONFIG_FILE`] (emoji by default) with its `[glyphs]` replacements.
 ///
 /// Errors:
-/// - Occurs in `parse_style_string`, `parse_color`, or when parsing JSON values.
-/// - The errors are propagated directly from these functions.
-///
-/// Safety:
-/// - This function does not perform any unsafe operations.
-///
-/// Notes:
-/// - The input JSON must match the expected format for style definitions (e.g., keys like `"fg"`, `"bg"`, and `"attr"`).
-/// - The function assumes that `parse_color` will return a valid color value for foreground and background.
-/// - Attribute strings are case-insensitive (e.g., `"BOLD"` is equivalent to `"bold"`).
-fn value_to_style(v: &serde_json::Value) -> Option<Style> {
-    match v {
-        serde_json::Value::String(s) => parse_style_string(s),
-        serde_json::Value::Object(map) => {
-            let mut st = Style::new();
-            if let Some(fg) = map.get("fg").and_then(|x| x.as_str()) {
-                if let Some(c) = parse_color(fg) {
-                    st = st.fg(c);
-                }
+/// - A config file that can't be read or parsed.
+fn glyphs(args: &Args) -> anyhow::Result<Glyphs> {
+    let cli = if args.boring {
+        Some(Connectors::Classic)
+    } else {
+        args.connectors
+    };
+    if let Some(set) = cli {
+        return Ok(set.glyphs());
+    }
+    let config = read_config::<Config>(CONFIG_FILE)?.unwrap_or_default();
+    let set = config.connectors.unwrap_or(Connectors::Emoji);
+    Ok(config.glyphs.apply(set.glyphs()))
+}
+
+/// Order of the entries in each directory, selected with `--sort`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Sort {
+    /// Case-insensitive name.
+    Name,
+    /// Largest first; directories by the total of what's below them.
+    Size,
+    /// Most recently modified first.
+    Modified,
+}
+
+/// How the tree is printed, selected with `--format`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// Connectors and colored names, like `tree`.
+    Tree,
+    /// A markdown nested bullet list.
+    Markdown,
+    /// The tree as nested JSON objects.
+    Json,
+    /// The tree as nested NUON records (for `open`/`from nuon` in Nushell).
+    Nuon,
+    /// A Graphviz graph, descriptions as node tooltips.
+    Dot,
+    /// A standalone HTML page with collapsible directories.
+    Html,
+}
+
+/// How a tree is printed, shared by every level of the walk.
+struct Walk<'a> {
+    /// Directory names to skip (case-sensitive).
+    ignore: &'a HashSet<String>,
+    /// Custom styling configuration for colors and symbols.
+    theme: &'a Theme,
+    /// LS_COLORS styles, when colors are on.
+    ls_colors: &'a Option<LsColors>,
+    /// Connector glyphs (`--connectors`, `--boring` or [`CONFIG_FILE`]).
+    glyphs: Glyphs,
+    /// Show hidden files (dotfiles).
+    show_all: bool,
+    /// Leave out what git ignores (`--gitignore`, unless `--no-gitignore`).
+    gitignore: bool,
+    /// Leave files out, printing directories only.
+    dirs_only: bool,
+    /// Names of the files to list (`-P`), if restricted.
+    include: Option<GlobSet>,
+    /// Names of the files and directories to leave out (`-I`).
+    exclude: Option<GlobSet>,
+    /// List only files without a description (`--undocumented`).
+    undocumented: bool,
+    /// Re-hash described files to find stale descriptions (`--verify`).
+    verify: bool,
+    /// Show personality emoji and joy ratings (`--fun`).
+    fun: bool,
+    /// Deepest level printed (1 is the start directory's entries); unlimited when `None`.
+    max_depth: Option<usize>,
+    /// Most entries printed per directory (`--filelimit`); unlimited when `None`.
+    file_limit: Option<usize>,
+    /// Print paths relative to the start directory in place of names (`--full-path`).
+    full_path: bool,
+    /// Show sizes after names (`--size`).
+    sizes: bool,
+    /// Order of the entries in each directory (`--sort`).
+    sort: Sort,
+    /// Reverse that order (`--reverse`).
+    reverse: bool,
+    /// List files before directories (`--files-first`).
+    files_first: bool,
+    /// Show modification times after names (`--date`).
+    dates: bool,
+    /// Leave out the closing counts (`--noreport`).
+    no_report: bool,
+    /// Start descriptions at a common column (`--align`).
+    align: bool,
+    /// Wrap long descriptions instead of truncating them (`--wrap`).
+    wrap: bool,
+    /// Terminal width, when printing to a terminal.
+    width: Option<usize>,
+    /// Link names to their files (`--hyperlink`).
+    hyperlinks: bool,
+    /// Descend into symlinked directories (`--follow-symlinks`).
+    follow_links: bool,
+}
+
+impl Walk<'_> {
+    /// The directories above `dir`'s entries, extended with `dir`, or `None` when `dir` leads
+    /// back to one of them through a symlink.
+    ///
+    /// Parameters:
+    /// - `dir`: The directory about to be read.
+    /// - `ancestors`: Canonical paths of the directories being read above it.
+    ///
+    /// Notes:
+    /// - Cycles are only possible when following symlinks, so otherwise nothing is resolved
+    ///   and `ancestors` stays empty.
+    fn enter(&self, dir: &Path, ancestors: &[PathBuf]) -> Option<Vec<PathBuf>> {
+        if !self.follow_links {
+            return Some(Vec::new());
+        }
+        let canonical = fs::canonicalize(dir).ok()?;
+        // A link to a directory above would list that directory again, and again
+        if ancestors.iter().any(|a| a.starts_with(&canonical)) {
+            return None;
+        }
+        let mut chain = ancestors.to_vec();
+        chain.push(canonical);
+        Some(chain)
+    }
+
+    /// Whether the children of an entry at `depth` are printed.
+    fn descends(&self, depth: usize) -> bool {
+        self.max_depth.map_or(true, |max| depth < max)
+    }
+
+    /// Whether an entry passes `-P` and `-I`; `-P` only applies to files, as in `tree`.
+    fn shows(&self, name: &str, is_dir: bool) -> bool {
+        if self.exclude.as_ref().is_some_and(|g| g.is_match(name)) {
+            return false;
+        }
+        is_dir || self.include.as_ref().map_or(true, |g| g.is_match(name))
+    }
+
+    /// With `--verify`, whether the file at `path` changed since `doc` was written; `None`
+    /// when not verifying or there's no recorded hash to compare with.
+    fn stale(&self, doc: Option<&FileDocInfo>, path: &Path) -> Option<bool> {
+        let hash = doc
+            .map(|d| d.hash.as_str())
+            .filter(|h| self.verify && !h.is_empty())?;
+        file_hash(path).ok().map(|h| h != hash)
+    }
+
+    /// The personality emoji and joy rating of `doc` with `--fun`; empty otherwise.
+    fn fun(&self, doc: Option<&FileDocInfo>) -> (String, String) {
+        match doc {
+            Some(d) if self.fun => (d.personality.clone(), d.joy.clone()),
+            _ => Default::default(),
+        }
+    }
+
+    /// `name` as a link to `path` with `--hyperlink` (bundle entries, which aren't on disk,
+    /// stay unlinked).
+    fn link(&self, path: &Path, name: String) -> String {
+        if self.hyperlinks {
+            hyperlink(path, &name)
+        } else {
+            name
+        }
+    }
+
+    /// Whether a file with this description is listed; `--undocumented` keeps only files
+    /// without one.
+    fn keeps(&self, is_dir: bool, description: &str) -> bool {
+        is_dir || !self.undocumented || description.is_empty()
+    }
+
+    /// Whether entries record their sizes: for `--size`, and for `--sort size`.
+    fn measures(&self) -> bool {
+        self.sizes || self.sort == Sort::Size
+    }
+
+    /// Sorts one directory's entries (directories first unless `--files-first`, then by
+    /// `--sort`, ties by case-insensitive name) and, when `-P`, `-I` or `--undocumented` is
+    /// given, prunes the subdirectories they left empty.
+    ///
+    /// Directories at the depth limit weren't read, so they're kept.
+    fn finish(&self, mut entries: Vec<TreeEntry>, depth: usize) -> Vec<TreeEntry> {
+        let filtered = self.include.is_some() || self.exclude.is_some() || self.undocumented;
+        if filtered && self.descends(depth) {
+            entries.retain(|e| !e.is_dir || !e.children.is_empty());
+        }
+        let modified = |e: &TreeEntry| e.meta.as_ref().and_then(|m| m.modified().ok());
+        entries.sort_by(|a, b| {
+            let group = if self.files_first {
+                a.is_dir.cmp(&b.is_dir)
+            } else {
+                b.is_dir.cmp(&a.is_dir)
+            };
+            // Entries without a size or time (bundles) go last
+            let order = match self.sort {
+                Sort::Name => Ordering::Equal,
+                Sort::Size => b.size.cmp(&a.size),
+                Sort::Modified => modified(b).cmp(&modified(a)),
             }
-            if let Some(bg) = map.get("bg").and_then(|x| x.as_str()) {
-                if let Some(c) = parse_color(bg) {
-                    st = st.on(c);
-                }
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+            group.then(if self.reverse { order.reverse() } else { order })
+        });
+        entries
+    }
+}
+
+/// One entry of the tree being printed, after filtering.
+struct TreeEntry {
+    /// File or directory name.
+    name: String,
+    /// Path used to pick LS_COLORS styles (relative to the bundle root for bundles).
+    path: PathBuf,
+    /// Metadata of files on disk; `None` for bundles.
+    meta: Option<fs::Metadata>,
+    /// Whether the entry is a directory.
+    is_dir: bool,
+    /// Description from the cache or extended attributes, empty when there is none.
+    description: String,
+    /// Personality emoji, with `--fun`.
+    personality: String,
+    /// Joy rating, with `--fun`.
+    joy: String,
+    /// Where a symlink points, as stored in the link.
+    target: Option<PathBuf>,
+    /// Size of a file; for a directory, with `--size` or `--sort size`, the total of the
+    /// files below it.
+    size: Option<u64>,
+    /// With `--verify`, whether a described file changed since it was described; `None`
+    /// when it wasn't checked.
+    stale: Option<bool>,
+    /// Entries of a directory; empty for files and for directories at the depth limit.
+    children: Vec<TreeEntry>,
+}
+
+/// A tree entry as printed by `--format json|nuon`.
+#[derive(Serialize)]
+struct TreeOut {
+    /// File or directory name.
+    name: String,
+    /// Path relative to the start directory (`.` for the start directory itself).
+    path: String,
+    /// `dir` or `file`.
+    #[serde(rename = "type")]
+    ty: &'static str,
+    /// Description, empty when there is none.
+    description: String,
+    /// With `--fun`, the personality emoji; left out otherwise or when there is none.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    personality: Option<String>,
+    /// With `--fun`, the joy rating; left out otherwise or when there is none.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    joy: Option<String>,
+    /// Where a symlink points; left out for other entries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    /// With `--verify`, whether a described file changed since; left out otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stale: Option<bool>,
+    /// Entries of a directory; left out for files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    children: Option<Vec<TreeOut>>,
+}
+
+impl TreeOut {
+    /// Converts `ent`, found at `path` below the start directory, and everything under it.
+    fn new(ent: &TreeEntry, path: &str) -> Self {
+        let child_path = |name: &str| match path {
+            "." => name.to_string(),
+            _ => format!("{path}/{name}"),
+        };
+        Self {
+            name: ent.name.clone(),
+            path: path.to_string(),
+            ty: if ent.is_dir { "dir" } else { "file" },
+            description: ent.description.clone(),
+            personality: (!ent.personality.is_empty()).then(|| ent.personality.clone()),
+            joy: (!ent.joy.is_empty()).then(|| ent.joy.clone()),
+            target: ent.target.as_ref().map(|t| t.display().to_string()),
+            stale: ent.stale,
+            children: ent.is_dir.then(|| {
+                ent.children
+                    .iter()
+                    .map(|c| Self::new(c, &child_path(&c.name)))
+                    .collect()
+            }),
+        }
+    }
+
+    /// Renders the entry as a NUON record, children as a nested list of records.
+    fn nuon(&self) -> String {
+        let mut out = format!(
+            "{{name: {}, path: {}, type: {}, description: {}",
+            nuon_string(&self.name),
+            nuon_string(&self.path),
+            self.ty,
+            nuon_string(&self.description)
+        );
+        for (key, value) in [("personality", &self.personality), ("joy", &self.joy)] {
+            if let Some(value) = value {
+                out.push_str(&format!(", {key}: {}", nuon_string(value)));
             }
-            if let Some(attr) = map.get("attr").and_then(|x| x.as_str()) {
-                let a = attr.to_lowercase();
-                if a.contains('b') {
-                    st = st.bold();
-                }
-                if a.contains('u') {
-                    st = st.underline();
-                }
-                if a.contains('r') {
-                    st = st.reverse();
-                }
+        }
+        if let Some(target) = &self.target {
+            out.push_str(&format!(", target: {}", nuon_string(target)));
+        }
+        if let Some(stale) = self.stale {
+            out.push_str(&format!(", stale: {stale}"));
+        }
+        if let Some(children) = &self.children {
+            let children: Vec<String> = children.iter().map(Self::nuon).collect();
+            out.push_str(&format!(", children: [{}]", children.join(", ")));
+        }
+        out.push('}');
+        out
+    }
+
+    /// Renders the tree as a Graphviz `digraph`, e.g. for `dot -Tsvg`.
+    ///
+    /// Nodes are keyed by path and labeled with their name, directories as folders and files
+    /// as notes; descriptions become tooltips, shown on hover in SVG output.
+    fn dot(&self) -> String {
+        fn push(out: &mut String, ent: &TreeOut) {
+            let (label, shape) = match ent.children {
+                Some(_) => (format!("{}/", ent.name), "folder"),
+                None => (ent.name.clone(), "note"),
+            };
+            out.push_str(&format!(
+                "  {} [label={}, shape={shape}, tooltip={}];\n",
+                dot_string(&ent.path),
+                dot_string(&label),
+                dot_string(&ent.description)
+            ));
+            for child in ent.children.iter().flatten() {
+                out.push_str(&format!(
+                    "  {} -> {};\n",
+                    dot_string(&ent.path),
+                    dot_string(&child.path)
+                ));
+                push(out, child);
             }
-            Some(st)
         }
-        _ => None,
+
+        let mut out = String::from(
+            "digraph dirdocs {\n  rankdir=LR;\n  node [fontname=\"Helvetica\", fontsize=10];\n",
+        );
+        push(&mut out, self);
+        out.push_str("}\n");
+        out
     }
-}
 
-/// Parse a style string into a `Style` object.
-///
-/// This function takes a string representing color or formatting options and
-/// returns an optional `Style` object. It first attempts to parse a hex RGB color.
-/// If that fails, it splits the string on underscores and applies formatting
-/// options like `bold`, `underline`, or `reverse`. It also supports shorthand
-/// for formatting (e.g., "u" = underline).
-///
-/// Parameters:
-/// - `s`: A string slice representing the style.
-///
-/// Returns:
-/// - An optional `Style` object if parsing is successful, or `None` on error.
-///
-/// Errors:
-/// - Returns `None` on failure to parse the string.
-fn parse_style_string(s: &str) -> Option<Style> {
-    let s = s.trim().to_lowercase();
-    if let Some(rgb) = parse_hex_rgb(&s) {
-        return Some(Style::new().fg(rgb));
-    }
-    let mut st = Style::new();
-    for p in s.split('_') {
-        match p {
-            "bold" => st = st.bold(),
-            "underline" | "u" => st = st.underline(),
-            "reverse" | "r" => st = st.reverse(),
-            other => {
-                if let Some(c) = parse_color(other) {
-                    st = st.fg(c);
+    /// Renders the tree as a standalone HTML page (see [`HTML_HEAD`]).
+    ///
+    /// Directories are `<details>` elements that expand and collapse on click; only the
+    /// root starts open. Names carry the same 🪾/🍃 emoji as the tree view, followed by
+    /// their descriptions.
+    fn html(&self) -> String {
+        fn push(out: &mut String, ent: &TreeOut, open: bool) {
+            let desc = if ent.description.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    " <span class=\"desc\">— {}</span>",
+                    html_escape(&ent.description)
+                )
+            };
+            let name = html_escape(&ent.name);
+            match &ent.children {
+                Some(children) => {
+                    let open = if open { " open" } else { "" };
+                    out.push_str(&format!(
+                        "<li><details{open}><summary><span class=\"dir\">🪾 {name}/</span>{desc}</summary>\n<ul>\n"
+                    ));
+                    for child in children {
+                        push(out, child, false);
+                    }
+                    out.push_str("</ul></details></li>\n");
                 }
+                None => out.push_str(&format!("<li>🍃 {name}{desc}</li>\n")),
             }
         }
+
+        let mut out = format!(
+            "{HTML_HEAD}<title>{} — dirdocs</title>\n</head>\n<body>\n<ul>\n",
+            html_escape(&self.name)
+        );
+        push(&mut out, self, true);
+        out.push_str("</ul>\n</body>\n</html>\n");
+        out
     }
-    Some(st)
 }
 
-/// Parses a hex-encoded RGB color string and returns an optional `Color::Rgb`.
-///
-/// This function checks if the input starts with a '#' and has exactly 6 hex characters.
-/// It then parses each pair of digits as an 8-bit unsigned integer (0-255)
-/// and returns a `Color::Rgb` if successful.
-///
-/// Parameters:
-/// - `s`: A string slice representing the hex-encoded RGB color (e.g., "#FF0000").
-///
-/// Returns:
-/// - `Some(Color::Rgb(r, g, b))` if the input is valid.
-/// - `None` otherwise.
+/// Start of the page written by `--format html`, up to the tree's title; styles are inline
+/// so the file can be shared on its own.
+const HTML_HEAD: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<style>
+  body { font: 14px/1.5 ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; margin: 2em; color: #1f2328; }
+  ul { list-style: none; margin: 0; padding-left: 1.4em; border-left: 1px dotted #d0d7de; }
+  body > ul { border-left: none; padding-left: 0; }
+  summary { cursor: pointer; }
+  .dir { font-weight: 600; color: #0969da; }
+  .desc { color: #59636e; }
+  @media (prefers-color-scheme: dark) {
+    body { background: #0d1117; color: #e6edf3; }
+    .dir { color: #4493f8; }
+    .desc { color: #9198a1; }
+  }
+</style>
+"#;
+
+/// Escape text for HTML element content and attribute values.
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Quote a string for DOT, escaping quotes and backslashes and keeping line breaks.
+fn dot_string(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "");
+    format!("\"{escaped}\"")
+}
+
+/// Compiles `-P`/`-I` globs, splitting each on `|` like `tree` does.
 ///
 /// Errors:
-/// This function does not explicitly handle errors, but internally uses `.ok()?.` to propagate any
-/// parsing failures from `u8::from_str_radix`.
-///
-/// Notes:
-/// The function assumes a standard hex RGB format (e.g., "#RRGGBB").
-fn parse_hex_rgb(s: &str) -> Option<Color> {
-    if let Some(hex) = s.strip_prefix('#') {
-        if hex.len() == 6 {
-            let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
-            let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
-            let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
-            return Some(Color::Rgb(r, g, b));
-        }
-    }
-    None
-}
-
-/// Handles parsing of color names into the `Color` enum.
-///
-/// Converts string representations like "black", "red", or "light_blue" into the corresponding color variant.
-/// Supports both named colors (e.g., `Black`, `Red`) and RGB codes for "dark_gray", "light_black", etc.
-///
-/// # Parameters:
-/// - `name`: A string slice representing the color name to parse.
-///
-/// # Returns:
-/// - `Some(Color)` if the `name` matches a valid color variant,
-///   or `None` otherwise.
-///
-/// # Notes:
-/// - The function supports both named colors (e.g., "black") and RGB-based variants.
-///   - For example, `"dark_gray"` is parsed as `Color::Rgb(128, 128, 128)`.
-///   - `"light_red"` is parsed as `Color::Rgb(255, 167, 167)`.
-/// - Unrecognized names result in `None`.
-fn parse_color(name: &str) -> Option<Color> {
-    use Color::*;
-    Some(match name {
-        "black" => Black,
-        "red" => Red,
-        "green" => Green,
-        "yellow" => Yellow,
-        "blue" => Blue,
-        "purple" | "magenta" => Purple,
-        "cyan" => Cyan,
-        "white" => White,
-        "dark_gray" | "grey" | "gray" => Color::Rgb(128, 128, 128),
-        "light_black" => Color::Rgb(96, 96, 96),
-        "light_white" => Color::Rgb(240, 240, 240),
-        "light_red" => LightRed,
-        "light_green" => LightGreen,
-        "light_yellow" => LightYellow,
-        "light_blue" => LightBlue,
-        "light_purple" | "light_magenta" => LightPurple,
-        "light_cyan" => LightCyan,
-        _ => return None,
-    })
+/// - When a pattern isn't a valid glob.
+fn name_globs(patterns: &[String]) -> anyhow::Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns.iter().flat_map(|p| p.split('|')) {
+        let glob = Glob::new(pattern).with_context(|| format!("invalid pattern {pattern:?}"))?;
+        builder.add(glob);
+    }
+    Ok(Some(builder.build()?))
 }
 
 /// Handle the `dtree` command, which displays a tree-style view of directory contents along with file descriptions loaded from `.dirdocs.nuon` files.
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
+    if let Some(shell) = args.completions {
+        completions::print(shell, &mut Args::command());
+        return Ok(());
+    }
 
     let start = PathBuf::from(&args.directory)
         .canonicalize()
         .unwrap_or_else(|_| PathBuf::from(&args.directory));
 
-    // Colors on?
-    let color_on = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    // Colors on? Never in a file unless asked for
+    let to_terminal = args.output.is_none() && std::io::stdout().is_terminal();
+    let color_on = match args.color {
+        When::Always => true,
+        When::Never => false,
+        When::Auto => to_terminal && std::env::var_os("NO_COLOR").is_none(),
+    };
     let theme = if color_on {
-        try_load_nu_theme().unwrap_or_else(|| Theme::default_enabled(true))
+        try_load_nu_theme("dtree").unwrap_or_else(|| Theme::default_enabled(true))
     } else {
         Theme::default_enabled(false)
     };
     let ls_colors = if color_on { LsColors::from_env() } else { None };
 
-    // descriptions
-    let project_root = find_project_root(&start);
-    let desc_map = project_root
-        .as_ref()
-        .and_then(|r| load_descriptions(r).ok())
-        .unwrap_or_default();
+    let ignore: HashSet<String> = args.ignore.iter().cloned().collect();
+    let walk = Walk {
+        ignore: &ignore,
+        theme: &theme,
+        ls_colors: &ls_colors,
+        glyphs: glyphs(&args)?,
+        show_all: args.all,
+        gitignore: !args.no_gitignore,
+        dirs_only: args.dirs_only,
+        include: name_globs(&args.pattern)?,
+        exclude: name_globs(&args.exclude)?,
+        undocumented: args.undocumented,
+        verify: args.verify,
+        fun: args.fun,
+        max_depth: args.depth.map(usize::from),
+        file_limit: args.filelimit.map(|n| n as usize),
+        full_path: args.full_path,
+        sizes: args.size,
+        sort: args.sort,
+        reverse: args.reverse,
+        files_first: args.files_first,
+        dates: args.date,
+        no_report: args.noreport,
+        align: args.align,
+        wrap: args.wrap,
+        width: to_terminal
+            .then(terminal_size)
+            .flatten()
+            .map(|(TermWidth(w), _)| usize::from(w)),
+        follow_links: args.follow_symlinks,
+        hyperlinks: match args.hyperlink {
+            When::Always => true,
+            When::Never => false,
+            When::Auto => to_terminal && std::env::var("TERM").map_or(true, |t| t != "dumb"),
+        },
+    };
 
-    // ignore set
-    let ignore: HashSet<String> = args.ignore.into_iter().collect();
+    let mut root = match &args.bundle {
+        Some(bundle) => bundle_root(bundle, &args.directory, &walk)?,
+        None if args.cached => cached_root(&start, args.at.as_deref(), &walk)?,
+        None => disk_root(&start, args.at.as_deref(), &walk)?,
+    };
+    if walk.dirs_only {
+        strip_files(&mut root.children);
+    }
+
+    let out = match args.format {
+        Format::Tree => render_tree(&root, &walk),
+        Format::Markdown => markdown_tree(&root),
+        Format::Json => serde_json::to_string_pretty(&TreeOut::new(&root, "."))? + "\n",
+        Format::Nuon => TreeOut::new(&root, ".").nuon() + "\n",
+        Format::Dot => TreeOut::new(&root, ".").dot(),
+        Format::Html => TreeOut::new(&root, ".").html(),
+    };
+    match &args.output {
+        Some(path) => {
+            fs::write(path, out).with_context(|| format!("cannot write {}", path.display()))?
+        }
+        None => print!("{out}"),
+    }
+    Ok(())
+}
 
-    // --- Colored root label (basename, not full path) ---
-    let root_label = start
+/// Reads the tree below `start` from the filesystem, with its descriptions.
+///
+/// Parameters:
+/// - `start`: The directory to show.
+/// - `at`: Git ref to read descriptions from (`--at`), instead of the current cache.
+/// - `walk`: Filtering and depth options.
+///
+/// Returns:
+/// - An entry for `start` itself, named by its basename, holding the tree.
+///
+/// Errors:
+/// - When `--at` is given and the cache can't be read at that ref.
+///
+/// Notes:
+/// - An unreadable current cache is only a warning; the tree is shown without descriptions.
+fn disk_root(start: &Path, at: Option<&str>, walk: &Walk) -> anyhow::Result<TreeEntry> {
+    // descriptions
+    let (project_root, desc_map) = match at {
+        Some(git_ref) => {
+            let (root, cache) = read_cache_at(start, git_ref)
+                .with_context(|| format!("cannot read descriptions at {git_ref}"))?;
+            (Some(root), parse_descriptions(&cache, "dtree")?)
+        }
+        None => {
+            let project_root = find_project_root(start);
+            let desc_map = match project_root
+                .as_deref()
+                .map(|root| read_cache(root, |root| load_descriptions(root, "dtree")))
+            {
+                Some(Ok(map)) => map,
+                Some(Err(e)) => {
+                    eprintln!(
+                        "dtree: warning: could not read descriptions ({e}); listing without them"
+                    );
+                    HashMap::new()
+                }
+                None => HashMap::new(),
+            };
+            (project_root, desc_map)
+        }
+    };
+
+    // --- Root label (basename, not full path) ---
+    let name = start
         .file_name()
         .map(|s| s.to_string_lossy().to_string())
         .unwrap_or_else(|| start.display().to_string());
-    let root_meta = fs::metadata(&start).ok();
-    let root_colored = paint_name(
-        &root_label,
-        &start,
-        root_meta.as_ref(),
-        true,
-        &theme,
-        &ls_colors,
-    );
-    println!("{root_colored}");
+    let doc = match project_root.as_deref() {
+        Some(r) => desc_map.get(&rel_str(start, r)).cloned(),
+        None => xattr_info(start),
+    };
+    let description = doc
+        .as_ref()
+        .map(|d| d.description.clone())
+        .unwrap_or_default();
+    let (personality, joy) = walk.fun(doc.as_ref());
 
     // walk
-    print_tree_dir(
-        &start,
+    let ancestors = walk.enter(start, &[]).unwrap_or_default();
+    let children = read_tree(
+        start,
         project_root.as_deref(),
         &desc_map,
-        &ignore,
-        "",
-        &theme,
-        &ls_colors,
-        !args.boring,
-        args.all,
-    )?;
+        walk,
+        1,
+        &ancestors,
+    );
+    Ok(TreeEntry {
+        name,
+        path: start.to_path_buf(),
+        meta: fs::metadata(start).ok(),
+        is_dir: true,
+        description,
+        personality,
+        joy,
+        target: None,
+        size: walk.measures().then(|| total_size(&children)),
+        stale: None,
+        children,
+    })
+}
 
-    Ok(())
+/// Renders a tree-style view: the colored root line, then its entries with connectors.
+fn render_tree(root: &TreeEntry, walk: &Walk) -> String {
+    let root_colored = paint_name(
+        &root.name,
+        &root.path,
+        root.meta.as_ref(),
+        true,
+        walk.theme,
+        walk.ls_colors,
+    );
+    let details = details(root, walk);
+    let fun = fun(root);
+    let mut lines = vec![Line {
+        head: format!(
+            "{}{}{fun}",
+            walk.link(&root.path, root_colored),
+            dim(&details, walk)
+        ),
+        width: format!("{}{details}{fun}", root.name).width(),
+        guide: String::new(),
+        description: root.description.clone(),
+    }];
+    let mut report = Report::default();
+    tree_lines(&root.children, "", "", walk, &mut report, &mut lines);
+    let mut out = render_lines(&lines, walk);
+    if !walk.no_report {
+        out.push_str(&format!("\n{}\n", report.line(walk.dirs_only, walk.verify)));
+    }
+    out
+}
+
+/// What [`tree_lines`] laid out, for the closing line.
+#[derive(Default)]
+struct Report {
+    /// Directories printed.
+    dirs: usize,
+    /// Files printed.
+    files: usize,
+    /// Files printed with a description (directories with `--dirs-only`).
+    documented: usize,
+    /// Files marked as changed since they were described (`--verify`).
+    stale: usize,
+}
+
+impl Report {
+    /// The closing line, e.g. `3 directories, 12 files, 9 documented`, as `tree` prints it;
+    /// files are left out with `--dirs-only`, and `--verify` adds the stale count.
+    fn line(&self, dirs_only: bool, verify: bool) -> String {
+        let plural = |n: usize, one: &str, many: &str| {
+            if n == 1 {
+                format!("1 {one}")
+            } else {
+                format!("{n} {many}")
+            }
+        };
+        let mut parts = vec![plural(self.dirs, "directory", "directories")];
+        if !dirs_only {
+            parts.push(plural(self.files, "file", "files"));
+        }
+        parts.push(format!("{} documented", self.documented));
+        if verify {
+            parts.push(format!("{} stale", self.stale));
+        }
+        parts.join(", ")
+    }
 }
 
-/// Prints a tree-style view of the directory structure, with colored names and optional descriptions from `.dirdocs.nuon` files.
+/// Renders a tree as a markdown nested bullet list, e.g. for a README "project layout" section.
+///
+/// Each entry is a bullet with its name in a code span (directories end in `/`), followed by
+/// ` — description` when it has one; children are indented two spaces below their directory.
+fn markdown_tree(root: &TreeEntry) -> String {
+    fn push(out: &mut String, ent: &TreeEntry, indent: usize) {
+        let slash = if ent.is_dir { "/" } else { "" };
+        out.push_str(&format!("{:indent$}- `{}{slash}`", "", ent.name));
+        if let Some(target) = &ent.target {
+            out.push_str(&format!(" → `{}`", target.display()));
+        }
+        let description = ent
+            .description
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !description.is_empty() {
+            out.push_str(&format!(" — {description}"));
+        }
+        out.push('\n');
+        for child in &ent.children {
+            push(out, child, indent + 2);
+        }
+    }
+
+    let mut out = String::new();
+    push(&mut out, root, 0);
+    out
+}
+
+/// Reads the tree below `dir` from the filesystem, with descriptions from `.dirdocs.nuon` files attached.
 ///
 /// Parameters:
-/// - `dir`: The directory to start printing from.
+/// - `dir`: The directory whose entries are read.
 /// - `project_root`: Optional path to the project root (for relative description lookups).
 /// - `desc_map`: A map of file paths to their descriptions, loaded from `.dirdocs.nuon` files.
-/// - `ignore`: A set of directory names to ignore (case-sensitive).
-/// - `prefix`: The current indentation level for the tree.
-/// - `theme`: Custom styling configuration for colors and symbols.
-/// - `ls_colors`: Whether to use LS_COLORS environment variable for colorization (if enabled).
-/// - `emoji_mode`: Whether to use emoji-based connectors instead of standard tree symbols.
-/// - `show_all`: If true, show hidden files (dotfiles).
+/// - `walk`: Filtering, styling and depth options shared by every level.
+/// - `depth`: The level of `dir`'s entries (1 for the start directory).
+/// - `ancestors`: With `--follow-symlinks`, the canonical paths of `dir` and the directories
+///   above it, from [`Walk::enter`].
 ///
 /// Returns:
-/// - `Ok(())` on success.
-///
-/// Errors:
-/// - I/O errors when reading/writing files or directory entries.
-/// - Deserialization errors from `.dirdocs.nuon` files.
-/// - Errors when resolving or applying custom themes.
+/// - The directory's entries, sorted and filtered, with subdirectories filled in down to the
+///   depth limit.
 ///
 /// Notes:
-/// - The tree is printed recursively, with directory structures showing under their parent.
-/// - Descriptions from `.dirdocs.nuon` are added if available, with emoji-based connector support.
-/// - The `prefix` is built incrementally to reflect directory depth, with `├──`, `└──`, or emoji-based symbols.
-fn print_tree_dir(
+/// - Without a project root, descriptions are read from extended attributes instead.
+fn read_tree(
     dir: &Path,
     project_root: Option<&Path>,
     desc_map: &HashMap<String, FileDocInfo>,
-    ignore: &HashSet<String>,
-    prefix: &str,
-    theme: &Theme,
-    ls_colors: &Option<LsColors>,
-    emoji_mode: bool,
-    show_all: bool,
-) -> anyhow::Result<()> {
+    walk: &Walk,
+    depth: usize,
+    ancestors: &[PathBuf],
+) -> Vec<TreeEntry> {
     // --- list immediate children honoring .gitignore + globals + hidden + user ignore ---
-    let mut entries = list_children(dir, show_all, ignore);
-
-    // sort: dirs first, then case-insensitive name
-    entries.sort_by(|a, b| match (a.is_dir, b.is_dir) {
-        (true, false) => std::cmp::Ordering::Less,
-        (false, true) => std::cmp::Ordering::Greater,
-        _ => a.name_lower.cmp(&b.name_lower),
-    });
-
-    let last_idx = entries.len().saturating_sub(1);
-
-    for (i, ent) in entries.into_iter().enumerate() {
-        let is_last = i == last_idx;
-        let path = ent.path;
-        let meta = ent.meta;
-        let is_dir = ent.is_dir;
-
-        // connectors
-        let (connector, next_prefix) = if emoji_mode {
-            if is_last {
-                (if is_dir { "🪾 " } else { "🍃 " }, format!("{prefix}   "))
-            } else {
-                (if is_dir { "🪾 " } else { "🍃 " }, format!("{prefix}🪾  "))
-            }
-        } else {
-            if is_last {
-                ("└── ", format!("{prefix}    "))
-            } else {
-                ("├── ", format!("{prefix}│   "))
-            }
-        };
-
-        // name (colorized)
-        let colored_name = paint_name(&ent.name, &path, meta.as_ref(), is_dir, theme, ls_colors);
+    let mut entries = Vec::new();
+    for ent in list_children(dir, walk) {
+        if !walk.shows(&ent.name, ent.is_dir) {
+            continue;
+        }
 
         // description
         let rel_key = match project_root {
-            Some(root) => rel_str(&path, root),
-            None => rel_str(&path, dir),
+            Some(root) => rel_str(&ent.path, root),
+            None => rel_str(&ent.path, dir),
         };
-        let desc = desc_map
-            .get(&rel_key)
-            .map(|d| d.description.as_str())
-            .unwrap_or("");
+        // Without a cache file, fall back to descriptions stored in extended attributes
+        let doc = match desc_map.get(&rel_key) {
+            Some(d) => Some(d.clone()),
+            None if project_root.is_none() => xattr_info(&ent.path),
+            None => None,
+        };
+        let description = doc
+            .as_ref()
+            .map(|d| d.description.clone())
+            .unwrap_or_default();
+        if !walk.keeps(ent.is_dir, &description) {
+            continue;
+        }
 
-        if desc.is_empty() {
-            println!("{prefix}{connector}{colored_name}");
+        let chain = (ent.is_dir && walk.descends(depth))
+            .then(|| walk.enter(&ent.path, ancestors))
+            .flatten();
+        let children = match chain {
+            Some(chain) => read_tree(&ent.path, project_root, desc_map, walk, depth + 1, &chain),
+            None => Vec::new(),
+        };
+        let size = if !ent.is_dir {
+            ent.meta.as_ref().map(|m| m.len())
+        } else if !walk.measures() {
+            None
+        } else if walk.descends(depth) {
+            Some(total_size(&children))
         } else {
-            println!("{prefix}{connector}{colored_name} — {desc}");
+            // Directories at the depth limit weren't read, so they're measured separately
+            Some(disk_usage(&ent.path, walk, ancestors))
+        };
+        let (personality, joy) = walk.fun(doc.as_ref());
+        entries.push(TreeEntry {
+            name: ent.name,
+            path: ent.path,
+            meta: ent.meta,
+            is_dir: ent.is_dir,
+            description,
+            personality,
+            joy,
+            target: ent.target,
+            size,
+            stale: walk.stale(doc.as_ref(), &ent.path),
+            children,
+        });
+    }
+    walk.finish(entries, depth)
+}
+
+/// One line of the tree view, before its description is placed.
+struct Line {
+    /// Guides, connector, colored name and `--size`/`--date` details.
+    head: String,
+    /// Display width of `head` without color codes.
+    width: usize,
+    /// Guides continuing below the line, put before wrapped description lines.
+    guide: String,
+    /// Description, empty when there is none.
+    description: String,
+}
+
+/// Lays out tree entries with their connectors, colored names and descriptions.
+///
+/// Parameters:
+/// - `entries`: One directory's entries, as returned by [`read_tree`] or [`bundle_tree`].
+/// - `prefix`: The current indentation level for the tree.
+/// - `rel`: The directory's path relative to the start directory (empty for the start
+///   directory itself), printed before names with `--full-path`.
+/// - `walk`: Styling options.
+/// - `report`: Counts of what was laid out, updated along the way.
+/// - `lines`: Receives one line per entry, in tree order.
+///
+/// Notes:
+/// - The `prefix` is built incrementally to reflect directory depth, from the [`Glyphs`] in `walk`.
+/// - With `--filelimit`, entries past the limit are left out (and not counted in `report`),
+///   replaced by a closing `… and N more files` line.
+fn tree_lines(
+    entries: &[TreeEntry],
+    prefix: &str,
+    rel: &str,
+    walk: &Walk,
+    report: &mut Report,
+    lines: &mut Vec<Line>,
+) {
+    let shown = walk
+        .file_limit
+        .map_or(entries.len(), |n| n.min(entries.len()));
+    let (entries, omitted) = entries.split_at(shown);
+    // The summary line, when there is one, closes the directory
+    let last_idx = if omitted.is_empty() {
+        entries.len().saturating_sub(1)
+    } else {
+        entries.len()
+    };
+    for (i, ent) in entries.iter().enumerate() {
+        let (connector, next_prefix) = connectors(prefix, i == last_idx, ent.is_dir, &walk.glyphs);
+        let path = if rel.is_empty() {
+            ent.name.clone()
+        } else {
+            format!("{rel}/{}", ent.name)
+        };
+        let name = if walk.full_path { &path } else { &ent.name };
+        let colored_name = paint_name(
+            name,
+            &ent.path,
+            ent.meta.as_ref(),
+            ent.is_dir,
+            walk.theme,
+            walk.ls_colors,
+        );
+        let details = details(ent, walk);
+        let fun = fun(ent);
+        let mark = if ent.stale == Some(true) { " *" } else { "" };
+        let target = match &ent.target {
+            Some(t) => format!(" -> {}", t.display()),
+            None => String::new(),
+        };
+        // Wrapped descriptions keep the guide down to the entry's first child
+        let bar = if ent.children.is_empty() {
+            ""
+        } else {
+            walk.glyphs.pipe.trim_end()
+        };
+        lines.push(Line {
+            head: format!(
+                "{prefix}{connector}{}{target}{}{}{fun}",
+                walk.link(&ent.path, colored_name),
+                stale_mark(mark, walk),
+                dim(&details, walk)
+            ),
+            width: format!("{prefix}{connector}{name}{target}{mark}{details}{fun}").width(),
+            guide: format!("{next_prefix}{bar}"),
+            description: ent.description.clone(),
+        });
+        if ent.is_dir {
+            report.dirs += 1;
+        } else {
+            report.files += 1;
+        }
+        if !ent.description.is_empty() && ent.is_dir == walk.dirs_only {
+            report.documented += 1;
+        }
+        if ent.stale == Some(true) {
+            report.stale += 1;
         }
+        tree_lines(&ent.children, &next_prefix, &path, walk, report, lines);
+    }
+    if !omitted.is_empty() {
+        let more = more_entries(omitted);
+        lines.push(Line {
+            head: format!("{prefix}{}{}", walk.glyphs.last, dim(&more, walk)),
+            width: format!("{prefix}{}{more}", walk.glyphs.last).width(),
+            guide: String::new(),
+            description: String::new(),
+        });
+    }
+}
 
-        if is_dir {
-            print_tree_dir(
-                &path,
-                project_root,
-                desc_map,
-                ignore,
-                &next_prefix,
-                theme,
-                ls_colors,
-                emoji_mode,
-                show_all,
-            )?;
+/// The `--filelimit` summary of the entries left out of a directory, e.g.
+/// `… and 342 more files`; `directories` or `entries` when they aren't all files.
+fn more_entries(omitted: &[TreeEntry]) -> String {
+    let dirs = omitted.iter().filter(|e| e.is_dir).count();
+    let kind = match (dirs, omitted.len()) {
+        (0, 1) => "file",
+        (0, _) => "files",
+        (1, 1) => "directory",
+        (d, n) if d == n => "directories",
+        (_, _) => "entries",
+    };
+    format!("… and {} more {kind}", omitted.len())
+}
+
+/// Renders laid-out lines, each description after an em dash.
+///
+/// With `--align`, descriptions start at a common column: one past the widest line with a
+/// description, capped so that at least [`MIN_DESCRIPTION_WIDTH`] columns are left for them
+/// in a terminal. Lines too wide for the column keep their description right after the name.
+///
+/// In a terminal, descriptions that would run past its edge are cut short with `…`, or with
+/// `--wrap` continue on the following lines, under the guides of the tree and indented to
+/// where the description started. Piped output is left as is.
+fn render_lines(lines: &[Line], walk: &Walk) -> String {
+    let mut out = String::new();
+    let column = walk
+        .align
+        .then(|| {
+            lines
+                .iter()
+                .filter(|l| !l.description.is_empty())
+                .map(|l| l.width)
+                .max()
+        })
+        .flatten()
+        .map(|col| match walk.width {
+            Some(w) => col.min(w.saturating_sub(MIN_DESCRIPTION_WIDTH + 3)),
+            None => col,
+        });
+
+    for line in lines {
+        if line.description.is_empty() {
+            out.push_str(&format!("{}\n", line.head));
+            continue;
+        }
+        let start = column.filter(|c| line.width <= *c).unwrap_or(line.width);
+        let room = walk
+            .width
+            .map(|w| w.saturating_sub(start + 3).max(MIN_DESCRIPTION_WIDTH));
+        let rows = match room {
+            Some(room) if walk.wrap => wrap(&line.description, room),
+            Some(room) => vec![truncate(&line.description, room)],
+            None => vec![line.description.clone()],
+        };
+        let mut rows = rows.into_iter();
+        out.push_str(&format!(
+            "{}{} — {}\n",
+            line.head,
+            " ".repeat(start - line.width),
+            rows.next().unwrap_or_default()
+        ));
+        let pad = start.saturating_sub(line.guide.width());
+        for row in rows {
+            out.push_str(&format!("{}{}   {row}\n", line.guide, " ".repeat(pad)));
         }
     }
+    out
+}
 
-    Ok(())
+/// Splits `text` at spaces into lines at most `width` columns wide (a longer word gets a
+/// line of its own).
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut rows = Vec::new();
+    let mut row = String::new();
+    for word in text.split_whitespace() {
+        if !row.is_empty() && row.width() + 1 + word.width() > width {
+            rows.push(std::mem::take(&mut row));
+        }
+        if !row.is_empty() {
+            row.push(' ');
+        }
+        row.push_str(word);
+    }
+    rows.push(row);
+    rows
+}
+
+/// `text` cut to at most `width` columns, ending in `…` when something was cut.
+fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        out.push(c);
+        used += w;
+    }
+    out.push('…');
+    out
+}
+
+/// The `--size`/`--date` annotation printed after a name, e.g. ` (12.3 kB, 3 days ago)`;
+/// empty when neither is asked for or nothing is known.
+fn details(ent: &TreeEntry, walk: &Walk) -> String {
+    let mut parts = Vec::new();
+    if walk.sizes {
+        parts.extend(ent.size.map(|n| format_size(n, DECIMAL)));
+    }
+    if walk.dates {
+        let modified = ent.meta.as_ref().and_then(|m| m.modified().ok());
+        parts.extend(modified.map(|t| humanize(Some(t.into()))));
+    }
+    if parts.is_empty() {
+        return String::new();
+    }
+    format!(" ({})", parts.join(", "))
+}
+
+/// The `--fun` annotation printed after a name, e.g. ` 🦀 joy 8`; empty when the entry has
+/// neither a personality emoji nor a joy rating.
+fn fun(ent: &TreeEntry) -> String {
+    let mut out = String::new();
+    if !ent.personality.is_empty() {
+        out.push(' ');
+        out.push_str(&as_emoji_presentation(&ent.personality));
+    }
+    if !ent.joy.is_empty() {
+        out.push_str(&format!(" joy {}", ent.joy));
+    }
+    out
+}
+
+/// The `--verify` marker of a changed file, yellow when colors are on.
+fn stale_mark(mark: &str, walk: &Walk) -> String {
+    if walk.theme.enabled && !mark.is_empty() {
+        Color::Yellow.bold().paint(mark).to_string()
+    } else {
+        mark.to_string()
+    }
+}
+
+/// `text` dimmed when colors are on.
+fn dim(text: &str, walk: &Walk) -> String {
+    if walk.theme.enabled && !text.is_empty() {
+        Style::new().dimmed().paint(text).to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+/// Total size of a directory's entries (directories count with their own totals).
+fn total_size(entries: &[TreeEntry]) -> u64 {
+    entries.iter().filter_map(|e| e.size).sum()
+}
+
+/// Total size of the files below `dir` that the tree would list, read from disk.
+///
+/// `ancestors` are the canonical paths of the directories above `dir`, as for [`read_tree`].
+fn disk_usage(dir: &Path, walk: &Walk, ancestors: &[PathBuf]) -> u64 {
+    let Some(chain) = walk.enter(dir, ancestors) else {
+        return 0;
+    };
+    list_children(dir, walk)
+        .into_iter()
+        .filter(|c| walk.shows(&c.name, c.is_dir))
+        .map(|c| match c.meta {
+            _ if c.is_dir => disk_usage(&c.path, walk, &chain),
+            Some(m) => m.len(),
+            None => 0,
+        })
+        .sum()
+}
+
+/// Leaves only directories in a tree, for `--dirs-only`.
+///
+/// This runs after the tree is built so that directories holding only files matched by `-P`
+/// aren't pruned as empty.
+fn strip_files(entries: &mut Vec<TreeEntry>) {
+    entries.retain(|e| e.is_dir);
+    for ent in entries {
+        strip_files(&mut ent.children);
+    }
+}
+
+/// The connector drawn before an entry and the prefix for its children.
+///
+/// Parameters:
+/// - `prefix`: The indentation of the current level.
+/// - `is_last`: Whether the entry is the last one in its directory.
+/// - `is_dir`: Whether the entry is a directory.
+/// - `glyphs`: The connector set to draw with.
+fn connectors(prefix: &str, is_last: bool, is_dir: bool, glyphs: &Glyphs) -> (String, String) {
+    let (lead, guide) = if is_last {
+        (&glyphs.last, &glyphs.indent)
+    } else {
+        (&glyphs.branch, &glyphs.pipe)
+    };
+    let kind = if is_dir { &glyphs.dir } else { &glyphs.file };
+    (format!("{lead}{kind}"), format!("{prefix}{guide}"))
+}
+
+/// Reads the tree stored in a `dirdocs bundle` file, without touching the filesystem.
+///
+/// Parameters:
+/// - `bundle`: The bundle file.
+/// - `start`: Directory inside the bundle to show (`.` for the bundled root).
+/// - `walk`: Filtering and depth options.
+///
+/// Returns:
+/// - An entry for `start`, named by its bundle path (the bundle's root name for `.`).
+///
+/// Errors:
+/// - When the bundle can't be read, or `start` is not a directory inside it.
+fn bundle_root(bundle: &Path, start: &str, walk: &Walk) -> anyhow::Result<TreeEntry> {
+    let cache = read_bundle(bundle)?;
+    let desc_map = parse_descriptions(&cache, "dtree")?;
+    let parsed: DirdocsRoot = serde_json::from_str(&cache)?;
+
+    let rel = match start.trim_start_matches("./").trim_end_matches('/') {
+        "." => "",
+        rel => rel,
+    };
+    let nodes = bundle_dir(&parsed.entries, rel)
+        .with_context(|| format!("{rel} is not a directory in {}", bundle.display()))?;
+
+    let name = if rel.is_empty() {
+        parsed.root.clone()
+    } else {
+        rel.to_string()
+    };
+    let (personality, joy) = walk.fun(desc_map.get(rel));
+    Ok(TreeEntry {
+        path: PathBuf::from(&name),
+        name,
+        meta: None,
+        is_dir: true,
+        description: desc_map
+            .get(rel)
+            .map(|d| d.description.clone())
+            .unwrap_or_default(),
+        personality,
+        joy,
+        target: None,
+        // Bundles don't record sizes
+        size: None,
+        stale: None,
+        children: bundle_tree(nodes, rel, &desc_map, None, walk, 1),
+    })
+}
+
+/// Reads the tree recorded in the cache of the project containing `start`, for `--cached`,
+/// without walking the filesystem.
+///
+/// Parameters:
+/// - `start`: The directory to show.
+/// - `at`: `--at`; read the tree committed at this git ref instead.
+/// - `walk`: Filtering and depth options.
+///
+/// Returns:
+/// - An entry for `start`, named by its basename, holding what the cache records below it
+///   (including files deleted since).
+///
+/// Errors:
+/// - When `start` isn't inside a documented project, its cache can't be read, or the cache
+///   has no directory at `start`.
+///
+/// Notes:
+/// - `.dirdocs.db` records only files, so directories are rebuilt from their paths.
+fn cached_root(start: &Path, at: Option<&str>, walk: &Walk) -> anyhow::Result<TreeEntry> {
+    let (root, tree, desc_map) = match at {
+        Some(git_ref) => {
+            let (root, cache) = read_cache_at(start, git_ref)
+                .with_context(|| format!("cannot read descriptions at {git_ref}"))?;
+            let tree: DirdocsRoot = serde_json::from_str(&cache)?;
+            (root, tree.entries, parse_descriptions(&cache, "dtree")?)
+        }
+        None => {
+            let root = find_project_root(start).with_context(|| {
+                format!(
+                    "no .dirdocs.nuon or .dirdocs.db in or above {}",
+                    start.display()
+                )
+            })?;
+            let (tree, desc_map) = read_cache(&root, |root| {
+                if prefer_db(root) {
+                    let desc_map = load_descriptions_db(&root.join(".dirdocs.db"), "dtree")?;
+                    return Ok((tree_from_paths(desc_map.keys()), desc_map));
+                }
+                let cache = fs::read_to_string(root.join(".dirdocs.nuon"))?;
+                let tree: DirdocsRoot = serde_json::from_str(&cache)?;
+                Ok((tree.entries, parse_descriptions(&cache, "dtree")?))
+            })?;
+            (root, tree, desc_map)
+        }
+    };
+
+    let rel = rel_str(start, &root).replace('\\', "/");
+    let nodes = bundle_dir(&tree, &rel).with_context(|| {
+        format!(
+            "{} is not in the cache of {}",
+            start.display(),
+            root.display()
+        )
+    })?;
+    let name = start
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| start.display().to_string());
+    let children = bundle_tree(nodes, &rel, &desc_map, Some(&root), walk, 1);
+    let (personality, joy) = walk.fun(desc_map.get(&rel));
+    Ok(TreeEntry {
+        name,
+        path: start.to_path_buf(),
+        meta: fs::metadata(start).ok(),
+        is_dir: true,
+        description: desc_map
+            .get(&rel)
+            .map(|d| d.description.clone())
+            .unwrap_or_default(),
+        personality,
+        joy,
+        target: None,
+        size: walk.measures().then(|| total_size(&children)),
+        stale: None,
+        children,
+    })
+}
+
+/// The entries of the directory at `rel` (slash-separated, empty for the root) in a bundle tree.
+fn bundle_dir<'a>(nodes: &'a [Node], rel: &str) -> Option<&'a [Node]> {
+    let mut cur = nodes;
+    for name in rel.split('/').filter(|c| !c.is_empty()) {
+        cur = cur.iter().find_map(|n| match n {
+            Node::Dir(d) if d.name == name => Some(d.entries.as_slice()),
+            _ => None,
+        })?;
+    }
+    Some(cur)
+}
+
+/// Builds one directory level of a bundle or cache tree, recursing into subdirectories.
+///
+/// Parameters:
+/// - `nodes`: The directory's entries.
+/// - `rel`: The directory's path inside the tree (empty for the root).
+/// - `desc_map`: Descriptions keyed by tree-relative path.
+/// - `disk`: For `--cached`, the project root; files that still exist there are stat'ed
+///   for `--size`, `--date` and LS_COLORS. `None` for bundles.
+/// - `walk`, `depth`: As for [`read_tree`].
+///
+/// Returns:
+/// - The directory's entries; for bundles, with their bundle-relative paths in place of
+///   filesystem paths.
+fn bundle_tree(
+    nodes: &[Node],
+    rel: &str,
+    desc_map: &HashMap<String, FileDocInfo>,
+    disk: Option<&Path>,
+    walk: &Walk,
+    depth: usize,
+) -> Vec<TreeEntry> {
+    let mut entries = Vec::new();
+    for node in nodes {
+        let name = match node {
+            Node::Dir(d) => d.name.clone(),
+            Node::File(f) => match Path::new(&f.path).file_name() {
+                Some(s) => s.to_string_lossy().to_string(),
+                None => continue,
+            },
+            Node::Unknown => continue,
+        };
+        let is_dir = matches!(node, Node::Dir(_));
+        if (!walk.show_all && name.starts_with('.'))
+            || (is_dir && walk.ignore.contains(&name))
+            || !walk.shows(&name, is_dir)
+        {
+            continue;
+        }
+
+        let key = if rel.is_empty() {
+            name.clone()
+        } else {
+            format!("{rel}/{name}")
+        };
+        let doc = desc_map.get(&key);
+        let description = doc.map(|d| d.description.clone()).unwrap_or_default();
+        if !walk.keeps(is_dir, &description) {
+            continue;
+        }
+        let children = match node {
+            Node::Dir(d) if walk.descends(depth) => {
+                bundle_tree(&d.entries, &key, desc_map, disk, walk, depth + 1)
+            }
+            _ => Vec::new(),
+        };
+        let path = match disk {
+            Some(root) => root.join(&key),
+            None => PathBuf::from(key),
+        };
+        let meta = disk.and_then(|_| fs::metadata(&path).ok());
+        let size = match &meta {
+            _ if is_dir && walk.measures() && disk.is_some() && walk.descends(depth) => {
+                Some(total_size(&children))
+            }
+            Some(m) if !is_dir => Some(m.len()),
+            _ => None,
+        };
+        let stale = match &meta {
+            Some(m) if m.is_file() => walk.stale(doc, &path),
+            _ => None,
+        };
+        let (personality, joy) = walk.fun(doc);
+        entries.push(TreeEntry {
+            name,
+            description,
+            personality,
+            joy,
+            path,
+            meta,
+            is_dir,
+            target: None,
+            size,
+            stale,
+            children,
+        });
+    }
+    walk.finish(entries, depth)
 }
 
 /// Represents a single node in the directory tree, containing metadata and path information.
@@ -518,20 +1658,21 @@ struct Child {
     path: PathBuf,
     /// The name of the file or directory, without the extension.
     name: String,
-    /// The name of the file or directory in lowercase for sorting purposes.
-    name_lower: String,
     /// Whether the path points to a directory or file.
     is_dir: bool,
-    /// Metadata about the path, if available.
+    /// Metadata about the path, if available (of the link itself for symlinks not followed).
     meta: Option<fs::Metadata>,
+    /// Where the path points, when it's a symlink.
+    target: Option<PathBuf>,
 }
 
 /// This function lists children of a directory, including files and subdirectories.
 ///
 /// Parameters:
 /// - `dir`: The path to the directory whose children are being listed.
-/// - `show_all`: If true, do not skip hidden files; otherwise, hide non-user-writable entries.
-/// - `ignore_names`: A set of names to skip when listing children (directories only).
+/// - `walk`: Its `show_all` keeps hidden files, `gitignore` leaves out what git ignores,
+///   `ignore` names directories to skip and `follow_links` treats symlinks to directories
+///   as directories.
 ///
 /// Returns:
 /// - `Vec<Child>`: A list of child entries representing files and directories.
@@ -539,13 +1680,13 @@ struct Child {
 /// Notes:
 /// - This function constructs a walk of the directory tree with specified options and filters out ignored names.
 /// - It handles both file metadata and directory existence checks to ensure accurate results.
-fn list_children(dir: &Path, show_all: bool, ignore_names: &HashSet<String>) -> Vec<Child> {
+fn list_children(dir: &Path, walk: &Walk) -> Vec<Child> {
     let mut wb = WalkBuilder::new(dir);
     wb.max_depth(Some(1))
-        .git_ignore(true)
-        .git_exclude(true)
-        .git_global(true)
-        .hidden(!show_all);
+        .git_ignore(walk.gitignore)
+        .git_exclude(walk.gitignore)
+        .git_global(walk.gitignore)
+        .hidden(!walk.show_all);
 
     let mut out: Vec<Child> = Vec::new();
 
@@ -553,31 +1694,37 @@ fn list_children(dir: &Path, show_all: bool, ignore_names: &HashSet<String>) ->
         if dent.depth() == 0 {
             continue; // skip the dir itself
         }
-        // Skip user-named ignores (directories only)
-        if let Some(name) = dent.file_name().to_str() {
-            if dent.file_type().map(|ft| ft.is_dir()).unwrap_or(false)
-                && ignore_names.contains(name)
-            {
-                continue;
-            }
-        }
-
         let path = dent.path().to_path_buf();
         let name = dent.file_name().to_string_lossy().to_string();
+        let target = dent
+            .path_is_symlink()
+            .then(|| fs::read_link(&path).ok())
+            .flatten();
 
-        // pull metadata (best effort)
-        let meta = dent.metadata().ok().or_else(|| fs::metadata(&path).ok());
-        let is_dir = dent
-            .file_type()
-            .map(|ft| ft.is_dir())
-            .unwrap_or_else(|| meta.as_ref().map(|m| m.is_dir()).unwrap_or(false));
+        // pull metadata (best effort); followed links report their target
+        let meta = match target {
+            Some(_) if walk.follow_links => fs::metadata(&path).ok(),
+            _ => dent.metadata().ok().or_else(|| fs::metadata(&path).ok()),
+        };
+        let is_dir = match target {
+            Some(_) if walk.follow_links => meta.as_ref().is_some_and(|m| m.is_dir()),
+            _ => dent
+                .file_type()
+                .map(|ft| ft.is_dir())
+                .unwrap_or_else(|| meta.as_ref().map(|m| m.is_dir()).unwrap_or(false)),
+        };
+
+        // Skip user-named ignores (directories only)
+        if is_dir && walk.ignore.contains(&name) {
+            continue;
+        }
 
         out.push(Child {
-            name_lower: name.to_lowercase(),
             name,
             path,
             is_dir,
             meta,
+            target,
         });
     }
 
@@ -633,111 +1780,3 @@ fn paint_name(
         name.to_string()
     }
 }
-
-/// Finds the project root by searching for a `.dirdocs.nuon` file starting from the given directory.
-///
-/// This function traverses up the directory hierarchy, checking for a `.dirdocs.nuon` file in each
-/// parent directory. If found, it returns the path to that root directory as a `PathBuf`; otherwise,
-/// it returns `None`.
-///
-/// Parameters:
-/// - `start`: A reference to the starting directory path.
-///
-/// Returns:
-/// - An `Option<PathBuf>` representing the project root, or `None` if no `.dirdocs.nuon` file is found.
-///
-/// Errors:
-/// - This function does not return explicit error values, but may panic if `cur.parent()?` fails
-///   (e.g., when the starting path is already at the root).
-///
-/// Notes:
-/// - The search continues upward until either a `.dirdocs.nuon` file is found or the root of the filesystem
-///   is reached.
-fn find_project_root(start: &Path) -> Option<PathBuf> {
-    let mut cur = start.to_path_buf();
-    loop {
-        if cur.join(".dirdocs.nuon").exists() {
-            return Some(cur);
-        }
-        let parent = cur.parent()?.to_path_buf();
-        if parent == cur {
-            return None;
-        }
-        cur = parent;
-    }
-}
-
-/// Load description files from a diredocs root.
-///
-/// Parses (`root.join(".dirdocs.nuon")`) to get a root diredocs tree,
-/// and recursively visits nodes to collect file descriptions.
-/// Each `Node::File`'s description is stored in a map with the full path.
-/// Returns an error if reading or parsing fails.
-///
-/// Parameters:
-/// - `root`: Path to diredocs root directory.
-///
-/// Returns:
-/// A `Result<HashMap<String, FileDocInfo>>` mapping file paths to their description info, or an error.
-///
-/// Errors:
-/// - I/O errors when reading files,
-/// - JSON parsing errors from `serde_json`,
-/// - or invalid diredocs structure.
-fn load_descriptions(root: &Path) -> anyhow::Result<HashMap<String, FileDocInfo>> {
-    let mut map: HashMap<String, FileDocInfo> = HashMap::new();
-    let s = fs::read_to_string(root.join(".dirdocs.nuon"))?;
-    let parsed: DirdocsRoot = serde_json::from_str(&s)?;
-
-    /// Recursively visits all nodes in a directory structure, collecting documentation info.
-    ///
-    /// Parameters:
-    /// - `nodes`: A slice of nodes to visit (typically from a directory tree).
-    /// - `out`: A mutable reference to a hash map storing file documentation info.
-    ///
-    /// Returns:
-    /// - None
-    fn visit(nodes: &[Node], out: &mut HashMap<String, FileDocInfo>) {
-        for n in nodes {
-            match n {
-                Node::Dir(d) => visit(&d.entries, out),
-                Node::File(f) => {
-                    let desc = f.doc.fileDescription.trim().to_string();
-                    if !desc.is_empty() {
-                        out.insert(f.path.clone(), FileDocInfo { description: desc });
-                    }
-                }
-            }
-        }
-    }
-
-    visit(&parsed.entries, &mut map);
-    Ok(map)
-}
-
-/// Handle a path relative to an anchor point, returning it as a string.
-/// This function computes the relative path between `p` and `base`, using the
-/// `pathdiff::diff_paths` crate to determine it. If no relative path is found,
-/// the original `p` path is returned instead.
-///
-/// Parameters:
-/// - `p`: The absolute path to compute the relative of.
-/// - `base`: The base path used for computing the relative path.
-///
-/// Returns:
-/// A string representing the relative path from `base` to `p`.
-///
-/// Errors:
-/// This function does not return explicit errors. It handles all failures internally
-/// via the `.unwrap_or_else()` chain, which returns `p` itself if no relative path
-/// can be computed.
-///
-/// Notes:
-/// This function is a convenience wrapper around `pathdiff::diff_paths`, with
-/// fallback behavior to return the original path if no relative path is found.
-fn rel_str(p: &Path, base: &Path) -> String {
-    pathdiff::diff_paths(p, base)
-        .unwrap_or_else(|| p.to_path_buf())
-        .to_string_lossy()
-        .into()
-}
@@ -0,0 +1,212 @@
+use crate::cache::{
+    CHILD_CACHE_NAMES, CacheBackend, index_dir_docs, index_files_by_path, insert_file_into_tree,
+    load_existing_tree, load_tree, rebase_child_tree_into_existing_by_path, restore_dir_docs,
+};
+use crate::gc::SIDECAR_DIR;
+use crate::types::{DirdocsRoot, Doc, FileEntry, SCHEMA_VERSION};
+use chrono::{DateTime, Utc};
+use ignore::WalkBuilder;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Value of a bundle's `format` field, which tells bundles apart from plain caches.
+pub(crate) const BUNDLE_FORMAT: &str = "dirdocs-bundle";
+
+/// Newest bundle layout this build writes and reads.
+pub(crate) const BUNDLE_VERSION: u32 = 1;
+
+/// Suffix of the default bundle file name (`<root name>.dirdocs-bundle.json`).
+pub(crate) const BUNDLE_SUFFIX: &str = ".dirdocs-bundle.json";
+
+/// One documented root (the bundle root or a child cache) whose entries went into a bundle.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct BundledProject {
+    /// Directory of the cache, relative to the bundle root (`.` for the root itself).
+    pub path: String,
+    /// The project description stored in that cache.
+    pub doc: Doc,
+    /// Files the cache contributed.
+    pub files: usize,
+}
+
+/// Every cache under a root merged into one self-contained document.
+///
+/// The tree fields are flattened into the top level, so a bundle parses as an ordinary
+/// `.dirdocs.nuon` tree whose paths are relative to the bundle root.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Bundle {
+    /// Always [`BUNDLE_FORMAT`].
+    pub format: String,
+    /// Layout version ([`BUNDLE_VERSION`]).
+    pub bundle_version: u32,
+    /// Version of dirdocs that wrote the bundle.
+    pub dirdocs_version: String,
+    /// When the bundle was written.
+    pub created_at: DateTime<Utc>,
+    /// The caches that were merged, outermost first.
+    pub projects: Vec<BundledProject>,
+    /// The merged documentation tree.
+    #[serde(flatten)]
+    pub tree: DirdocsRoot,
+}
+
+/// Collect the cache at `root` and every cache nested below it into one bundle.
+///
+/// Nested roots are directories holding a `.dirdocs.nuon`/`.dirdocs.db` of their own or a
+/// child cache (`.dirdocs.nu`, `.dir.nuon`). When two caches describe the same file, the
+/// innermost one wins, since it belongs to the run that documented that file directly.
+///
+/// Parameters:
+/// - `root`: The (canonicalized) directory to bundle.
+/// - `cwd`: Current working directory, used when a cache has to be rebuilt from scratch.
+///
+/// Returns:
+/// - The bundle; its `root` is the directory's name so it carries no local paths.
+///
+/// Errors:
+/// - When no cache exists at or below `root`.
+pub(crate) fn build_bundle(root: &Path, cwd: &Path) -> anyhow::Result<Bundle> {
+    let roots = find_cache_roots(root);
+    if roots.is_empty() {
+        anyhow::bail!(
+            "no dirdocs caches in or below {}; run `dirdocs run` first",
+            root.display()
+        );
+    }
+
+    let mut by_path: HashMap<String, FileEntry> = HashMap::new();
+    let mut dir_docs: HashMap<String, Doc> = HashMap::new();
+    let mut projects = Vec::new();
+    let mut root_doc = Doc::default();
+    let mut updated_at = None;
+    for dir in &roots {
+        let Some(tree) = load_any_cache(dir, cwd) else {
+            warn!(dir=%dir.display(), "Cache unreadable; leaving it out of the bundle");
+            continue;
+        };
+        let rel = pathdiff::diff_paths(dir, root).unwrap_or_default();
+        let rel = match rel.to_string_lossy().as_ref() {
+            "" => ".".to_string(),
+            s => s.to_string(),
+        };
+        let mut files = HashMap::new();
+        if dir == root {
+            index_files_by_path(&tree.entries, &mut files);
+            index_dir_docs(&tree.entries, "", &mut dir_docs);
+            root_doc = tree.doc.clone();
+        } else {
+            rebase_child_tree_into_existing_by_path(dir, root, &tree, &mut files);
+            index_dir_docs(&tree.entries, &rel, &mut dir_docs);
+            // A child project's root doc describes its directory in the bundle
+            if !tree.doc.fileDescription.is_empty() {
+                dir_docs.insert(rel.clone(), tree.doc.clone());
+            }
+        }
+        info!(cache = %rel, files = files.len(), "Bundling cache");
+        projects.push(BundledProject {
+            path: rel,
+            doc: tree.doc.clone(),
+            files: files.len(),
+        });
+        updated_at = updated_at.max(Some(tree.updated_at));
+        by_path.extend(files);
+    }
+
+    let mut paths: Vec<&String> = by_path.keys().collect();
+    paths.sort();
+    let mut entries = Vec::new();
+    for path in paths {
+        insert_file_into_tree(&mut entries, path, &by_path[path]);
+    }
+    restore_dir_docs(&mut entries, "", &dir_docs);
+
+    let name = root
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| ".".to_string());
+    Ok(Bundle {
+        format: BUNDLE_FORMAT.to_string(),
+        bundle_version: BUNDLE_VERSION,
+        dirdocs_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: Utc::now(),
+        projects,
+        tree: DirdocsRoot {
+            schema_version: SCHEMA_VERSION,
+            root: name,
+            updated_at: updated_at.unwrap_or_else(Utc::now),
+            doc: root_doc,
+            entries,
+        },
+    })
+}
+
+/// Write `bundle` to `path` as pretty-printed JSON, replacing it atomically.
+///
+/// Errors:
+/// - Serialization or I/O errors.
+pub(crate) fn write_bundle(path: &Path, bundle: &Bundle) -> anyhow::Result<()> {
+    let body = serde_json::to_string_pretty(bundle)? + "\n";
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, body)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Directories at or below `root` that hold a dirdocs cache, outermost first.
+///
+/// Follows `.gitignore` like a run does, but looks inside hidden entries so the cache
+/// files themselves are seen; `.git` and `.dirdocs/` sidecars are skipped.
+fn find_cache_roots(root: &Path) -> Vec<PathBuf> {
+    let is_cache = |name: &str| {
+        name == crate::paths::CACHE_FILE
+            || name == crate::paths::DB_FILE
+            || CHILD_CACHE_NAMES
+                .iter()
+                .any(|n| n.eq_ignore_ascii_case(name))
+    };
+
+    let mut wb = WalkBuilder::new(root);
+    wb.hidden(false)
+        .git_ignore(true)
+        .git_exclude(true)
+        .git_global(true)
+        .filter_entry(|e| {
+            let name = e.file_name().to_string_lossy();
+            name != ".git" && name != SIDECAR_DIR
+        });
+
+    let mut out: Vec<PathBuf> = Vec::new();
+    for dent in wb.build().filter_map(|r| r.ok()) {
+        if !dent.file_type().is_some_and(|ft| ft.is_file()) {
+            continue;
+        }
+        if is_cache(&dent.file_name().to_string_lossy()) {
+            if let Some(dir) = dent.path().parent() {
+                out.push(dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf()));
+            }
+        }
+    }
+    out.sort_by(|a, b| {
+        a.components()
+            .count()
+            .cmp(&b.components().count())
+            .then_with(|| a.cmp(b))
+    });
+    out.dedup();
+    out
+}
+
+/// Load the cache in `dir`, whichever kind it is; `None` when none can be read.
+fn load_any_cache(dir: &Path, cwd: &Path) -> Option<DirdocsRoot> {
+    if let Some(backend) = CacheBackend::detect(dir) {
+        return Some(load_tree(backend, dir, cwd));
+    }
+    CHILD_CACHE_NAMES
+        .iter()
+        .map(|n| dir.join(n))
+        .find(|p| p.exists())
+        .map(|p| load_existing_tree(&p, dir, cwd))
+}
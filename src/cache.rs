@@ -1,12 +1,137 @@
-use crate::types::{DirdocsRoot, FileEntry, Node};
+use crate::db::{load_tree_db, write_tree_db};
+use crate::lock::lock_cache;
+use crate::paths::{CACHE_FILE, DB_FILE};
+use crate::types::{DirdocsRoot, Doc, FileEntry, Node, SCHEMA_VERSION};
+use crate::xattrs::{has_xattr_tree, load_tree_xattr, write_tree_xattr};
 use chrono::Utc;
+use clap::ValueEnum;
 use serde_json;
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use tracing::warn;
 
 pub(crate) const CHILD_CACHE_NAMES: &[&str] = &[".dirdocs.nu", ".dir.nuon"];
 
+/// Storage format for the documentation cache at a root.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum CacheBackend {
+    /// Pretty-printed JSON in `.dirdocs.nuon` (Nuon-compatible).
+    #[default]
+    Json,
+    /// A SQLite database in `.dirdocs.db`, indexed by path and hash.
+    Sqlite,
+    /// No cache file: each entry lives in its file's `user.dirdocs` extended attribute.
+    Xattr,
+}
+
+impl CacheBackend {
+    /// The cache file this backend reads and writes under `root` (the root itself for xattrs).
+    pub(crate) fn path(self, root: &Path) -> PathBuf {
+        match self {
+            CacheBackend::Json => root.join(CACHE_FILE),
+            CacheBackend::Sqlite => root.join(DB_FILE),
+            CacheBackend::Xattr => root.to_path_buf(),
+        }
+    }
+
+    /// Pick the backend of the most recently written cache under `root`, if any exists.
+    ///
+    /// Extended attributes are only considered when there is no cache file.
+    pub(crate) fn detect(root: &Path) -> Option<Self> {
+        let mtime = |b: Self| fs::metadata(b.path(root)).and_then(|m| m.modified()).ok();
+        match (mtime(CacheBackend::Json), mtime(CacheBackend::Sqlite)) {
+            (Some(j), Some(s)) if s > j => Some(CacheBackend::Sqlite),
+            (Some(_), _) => Some(CacheBackend::Json),
+            (None, Some(_)) => Some(CacheBackend::Sqlite),
+            (None, None) if has_xattr_tree(root) => Some(CacheBackend::Xattr),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Load the documentation tree for `root` from the given backend.
+///
+/// Falls back to an empty tree (like [`load_existing_tree`]) when the cache is missing
+/// or unreadable.
+///
+/// Parameters:
+/// - `backend`: Which cache to read.
+/// - `root_abs`: Absolute path of the dirdocs root directory.
+/// - `cwd`: Current working directory for relative path resolution.
+///
+/// Returns:
+/// The loaded or newly created `DirdocsRoot` object.
+pub(crate) fn load_tree(backend: CacheBackend, root_abs: &Path, cwd: &Path) -> DirdocsRoot {
+    let path = backend.path(root_abs);
+    match backend {
+        CacheBackend::Json => load_existing_tree(&path, root_abs, cwd),
+        CacheBackend::Sqlite if path.exists() => load_tree_db(&path).unwrap_or_else(|e| {
+            warn!(
+                path = %path.display(),
+                error = %e,
+                "SQLite cache unreadable; starting from an empty tree"
+            );
+            empty_tree(root_abs, cwd)
+        }),
+        CacheBackend::Sqlite => empty_tree(root_abs, cwd),
+        CacheBackend::Xattr => load_tree_xattr(root_abs)
+            .unwrap_or_else(|_| load_existing_tree(&root_abs.join(CACHE_FILE), root_abs, cwd)),
+    }
+}
+
+/// Write the documentation tree for `root` with the given backend.
+///
+/// Parameters:
+/// - `backend`: Which cache file to write.
+/// - `root_abs`: Absolute path of the dirdocs root directory.
+/// - `tree`: The tree to store.
+///
+/// Returns:
+/// The path of the written cache file.
+///
+/// Errors:
+/// - Any error from [`write_tree`], [`write_tree_db`] or [`write_tree_xattr`].
+///
+/// Notes:
+/// - Blocks while another writer holds the cache lock (see [`lock_cache`]); async callers
+///   should run it with `tokio::task::spawn_blocking`.
+pub(crate) fn save_tree(
+    backend: CacheBackend,
+    root_abs: &Path,
+    tree: &DirdocsRoot,
+) -> anyhow::Result<PathBuf> {
+    let path = backend.path(root_abs);
+    let _lock = lock_cache(root_abs, true);
+    match backend {
+        CacheBackend::Json => write_tree(&path, tree)?,
+        CacheBackend::Sqlite => write_tree_db(&path, tree)?,
+        CacheBackend::Xattr => write_tree_xattr(root_abs, tree)?,
+    }
+    Ok(path)
+}
+
+/// Load the documentation tree for `root` from whichever cache was written last.
+///
+/// Parameters:
+/// - `root`: The documented root directory.
+///
+/// Returns:
+/// - The tree from `.dirdocs.nuon`, `.dirdocs.db` or the `user.dirdocs` extended attributes.
+///
+/// Errors:
+/// - When no cache exists.
+pub(crate) fn load_documented_tree(root: &Path) -> anyhow::Result<DirdocsRoot> {
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let Some(backend) = CacheBackend::detect(root) else {
+        anyhow::bail!(
+            "no .dirdocs.nuon or .dirdocs.db in {}; run `dirdocs run` first",
+            root.display()
+        );
+    };
+    Ok(load_tree(backend, root, &cwd))
+}
+
 /// Load an existing dirdocs tree from a JSON file.
 ///
 /// Reads the JSON content of `path`, deserializes it into a
@@ -39,18 +164,25 @@ pub(crate) const CHILD_CACHE_NAMES: &[&str] = &[".dirdocs.nu", ".dir.nuon"];
 pub(crate) fn load_existing_tree(path: &Path, root_abs: &Path, cwd: &Path) -> DirdocsRoot {
     match fs::read_to_string(path) {
         Ok(s) => match serde_json::from_str::<DirdocsRoot>(&s) {
-            Ok(tree) => tree,
-            Err(_) => DirdocsRoot {
-                root: rel_label(root_abs, cwd),
-                updated_at: Utc::now(),
-                entries: Vec::new(),
+            // Once parsed, the tree is in this build's layout and is written back as such
+            Ok(tree) => DirdocsRoot {
+                schema_version: SCHEMA_VERSION,
+                ..tree
             },
+            Err(_) => empty_tree(root_abs, cwd),
         },
-        Err(_) => DirdocsRoot {
-            root: rel_label(root_abs, cwd),
-            updated_at: Utc::now(),
-            entries: Vec::new(),
-        },
+        Err(_) => empty_tree(root_abs, cwd),
+    }
+}
+
+/// A tree with no entries for `root_abs`, labelled relative to `cwd`.
+fn empty_tree(root_abs: &Path, cwd: &Path) -> DirdocsRoot {
+    DirdocsRoot {
+        schema_version: SCHEMA_VERSION,
+        root: rel_label(root_abs, cwd),
+        updated_at: Utc::now(),
+        doc: Doc::default(),
+        entries: Vec::new(),
     }
 }
 
@@ -75,7 +207,10 @@ pub(crate) fn load_existing_tree(path: &Path, root_abs: &Path, cwd: &Path) -> Di
 /// The function uses `serde_json::to_string_pretty` for serialization and `fs::write` to write the output.
 pub(crate) fn write_tree(path: &Path, tree: &DirdocsRoot) -> anyhow::Result<()> {
     let body = serde_json::to_string_pretty(tree)? + "\n";
-    fs::write(path, body)?;
+    // Replace via rename so readers see the old or the new cache, never a partial one
+    let tmp = path.with_extension("nuon.tmp");
+    fs::write(&tmp, body)?;
+    fs::rename(&tmp, path)?;
     Ok(())
 }
 
@@ -100,6 +235,59 @@ pub(crate) fn index_files_by_path(nodes: &[Node], map: &mut HashMap<String, File
         }
     }
 }
+
+/// Collect the descriptions of documented directories, keyed by their path in the tree.
+///
+/// Parameters:
+/// - `nodes`: The entries to walk.
+/// - `prefix`: Path of the directory holding `nodes` (`""` for the root).
+/// - `map`: Receives `path -> doc` for every directory carrying a `doc`.
+///
+/// Notes:
+/// - Directory nodes are rebuilt from file paths on every run, so their docs have to be
+///   carried over with [`restore_dir_docs`].
+pub(crate) fn index_dir_docs(nodes: &[Node], prefix: &str, map: &mut HashMap<String, Doc>) {
+    for n in nodes {
+        if let Node::Dir(d) = n {
+            let path = join_tree_path(prefix, &d.name);
+            if let Some(doc) = &d.doc {
+                map.insert(path.clone(), doc.clone());
+            }
+            index_dir_docs(&d.entries, &path, map);
+        }
+    }
+}
+
+/// Put directory descriptions collected by [`index_dir_docs`] back onto a rebuilt tree.
+///
+/// Parameters:
+/// - `nodes`: The entries to walk.
+/// - `prefix`: Path of the directory holding `nodes` (`""` for the root).
+/// - `docs`: `path -> doc` for the documented directories.
+///
+/// Notes:
+/// - Docs of directories that no longer hold any documented file are dropped with them.
+pub(crate) fn restore_dir_docs(nodes: &mut [Node], prefix: &str, docs: &HashMap<String, Doc>) {
+    for n in nodes {
+        if let Node::Dir(d) = n {
+            let path = join_tree_path(prefix, &d.name);
+            if let Some(doc) = docs.get(&path) {
+                d.doc = Some(doc.clone());
+            }
+            restore_dir_docs(&mut d.entries, &path, docs);
+        }
+    }
+}
+
+/// Join a directory path in the tree and a child name with `/`.
+fn join_tree_path(prefix: &str, name: &str) -> String {
+    let name = name.trim_end_matches('/');
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{prefix}/{name}")
+    }
+}
 /// Handle finding child cache directories under a parent root.
 ///
 /// This function scans the filesystem starting at `parent_root` to find all directories
@@ -328,6 +516,7 @@ fn insert_recursive(entries: &mut Vec<Node>, comps: &[String], fe: &FileEntry) {
             hash: fe.hash.clone(),
             updated_at: fe.updated_at,
             doc: fe.doc.clone(),
+            error: fe.error.clone(),
         };
         entries.push(Node::File(file));
         return;
@@ -345,6 +534,7 @@ fn insert_recursive(entries: &mut Vec<Node>, comps: &[String], fe: &FileEntry) {
             name: dir_name.clone(),
             path: comps[..1].join("/"),
             updated_at: Utc::now(),
+            doc: None,
             entries: Vec::new(),
         };
         insert_recursive(&mut new_dir.entries, &comps[1..], fe);
@@ -0,0 +1,252 @@
+use crate::progress::ProgressObserver;
+use awful_aj::{config::AwfulJadeConfig, template::ChatTemplate};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tracing::{debug, trace};
+
+/// Minimum time between two `on_typing` notifications for the same answer.
+const TYPING_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The backend answered a chat-completions request with a non-success status.
+#[derive(Debug)]
+pub(crate) struct ApiStatusError {
+    /// The endpoint that was called.
+    pub url: String,
+    /// The HTTP status it answered with.
+    pub status: reqwest::StatusCode,
+    /// The response body, usually an OpenAI-style `{"error": {...}}` object.
+    pub body: String,
+}
+
+impl std::fmt::Display for ApiStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} answered {}: {}", self.url, self.status, self.body)
+    }
+}
+
+impl std::error::Error for ApiStatusError {}
+
+/// Sampling parameters passed through to the chat-completions request.
+///
+/// Unset fields are left out of the request, so the server's defaults apply.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub(crate) struct SamplingParams {
+    /// Randomness of the answer; `0` makes generation (near-)greedy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Nucleus sampling cut-off.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Seed for servers that support reproducible sampling.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+}
+
+impl SamplingParams {
+    /// Whether no parameter is set, i.e. the request can go through `api::ask` unchanged.
+    pub(crate) fn is_unset(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// `self`, with unset fields taken from `fallback`.
+    pub(crate) fn or(self, fallback: Self) -> Self {
+        Self {
+            temperature: self.temperature.or(fallback.temperature),
+            top_p: self.top_p.or(fallback.top_p),
+            seed: self.seed.or(fallback.seed),
+        }
+    }
+}
+
+/// Ask the model for a complete (non-streamed) answer with explicit sampling parameters.
+///
+/// `api::ask` has no way to set temperature, `top_p` or seed, and its errors keep only the
+/// message text, so requests that pin sampling parameters or carry a `response_format`
+/// are sent directly, in the same shape `api::ask` would use.
+///
+/// Parameters:
+/// - `cfg`: The backend configuration (`api_base`, `api_key`, model, stop words).
+/// - `prompt`: The user message; the template's pre/post content is wrapped around it.
+/// - `tpl`: The rendered template: system prompt, seed messages and optional `response_format`.
+/// - `sampling`: The sampling parameters to send.
+///
+/// Returns:
+/// - The `message.content` of the first choice.
+///
+/// Errors:
+/// - Connection errors, an [`ApiStatusError`] for a non-success status, or a response
+///   without content.
+pub(crate) async fn ask_completion(
+    cfg: &AwfulJadeConfig,
+    prompt: &str,
+    tpl: &ChatTemplate,
+    sampling: SamplingParams,
+) -> anyhow::Result<String> {
+    let resp = send(cfg, &request_body(cfg, prompt, tpl, sampling, false)?).await?;
+    let body: serde_json::Value = resp.json().await?;
+    body["choices"][0]["message"]["content"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("No assistant response"))
+}
+
+/// Ask the model with `stream: true`, assembling the streamed chunks into the full answer.
+///
+/// `awful_aj`'s own streaming path prints tokens to stdout, which would corrupt NDJSON
+/// events and interleave with logs, so dirdocs speaks the OpenAI server-sent-events
+/// protocol itself and keeps the tokens to report progress instead.
+///
+/// Parameters:
+/// - `cfg`: The backend configuration (`api_base`, `api_key`, model, stop words).
+/// - `prompt`: The user message; the template's pre/post content is wrapped around it.
+/// - `tpl`: The rendered template: system prompt, seed messages and optional `response_format`.
+/// - `sampling`: Sampling parameters; unset fields are left to the server.
+/// - `rel_path`: The file being described, passed on to `observer`.
+/// - `observer`: Notified through `on_typing` while chunks arrive.
+///
+/// Returns:
+/// - The concatenated `delta.content` of every chunk.
+///
+/// Errors:
+/// - Connection errors, an [`ApiStatusError`] for a non-success status (so structured-output
+///   rejections are recognized), or an `error` object in the stream.
+///
+/// Notes:
+/// - A stream that ends without `data: [DONE]` still returns what was received; the caller
+///   decides whether it parses.
+pub(crate) async fn ask_streaming(
+    cfg: &AwfulJadeConfig,
+    prompt: &str,
+    tpl: &ChatTemplate,
+    sampling: SamplingParams,
+    rel_path: &str,
+    observer: &dyn ProgressObserver,
+) -> anyhow::Result<String> {
+    let mut resp = send(cfg, &request_body(cfg, prompt, tpl, sampling, true)?).await?;
+
+    let mut answer = String::new();
+    let mut pending: Vec<u8> = Vec::new();
+    let mut last_report = Instant::now();
+    'stream: while let Some(bytes) = resp.chunk().await? {
+        pending.extend_from_slice(&bytes);
+        // Events are newline-terminated; keep a trailing partial line for the next chunk.
+        // Only whole lines are decoded, so a character split across chunks stays intact.
+        while let Some(nl) = pending.iter().position(|&b| b == b'\n') {
+            let raw: Vec<u8> = pending.drain(..=nl).collect();
+            let line = String::from_utf8_lossy(&raw);
+            let Some(data) = line.trim().strip_prefix("data:") else {
+                continue;
+            };
+            let data = data.trim();
+            if data == "[DONE]" {
+                break 'stream;
+            }
+            if let Some(delta) = parse_delta(data)? {
+                trace!(path = rel_path, %delta, "Streamed chunk");
+                answer.push_str(&delta);
+                if last_report.elapsed() >= TYPING_INTERVAL {
+                    observer.on_typing(rel_path, answer.chars().count());
+                    last_report = Instant::now();
+                }
+            }
+        }
+    }
+    observer.on_typing(rel_path, answer.chars().count());
+    debug!(
+        path = rel_path,
+        chars = answer.len(),
+        "Streamed answer assembled"
+    );
+    Ok(answer)
+}
+
+/// POST `body` to the backend's chat-completions endpoint.
+///
+/// Errors:
+/// - Connection errors, or an [`ApiStatusError`] for a non-success status.
+async fn send(
+    cfg: &AwfulJadeConfig,
+    body: &serde_json::Value,
+) -> anyhow::Result<reqwest::Response> {
+    let url = format!("{}/chat/completions", cfg.api_base.trim_end_matches('/'));
+    let mut req = reqwest::Client::new().post(&url).json(body);
+    if !cfg.api_key.is_empty() {
+        req = req.bearer_auth(&cfg.api_key);
+    }
+    let resp = req.send().await?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        return Err(ApiStatusError { url, status, body }.into());
+    }
+    Ok(resp)
+}
+
+/// The chat-completions request `api::ask` would send for `prompt`, plus `sampling` and `stream`.
+fn request_body(
+    cfg: &AwfulJadeConfig,
+    prompt: &str,
+    tpl: &ChatTemplate,
+    sampling: SamplingParams,
+    stream: bool,
+) -> anyhow::Result<serde_json::Value> {
+    let mut question = prompt.to_string();
+    if let Some(pre) = &tpl.pre_user_message_content {
+        question = format!("{pre} {question}");
+    }
+    if let Some(post) = &tpl.post_user_message_content {
+        question = format!("{question} {post}");
+    }
+
+    let mut messages = vec![serde_json::json!({
+        "role": "system",
+        "content": tpl.system_prompt,
+    })];
+    for m in &tpl.messages {
+        messages.push(serde_json::to_value(m)?);
+    }
+    messages.push(serde_json::json!({ "role": "user", "content": question }));
+
+    let mut body = serde_json::json!({
+        "model": cfg.model,
+        "messages": messages,
+        "max_tokens": cfg.context_max_tokens,
+        "stop": cfg.stop_words,
+        "stream": stream,
+    });
+    if let (Some(body), serde_json::Value::Object(params)) =
+        (body.as_object_mut(), serde_json::to_value(sampling)?)
+    {
+        body.extend(params);
+    }
+    if let Some(schema) = &tpl.response_format {
+        body["response_format"] = serde_json::json!({
+            "type": "json_schema",
+            "json_schema": schema,
+        });
+    }
+    Ok(body)
+}
+
+/// The text carried by one `data:` event, if any.
+///
+/// Errors:
+/// - When the server reports an error inside the stream.
+fn parse_delta(data: &str) -> anyhow::Result<Option<String>> {
+    let Ok(event) = serde_json::from_str::<serde_json::Value>(data) else {
+        debug!(%data, "Ignoring unparseable stream event");
+        return Ok(None);
+    };
+    if let Some(err) = event.get("error") {
+        let msg = err
+            .get("message")
+            .and_then(|m| m.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| err.to_string());
+        anyhow::bail!("stream error: {msg}");
+    }
+    Ok(event["choices"][0]["delta"]["content"]
+        .as_str()
+        .filter(|s| !s.is_empty())
+        .map(str::to_string))
+}
@@ -1,8 +1,66 @@
-use crate::content::read_text_lossy_limited;
+use crate::content::{detect_encoding, read_text_lossy_limited, shebang_language};
+use crate::extract::{extract_text, is_extractable};
+use crate::tokenizer::Tokenizer;
+use clap::ValueEnum;
+use encoding_rs::Decoder;
+use regex::Regex;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::Read;
 use std::path::Path;
 use text_splitter::{ChunkConfig, CodeSplitter, MarkdownSplitter, TextSplitter};
-use tiktoken_rs::cl100k_base;
-use tree_sitter::Language;
+use tree_sitter::{Language, Node, Parser};
+
+/// Most lines [`symbol_outline`] returns; the rest of a huge file's symbols are dropped.
+const MAX_OUTLINE_SYMBOLS: usize = 150;
+
+/// Node-kind fragments that mark a definition, with the label shown in the outline.
+/// Checked in order, so more specific fragments come first.
+const DEFINITION_KINDS: &[(&str, &str)] = &[
+    ("method", "method"),
+    ("function", "fn"),
+    ("class", "class"),
+    ("struct", "struct"),
+    ("enum", "enum"),
+    ("union", "union"),
+    ("trait", "trait"),
+    ("interface", "interface"),
+    ("impl", "impl"),
+    ("constructor", "constructor"),
+    ("mod_item", "mod"),
+    ("module", "module"),
+    ("namespace", "namespace"),
+    ("object_definition", "object"),
+    ("type", "type"),
+    ("macro", "macro"),
+    ("const", "const"),
+    ("static", "static"),
+    ("lexical_declaration", "const"),
+];
+
+/// Node-kind suffixes of definitions (as opposed to uses, e.g. `type_identifier`).
+const DEFINITION_SUFFIXES: &[&str] = &[
+    "_item",
+    "_definition",
+    "_declaration",
+    "_specifier",
+    "_signature",
+];
+
+/// Chunks per prompt when neither the template nor the command line sets `chunk_count`.
+const DEFAULT_CHUNK_COUNT: usize = 3;
+
+/// Tokens per chunk when neither the template nor the command line sets `chunk_tokens`.
+const DEFAULT_CHUNK_TOKENS: usize = 1000;
+
+/// Most bytes of a file that are chunked; the rest of a huge file is ignored.
+const MAX_TEXT_BYTES: usize = 2_000_000;
+
+/// Decoded text split at a time; only this much of a file (plus one block) is held in memory.
+const READ_WINDOW: usize = 256 * 1024;
+
+/// Bytes read from disk at a time.
+const READ_BLOCK: usize = 64 * 1024;
 
 #[derive(Debug, Clone)]
 pub(crate) enum SplitterKind {
@@ -11,73 +69,526 @@ pub(crate) enum SplitterKind {
     Text,
 }
 
+/// Line patterns that make a chunk worth sending, with the score each matching line adds.
+///
+/// Unindented definitions count most: they are what a file is for. Exports, headings and
+/// documentation comments follow; ordinary code and prose score nothing.
+const SIGNALS: &[(&str, f64)] = &[
+    // Top-level definitions
+    (
+        r"^(pub(\([a-z]+\))?\s+)?(export\s+)?(default\s+)?(async\s+)?(fn|def|class|struct|enum|trait|impl|interface|func|function|module|object|record|protocol|extension|type|macro_rules)\b",
+        3.0,
+    ),
+    // Nested definitions (methods, inner functions)
+    (
+        r"^\s+(pub(\([a-z]+\))?\s+)?(static\s+)?(async\s+)?(fn|def|func|function)\b",
+        1.0,
+    ),
+    // Exported symbols
+    (
+        r"^\s*(pub\s|export\s|module\.exports|__all__|@public|public\s)",
+        2.0,
+    ),
+    // Markdown / reStructuredText headings
+    (r"^(#{1,6}\s+\S|[=\-~]{4,}\s*$)", 3.0),
+    // Documentation comments
+    (r#"^\s*(///|//!|/\*\*|"""|'''|#'|--\s*\||;;;)"#, 1.0),
+];
+
+/// Which of a file's chunks are put into the prompt.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum ChunkStrategy {
+    /// The most informative chunks: those with top-level definitions, exports, headings
+    /// and doc comments (ties go to the earlier chunk).
+    #[default]
+    Important,
+    /// Spread evenly from the first chunk to the last (first/middle/last for three).
+    Even,
+    /// The first chunks only.
+    Head,
+    /// Half from the start of the file, the rest from its end.
+    HeadTail,
+}
+
+impl std::str::FromStr for ChunkStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Self as ValueEnum>::from_str(s, true)
+    }
+}
+
+/// How many chunks of what size a prompt gets, and where they're taken from.
+///
+/// Unset fields fall back to the three most informative chunks of 1000 tokens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct ChunkSettings {
+    /// Number of chunks sent.
+    pub count: Option<usize>,
+    /// Tokens per chunk before the prompt is shrunk to fit the context window.
+    pub tokens: Option<usize>,
+    /// Where in the file the chunks are taken from.
+    pub strategy: Option<ChunkStrategy>,
+}
+
+impl ChunkSettings {
+    /// `self`, with unset fields taken from `fallback`.
+    pub(crate) fn or(self, fallback: Self) -> Self {
+        Self {
+            count: self.count.or(fallback.count),
+            tokens: self.tokens.or(fallback.tokens),
+            strategy: self.strategy.or(fallback.strategy),
+        }
+    }
+
+    /// Number of chunks sent (at least one).
+    pub(crate) fn count(&self) -> usize {
+        self.count.unwrap_or(DEFAULT_CHUNK_COUNT).max(1)
+    }
+
+    /// Where the chunks are taken from.
+    pub(crate) fn strategy(&self) -> ChunkStrategy {
+        self.strategy.unwrap_or_default()
+    }
+
+    /// Chunk sizes tried in order until the rendered prompt fits the context window:
+    /// the configured size, then a half, a quarter and a tenth of it.
+    pub(crate) fn token_steps(&self) -> Vec<usize> {
+        let tokens = self.tokens.unwrap_or(DEFAULT_CHUNK_TOKENS).max(1);
+        let mut steps: Vec<usize> = [tokens, tokens / 2, tokens / 4, tokens / 10]
+            .into_iter()
+            .filter(|&t| t > 0)
+            .collect();
+        steps.dedup();
+        steps
+    }
+}
+
+/// Indices of `count` chunks spread evenly over `total`, in file order and without repeats.
+///
+/// Rounded to the nearest chunk, so three of four chunks are 0, 2 and 3.
+fn even_indices(total: usize, count: usize) -> Vec<usize> {
+    if total == 0 || count == 0 {
+        return Vec::new();
+    }
+    if count == 1 {
+        return vec![0];
+    }
+    let mut picked: Vec<usize> = (0..count)
+        .map(|i| (2 * i * (total - 1) + (count - 1)) / (2 * (count - 1)))
+        .collect();
+    picked.dedup();
+    picked
+}
+
+/// Keeps the chunks a [`ChunkStrategy`] wants while a file streams past, so only those
+/// stay in memory.
+struct ChunkPicker {
+    strategy: ChunkStrategy,
+    count: usize,
+    /// For [`ChunkStrategy::Even`]: the indices wanted, from a counting pass.
+    wanted: Vec<usize>,
+    /// Compiled [`SIGNALS`], for [`ChunkStrategy::Important`].
+    signals: Vec<(Regex, f64)>,
+    /// Chunks kept so far as `(index, score, text)`.
+    kept: Vec<(usize, f64, String)>,
+    /// For [`ChunkStrategy::HeadTail`]: the most recent chunks past the head.
+    tail: VecDeque<(usize, String)>,
+    /// Index of the next chunk.
+    next: usize,
+}
+
+impl ChunkPicker {
+    /// A picker for `count` chunks; `total` is only needed (and only known) for `Even`.
+    fn new(strategy: ChunkStrategy, count: usize, total: usize) -> Self {
+        let signals = if strategy == ChunkStrategy::Important {
+            SIGNALS
+                .iter()
+                .filter_map(|&(re, weight)| Regex::new(re).ok().map(|re| (re, weight)))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Self {
+            strategy,
+            count,
+            wanted: even_indices(total, count),
+            signals,
+            kept: Vec::new(),
+            tail: VecDeque::new(),
+            next: 0,
+        }
+    }
+
+    /// Offer the next chunk.
+    ///
+    /// Returns:
+    /// - `false` once no later chunk can be picked, so reading can stop early.
+    fn push(&mut self, chunk: &str) -> bool {
+        let index = self.next;
+        self.next += 1;
+        match self.strategy {
+            ChunkStrategy::Head => {
+                self.kept.push((index, 0.0, chunk.to_owned()));
+                self.kept.len() < self.count
+            }
+            ChunkStrategy::Even => {
+                if self.wanted.binary_search(&index).is_ok() {
+                    self.kept.push((index, 0.0, chunk.to_owned()));
+                }
+                self.wanted.last().is_some_and(|&last| index < last)
+            }
+            ChunkStrategy::HeadTail => {
+                let head = (self.count + 1) / 2;
+                if self.kept.len() < head {
+                    self.kept.push((index, 0.0, chunk.to_owned()));
+                } else if self.count > head {
+                    self.tail.push_back((index, chunk.to_owned()));
+                    if self.tail.len() > self.count - head {
+                        self.tail.pop_front();
+                    }
+                }
+                true
+            }
+            ChunkStrategy::Important => {
+                let score = chunk_score(&self.signals, chunk);
+                if self.kept.len() < self.count {
+                    self.kept.push((index, score, chunk.to_owned()));
+                } else if let Some(worst) = self
+                    .kept
+                    .iter_mut()
+                    // Ties go to the earlier chunk, so only strictly better scores replace
+                    .min_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)))
+                    .filter(|w| score > w.1)
+                {
+                    *worst = (index, score, chunk.to_owned());
+                }
+                true
+            }
+        }
+    }
+
+    /// The kept chunks in file order.
+    fn finish(mut self) -> Vec<String> {
+        self.kept
+            .extend(self.tail.drain(..).map(|(index, text)| (index, 0.0, text)));
+        self.kept.sort_by_key(|k| k.0);
+        self.kept.into_iter().map(|(_, _, text)| text).collect()
+    }
+}
+
+/// Score a chunk by the [`SIGNALS`] its lines match.
+fn chunk_score(signals: &[(Regex, f64)], chunk: &str) -> f64 {
+    chunk
+        .lines()
+        .map(|line| {
+            signals
+                .iter()
+                .filter(|(re, _)| re.is_match(line))
+                .map(|(_, w)| w)
+                .sum::<f64>()
+        })
+        .sum()
+}
+
+/// A text splitter of any kind, sized by the run's tokenizer.
+enum Splitter<'t> {
+    Code(CodeSplitter<&'t Tokenizer>),
+    Markdown(MarkdownSplitter<&'t Tokenizer>),
+    Text(TextSplitter<&'t Tokenizer>),
+}
+
+impl<'t> Splitter<'t> {
+    /// Build the splitter for `kind` with chunks of at most `max_tokens`.
+    fn new(kind: SplitterKind, max_tokens: usize, tokenizer: &'t Tokenizer) -> Self {
+        let cfg = ChunkConfig::new(max_tokens).with_sizer(tokenizer);
+        match kind {
+            SplitterKind::Code(lang) => {
+                Self::Code(CodeSplitter::new(lang, cfg).expect("valid tree-sitter language"))
+            }
+            SplitterKind::Markdown => Self::Markdown(MarkdownSplitter::new(cfg)),
+            SplitterKind::Text => Self::Text(TextSplitter::new(cfg)),
+        }
+    }
+
+    /// The label reported as `used_splitter`.
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Code(_) => "code",
+            Self::Markdown(_) => "markdown",
+            Self::Text(_) => "text",
+        }
+    }
+
+    /// Split one window of text.
+    fn chunks<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        match self {
+            Self::Code(s) => s.chunks(text).collect(),
+            Self::Markdown(s) => s.chunks(text).collect(),
+            Self::Text(s) => s.chunks(text).collect(),
+        }
+    }
+}
+
+/// Where to cut a window of text: after its last blank line, else its last line break,
+/// so a split rarely lands inside a paragraph or statement.
+fn window_end(text: &str) -> usize {
+    text.rfind("\n\n")
+        .map(|i| i + 2)
+        .or_else(|| text.rfind('\n').map(|i| i + 1))
+        .filter(|&i| i > 0)
+        .unwrap_or(text.len())
+}
+
+/// Split the text of a file window by window, handing each chunk to `on_chunk`.
+///
+/// At most [`READ_WINDOW`] bytes of decoded text (plus one read block) are held at a time,
+/// so memory stays bounded however large the file is. Reading stops when `on_chunk`
+/// returns `false` or after [`MAX_TEXT_BYTES`] bytes of the file.
+///
+/// Returns:
+/// - Whether the file had any non-whitespace text, or `None` when text can't be
+///   extracted from a document format.
+fn stream_chunks(
+    path: &Path,
+    mimetype: &str,
+    splitter: &Splitter<'_>,
+    mut on_chunk: impl FnMut(&str) -> bool,
+) -> Option<bool> {
+    let mut had_text = false;
+    // Feed one window to the splitter; false once the consumer wants no more
+    let mut split = |window: &str| -> bool {
+        had_text |= !window.trim().is_empty();
+        splitter.chunks(window).into_iter().all(&mut on_chunk)
+    };
+
+    if is_extractable(mimetype) {
+        // Extracted text is already in memory; it is still split in windows
+        let text = extract_text(path, mimetype)?;
+        let mut rest = text.as_str();
+        while !rest.is_empty() {
+            let cut = if rest.len() > READ_WINDOW {
+                let mut end = READ_WINDOW;
+                while !rest.is_char_boundary(end) {
+                    end -= 1;
+                }
+                window_end(&rest[..end])
+            } else {
+                rest.len()
+            };
+            if !split(&rest[..cut]) {
+                break;
+            }
+            rest = &rest[cut..];
+        }
+        return Some(had_text);
+    }
+
+    let Ok(mut file) = fs::File::open(path) else {
+        return Some(false);
+    };
+    let mut block = vec![0u8; READ_BLOCK];
+    let mut decoder: Option<Decoder> = None;
+    let mut pending = String::new();
+    let mut remaining = MAX_TEXT_BYTES;
+    loop {
+        let n = file
+            .read(&mut block[..READ_BLOCK.min(remaining)])
+            .unwrap_or(0);
+        remaining -= n;
+        let last = n == 0;
+        let dec = decoder
+            .get_or_insert_with(|| detect_encoding(&block[..n]).new_decoder_with_bom_removal());
+        pending.reserve(dec.max_utf8_buffer_length(n).unwrap_or(n * 3 + 4));
+        let _ = dec.decode_to_string(&block[..n], &mut pending, last);
+
+        if pending.len() >= READ_WINDOW || last {
+            let cut = if last {
+                pending.len()
+            } else {
+                window_end(&pending)
+            };
+            if !split(&pending[..cut]) {
+                break;
+            }
+            pending.drain(..cut);
+        }
+        if last {
+            break;
+        }
+    }
+    Some(had_text)
+}
+
 /// Handle token chunking for a file based on its mimetype and content.
 ///
 /// Splits the text in `path` into chunks of tokens, using a splitter configured
-/// with `max_tokens`. The function returns the first, middle, and last chunks of text.
+/// with `max_tokens`. The function returns the chunks picked by `settings` (by default
+/// the three most informative chunks, see [`ChunkStrategy::Important`]).
 ///
 /// # Parameters:
 /// - `path`: Path to the file containing text.
 /// - `mimetype`: MIME type of the file (used to determine splitter).
 /// - `max_tokens`: Maximum number of tokens per chunk.
+/// - `forced`: Splitter to use instead of guessing one (from `splitters.yaml`).
+/// - `settings`: How many chunks to return and where to take them from.
+/// - `tokenizer`: Counts the tokens of each chunk (the configured model's tokenizer).
 ///
 /// # Returns:
-/// An `Option<(String, String, String, String)>` containing the first, middle, last chunks 
-/// of text and a string indicating the splitter type (`"code"`, `"markdown"`, or `"text"`).
+/// An `Option<(Vec<String>, String)>` containing the selected chunks in file order
+/// and a string indicating the splitter type (`"code"`, `"markdown"`, or `"text"`).
 ///
 /// # Errors:
-/// - Returns `None` if the file fails to be read or contains no content.
+/// - Returns `None` if text can't be extracted from a document format (see [`extract_text`]).
 ///
 /// # Notes:
-/// - The function handles empty files by returning empty strings.
-/// - It uses a BPE tokenizer and configures chunking based on the file's content.
+/// - The function handles empty and unreadable files by returning no chunks.
+/// - Documents such as PDFs (with the `pdf` feature) are chunked from their extracted text.
+/// - Chunk sizes are measured with `tokenizer`, so they match what the model will count.
+/// - The file is read and split in windows (see [`stream_chunks`]) and only the selected
+///   chunks are kept, so memory stays bounded with many files in flight. `head` stops
+///   reading once it has its chunks; `even` reads the file twice, first to count chunks.
 pub(crate) fn token_chunks_for_file(
     path: &Path,
     mimetype: &str,
     max_tokens: usize,
-) -> Option<(String, String, String, String)> {
-    let text = read_text_lossy_limited(path, 2_000_000);
-    if text.trim().is_empty() {
-        return Some((String::new(), String::new(), String::new(), "empty".into()));
+    forced: Option<SplitterKind>,
+    settings: &ChunkSettings,
+    tokenizer: &Tokenizer,
+) -> Option<(Vec<String>, String)> {
+    let kind = forced.unwrap_or_else(|| guess_splitter(mimetype, path));
+    let splitter = Splitter::new(kind, max_tokens, tokenizer);
+    let strategy = settings.strategy();
+
+    let total = if strategy == ChunkStrategy::Even {
+        let mut total = 0usize;
+        stream_chunks(path, mimetype, &splitter, |_| {
+            total += 1;
+            true
+        })?;
+        total
+    } else {
+        0
+    };
+
+    let mut picker = ChunkPicker::new(strategy, settings.count(), total);
+    let had_text = stream_chunks(path, mimetype, &splitter, |chunk| picker.push(chunk))?;
+    if !had_text {
+        return Some((Vec::new(), "empty".into()));
     }
 
-    let bpe = cl100k_base().ok()?;
-    let cfg = ChunkConfig::new(max_tokens).with_sizer(&bpe);
+    Some((picker.finish(), splitter.label().to_string()))
+}
+
+/// List the top-level symbols of a source file, one per line, e.g. `fn main` or `class Parser`.
+///
+/// The file is parsed with the same Tree-sitter grammar the code splitter uses. Members of
+/// classes, impls, traits and modules are listed indented below their container.
+///
+/// Parameters:
+/// - `path`: Path to the file.
+/// - `mimetype`: MIME type of the file (passed to [`guess_tree_sitter_language`]).
+///
+/// Returns:
+/// - The outline (at most [`MAX_OUTLINE_SYMBOLS`] lines), or an empty string for files
+///   without a grammar, unparseable files and files without named definitions.
+pub(crate) fn symbol_outline(path: &Path, mimetype: &str) -> String {
+    let Some(lang) = guess_tree_sitter_language(mimetype, path) else {
+        return String::new();
+    };
+    let text = read_text_lossy_limited(path, 2_000_000);
+    let mut parser = Parser::new();
+    if parser.set_language(&lang).is_err() {
+        return String::new();
+    }
+    let Some(tree) = parser.parse(&text, None) else {
+        return String::new();
+    };
 
-    let kind = guess_splitter(mimetype, path);
+    let mut lines = Vec::new();
+    outline_children(tree.root_node(), text.as_bytes(), 0, &mut lines);
+    lines.truncate(MAX_OUTLINE_SYMBOLS);
+    lines.join("\n")
+}
 
-    let (chunks, used): (Vec<&str>, String) = match kind {
-        SplitterKind::Code(lang) => {
-            let splitter = CodeSplitter::new(lang, cfg).expect("valid tree-sitter language");
-            (splitter.chunks(&text).collect(), "code".to_string())
+/// Append an outline line for each definition among `node`'s named children.
+fn outline_children(node: Node<'_>, src: &[u8], depth: usize, out: &mut Vec<String>) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        if out.len() >= MAX_OUTLINE_SYMBOLS {
+            return;
         }
-        SplitterKind::Markdown => {
-            let splitter = MarkdownSplitter::new(cfg);
-            (splitter.chunks(&text).collect(), "markdown".to_string())
+        // `export ...` (JS/TS) wraps the actual declaration
+        if child.kind() == "export_statement" {
+            if let Some(decl) = child.child_by_field_name("declaration") {
+                outline_node(decl, src, depth, out);
+            }
+            continue;
         }
-        SplitterKind::Text => {
-            let splitter = TextSplitter::new(cfg);
-            (splitter.chunks(&text).collect(), "text".to_string())
+        outline_node(child, src, depth, out);
+    }
+}
+
+/// Append the outline line for `node` if it's a definition, then its members.
+fn outline_node(node: Node<'_>, src: &[u8], depth: usize, out: &mut Vec<String>) {
+    let kind = node.kind();
+    if kind == "decorated_definition" {
+        if let Some(def) = node.child_by_field_name("definition") {
+            outline_node(def, src, depth, out);
         }
+        return;
+    }
+    if !DEFINITION_SUFFIXES.iter().any(|s| kind.ends_with(s)) {
+        return;
+    }
+    let Some(&(_, label)) = DEFINITION_KINDS.iter().find(|(k, _)| kind.contains(k)) else {
+        return;
+    };
+    let Some(name) = definition_name(node, src) else {
+        return;
     };
+    out.push(format!("{}{label} {name}", "  ".repeat(depth)));
 
-    if chunks.is_empty() {
-        return Some((String::new(), String::new(), String::new(), used));
+    if depth == 0 {
+        if let Some(body) = node.child_by_field_name("body") {
+            outline_children(body, src, depth + 1, out);
+        }
     }
+}
 
-    let first = chunks.first().copied().unwrap_or_default().to_owned();
-    let mid = chunks
-        .get(chunks.len() / 2)
-        .copied()
-        .unwrap_or_else(|| chunks[0])
-        .to_owned();
-    let last = chunks
-        .last()
-        .copied()
-        .unwrap_or_else(|| chunks[0])
-        .to_owned();
+/// The name a definition introduces: its `name` field, the type of an `impl`, or the
+/// identifier at the end of a C-style declarator or a variable declarator.
+fn definition_name(node: Node<'_>, src: &[u8]) -> Option<String> {
+    let text = |n: Node<'_>| {
+        n.utf8_text(src)
+            .ok()
+            .map(|s| s.split_whitespace().collect::<Vec<_>>().join(" "))
+    };
 
-    Some((first, mid, last, used))
+    if let Some(name) = node.child_by_field_name("name") {
+        return text(name);
+    }
+    if let Some(ty) = node.child_by_field_name("type") {
+        return match node.child_by_field_name("trait") {
+            Some(tr) => Some(format!("{} for {}", text(tr)?, text(ty)?)),
+            None => text(ty),
+        };
+    }
+    let mut declarator = node.child_by_field_name("declarator");
+    while let Some(d) = declarator {
+        if d.kind().contains("identifier") {
+            return text(d);
+        }
+        declarator = d
+            .child_by_field_name("declarator")
+            .or_else(|| d.child_by_field_name("name"));
+    }
+    // `const x = ...` (JS/TS) and `type T ...` (Go) keep the name one level down
+    let mut cursor = node.walk();
+    let inner = node
+        .named_children(&mut cursor)
+        .find_map(|c| c.child_by_field_name("name"));
+    inner.and_then(text)
 }
 
 /// Determine the appropriate splitter kind based on MIME type and file extension.
@@ -128,11 +639,13 @@ pub(crate) fn guess_splitter(mime: &str, path: &Path) -> SplitterKind {
 /// Notes:
 /// - The function uses a macro to compare file extensions against known language
 ///   patterns. It relies on Cargo features being enabled for specific languages.
+/// - Files without an extension are matched by their shebang instead, so `bin/deploy`
+///   with `#!/usr/bin/env python3` parses as Python.
 pub(crate) fn guess_tree_sitter_language(_mime: &str, path: &Path) -> Option<Language> {
-    let ext = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .map(|s| s.to_ascii_lowercase());
+    let ext = match path.extension() {
+        Some(e) => e.to_str().map(|s| s.to_ascii_lowercase()),
+        None => shebang_language(path).map(|(e, _)| e.to_string()),
+    };
 
     macro_rules! ext_is {
         ($($lit:literal),* $(,)?) => {{
@@ -280,5 +793,53 @@ pub(crate) fn guess_tree_sitter_language(_mime: &str, path: &Path) -> Option<Lan
         return Some(tree_sitter_verilog::LANGUAGE.into());
     }
 
+    // Kotlin
+    #[cfg(feature = "lang-kotlin")]
+    if ext_is!("kt", "kts") {
+        return Some(tree_sitter_kotlin_ng::LANGUAGE.into());
+    }
+
+    // Swift
+    #[cfg(feature = "lang-swift")]
+    if ext_is!("swift") {
+        return Some(tree_sitter_swift::LANGUAGE.into());
+    }
+
+    // Lua
+    #[cfg(feature = "lang-lua")]
+    if ext_is!("lua") {
+        return Some(tree_sitter_lua::LANGUAGE.into());
+    }
+
+    // Zig
+    #[cfg(feature = "lang-zig")]
+    if ext_is!("zig") {
+        return Some(tree_sitter_zig::LANGUAGE.into());
+    }
+
+    // Elixir
+    #[cfg(feature = "lang-elixir")]
+    if ext_is!("ex", "exs") {
+        return Some(tree_sitter_elixir::LANGUAGE.into());
+    }
+
+    // Erlang
+    #[cfg(feature = "lang-erlang")]
+    if ext_is!("erl", "hrl", "escript") {
+        return Some(tree_sitter_erlang::LANGUAGE.into());
+    }
+
+    // TOML
+    #[cfg(feature = "lang-toml")]
+    if ext_is!("toml") {
+        return Some(tree_sitter_toml_ng::LANGUAGE.into());
+    }
+
+    // YAML
+    #[cfg(feature = "lang-yaml")]
+    if ext_is!("yaml", "yml") {
+        return Some(tree_sitter_yaml::LANGUAGE.into());
+    }
+
     None
 }
@@ -0,0 +1,2738 @@
+//! The `dirdocs` command-line interface.
+//!
+//! [`main`] parses the process arguments and runs the chosen subcommand (`init`, `run`,
+//! `gc`, `serve`, ...) exactly as the `dirdocs` binary does, so a wrapper binary can ship
+//! the full CLI. Programs that only need to scan, load or generate docs should use the
+//! functions at the crate root instead.
+
+use crate::archive::{ArchiveInfo, archive_info};
+use crate::bundle::{BUNDLE_SUFFIX, build_bundle, write_bundle};
+use crate::cache::{
+    CHILD_CACHE_NAMES, CacheBackend, find_child_cache_dirs, index_dir_docs, index_files_by_path,
+    insert_file_into_tree, load_documented_tree, load_existing_tree, load_tree,
+    rebase_child_tree_into_existing_by_path, restore_dir_docs, save_tree,
+};
+use crate::chat::SamplingParams;
+use crate::chunk::{ChunkSettings, ChunkStrategy, symbol_outline, token_chunks_for_file};
+use crate::completions::{self, Shell};
+use crate::content::{as_ms, file_meta, hash_file, human_bytes, is_probably_text, readme_context};
+use crate::discover::{detect_local_servers, list_models};
+use crate::executable::{ExecutableInfo, executable_info};
+use crate::export::render_markdown;
+use crate::extract::is_extractable;
+use crate::gc::{Retention, prune_tree, sweep_sidecars};
+use crate::git::{
+    FileHistory, GitHook, changed_since, file_histories, install_hook, uninstall_hook,
+};
+use crate::global_cache::GlobalCache;
+use crate::hook::run_hook;
+use crate::http::{self, Endpoints};
+use crate::language::{clear_other_languages, language_report};
+use crate::lsp::{self, LspTransport};
+use crate::manifest::{FileRecord, RecordSource, RunManifest, hash_str, output_hash, prompt_hash};
+use crate::mcp;
+use crate::media::{ImageInfo, image_info};
+use crate::metrics::{Histogram, RunStats};
+use crate::paths::{CachePaths, ConfigPaths};
+use crate::priority::{DEFAULT_PRIORITY_YAML, Planner, PriorityRules};
+use crate::progress::{
+    ContextOverflow, FileOutcome, NdjsonObserver, NoopObserver, ProgressObserver, RunReport,
+};
+use crate::prompt_llm::{
+    OutputMode, OutputModeState, ask_with_fallback, doc_with_correction, prompt_budget,
+    prompt_tokens, suppressed_block,
+};
+use crate::scan::walk_files;
+use crate::search::{highlight_matches, search_tree};
+use crate::serve::{INVALID_PARAMS, METHOD_NOT_FOUND, RpcError, StdioTransport, str_param};
+use crate::splitters::{DEFAULT_SPLITTERS_YAML, SplitterOverrides};
+use crate::template::{
+    PromptTemplate, TemplateSet, register_helpers, register_partials, validate_templates,
+};
+use crate::tokenizer::Tokenizer;
+use crate::types::{DirdocsRoot, Doc, EntryError, FileEntry, SCHEMA_VERSION};
+
+use awful_aj::config::AwfulJadeConfig;
+use chrono::Utc;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum, ValueHint};
+use handlebars::Handlebars;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+use tracing_subscriber::{EnvFilter, fmt};
+
+/// Top-level CLI for `dirdocs`.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Use this directory instead of the Awful Jade config dir for config.yaml and templates.
+    #[clap(long, global = true, env = "DIRDOCS_CONFIG_DIR")]
+    config_dir: Option<PathBuf>,
+
+    /// `cmd` is the subcommand to execute.
+    #[clap(subcommand)]
+    cmd: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Initialize Awful Jade config and dir_docs template in your user config directory.
+    Init(InitArgs),
+    /// Run documentation generation (this is the behavior you had before).
+    Run(RunArgs),
+    /// Diagnose the setup: config, template, and whether the configured model server answers.
+    Doctor,
+    /// Prune orphaned entries and expired sidecar artifacts (vectors, checkpoints, locks).
+    Gc(GcArgs),
+    /// Search file paths and descriptions for a regex or substring.
+    #[clap(alias = "search")]
+    Grep(GrepArgs),
+    /// Export the documentation tree to another format (e.g. a FILES.md document).
+    Export(ExportArgs),
+    /// Merge the root cache and every child cache into one shareable bundle file.
+    Bundle(BundleArgs),
+    /// Check cached descriptions for problems, such as a mix of description languages.
+    Check(CheckArgs),
+    /// Install a git hook that keeps descriptions current on every commit.
+    InstallHooks(InstallHooksArgs),
+    /// Remove the git hooks added by `install-hooks`.
+    UninstallHooks(UninstallHooksArgs),
+    /// Serve descriptions to editor plugins over JSON-RPC, or to browsers over HTTP.
+    Serve(ServeArgs),
+    /// Run a Model Context Protocol server on stdio for AI coding agents.
+    Mcp(McpArgs),
+    /// Run a Language Server that shows file descriptions on hover and as workspace symbols.
+    Lsp(LspArgs),
+    /// Print a shell completion script, e.g. `dirdocs completions zsh > ~/.zfunc/_dirdocs`.
+    Completions(CompletionsArgs),
+    /// Inspect the configuration dirdocs resolves.
+    Config {
+        /// `action` is the config subcommand to execute.
+        #[clap(subcommand)]
+        action: ConfigCommand,
+    },
+}
+
+/// Subcommands of `dirdocs config`.
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Print the resolved config (secrets masked), template and cache locations as JSON.
+    Show(ConfigShowArgs),
+}
+
+/// Arguments for the `init` subcommand.
+#[derive(Parser, Debug, Clone)]
+struct InitArgs {
+    /// Print the config/template paths as JSON and exit without writing anything.
+    #[clap(long)]
+    print_paths: bool,
+}
+
+/// Arguments for the `completions` subcommand.
+#[derive(Parser, Debug, Clone)]
+struct CompletionsArgs {
+    /// The shell to generate the script for.
+    #[clap(value_enum)]
+    shell: Shell,
+}
+
+/// Arguments for the `config show` subcommand.
+#[derive(Parser, Debug, Clone)]
+struct ConfigShowArgs {
+    /// Directory whose cache location should be reported.
+    #[clap(long, short, default_value = ".", value_hint = ValueHint::DirPath)]
+    directory: String,
+}
+
+/// Arguments for the `run` subcommand (previously your root CLI args).
+#[derive(Parser, Debug, Clone, Default)]
+struct RunArgs {
+    /// Root directory to start from.
+    #[clap(long, short, default_value = ".", value_hint = ValueHint::DirPath)]
+    directory: String,
+
+    /// Extra directory names to ignore (repeat flag or comma list).
+    /// e.g. --ignore target,node_modules --ignore dist
+    #[clap(long, short = 'i', value_delimiter = ',')]
+    ignore: Vec<String>,
+
+    /// Force re-generate docs for every file, even if unchanged.
+    #[clap(long, short = 'f')]
+    force: bool,
+
+    /// Cache storage: `json` (.dirdocs.nuon), `sqlite` (.dirdocs.db, for very large trees) or
+    /// `xattr` (a `user.dirdocs` extended attribute per file, no cache file).
+    #[clap(long, value_enum, default_value_t = CacheBackend::Json)]
+    backend: CacheBackend,
+
+    /// Share descriptions across repositories via a global cache keyed by content, template and model.
+    #[clap(long, env = "DIRDOCS_GLOBAL_CACHE")]
+    global_cache: bool,
+
+    /// Shell command run after each file is documented; gets DIRDOCS_PATH,
+    /// DIRDOCS_ABS_PATH and DIRDOCS_DESCRIPTION in its env and the entry JSON on stdin.
+    #[clap(long, env = "DIRDOCS_HOOK")]
+    hook: Option<String>,
+
+    /// Progress output: `text` (tracing logs) or `ndjson` (one JSON event per file).
+    #[clap(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// With `--log-format ndjson`, write the events to this file instead of stdout.
+    #[clap(long)]
+    events_file: Option<PathBuf>,
+
+    /// Only (re)document these files or directories, relative to `--directory` (repeat flag
+    /// or comma list); every other file keeps its cached doc.
+    #[clap(long, value_delimiter = ',')]
+    only: Vec<String>,
+
+    /// Send at most this many files to the model; the rest (lowest priority first) keep
+    /// their cached docs until a later run. Ordering is configured in `priority.yaml`.
+    #[clap(long, value_name = "N")]
+    max_generate: Option<usize>,
+
+    /// Write a reproducibility manifest (model, config, template and per-file prompt/output hashes) here.
+    #[clap(long)]
+    manifest: Option<PathBuf>,
+
+    /// Only (re)document files changed since this git revision (committed, staged,
+    /// unstaged or untracked); every other file keeps its cached doc. Used by the hooks
+    /// that `dirdocs install-hooks` writes.
+    #[clap(long, value_name = "GIT_REF")]
+    changed_since: Option<String>,
+
+    /// Sampling temperature sent to the model (e.g. `0` for repeatable descriptions);
+    /// overrides the template's `temperature:`.
+    #[clap(long)]
+    temperature: Option<f32>,
+
+    /// Nucleus sampling `top_p` sent to the model; overrides the template's `top_p:`.
+    #[clap(long)]
+    top_p: Option<f32>,
+
+    /// Sampling seed for servers that support it; overrides the template's `seed:`.
+    #[clap(long)]
+    seed: Option<i64>,
+
+    /// Number of chunks of each file put into the prompt (default 3); overrides the
+    /// template's `chunk_count:`.
+    #[clap(long, value_name = "N")]
+    chunk_count: Option<usize>,
+
+    /// Tokens per chunk (default 1000), shrunk when the prompt doesn't fit the context
+    /// window; overrides the template's `chunk_tokens:`.
+    #[clap(long, value_name = "N")]
+    chunk_tokens: Option<usize>,
+
+    /// Where chunks are taken from: `important` (the chunks with the most definitions,
+    /// exports, headings and doc comments; the default), `even` (spread from first to last),
+    /// `head` or `head-tail`; overrides the template's `chunk_strategy:`.
+    #[clap(long, value_enum)]
+    chunk_strategy: Option<ChunkStrategy>,
+
+    /// Tokenizer used to size chunks and check the context window: a tiktoken encoding
+    /// (`cl100k_base`, `o200k_base`, ...) or a Hugging Face `tokenizer.json` (with the
+    /// `hf-tokenizer` feature). Defaults to the model's tiktoken encoding, else `cl100k_base`.
+    #[clap(long, env = "DIRDOCS_TOKENIZER", value_name = "NAME|PATH")]
+    tokenizer: Option<String>,
+}
+
+impl RunArgs {
+    /// A `dirdocs run` over `root` with every other flag at its default, for runs started
+    /// by the library and the LSP/MCP servers rather than the command line.
+    ///
+    /// # Parameters:
+    /// - `root`: The run root; the cache backend already in use there is kept.
+    /// - `rel`: The path to document below `root` (`--only`), or empty for all of it.
+    fn for_path(root: &std::path::Path, rel: &str) -> Self {
+        Self {
+            directory: root.to_string_lossy().to_string(),
+            backend: CacheBackend::detect(root).unwrap_or_default(),
+            only: if rel.is_empty() {
+                Vec::new()
+            } else {
+                vec![rel.to_string()]
+            },
+            ..Self::default()
+        }
+    }
+}
+
+/// Arguments for the `mcp` subcommand.
+#[derive(Parser, Debug, Clone)]
+struct McpArgs {
+    /// Root directory whose documentation is exposed.
+    #[clap(long, short, default_value = ".", value_hint = ValueHint::DirPath)]
+    directory: String,
+}
+
+/// Arguments for the `lsp` subcommand.
+#[derive(Parser, Debug, Clone)]
+struct LspArgs {
+    /// Root directory whose documentation is served (usually the editor's workspace folder).
+    #[clap(long, short, default_value = ".", value_hint = ValueHint::DirPath)]
+    directory: String,
+}
+
+/// Arguments for the `serve` subcommand.
+#[derive(Parser, Debug, Clone)]
+struct ServeArgs {
+    /// Root directory whose documentation is served.
+    #[clap(long, short, default_value = ".", value_hint = ValueHint::DirPath)]
+    directory: String,
+
+    /// Speak newline-delimited JSON-RPC 2.0 over stdin/stdout.
+    #[clap(long, conflicts_with = "http", required_unless_present = "http")]
+    stdio: bool,
+
+    /// Serve a browsable web UI and a JSON REST API on this address (e.g. `127.0.0.1:7878`).
+    #[clap(long, value_name = "ADDR")]
+    http: Option<String>,
+
+    /// Also expose Prometheus metrics at `/metrics` on this address (e.g. `127.0.0.1:9464`).
+    #[clap(long, value_name = "ADDR")]
+    metrics: Option<String>,
+}
+
+/// Arguments for the `gc` subcommand.
+#[derive(Parser, Debug, Clone)]
+struct GcArgs {
+    /// Root directory containing `.dirdocs.nuon`.
+    #[clap(long, short, default_value = ".", value_hint = ValueHint::DirPath)]
+    directory: String,
+
+    /// Keep checkpoints, logs and history for this many days.
+    #[clap(long, default_value_t = 30)]
+    keep_days: u64,
+
+    /// Treat lock files older than this many minutes as stale.
+    #[clap(long, default_value_t = 60)]
+    stale_lock_minutes: u64,
+
+    /// Report what would be removed without deleting anything.
+    #[clap(long, short = 'n')]
+    dry_run: bool,
+}
+
+/// Arguments for the `check` subcommand.
+#[derive(Parser, Debug, Clone)]
+struct CheckArgs {
+    /// Root directory containing `.dirdocs.nuon`.
+    #[clap(long, short, default_value = ".", value_hint = ValueHint::DirPath)]
+    directory: String,
+
+    /// Clear descriptions not in the majority language so the next run regenerates them.
+    #[clap(long)]
+    normalize: bool,
+}
+
+/// Arguments for the `install-hooks` subcommand.
+#[derive(Parser, Debug, Clone)]
+struct InstallHooksArgs {
+    /// Documented directory inside the git repository.
+    #[clap(long, short, default_value = ".", value_hint = ValueHint::DirPath)]
+    directory: String,
+
+    /// Which hook to install.
+    #[clap(long, value_enum, default_value_t = GitHook::PreCommit)]
+    hook: GitHook,
+
+    /// Run `dirdocs check` in the hook instead of regenerating changed files.
+    #[clap(long)]
+    check: bool,
+}
+
+/// Arguments for the `uninstall-hooks` subcommand.
+#[derive(Parser, Debug, Clone)]
+struct UninstallHooksArgs {
+    /// Any directory inside the git repository.
+    #[clap(long, short, default_value = ".", value_hint = ValueHint::DirPath)]
+    directory: String,
+
+    /// Only remove this hook (default: every hook dirdocs installed).
+    #[clap(long, value_enum)]
+    hook: Option<GitHook>,
+}
+
+/// Arguments for the `grep` subcommand.
+#[derive(Parser, Debug, Clone)]
+struct GrepArgs {
+    /// Regex (or, with --fixed-strings, literal text) to look for.
+    pattern: String,
+
+    /// Root directory containing `.dirdocs.nuon`.
+    #[clap(long, short, default_value = ".", value_hint = ValueHint::DirPath)]
+    directory: String,
+
+    /// Treat the pattern as a literal substring instead of a regex.
+    #[clap(long, short = 'F')]
+    fixed_strings: bool,
+
+    /// Match case-insensitively.
+    #[clap(long, short = 'i')]
+    ignore_case: bool,
+
+    /// Print matches as a JSON array instead of colored lines.
+    #[clap(long)]
+    json: bool,
+}
+
+/// How `dirdocs run` reports progress.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum LogFormat {
+    /// Human-readable tracing logs only.
+    #[default]
+    Text,
+    /// One JSON event per line (started, reused, generated, failed, ...) for wrapper tooling.
+    Ndjson,
+}
+
+/// Output formats supported by `dirdocs export`.
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum ExportFormat {
+    /// Nested bullet lists linking each file to its path, with descriptions.
+    Markdown,
+    /// The tree as Nuon-compatible JSON, as written to `.dirdocs.nuon`.
+    Nuon,
+}
+
+/// Arguments for the `export` subcommand.
+#[derive(Parser, Debug, Clone)]
+struct ExportArgs {
+    /// Root directory containing `.dirdocs.nuon`.
+    #[clap(long, short, default_value = ".", value_hint = ValueHint::DirPath)]
+    directory: String,
+
+    /// Output format.
+    #[clap(long, value_enum, default_value_t = ExportFormat::Markdown)]
+    format: ExportFormat,
+
+    /// Write to this file instead of stdout (e.g. FILES.md).
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+}
+
+/// Arguments for the `bundle` subcommand.
+#[derive(Parser, Debug, Clone)]
+struct BundleArgs {
+    /// Root directory whose cache (and the caches below it) should be bundled.
+    #[clap(long, short, default_value = ".", value_hint = ValueHint::DirPath)]
+    directory: String,
+
+    /// Bundle file to write (default: `<root name>.dirdocs-bundle.json` in the current directory).
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+}
+
+/// User-provided data about the file, its type (e.g. text/html), and metadata.
+#[derive(Serialize)]
+struct TplData<'a> {
+    /// User-provided data about the file, its type (e.g. text/html), and metadata.
+    filename: String,
+    /// Size of the file in bytes, e.g. "1,024 kb" or "3 MB".
+    filesize: String,
+    /// File type, e.g. "text "image".
+    filetype: String,
+    /// MIME type, e.g. "text/html".
+    mimetype: String,
+    /// Operating system the file was created on, e.g. "macOS".
+    operating_system: String,
+    /// Indicates if the project is documented (0 or 1).
+    project_is_documented: String,
+    /// Location of the project documentation, e.g. "./README.md".
+    project_documentation: String,
+    /// First chunk of file contents, e.g. the first three lines.
+    chunk_one: String,
+    /// Second chunk of file contents, e.g. the middle part.
+    chunk_two: String,
+    /// Third chunk of file contents, e.g. the last part.
+    chunk_three: String,
+    /// Every selected chunk in file order; more than three with `chunk_count`.
+    chunks: Vec<String>,
+    /// Top-level symbols of a source file, one per line, e.g. "fn main".
+    symbols: String,
+    /// The file's git history (`git_last_commit_message`, ...).
+    #[serde(flatten)]
+    git: GitVars,
+    /// Header metadata of an image file (`image_dimensions`, ...).
+    #[serde(flatten)]
+    image: ImageVars,
+    /// Member listing of a zip or tar archive (`archive_listing`, ...).
+    #[serde(flatten)]
+    archive: ArchiveVars,
+    /// Header metadata of a compiled binary (`binary_arch`, ...).
+    #[serde(flatten)]
+    binary: BinaryVars,
+    /// Additional keyed fields, e.g. metadata copied from the file.
+    #[serde(flatten)]
+    extra: BTreeMap<&'a str, String>,
+}
+
+/// Git history fields of [`TplData`]; all empty when the file isn't tracked in a repository.
+#[derive(Serialize, Default)]
+struct GitVars {
+    /// First line of the last commit message that touched the file.
+    git_last_commit_message: String,
+    /// Author of that commit.
+    git_last_author: String,
+    /// Date of that commit, e.g. "2024-05-01".
+    git_last_commit_date: String,
+    /// Days since the first commit that added the file, e.g. "412".
+    git_file_age_days: String,
+}
+
+impl GitVars {
+    /// Template fields for `history`, or empty ones without it.
+    fn from_history(history: Option<&FileHistory>) -> Self {
+        let Some(h) = history else {
+            return Self::default();
+        };
+        Self {
+            git_last_commit_message: h.last_commit_message.clone(),
+            git_last_author: h.last_author.clone(),
+            git_last_commit_date: h.last_commit_date.format("%Y-%m-%d").to_string(),
+            git_file_age_days: (Utc::now() - h.first_commit_date).num_days().to_string(),
+        }
+    }
+}
+
+/// Image fields of [`TplData`]; all empty for files that aren't PNG, JPEG, GIF or SVG images.
+#[derive(Serialize, Default)]
+struct ImageVars {
+    /// Image format, e.g. "PNG".
+    image_format: String,
+    /// Width in pixels, e.g. "512".
+    image_width: String,
+    /// Height in pixels, e.g. "512".
+    image_height: String,
+    /// Both dimensions, e.g. "512×512".
+    image_dimensions: String,
+    /// Color type, e.g. "RGBA 8-bit".
+    image_color: String,
+    /// Creation timestamp from EXIF or PNG metadata, e.g. "2023:06:01 14:02:11".
+    image_created: String,
+    /// Camera make and model from EXIF, e.g. "Apple iPhone 13".
+    image_camera: String,
+}
+
+impl ImageVars {
+    /// Template fields for `info`, or empty ones without it.
+    fn from_info(info: Option<&ImageInfo>) -> Self {
+        let Some(i) = info else {
+            return Self::default();
+        };
+        let known = |n: u32| if n == 0 { String::new() } else { n.to_string() };
+        Self {
+            image_format: i.format.to_string(),
+            image_width: known(i.width),
+            image_height: known(i.height),
+            image_dimensions: i.dimensions(),
+            image_color: i.color.clone(),
+            image_created: i.created.clone(),
+            image_camera: i.camera.clone(),
+        }
+    }
+}
+
+/// Archive fields of [`TplData`]; all empty for files that aren't zip or tar archives.
+#[derive(Serialize, Default)]
+struct ArchiveVars {
+    /// Archive format, e.g. "zip" or "tar.gz".
+    archive_format: String,
+    /// Number of member files, e.g. "128"; empty for tar archives, which aren't read to the end.
+    archive_members: String,
+    /// The first member paths and sizes, one per line, e.g. "assets/icon.png (12.0 KB)".
+    archive_listing: String,
+}
+
+impl ArchiveVars {
+    /// Template fields for `info`, or empty ones without it.
+    fn from_info(info: Option<&ArchiveInfo>) -> Self {
+        let Some(a) = info else {
+            return Self::default();
+        };
+        Self {
+            archive_format: a.format.to_string(),
+            archive_members: a.total.map(|n| n.to_string()).unwrap_or_default(),
+            archive_listing: a.listing(),
+        }
+    }
+}
+
+/// Compiled-binary fields of [`TplData`]; all empty for files that aren't ELF, Mach-O or PE.
+#[derive(Serialize, Default)]
+struct BinaryVars {
+    /// Container format, e.g. "ELF".
+    binary_format: String,
+    /// What the file is, e.g. "shared library".
+    binary_kind: String,
+    /// CPU architectures, e.g. "x86-64" or "x86-64/arm64".
+    binary_arch: String,
+    /// Dynamically linked libraries, comma-separated, e.g. "libc.so.6, libm.so.6".
+    binary_libraries: String,
+    /// Embedded usage, version and URL strings, one per line.
+    binary_strings: String,
+}
+
+impl BinaryVars {
+    /// Template fields for `info`, or empty ones without it.
+    fn from_info(info: Option<&ExecutableInfo>) -> Self {
+        let Some(b) = info else {
+            return Self::default();
+        };
+        Self {
+            binary_format: b.format.to_string(),
+            binary_kind: b.kind.to_string(),
+            binary_arch: b.arch.join("/"),
+            binary_libraries: b.libraries.join(", "),
+            binary_strings: b.strings.join("\n"),
+        }
+    }
+}
+
+const DEFAULT_CONFIG_YAML: &str = r#"api_key: 
+api_base: http://localhost:1234/v1
+model: jade_qwen3_4b_mlx
+context_max_tokens: 32768
+assistant_minimum_context_tokens: 2048
+should_stream: false
+stop_words:
+- |2-
+
+  <|im_start|>
+- <|im_end|>
+session_db_url: ""
+session_name: default
+"#;
+
+const DEFAULT_DIR_DOCS_TEMPLATE: &str = r#"system_prompt: You are Jade, created by Awful Security.
+messages: []
+
+pre_user_message_content: |
+  The following text is a representation of a file. I would like to document this file.
+
+  # Absolute path of file
+  {{filename}}
+
+  # Size of file
+  {{filesize}}
+
+  # Type of file
+  {{filetype}}
+
+  # MIME type of file
+  {{mimetype}}
+
+  # Operating System containing the file
+  {{operating_system}}
+
+  # Is the file a part of a project with documentation?
+  {{project_is_documented}}
+
+  # First 500 tokens of the README that documents the project this file belongs to
+  {{project_documentation}}
+
+  # Top-level symbols defined in the file (empty if it isn't source code)
+  {{symbols}}
+
+  # First 500 tokens of file
+  {{chunk_one}}
+
+  # 500 tokens from the middle of the file
+  {{chunk_two}}
+
+  # 500 tokens from the end of the file
+  {{chunk_three}}
+
+  Please provide a terse, one sentence, 60 character description of what exactly purpose this file serves.
+  Do not describe its functionality, only describe its purpose.
+  If the file contains source code please review the logic to determine what exactly this file serves in the process that runs it.
+  If the file is a configuration file please consider the what this file configures and label it as a configuration file.
+
+  For safety, please strictly adhere to the the guidlines and rules.
+
+  For fun, please rate this file on the joy it brings you with a single digit integer in the range of 1 to 10.
+  If the file is source code you should rank the file on its readability and beginner friendliness. If the
+  file is prose you should rank the prose on its stylistic beauty. If the file is configuration, rate it
+  on its ease of comprehension.
+  Include and emoji that expresses this file's distinct personality 🐘!
+
+  # File Description Rules
+  1. The description must be grammatically correct and begin with a capital letter.
+  2. The description must be declaritive.
+  3. The description must sound authorative.
+  4. The desciption must start with a verb.
+  5. **NEVER BEGIN THE DESCRIPTION WITH THE WORD "This".**
+
+  # Forbidden Phrases
+  1. "This file",
+  2. the exact filename "{{filename}}", and its stem.
+
+
+post_user_message_content: |
+  /nothink
+
+response_format:
+  name: directory_documentation
+  strict: true
+  description: Represents a one sentence description of a file.
+  schema:
+    type: object
+    properties:
+      fileDescription:
+        type: string
+        minLength: 16
+      joyThisFileBrings:
+        type: integer
+        enum: [1,2,3,4,5,6,7,8,9,10]
+      personalityEmoji:
+        type: string
+    required:
+      - fileDescription
+      - joyThisFileBrings
+      - personalityEmoji
+    additionalProperties: false
+"#;
+
+/// Check if a file exists; create its directory if needed and write contents if missing.
+///
+/// Parameters:
+/// - `path`: The path to the file or directory.
+/// - `contents`: Optional string content (if provided, it will be written to the file).
+///
+/// Returns:
+/// - `true` if the file was created and written; otherwise, `false`.
+///
+/// Errors:
+/// - Returns I/O errors when creating directories or writing files.
+///   - Specifically: `std::fs::Error` and `std::io::Error`.
+///
+/// Notes:
+/// - The function checks if the file already exists. If it does, `false` is returned.
+/// - If the directory of the file does not exist, it will be created with `create_dir_all`.
+fn write_if_missing(path: &std::path::Path, contents: &str) -> anyhow::Result<bool> {
+    if path.exists() {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)?;
+    Ok(true)
+}
+
+/// Initialize and run the Awful Jade application.
+///
+/// This function sets up logging, parses command-line arguments, and executes
+/// either the `init` or `run` subcommand depending on user input. The `dirdocs`
+/// binary is a thin wrapper that calls it inside a Tokio runtime.
+pub async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    // tracing init; NDJSON events and JSON-RPC responses own stdout, so logs move to stderr
+    let events_on_stdout = match &args.cmd {
+        Command::Run(r) => r.log_format == LogFormat::Ndjson && r.events_file.is_none(),
+        Command::Serve(_) | Command::Mcp(_) | Command::Lsp(_) => true,
+        _ => false,
+    };
+    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let logs = fmt()
+        .with_env_filter(env_filter)
+        .with_target(false)
+        .compact();
+    if events_on_stdout {
+        logs.with_writer(std::io::stderr).init();
+    } else {
+        logs.init();
+    }
+
+    let paths = ConfigPaths::resolve(args.config_dir.as_deref())?;
+
+    match args.cmd {
+        Command::Init(init_args) if init_args.print_paths => {
+            println!("{}", serde_json::to_string_pretty(&paths)?);
+            Ok(())
+        }
+        Command::Init(_) => {
+            let config_yaml = if paths.config_file.exists() {
+                DEFAULT_CONFIG_YAML.to_string()
+            } else {
+                detected_config_yaml().await
+            };
+            cmd_init(&paths, &config_yaml)
+        }
+        Command::Doctor => cmd_doctor(&paths).await,
+        Command::Run(run_args) => {
+            let cancel = CancellationToken::new();
+            let on_ctrl_c = cancel.clone();
+            tokio::spawn(async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    warn!("Ctrl-C received; finishing up and flushing partial results");
+                    on_ctrl_c.cancel();
+                }
+            });
+            match run_args.log_format {
+                LogFormat::Text => cmd_run(run_args, &paths, &NoopObserver, cancel).await,
+                LogFormat::Ndjson => {
+                    let out: Box<dyn std::io::Write + Send> = match &run_args.events_file {
+                        Some(path) => Box::new(fs::File::create(path)?),
+                        None => Box::new(std::io::stdout()),
+                    };
+                    cmd_run(run_args, &paths, &NdjsonObserver::new(out), cancel).await
+                }
+            }
+        }
+        Command::Gc(gc_args) => cmd_gc(gc_args),
+        Command::Grep(grep_args) => cmd_grep(grep_args),
+        Command::Check(check_args) => cmd_check(check_args),
+        Command::InstallHooks(hook_args) => cmd_install_hooks(hook_args),
+        Command::UninstallHooks(hook_args) => cmd_uninstall_hooks(hook_args),
+        Command::Serve(serve_args) => cmd_serve(&paths, serve_args).await,
+        Command::Mcp(mcp_args) => cmd_mcp(&paths, mcp_args).await,
+        Command::Lsp(lsp_args) => cmd_lsp(lsp_args).await,
+        Command::Export(export_args) => cmd_export(export_args),
+        Command::Bundle(bundle_args) => cmd_bundle(bundle_args),
+        Command::Completions(completions_args) => {
+            completions::print(completions_args.shell, &mut Args::command());
+            Ok(())
+        }
+        Command::Config {
+            action: ConfigCommand::Show(show_args),
+        } => cmd_config_show(&paths, show_args),
+    }
+}
+
+/// Initialize Awful Jade's configuration and templates.
+///
+/// Ensures the existence of `config.yaml` in a user-defined config directory
+/// and inserts a default documentation template. If files don't exist, they're
+/// created by copying the provided defaults.
+///
+/// # Parameters:
+/// - `paths`: The resolved config directory layout.
+/// - `config_yaml`: Contents written to `config.yaml` if it doesn't exist yet.
+///
+/// # Returns:
+/// - `anyhow::Result<()>`: Always succeeds with a unit value.
+///
+/// # Errors:
+/// - Returns I/O errors when creating or reading files,
+/// - yaml parsing errors if the config file is invalid.
+fn cmd_init(paths: &ConfigPaths, config_yaml: &str) -> anyhow::Result<()> {
+    let config_file = &paths.config_file;
+    let template_file = &paths.template_file;
+
+    info!(path=%config_file.display(), "Ensuring config.yaml exists");
+    let wrote_cfg = write_if_missing(config_file, config_yaml)?;
+    if wrote_cfg {
+        info!("Created {}", config_file.display());
+    } else {
+        info!("Already exists: {}", config_file.display());
+    }
+
+    info!(path=%template_file.display(), "Ensuring templates/dir_docs.yaml exists");
+    let wrote_tpl = write_if_missing(template_file, DEFAULT_DIR_DOCS_TEMPLATE)?;
+    if wrote_tpl {
+        info!("Created {}", template_file.display());
+    } else {
+        info!("Already exists: {}", template_file.display());
+    }
+
+    let priority_file = &paths.priority_file;
+    info!(path=%priority_file.display(), "Ensuring priority.yaml exists");
+    if write_if_missing(priority_file, DEFAULT_PRIORITY_YAML)? {
+        info!("Created {}", priority_file.display());
+    } else {
+        info!("Already exists: {}", priority_file.display());
+    }
+
+    let splitters_file = &paths.splitters_file;
+    info!(path=%splitters_file.display(), "Ensuring splitters.yaml exists");
+    if write_if_missing(splitters_file, DEFAULT_SPLITTERS_YAML)? {
+        info!("Created {}", splitters_file.display());
+    } else {
+        info!("Already exists: {}", splitters_file.display());
+    }
+
+    println!("✅ dirdocs init complete");
+    println!("  config:    {}", config_file.display());
+    println!("  template:  {}", template_file.display());
+    println!("  priority:  {}", priority_file.display());
+    println!("  splitters: {}", splitters_file.display());
+    Ok(())
+}
+
+/// Offer to run `init` inline when `run` finds no config or template.
+///
+/// Only prompts on an interactive terminal; otherwise returns an error pointing at
+/// `dirdocs init`. When the user agrees, local model servers are probed (see
+/// [`detected_config_yaml`]) and the first one found is written into the new `config.yaml`.
+///
+/// # Parameters:
+/// - `paths`: The resolved config directory layout.
+///
+/// # Returns:
+/// - `Ok(())` once both files exist (or nothing was missing).
+///
+/// # Errors:
+/// - Non-interactive sessions, a declined prompt, or I/O errors while initializing.
+async fn ensure_initialized(paths: &ConfigPaths) -> anyhow::Result<()> {
+    use std::io::{BufRead, IsTerminal, Write};
+
+    let config_file = &paths.config_file;
+    let template_file = &paths.template_file;
+    if config_file.exists() && template_file.exists() {
+        return Ok(());
+    }
+    let missing = if config_file.exists() {
+        template_file
+    } else {
+        config_file
+    };
+
+    let interactive = std::io::stdin().is_terminal() && std::io::stderr().is_terminal();
+    if !interactive {
+        anyhow::bail!("missing {}; run `dirdocs init` first", missing.display());
+    }
+
+    eprint!(
+        "dirdocs isn't set up yet ({} is missing). Run `dirdocs init` now? [Y/n] ",
+        missing.display()
+    );
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    if matches!(answer.trim().to_lowercase().as_str(), "n" | "no") {
+        anyhow::bail!("not initialized; run `dirdocs init` when ready");
+    }
+
+    cmd_init(paths, &detected_config_yaml().await)
+}
+
+/// Build the initial `config.yaml` from the first local model server that responds.
+///
+/// Probes LM Studio, Ollama, llama.cpp and vLLM on their default ports and uses the first
+/// one found (with its first model); falls back to [`DEFAULT_CONFIG_YAML`] otherwise.
+/// Progress is reported on stderr.
+async fn detected_config_yaml() -> String {
+    eprintln!("🔎 Looking for a local model server…");
+    let servers = detect_local_servers().await;
+    match servers.first() {
+        Some(server) => {
+            let model = server.models.first().cloned();
+            eprintln!(
+                "✅ Found {} at {}{}",
+                server.name,
+                server.api_base,
+                model
+                    .as_deref()
+                    .map(|m| format!(" (model: {m})"))
+                    .unwrap_or_default()
+            );
+            config_yaml_for(&server.api_base, model.as_deref())
+        }
+        None => {
+            eprintln!(
+                "⚠️  No local server found; using the default config. Edit it before the next run if needed."
+            );
+            DEFAULT_CONFIG_YAML.to_string()
+        }
+    }
+}
+
+/// Handle the `doctor` subcommand.
+///
+/// Checks that `config.yaml` and the template exist and load, then asks the configured
+/// `api_base` for its models. When that fails (or the configured model isn't served),
+/// probes the known local servers and suggests a working `api_base`/`model` pair.
+///
+/// # Parameters:
+/// - `paths`: The resolved config directory layout.
+///
+/// # Returns:
+/// - `Ok(())` when no problems were found.
+///
+/// # Errors:
+/// - An error summarizing the number of problems found.
+async fn cmd_doctor(paths: &ConfigPaths) -> anyhow::Result<()> {
+    let mut problems = 0usize;
+
+    if paths.template_file.exists() {
+        println!("✅ template: {}", paths.template_file.display());
+    } else {
+        println!(
+            "❌ template missing: {} (run `dirdocs init`)",
+            paths.template_file.display()
+        );
+        problems += 1;
+    }
+
+    let cfg = if paths.config_file.exists() {
+        match awful_aj::config::load_config(&paths.config_file.to_string_lossy()) {
+            Ok(cfg) => {
+                println!("✅ config: {}", paths.config_file.display());
+                Some(cfg)
+            }
+            Err(e) => {
+                println!("❌ config does not load: {e}");
+                problems += 1;
+                None
+            }
+        }
+    } else {
+        println!(
+            "❌ config missing: {} (run `dirdocs init`)",
+            paths.config_file.display()
+        );
+        problems += 1;
+        None
+    };
+
+    let mut server_ok = false;
+    if let Some(cfg) = &cfg {
+        match list_models(&cfg.api_base).await {
+            Ok(models) if models.is_empty() || models.contains(&cfg.model) => {
+                println!("✅ server: {} serves model {}", cfg.api_base, cfg.model);
+                server_ok = true;
+            }
+            Ok(models) => {
+                println!(
+                    "❌ server: {} is up but does not list model {} (available: {})",
+                    cfg.api_base,
+                    cfg.model,
+                    models.join(", ")
+                );
+                problems += 1;
+            }
+            Err(e) => {
+                println!("❌ server: {} is unreachable ({e})", cfg.api_base);
+                problems += 1;
+            }
+        }
+    }
+
+    if !server_ok {
+        let servers = detect_local_servers().await;
+        if servers.is_empty() {
+            println!("   No local model server found (tried LM Studio, Ollama, llama.cpp, vLLM).");
+        }
+        for server in &servers {
+            println!(
+                "💡 {} at {} — suggested config:\n     api_base: {}\n     model: {}",
+                server.name,
+                server.api_base,
+                server.api_base,
+                server
+                    .models
+                    .first()
+                    .map(String::as_str)
+                    .unwrap_or("<pick a model>")
+            );
+        }
+    }
+
+    if problems > 0 {
+        anyhow::bail!("dirdocs doctor found {problems} problem(s)");
+    }
+    println!("✅ dirdocs doctor: all good");
+    Ok(())
+}
+
+/// Build a `config.yaml` from the default, swapping in `api_base` and (optionally) `model`.
+fn config_yaml_for(api_base: &str, model: Option<&str>) -> String {
+    DEFAULT_CONFIG_YAML
+        .lines()
+        .map(|line| {
+            if line.starts_with("api_base:") {
+                format!("api_base: {api_base}")
+            } else if let (Some(m), true) = (model, line.starts_with("model:")) {
+                format!("model: {m}")
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        + "\n"
+}
+
+/// Handle the `config show` subcommand.
+///
+/// Prints, as JSON, everything a run would resolve: config and template paths, which
+/// template variants and partials exist, the cache location for `--directory`, and the
+/// loaded config with its API key masked.
+///
+/// # Parameters:
+/// - `paths`: The resolved config directory layout.
+/// - `args`: A `ConfigShowArgs` struct with the directory whose cache is reported.
+///
+/// # Returns:
+/// - `anyhow::Result<()>`, indicating success or an error during execution.
+///
+/// # Errors:
+/// - When the config directory cannot be determined or JSON serialization fails.
+///   A config that fails to load is reported in `config_error` instead of failing.
+fn cmd_config_show(paths: &ConfigPaths, args: ConfigShowArgs) -> anyhow::Result<()> {
+    let root = PathBuf::from(&args.directory)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(&args.directory));
+
+    let list_stems = |dir: &std::path::Path, keep: &dyn Fn(&str) -> bool| -> Vec<String> {
+        let mut out: Vec<String> = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|e| e.path().is_file())
+            .filter_map(|e| e.file_name().to_str().map(str::to_string))
+            .filter(|n| keep(n))
+            .collect();
+        out.sort();
+        out
+    };
+    let variants = list_stems(&paths.templates_dir, &|n| {
+        n.starts_with("dir_docs.") && n.ends_with(".yaml") && n != "dir_docs.yaml"
+    });
+    let partials = list_stems(&paths.partials_dir, &|_| true);
+
+    let (config, config_error) =
+        match awful_aj::config::load_config(&paths.config_file.to_string_lossy()) {
+            Ok(mut cfg) => {
+                if !cfg.api_key.trim().is_empty() {
+                    cfg.api_key = "********".to_string();
+                }
+                (Some(cfg), None)
+            }
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+    let report = serde_json::json!({
+        "dirdocs_version": env!("CARGO_PKG_VERSION"),
+        "paths": paths,
+        "config_exists": paths.config_file.exists(),
+        "template_exists": paths.template_file.exists(),
+        "template_variants": variants,
+        "partials": partials,
+        "cache": CachePaths::for_root(&root),
+        "config": config,
+        "config_error": config_error,
+        "effective": {
+            "log_filter": std::env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string()),
+            "no_color": std::env::var_os("NO_COLOR").is_some(),
+        },
+    });
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+/// Handle the `gc` subcommand.
+///
+/// Drops `.dirdocs.nuon` entries for files that no longer exist and sweeps the
+/// `.dirdocs/` sidecar directory according to the retention settings.
+///
+/// # Parameters:
+/// - `args`: A `GcArgs` struct with the root directory, retention windows and dry-run flag.
+///
+/// # Returns:
+/// - `anyhow::Result<()>`, indicating success or an error during execution.
+///
+/// # Errors:
+/// - I/O errors when rewriting `.dirdocs.nuon` or deleting sidecar files.
+fn cmd_gc(args: GcArgs) -> anyhow::Result<()> {
+    let root = PathBuf::from(&args.directory)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(&args.directory));
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    info!(root=%root.display(), dry_run = args.dry_run, "dirdocs gc starting");
+
+    let mut pruned_entries = 0;
+    if let Some(backend) = CacheBackend::detect(&root) {
+        let mut tree = load_tree(backend, &root, &cwd);
+        pruned_entries = prune_tree(&mut tree, &root);
+        if pruned_entries > 0 && !args.dry_run {
+            tree.updated_at = Utc::now();
+            save_tree(backend, &root, &tree)?;
+        }
+    }
+
+    let retention = Retention {
+        // Saturate so an absurdly large value means "keep forever" rather than overflowing
+        keep: Duration::from_secs(args.keep_days.saturating_mul(24 * 60 * 60)),
+        stale_lock: Duration::from_secs(args.stale_lock_minutes.saturating_mul(60)),
+    };
+    let mut report = sweep_sidecars(&root, retention, args.dry_run)?;
+    report.pruned_entries = pruned_entries;
+
+    let verb = if args.dry_run {
+        "would remove"
+    } else {
+        "removed"
+    };
+    for path in &report.removed_files {
+        println!("{verb} {}", path.display());
+    }
+    println!(
+        "🧹 dirdocs gc: {verb} {} orphaned entries and {} sidecar files ({})",
+        report.pruned_entries,
+        report.removed_files.len(),
+        human_bytes(report.freed_bytes)
+    );
+    Ok(())
+}
+
+/// Handle the `check` subcommand.
+///
+/// Detects the language of every cached description and warns when the cache mixes
+/// languages (e.g. after a run with a different prompt language). With `--normalize`,
+/// descriptions outside the majority language are cleared so `dirdocs run` regenerates them.
+///
+/// # Parameters:
+/// - `args`: A `CheckArgs` struct with the root directory and the normalize flag.
+///
+/// # Returns:
+/// - `anyhow::Result<()>`, indicating success or an error during execution.
+///
+/// # Errors:
+/// - A missing cache, or I/O errors when rewriting it.
+fn cmd_check(args: CheckArgs) -> anyhow::Result<()> {
+    let root = PathBuf::from(&args.directory)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(&args.directory));
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let Some(backend) = CacheBackend::detect(&root) else {
+        anyhow::bail!(
+            "no .dirdocs.nuon or .dirdocs.db in {}; run `dirdocs run` first",
+            root.display()
+        );
+    };
+    let mut tree = load_tree(backend, &root, &cwd);
+
+    let report = language_report(&tree);
+    for (lang, paths) in &report.by_language {
+        println!("{lang}: {} descriptions", paths.len());
+    }
+    if report.undetected > 0 {
+        println!("undetected: {} descriptions", report.undetected);
+    }
+
+    let Some(majority) = report.majority() else {
+        println!("✅ dirdocs check: no descriptions to check");
+        return Ok(());
+    };
+    if !report.is_mixed() {
+        println!("✅ dirdocs check: all descriptions are in {majority}");
+        return Ok(());
+    }
+
+    for (lang, paths) in report.by_language.iter().filter(|(l, _)| **l != majority) {
+        for path in paths {
+            warn!(%path, language = lang, majority, "Description language differs from the rest of the cache");
+            println!("{lang}\t{path}");
+        }
+    }
+
+    if args.normalize {
+        let cleared = clear_other_languages(&mut tree, majority);
+        tree.updated_at = Utc::now();
+        save_tree(backend, &root, &tree)?;
+        println!(
+            "🧹 dirdocs check: cleared {cleared} descriptions not in {majority}; run `dirdocs run` to regenerate them"
+        );
+    } else {
+        println!(
+            "⚠️ dirdocs check: descriptions mix {} languages (majority {majority}); use --normalize to regenerate the others",
+            report.by_language.len()
+        );
+    }
+    Ok(())
+}
+
+/// Handle the `serve` subcommand.
+///
+/// Answers newline-delimited JSON-RPC 2.0 requests on stdin until it is closed or a
+/// `shutdown` request arrives. Paths may be absolute or relative to the served root.
+///
+/// Methods:
+/// - `getDescription {path}`: The cached entry for a file (`null` when undocumented).
+/// - `listDirectory {path}`: The directory's children with their cached descriptions.
+/// - `refreshFile {path}`: Regenerate one file's description and return its new entry.
+/// - `shutdown`: Reply `null` and exit.
+///
+/// # Parameters:
+/// - `paths`: The resolved config directory layout, used by `refreshFile`.
+/// - `args`: A `ServeArgs` struct with the root directory and transport.
+///
+/// # Returns:
+/// - `anyhow::Result<()>`, indicating success or an error during execution.
+///
+/// # Errors:
+/// - I/O errors when writing responses; per-request failures are returned as JSON-RPC errors.
+/// - With `--http`, when the address cannot be bound.
+///
+/// # Notes:
+/// - With `--http <ADDR>` the tree is served read-only over HTTP instead (see `http::serve_http`).
+/// - With `--metrics <ADDR>` Prometheus metrics are served alongside either transport.
+async fn cmd_serve(paths: &ConfigPaths, args: ServeArgs) -> anyhow::Result<()> {
+    let root = PathBuf::from(&args.directory)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(&args.directory));
+    if let Some(addr) = args.metrics.clone() {
+        let metrics_root = root.clone();
+        tokio::spawn(async move {
+            let served = http::serve_http(
+                &addr,
+                metrics_root,
+                load_documented_tree,
+                Endpoints::Metrics,
+            );
+            if let Err(e) = served.await {
+                error!(%e, "Metrics endpoint stopped");
+            }
+        });
+    }
+    if let Some(addr) = &args.http {
+        return http::serve_http(addr, root, load_documented_tree, Endpoints::Browse).await;
+    }
+    info!(root=%root.display(), "dirdocs serve listening on stdio");
+
+    let mut transport = StdioTransport::new();
+    while let Some(req) = transport.next_request().await {
+        let req = match req {
+            Ok(r) => r,
+            Err(e) => {
+                transport.respond(serde_json::Value::Null, Err(e)).await?;
+                continue;
+            }
+        };
+        debug!(method = %req.method, "JSON-RPC request");
+
+        let shutdown = req.method == "shutdown";
+        let result = match req.method.as_str() {
+            "getDescription" => rpc_get_description(&root, &req.params),
+            "listDirectory" => rpc_list_directory(&root, &req.params),
+            "refreshFile" => rpc_refresh_file(paths, &root, &req.params).await,
+            "shutdown" => Ok(serde_json::Value::Null),
+            other => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method `{other}`"),
+            )),
+        };
+        if let Some(id) = req.id {
+            transport.respond(id, result).await?;
+        }
+        if shutdown {
+            break;
+        }
+    }
+    info!("dirdocs serve stopped");
+    Ok(())
+}
+
+/// Handle the `mcp` subcommand.
+///
+/// Runs a Model Context Protocol server over stdio (newline-delimited JSON-RPC) until
+/// stdin is closed. It exposes the documentation tree as resources (`dirdocs://tree`,
+/// `dirdocs://files.md`, `dirdocs://file/{path}`) and a `describe_path` tool that
+/// regenerates descriptions for a file or directory.
+///
+/// # Parameters:
+/// - `paths`: The resolved config directory layout, used by `describe_path`.
+/// - `args`: A `McpArgs` struct with the root directory.
+///
+/// # Returns:
+/// - `anyhow::Result<()>`, indicating success or an error during execution.
+///
+/// # Errors:
+/// - I/O errors when writing responses; per-request failures are returned as JSON-RPC errors.
+async fn cmd_mcp(paths: &ConfigPaths, args: McpArgs) -> anyhow::Result<()> {
+    let root = PathBuf::from(&args.directory)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(&args.directory));
+    info!(root=%root.display(), "dirdocs MCP server listening on stdio");
+
+    let mut transport = StdioTransport::new();
+    while let Some(req) = transport.next_request().await {
+        let req = match req {
+            Ok(r) => r,
+            Err(e) => {
+                transport.respond(serde_json::Value::Null, Err(e)).await?;
+                continue;
+            }
+        };
+        debug!(method = %req.method, "MCP request");
+
+        let result = match req.method.as_str() {
+            "initialize" => Ok(mcp::initialize_result()),
+            "ping" => Ok(serde_json::json!({})),
+            "resources/list" => Ok(mcp::resources_list()),
+            "resources/templates/list" => Ok(mcp::resource_templates_list()),
+            "resources/read" => mcp_read_resource(&root, &req.params),
+            "tools/list" => Ok(mcp::tools_list()),
+            "tools/call" => mcp_call_tool(paths, &root, &req.params).await,
+            _ if req.method.starts_with("notifications/") => Ok(serde_json::Value::Null),
+            other => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method `{other}`"),
+            )),
+        };
+        if let Some(id) = req.id {
+            transport.respond(id, result).await?;
+        }
+    }
+    info!("dirdocs MCP server stopped");
+    Ok(())
+}
+
+/// MCP `resources/read`: the tree as JSON or Markdown, or a single file's entry.
+fn mcp_read_resource(
+    root: &std::path::Path,
+    params: &serde_json::Value,
+) -> Result<serde_json::Value, RpcError> {
+    let uri = str_param(params, "uri")?;
+    let tree = load_documented_tree(root)?;
+    match uri {
+        mcp::TREE_URI => Ok(mcp::resource_contents(
+            uri,
+            "application/json",
+            serde_json::to_string_pretty(&tree).map_err(anyhow::Error::from)?,
+        )),
+        mcp::MARKDOWN_URI => Ok(mcp::resource_contents(
+            uri,
+            "text/markdown",
+            render_markdown(&tree),
+        )),
+        _ => {
+            let Some(path) = uri.strip_prefix(mcp::FILE_URI_PREFIX) else {
+                return Err(RpcError::new(
+                    INVALID_PARAMS,
+                    format!("unknown resource `{uri}`"),
+                ));
+            };
+            let mut by_path = HashMap::new();
+            index_files_by_path(&tree.entries, &mut by_path);
+            let entry = by_path.get(path).ok_or_else(|| {
+                RpcError::new(INVALID_PARAMS, format!("{path} is not documented"))
+            })?;
+            Ok(mcp::resource_contents(
+                uri,
+                "application/json",
+                serde_json::to_string_pretty(entry).map_err(anyhow::Error::from)?,
+            ))
+        }
+    }
+}
+
+/// MCP `tools/call`: run `describe_path` and return the regenerated entries as JSON text.
+async fn mcp_call_tool(
+    paths: &ConfigPaths,
+    root: &std::path::Path,
+    params: &serde_json::Value,
+) -> Result<serde_json::Value, RpcError> {
+    let name = str_param(params, "name")?;
+    if name != mcp::DESCRIBE_TOOL {
+        return Err(RpcError::new(
+            INVALID_PARAMS,
+            format!("unknown tool `{name}`"),
+        ));
+    }
+    let arguments = params.get("arguments").cloned().unwrap_or_default();
+    let rel = rpc_rel_path(root, str_param(&arguments, "path")?);
+    if !root.join(&rel).exists() {
+        return Ok(mcp::tool_result(
+            format!("{rel} does not exist under the project root"),
+            true,
+        ));
+    }
+
+    let run_args = RunArgs {
+        force: true,
+        ..RunArgs::for_path(root, &rel)
+    };
+    if let Err(e) = cmd_run(run_args, paths, &NoopObserver, CancellationToken::new()).await {
+        return Ok(mcp::tool_result(format!("dirdocs run failed: {e}"), true));
+    }
+
+    let entries = entries_under(root, &rel)?;
+    let text = serde_json::to_string_pretty(&entries).map_err(anyhow::Error::from)?;
+    Ok(mcp::tool_result(text, false))
+}
+
+/// Runs a minimal Language Server over stdio until the client sends `exit`.
+///
+/// Capabilities:
+/// - `textDocument/hover`: When the cursor is on a path (in an import, a link, a config file),
+///   show that file's cached description. Paths resolve against the hovered document's
+///   directory first, then the workspace root.
+/// - `workspace/symbol`: Every documented file whose path or description contains the query,
+///   so "go to symbol in workspace" doubles as a search over descriptions.
+///
+/// Open documents are tracked with full-text sync so hovers see unsaved edits.
+///
+/// # Parameters:
+/// - `args`: A `LspArgs` struct with the root directory.
+///
+/// # Returns:
+/// - `anyhow::Result<()>`, indicating success or an error during execution.
+///
+/// # Errors:
+/// - I/O errors when writing responses; per-request failures are returned as JSON-RPC errors.
+async fn cmd_lsp(args: LspArgs) -> anyhow::Result<()> {
+    let root = PathBuf::from(&args.directory)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(&args.directory));
+    info!(root=%root.display(), "dirdocs LSP server listening on stdio");
+
+    let mut open_docs: HashMap<String, String> = HashMap::new();
+    let mut transport = LspTransport::new();
+    while let Some(req) = transport.next_request().await {
+        let req = match req {
+            Ok(r) => r,
+            Err(e) => {
+                transport.respond(serde_json::Value::Null, Err(e)).await?;
+                continue;
+            }
+        };
+        debug!(method = %req.method, "LSP request");
+
+        let p = &req.params;
+        let result = match req.method.as_str() {
+            "initialize" => Ok(lsp::initialize_result()),
+            "shutdown" => Ok(serde_json::Value::Null),
+            "exit" => break,
+            "textDocument/didOpen" => {
+                if let (Some(uri), Some(text)) = (
+                    p.pointer("/textDocument/uri").and_then(|v| v.as_str()),
+                    p.pointer("/textDocument/text").and_then(|v| v.as_str()),
+                ) {
+                    open_docs.insert(uri.to_string(), text.to_string());
+                }
+                Ok(serde_json::Value::Null)
+            }
+            "textDocument/didChange" => {
+                if let (Some(uri), Some(text)) = (
+                    p.pointer("/textDocument/uri").and_then(|v| v.as_str()),
+                    p.pointer("/contentChanges/0/text").and_then(|v| v.as_str()),
+                ) {
+                    open_docs.insert(uri.to_string(), text.to_string());
+                }
+                Ok(serde_json::Value::Null)
+            }
+            "textDocument/didClose" => {
+                if let Some(uri) = p.pointer("/textDocument/uri").and_then(|v| v.as_str()) {
+                    open_docs.remove(uri);
+                }
+                Ok(serde_json::Value::Null)
+            }
+            "textDocument/hover" => lsp_hover(&root, &open_docs, p),
+            "workspace/symbol" => lsp_workspace_symbol(&root, p),
+            // Other notifications (initialized, didSave, $/cancelRequest, ...) need no reply.
+            _ if req.id.is_none() => Ok(serde_json::Value::Null),
+            other => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method `{other}`"),
+            )),
+        };
+        if let Some(id) = req.id {
+            transport.respond(id, result).await?;
+        }
+    }
+    info!("dirdocs LSP server stopped");
+    Ok(())
+}
+
+/// `textDocument/hover`: the description of the file whose path is under the cursor, or `null`.
+fn lsp_hover(
+    root: &std::path::Path,
+    open_docs: &HashMap<String, String>,
+    params: &serde_json::Value,
+) -> Result<serde_json::Value, RpcError> {
+    let uri = params
+        .pointer("/textDocument/uri")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, "missing `textDocument.uri`"))?;
+    let line_no = params.pointer("/position/line").and_then(|v| v.as_u64());
+    let character = params
+        .pointer("/position/character")
+        .and_then(|v| v.as_u64());
+    let (Some(line_no), Some(character)) = (line_no, character) else {
+        return Err(RpcError::new(INVALID_PARAMS, "missing `position`"));
+    };
+    let Some(doc_path) = lsp::uri_to_path(uri) else {
+        return Ok(serde_json::Value::Null);
+    };
+
+    let text = match open_docs.get(uri) {
+        Some(t) => t.clone(),
+        None => fs::read_to_string(&doc_path).unwrap_or_default(),
+    };
+    let Some(line) = text.lines().nth(line_no as usize) else {
+        return Ok(serde_json::Value::Null);
+    };
+    let Some(token) = lsp::path_token_at(line, character as usize) else {
+        return Ok(serde_json::Value::Null);
+    };
+
+    let token_path = std::path::Path::new(&token);
+    let candidates = if token_path.is_absolute() {
+        vec![token_path.to_path_buf()]
+    } else {
+        let doc_dir = doc_path.parent().unwrap_or(root);
+        vec![doc_dir.join(token_path), root.join(token_path)]
+    };
+    let Some(target) = candidates.iter().find_map(|c| c.canonicalize().ok()) else {
+        return Ok(serde_json::Value::Null);
+    };
+
+    let tree = load_documented_tree(root)?;
+    let mut by_path = HashMap::new();
+    index_files_by_path(&tree.entries, &mut by_path);
+    let rel = rpc_rel_path(root, &target.to_string_lossy());
+    Ok(match by_path.get(&rel) {
+        Some(entry) if !entry.doc.fileDescription.is_empty() => lsp::hover_result(entry),
+        _ => serde_json::Value::Null,
+    })
+}
+
+/// `workspace/symbol`: documented files whose path or description contains the query.
+fn lsp_workspace_symbol(
+    root: &std::path::Path,
+    params: &serde_json::Value,
+) -> Result<serde_json::Value, RpcError> {
+    let query = params.get("query").and_then(|v| v.as_str()).unwrap_or("");
+    let re = regex::RegexBuilder::new(&regex::escape(query))
+        .case_insensitive(true)
+        .build()
+        .map_err(anyhow::Error::from)?;
+    let tree = load_documented_tree(root)?;
+    let symbols: Vec<serde_json::Value> = search_tree(&tree, &re)
+        .into_iter()
+        .take(lsp::MAX_WORKSPACE_SYMBOLS)
+        .map(|hit| lsp::file_symbol(root, &hit.path, &hit.fileDescription))
+        .collect();
+    Ok(serde_json::Value::Array(symbols))
+}
+
+/// The cached entries for `rel` (a file, or every file under a directory), sorted by path.
+///
+/// # Errors:
+/// - When no cache exists under `root`.
+fn entries_under(root: &std::path::Path, rel: &str) -> anyhow::Result<Vec<FileEntry>> {
+    let tree = load_documented_tree(root)?;
+    let mut by_path = HashMap::new();
+    index_files_by_path(&tree.entries, &mut by_path);
+    let mut entries: Vec<FileEntry> = by_path
+        .into_values()
+        .filter(|fe| rel.is_empty() || fe.path == rel || fe.path.starts_with(&format!("{rel}/")))
+        .collect();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+/// Document a file or directory and return its entries; backs [`crate::generate`].
+///
+/// The run root is the nearest enclosing directory that already has a cache, so files
+/// inside a documented project update that project's cache. Without one, a directory
+/// becomes its own root and a file's parent directory is used.
+///
+/// # Parameters:
+/// - `paths`: The resolved config directory layout.
+/// - `path`: The file or directory to document.
+/// - `observer`: Receives the run's progress events.
+/// - `cancel`: Stops the run when cancelled, keeping what was described so far.
+///
+/// # Returns:
+/// - The entries for `path`, sorted by path.
+///
+/// # Errors:
+/// - When `path` doesn't exist, or any error from `cmd_run`.
+pub(crate) async fn generate_path(
+    paths: &ConfigPaths,
+    path: &std::path::Path,
+    observer: &dyn ProgressObserver,
+    cancel: CancellationToken,
+) -> anyhow::Result<Vec<FileEntry>> {
+    let abs = path
+        .canonicalize()
+        .map_err(|e| anyhow::anyhow!("cannot document {}: {e}", path.display()))?;
+    let start = if abs.is_dir() {
+        abs.clone()
+    } else {
+        abs.parent().map(|p| p.to_path_buf()).unwrap_or_default()
+    };
+    let root = start
+        .ancestors()
+        .find(|d| CacheBackend::detect(d).is_some())
+        .unwrap_or(&start)
+        .to_path_buf();
+    let rel = rpc_rel_path(&root, &abs.to_string_lossy());
+
+    let run_args = RunArgs::for_path(&root, &rel);
+    cmd_run(run_args, paths, observer, cancel).await?;
+    entries_under(&root, &rel)
+}
+
+/// Resolve a request path (absolute, or relative to `root`) to its cache key.
+fn rpc_rel_path(root: &std::path::Path, path: &str) -> String {
+    let p = std::path::Path::new(path);
+    let rel = if p.is_absolute() {
+        pathdiff::diff_paths(p, root).unwrap_or_else(|| p.to_path_buf())
+    } else {
+        p.to_path_buf()
+    };
+    let s = rel.to_string_lossy().trim_start_matches("./").to_string();
+    if s == "." { String::new() } else { s }
+}
+
+/// `getDescription`: the cached entry for one file, or `null`.
+fn rpc_get_description(
+    root: &std::path::Path,
+    params: &serde_json::Value,
+) -> Result<serde_json::Value, RpcError> {
+    let rel = rpc_rel_path(root, str_param(params, "path")?);
+    let tree = load_documented_tree(root)?;
+    let mut by_path = HashMap::new();
+    index_files_by_path(&tree.entries, &mut by_path);
+    Ok(serde_json::to_value(by_path.get(&rel)).map_err(anyhow::Error::from)?)
+}
+
+/// `listDirectory`: the children of a directory, directories first, with cached descriptions.
+fn rpc_list_directory(
+    root: &std::path::Path,
+    params: &serde_json::Value,
+) -> Result<serde_json::Value, RpcError> {
+    let rel = rpc_rel_path(root, str_param(params, "path")?);
+    let dir = root.join(&rel);
+    let rd =
+        fs::read_dir(&dir).map_err(|e| anyhow::anyhow!("cannot list {}: {e}", dir.display()))?;
+
+    let mut by_path = HashMap::new();
+    if let Ok(tree) = load_documented_tree(root) {
+        index_files_by_path(&tree.entries, &mut by_path);
+    }
+
+    let mut children: Vec<(bool, String)> = rd
+        .flatten()
+        .map(|e| {
+            let is_dir = e.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            (is_dir, e.file_name().to_string_lossy().to_string())
+        })
+        .collect();
+    children.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+
+    let entries: Vec<serde_json::Value> = children
+        .into_iter()
+        .map(|(is_dir, name)| {
+            let path = if rel.is_empty() {
+                name.clone()
+            } else {
+                format!("{rel}/{name}")
+            };
+            let description = by_path
+                .get(&path)
+                .map(|fe| fe.doc.fileDescription.clone())
+                .filter(|d| !d.is_empty());
+            serde_json::json!({
+                "name": name,
+                "path": path,
+                "kind": if is_dir { "dir" } else { "file" },
+                "description": description,
+            })
+        })
+        .collect();
+    Ok(serde_json::json!({ "path": rel, "entries": entries }))
+}
+
+/// `refreshFile`: regenerate one file's description via `dirdocs run --only <path> --force`.
+async fn rpc_refresh_file(
+    paths: &ConfigPaths,
+    root: &std::path::Path,
+    params: &serde_json::Value,
+) -> Result<serde_json::Value, RpcError> {
+    let rel = rpc_rel_path(root, str_param(params, "path")?);
+    if !root.join(&rel).is_file() {
+        return Err(RpcError::new(
+            INVALID_PARAMS,
+            format!("{rel} is not a file under the served root"),
+        ));
+    }
+    let run_args = RunArgs {
+        force: true,
+        ..RunArgs::for_path(root, &rel)
+    };
+    cmd_run(run_args, paths, &NoopObserver, CancellationToken::new()).await?;
+    rpc_get_description(root, params)
+}
+
+/// Handle the `grep` subcommand.
+///
+/// Loads `.dirdocs.nuon` and prints every file whose path or description matches
+/// the pattern, highlighting the matches when writing to a terminal.
+///
+/// # Parameters:
+/// - `args`: A `GrepArgs` struct with the pattern, root directory and output options.
+///
+/// # Returns:
+/// - `anyhow::Result<()>`, indicating success or an error during execution.
+///
+/// # Errors:
+/// - An invalid regex, a missing `.dirdocs.nuon`, or JSON serialization errors.
+fn cmd_grep(args: GrepArgs) -> anyhow::Result<()> {
+    use std::io::IsTerminal;
+
+    let root = PathBuf::from(&args.directory)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(&args.directory));
+    let tree = load_documented_tree(&root)?;
+
+    let pattern = if args.fixed_strings {
+        regex::escape(&args.pattern)
+    } else {
+        args.pattern.clone()
+    };
+    let re = regex::RegexBuilder::new(&pattern)
+        .case_insensitive(args.ignore_case)
+        .build()
+        .map_err(|e| anyhow::anyhow!("invalid pattern {:?}: {e}", args.pattern))?;
+
+    let hits = search_tree(&tree, &re);
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&hits)?);
+        return Ok(());
+    }
+
+    let color = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    for hit in &hits {
+        let path = highlight_matches(&hit.path, &re, color);
+        if hit.fileDescription.is_empty() {
+            println!("{path}");
+        } else {
+            let desc = highlight_matches(&hit.fileDescription, &re, color);
+            println!("{path} — {desc}");
+        }
+    }
+    Ok(())
+}
+
+/// Handle the `export` subcommand.
+///
+/// Loads `.dirdocs.nuon` and renders it in the requested format, either to stdout
+/// or to the file given with `--output`.
+///
+/// # Parameters:
+/// - `args`: An `ExportArgs` struct with the root directory, format and output path.
+///
+/// # Returns:
+/// - `anyhow::Result<()>`, indicating success or an error during execution.
+///
+/// # Errors:
+/// - A missing `.dirdocs.nuon`, or I/O errors when writing the output file.
+fn cmd_export(args: ExportArgs) -> anyhow::Result<()> {
+    let root = PathBuf::from(&args.directory)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(&args.directory));
+    let tree = load_documented_tree(&root)?;
+
+    let body = match args.format {
+        ExportFormat::Markdown => render_markdown(&tree),
+        ExportFormat::Nuon => serde_json::to_string_pretty(&tree)? + "\n",
+    };
+
+    match args.output {
+        Some(path) => {
+            fs::write(&path, body)?;
+            info!(path=%path.display(), "Export written");
+        }
+        None => print!("{body}"),
+    }
+    Ok(())
+}
+
+/// Handle the `install-hooks` subcommand.
+///
+/// Adds a dirdocs block to the repository's pre-commit (or post-commit) hook, keeping any
+/// commands already in the script. Running it again replaces the block.
+///
+/// # Parameters:
+/// - `args`: An `InstallHooksArgs` struct with the directory, hook and mode.
+///
+/// # Returns:
+/// - `anyhow::Result<()>`, indicating success or an error during execution.
+///
+/// # Errors:
+/// - When the directory isn't inside a git work tree, or the hook can't be written.
+fn cmd_install_hooks(args: InstallHooksArgs) -> anyhow::Result<()> {
+    let root = PathBuf::from(&args.directory)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(&args.directory));
+    let path = install_hook(&root, args.hook, args.check)?;
+    println!(
+        "✅ dirdocs: installed {} hook at {}",
+        args.hook.file_name(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Handle the `uninstall-hooks` subcommand.
+///
+/// # Parameters:
+/// - `args`: An `UninstallHooksArgs` struct with the directory and optional hook.
+///
+/// # Returns:
+/// - `anyhow::Result<()>`, indicating success or an error during execution.
+///
+/// # Errors:
+/// - When the directory isn't inside a git work tree, or a hook can't be rewritten.
+fn cmd_uninstall_hooks(args: UninstallHooksArgs) -> anyhow::Result<()> {
+    let root = PathBuf::from(&args.directory)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(&args.directory));
+    let hooks = match args.hook {
+        Some(hook) => vec![hook],
+        None => vec![GitHook::PreCommit, GitHook::PostCommit],
+    };
+    let mut removed = 0;
+    for hook in hooks {
+        if uninstall_hook(&root, hook)? {
+            println!("🧹 dirdocs: removed {} hook", hook.file_name());
+            removed += 1;
+        }
+    }
+    if removed == 0 {
+        println!("dirdocs: no dirdocs hooks installed");
+    }
+    Ok(())
+}
+
+/// Handle the `bundle` subcommand.
+///
+/// Merges the cache at the root with every cache nested below it (monorepo packages,
+/// vendored repos with their own `.dirdocs.nuon`) into a single JSON file that `dls` and
+/// `dtree` can read with `--bundle`, without the repositories checked out.
+///
+/// # Parameters:
+/// - `args`: A `BundleArgs` struct with the root directory and output path.
+///
+/// # Returns:
+/// - `anyhow::Result<()>`, indicating success or an error during execution.
+///
+/// # Errors:
+/// - No cache at or below the root, or I/O errors when writing the bundle.
+fn cmd_bundle(args: BundleArgs) -> anyhow::Result<()> {
+    let root = PathBuf::from(&args.directory)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(&args.directory));
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let bundle = build_bundle(&root, &cwd)?;
+
+    let output = args
+        .output
+        .unwrap_or_else(|| PathBuf::from(format!("{}{BUNDLE_SUFFIX}", bundle.tree.root)));
+    write_bundle(&output, &bundle)?;
+    info!(
+        path=%output.display(),
+        caches = bundle.projects.len(),
+        "Bundle written"
+    );
+    Ok(())
+}
+
+/// Handle the `cmd_run` subcommand.
+///
+/// Loads and processes directory documentation files, using a template to generate structured content.
+/// It parses configuration, reads file metadata, and uses the `handlebars` templating engine to render the prompt.
+///
+/// # Parameters:
+/// - `args`: A `RunArgs` struct containing command-line arguments, such as directory path and ignore patterns.
+/// - `paths`: The resolved config directory layout (config.yaml, templates).
+/// - `observer`: Receives per-file, retry and checkpoint progress events.
+/// - `cancel`: Stops the walk when cancelled; docs gathered so far (plus cached docs for
+///   files not reached yet) are still written.
+///
+/// # Returns:
+/// - `anyhow::Result<()>`, indicating success or an error during execution.
+///
+/// # Errors:
+/// - I/O errors when reading/writing files,
+/// - YAML/JSON parsing errors during template rendering or configuration loading,
+/// - Errors from `handlebars` operations,
+/// - Any error returned by the underlying API calls.
+///
+/// # Notes:
+/// - This function uses `canonialize()` to resolve paths and `pathdiff` for relative path differences.
+/// - It lazily loads configuration files, allowing optional error handling during config parsing.
+/// - Binary files are handled with safe placeholders instead of actual content.
+async fn cmd_run(
+    args: RunArgs,
+    paths: &ConfigPaths,
+    observer: &dyn ProgressObserver,
+    cancel: CancellationToken,
+) -> anyhow::Result<()> {
+    info!(?args, "dir_docs starting");
+    let run_started = Instant::now();
+
+    let root = PathBuf::from(&args.directory)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(&args.directory));
+    info!(root=%root.display(), "Resolved root");
+
+    // label root relative to CWD
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let rel_root_path = pathdiff::diff_paths(&root, &cwd).unwrap_or_else(|| PathBuf::from("."));
+    let root_label = {
+        let s = rel_root_path.to_string_lossy();
+        if s.is_empty() {
+            ".".to_string()
+        } else {
+            s.to_string()
+        }
+    };
+
+    // AJ config
+    let config_file = &paths.config_file;
+    ensure_initialized(paths).await?;
+    info!(config=%config_file.display(), "Loading Awful Jade config");
+    let cfg: AwfulJadeConfig = awful_aj::config::load_config(&config_file.to_string_lossy())
+        .map_err(|e| {
+            anyhow::anyhow!("failed to load Awful Jade config at {:?}: {e}", config_file)
+        })?;
+    let tokenizer = Tokenizer::load(args.tokenizer.as_deref(), &cfg.model)?;
+
+    // Load dir_docs template (resolving `extends:`) and any partials
+    let templates_dir = &paths.templates_dir;
+    info!(template=%paths.template_file.display(), "Reading dir_docs template");
+    let mut hbs = Handlebars::new();
+    // Rendered values are plain strings in the request, not HTML
+    hbs.register_escape_fn(handlebars::no_escape);
+    register_helpers(&mut hbs);
+    let partials = register_partials(&mut hbs, templates_dir)?;
+    debug!(partials, "Template partials registered");
+    validate_templates(&hbs, templates_dir)?;
+    let mut templates = TemplateSet::load(templates_dir)?;
+    debug!(
+        template_size_bytes = templates.default_template().len(),
+        "Template loaded"
+    );
+
+    // Opt-in global description cache (shared across repositories)
+    let global_cache = if args.global_cache {
+        match GlobalCache::open(&cfg.model) {
+            Ok(gc) => Some(gc),
+            Err(e) => {
+                warn!(%e, "Global cache unavailable; continuing without it");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // README context
+    let (project_is_documented, project_doc_snippet) = readme_context(&root)?;
+    debug!(project_is_documented=%project_is_documented, doc_snippet_len=project_doc_snippet.len(), "README context collected");
+
+    // Existing cache (.dirdocs.nuon or .dirdocs.db)
+    let dirdocs_path = args.backend.path(&root);
+    info!(path=%dirdocs_path.display(), "Loading existing cache (if any)");
+    let existing_tree = load_tree(args.backend, &root, &cwd);
+
+    // For quick lookups when merging
+    let mut existing_by_path: HashMap<String, FileEntry> = HashMap::new();
+    index_files_by_path(&existing_tree.entries, &mut existing_by_path);
+    info!(
+        existing_files = existing_by_path.len(),
+        "Indexed existing files"
+    );
+
+    // Merge child caches so we can skip clean files in subtrees
+    let child_cache_dirs = find_child_cache_dirs(&root);
+    info!(count = child_cache_dirs.len(), "Child caches found");
+    for child_abs in &child_cache_dirs {
+        if let Some(cache_path) = CHILD_CACHE_NAMES
+            .iter()
+            .map(|n| child_abs.join(n))
+            .find(|p| p.exists())
+        {
+            let child_tree = load_existing_tree(&cache_path, child_abs, &cwd);
+            let before = existing_by_path.len();
+            rebase_child_tree_into_existing_by_path(
+                child_abs,
+                &root,
+                &child_tree,
+                &mut existing_by_path,
+            );
+            info!(child=%child_abs.display(), added = existing_by_path.len() as i64 - before as i64, "Merged child cache into existing_by_path");
+        } else {
+            warn!(child=%child_abs.display(), "Cache file missing; skipping merge");
+        }
+    }
+
+    // Walk first so the planner can put the most valuable files up front
+    let mut files = walk_files(&root, &args.ignore);
+    if !args.only.is_empty() {
+        files.retain(|(_, rel)| {
+            args.only
+                .iter()
+                .any(|o| rel == o || rel.starts_with(&format!("{o}/")))
+        });
+    }
+    if let Some(rev) = &args.changed_since {
+        let changed = changed_since(&root, rev)?;
+        info!(%rev, changed = changed.len(), "Limiting run to changed files");
+        files.retain(|(_, rel)| changed.contains(rel));
+    }
+    // Runs limited to some files keep the cached docs of the rest
+    let partial = !args.only.is_empty() || args.changed_since.is_some();
+    let rules = PriorityRules::load(&paths.priority_file)?;
+    let planner = Planner::new(&rules, &root)?;
+    let splitter_overrides = SplitterOverrides::load(&paths.splitters_file)?;
+    let mut scored: Vec<(f64, (PathBuf, String))> = files
+        .into_iter()
+        .map(|f| (planner.score(&f.1), f))
+        .collect();
+    // Stable: equal scores keep walk order
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    info!(files = scored.len(), "Files planned by priority");
+
+    // Git history for the git_* template fields; empty outside a repository
+    let git_history = file_histories(&root).unwrap_or_else(|e| {
+        debug!(%e, "No git history for template fields");
+        HashMap::new()
+    });
+
+    // Collect flat new/updated file map (path -> FileEntry)
+    let mut updated_files: HashMap<String, FileEntry> = HashMap::new();
+
+    let mut walked = 0usize;
+    let mut regenerated = 0usize;
+    // Files sent to the model, counted against --max-generate
+    let mut generated = 0usize;
+    let mut backend_latency = Histogram::default();
+    let mut report = RunReport::default();
+    // Per-file (template hash, prompt hash) of prompts actually sent, for --manifest
+    let mut sent_prompts: HashMap<String, (Option<String>, Option<String>)> = HashMap::new();
+    // How each answer was requested, for --manifest
+    let mut output_modes: HashMap<String, OutputMode> = HashMap::new();
+    // Flips to plain JSON prompts for the whole run once the endpoint rejects response_format
+    let output_mode = OutputModeState::default();
+    // Sampling flags win over the template's temperature/top_p/seed keys
+    let sampling_overrides = SamplingParams {
+        temperature: args.temperature,
+        top_p: args.top_p,
+        seed: args.seed,
+    };
+    // Chunk flags win over the template's chunk_count/chunk_tokens/chunk_strategy keys
+    let chunk_overrides = ChunkSettings {
+        count: args.chunk_count,
+        tokens: args.chunk_tokens,
+        strategy: args.chunk_strategy,
+    };
+    let budget = prompt_budget(&cfg);
+    debug!(budget, "Prompt token budget");
+    'files: for (score, (path, rel_str)) in scored {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let path = path.as_path();
+        walked += 1;
+        let name = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("")
+            .to_string();
+
+        let _span = tracing::info_span!("process_file", rel=%rel_str, name=%name, score).entered();
+        observer.on_file_start(&rel_str);
+
+        // Hash file to detect dirtiness
+        let file_hash = match hash_file(path) {
+            Ok(h) => h,
+            Err(e) => {
+                warn!(%e, path=%path.display(), "Hash failed; skipping");
+                observer.on_file_done(&rel_str, FileOutcome::Skipped);
+                continue;
+            }
+        };
+        debug!(hash=%file_hash, "File hashed");
+
+        // Cache reuse (unless --force)
+        if let Some(prev) = existing_by_path.get(&rel_str) {
+            if !args.force && prev.hash == file_hash && !prev.doc.fileDescription.is_empty() {
+                info!("Reusing previous doc (clean)");
+                updated_files.insert(
+                    rel_str.clone(),
+                    FileEntry {
+                        name: name.clone(),
+                        path: rel_str.clone(),
+                        hash: file_hash.clone(),
+                        updated_at: prev.updated_at,
+                        doc: prev.doc.clone(),
+                        error: None,
+                    },
+                );
+                observer.on_file_done(&rel_str, FileOutcome::Reused);
+                continue;
+            } else if args.force {
+                info!("Forcing regeneration (--force)");
+            } else {
+                info!("Changed content detected; regenerating");
+            }
+        } else {
+            info!("New file; generating");
+        }
+
+        // Global cache: the same content may already be described in another repository
+        let global_key = match &global_cache {
+            Some(gc) if !args.force => templates
+                .for_path(path)
+                .ok()
+                .map(|raw_template| gc.key(&file_hash, raw_template)),
+            _ => None,
+        };
+        if let Some(doc) = global_cache
+            .as_ref()
+            .zip(global_key.as_deref())
+            .and_then(|(gc, key)| gc.get(key))
+        {
+            info!("Reusing description from global cache");
+            let file_entry = FileEntry {
+                name,
+                path: rel_str.clone(),
+                hash: file_hash,
+                updated_at: Utc::now(),
+                doc,
+                error: None,
+            };
+            if let Some(cmd) = &args.hook {
+                run_hook(cmd, &root, &file_entry).await;
+            }
+            updated_files.insert(rel_str.clone(), file_entry);
+            regenerated += 1;
+            observer.on_file_done(&rel_str, FileOutcome::Reused);
+            continue;
+        }
+
+        // Budget spent: leave the remaining (lower-priority) files for a later run
+        if args.max_generate.is_some_and(|max| generated >= max) {
+            info!("Generation budget spent; deferring");
+            if let Some(prev) = existing_by_path.get(&rel_str) {
+                updated_files.insert(rel_str.clone(), prev.clone());
+            }
+            report.deferred += 1;
+            observer.on_file_done(&rel_str, FileOutcome::Skipped);
+            continue;
+        }
+        generated += 1;
+
+        // Otherwise (new or dirty), render template and ask the model
+        let (filesize, filetype, mimetype) = file_meta(path);
+        let is_text = is_probably_text(path, 4096) || is_extractable(&mimetype);
+
+        // Regex tripwires for filename/stem (optional)
+        let fname = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        let stem = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+
+        let symbols = if is_text {
+            symbol_outline(path, &mimetype)
+        } else {
+            String::new()
+        };
+        let image = image_info(path, &mimetype);
+        let archive = if is_text || image.is_some() {
+            None
+        } else {
+            archive_info(path)
+        };
+        let binary = if is_text || image.is_some() || archive.is_some() {
+            None
+        } else {
+            executable_info(path)
+        };
+
+        // Binary files are summarized from their headers or archive listing when possible
+        let binary_chunks = match (&image, &archive, &binary) {
+            (Some(img), _, _) => (vec![format!("[[{}]]", img.summary())], "image".to_string()),
+            (None, Some(arc), _) => (
+                vec![format!("[[{} archive]]\n{}", arc.format, arc.listing())],
+                "archive".to_string(),
+            ),
+            (None, None, Some(bin)) => (
+                vec![format!("[[{}]]", bin.summary())],
+                "executable".to_string(),
+            ),
+            (None, None, None) => (vec![suppressed_block(); 3], "binary".to_string()),
+        };
+
+        let chunking = templates
+            .prompt_for_path(path)
+            .map(PromptTemplate::chunking)
+            .map(|t| chunk_overrides.or(t))
+            .unwrap_or(chunk_overrides);
+
+        // Render → ChatTemplate, shrinking the chunks until the prompt fits the context window
+        let mut tpl = None;
+        let mut overflow_tokens = 0usize;
+        for chunk_tokens in chunking.token_steps() {
+            // For text: chunk as before; for binary (or unreadable documents): use the summary above.
+            let chunks = if is_text {
+                token_chunks_for_file(
+                    path,
+                    &mimetype,
+                    chunk_tokens,
+                    splitter_overrides.kind_for(&rel_str),
+                    &chunking,
+                    &tokenizer,
+                )
+            } else {
+                None
+            };
+            let (chunks, used_splitter) = chunks.unwrap_or_else(|| binary_chunks.clone());
+            let chunk_at = |i: usize| chunks.get(i).cloned().unwrap_or_default();
+
+            debug!(
+                filesize=%filesize, filetype=%filetype, mimetype=%mimetype, used_splitter=%used_splitter,
+                chunk_tokens, chunks=chunks.len(), chunks_len=chunks.iter().map(String::len).sum::<usize>(),
+                "Collected file metadata and token-aware chunks"
+            );
+
+            let mut extra = BTreeMap::new();
+            extra.insert("filename_re", regex::escape(fname));
+            extra.insert("filename_stem_re", regex::escape(stem));
+
+            let data = TplData {
+                filename: path.display().to_string(),
+                filesize: filesize.clone(),
+                filetype: filetype.clone(),
+                mimetype: mimetype.clone(),
+                operating_system: std::env::consts::OS.to_string(),
+                project_is_documented: project_is_documented.clone(),
+                project_documentation: project_doc_snippet.clone(),
+                chunk_one: chunk_at(0),
+                chunk_two: chunk_at(1),
+                chunk_three: chunk_at(2),
+                chunks: chunks.clone(),
+                symbols: symbols.clone(),
+                git: GitVars::from_history(git_history.get(&rel_str)),
+                image: ImageVars::from_info(image.as_ref()),
+                archive: ArchiveVars::from_info(archive.as_ref()),
+                binary: BinaryVars::from_info(binary.as_ref()),
+                extra,
+            };
+
+            let rendered = templates
+                .prompt_for_path(path)
+                .and_then(|prompt| prompt.render(&hbs, &data));
+            let t = match rendered {
+                Ok(t) => t,
+                Err(e) => {
+                    error!(%e, file=%path.display(), "Template/YAML error");
+                    observer.on_file_done(&rel_str, FileOutcome::Failed);
+                    continue 'files;
+                }
+            };
+
+            let tokens = prompt_tokens(&t, &tokenizer);
+            if let Some(n) = tokens {
+                observer.on_prompt(&rel_str, n);
+            }
+            match tokens {
+                Some(n) if n > budget => {
+                    warn!(
+                        prompt_tokens = n,
+                        limit_tokens = budget,
+                        chunk_tokens,
+                        "Prompt exceeds context window; shrinking chunks"
+                    );
+                    overflow_tokens = n;
+                    if !is_text {
+                        break;
+                    }
+                }
+                _ => {
+                    tpl = Some(t);
+                    break;
+                }
+            }
+        }
+
+        let Some(tpl) = tpl else {
+            error!(
+                prompt_tokens = overflow_tokens,
+                limit_tokens = budget,
+                file=%path.display(),
+                "Prompt still exceeds context window; recording context_overflow"
+            );
+            report.context_overflows.push(ContextOverflow {
+                path: rel_str.clone(),
+                prompt_tokens: overflow_tokens,
+                limit_tokens: budget,
+            });
+            regenerated += 1;
+            updated_files.insert(
+                rel_str.clone(),
+                FileEntry {
+                    name,
+                    path: rel_str.clone(),
+                    hash: file_hash,
+                    updated_at: Utc::now(),
+                    doc: Doc::default(),
+                    error: Some(EntryError::ContextOverflow {
+                        prompt_tokens: overflow_tokens,
+                        limit_tokens: budget,
+                    }),
+                },
+            );
+            observer.on_file_done(&rel_str, FileOutcome::Failed);
+            continue;
+        };
+
+        if args.manifest.is_some() {
+            let template_hash = templates.for_path(path).ok().map(hash_str);
+            sent_prompts.insert(rel_str.clone(), (template_hash, prompt_hash(&tpl)));
+        }
+        let sampling = templates
+            .prompt_for_path(path)
+            .map(PromptTemplate::sampling)
+            .map(|t| sampling_overrides.or(t))
+            .unwrap_or(sampling_overrides);
+
+        let updated_at = Utc::now();
+
+        // Timed API call (with backoff)
+        let t0 = Instant::now();
+        let answer = tokio::select! {
+            res = ask_with_fallback(&cfg, &tpl, 5, sampling, &rel_str, observer, &output_mode) => res,
+            _ = cancel.cancelled() => {
+                info!("Cancelled while waiting on the model");
+                observer.on_file_done(&rel_str, FileOutcome::Cancelled);
+                break;
+            }
+        };
+        backend_latency.observe(t0.elapsed());
+        let (answer, mode) = match answer {
+            Ok((ans, mode)) => {
+                info!(elapsed_ms = %as_ms(t0.elapsed()), ?mode, "api::ask finished");
+                if mode == OutputMode::PlainJson {
+                    report.plain_json_files += 1;
+                }
+                output_modes.insert(rel_str.clone(), mode);
+                (ans, mode)
+            }
+            Err(e) => {
+                error!(%e, elapsed_ms = %as_ms(t0.elapsed()), file=%path.display(), "api::ask failed after retries");
+                (String::new(), output_mode.current())
+            }
+        };
+
+        let doc = tokio::select! {
+            doc = doc_with_correction(&cfg, &tpl, &answer, mode, sampling, &rel_str, observer) => doc,
+            _ = cancel.cancelled() => {
+                info!("Cancelled while waiting on the model");
+                observer.on_file_done(&rel_str, FileOutcome::Cancelled);
+                break;
+            }
+        };
+        regenerated += 1;
+        if let (Some(gc), Some(d)) = (&global_cache, &doc) {
+            let key = global_key.clone().or_else(|| {
+                templates
+                    .for_path(path)
+                    .ok()
+                    .map(|raw| gc.key(&file_hash, raw))
+            });
+            if let Some(key) = key {
+                if let Err(e) = gc.put(&key, d) {
+                    warn!(%e, "Failed to update global cache");
+                }
+            }
+        }
+        let outcome = if doc.is_some() {
+            FileOutcome::Generated
+        } else {
+            FileOutcome::Failed
+        };
+
+        let file_entry = FileEntry {
+            name,
+            path: rel_str.clone(),
+            hash: file_hash,
+            updated_at,
+            doc: doc.unwrap_or_default(),
+            error: None,
+        };
+        if let (Some(cmd), FileOutcome::Generated) = (&args.hook, outcome) {
+            run_hook(cmd, &root, &file_entry).await;
+        }
+
+        updated_files.insert(rel_str.clone(), file_entry);
+        observer.on_file_done(&rel_str, outcome);
+    }
+
+    info!(
+        walked,
+        updated_count = updated_files.len(),
+        "Walking complete"
+    );
+
+    // On cancellation (or with --only/--changed-since) keep cached docs for files the walk never reached
+    let cancelled = cancel.is_cancelled();
+    if cancelled || partial {
+        let mut kept = 0usize;
+        for (rel, fe) in &existing_by_path {
+            if !updated_files.contains_key(rel) && root.join(rel).is_file() {
+                updated_files.insert(rel.clone(), fe.clone());
+                kept += 1;
+            }
+        }
+        if cancelled {
+            warn!(
+                kept,
+                "Run cancelled; keeping cached docs for unvisited files"
+            );
+        } else {
+            info!(kept, "Keeping cached docs for files outside the run");
+        }
+    }
+
+    // Root-level doc: only re-describe the project when something underneath changed
+    let root_doc = if !cancelled
+        && !partial
+        && (args.force || regenerated > 0 || existing_tree.doc.fileDescription.is_empty())
+    {
+        info!("Describing project root");
+        let listing = top_level_listing(&updated_files);
+        describe_root(
+            &cfg,
+            &hbs,
+            &templates,
+            &root,
+            (&project_is_documented, &project_doc_snippet),
+            &listing,
+            observer,
+            &output_mode,
+            sampling_overrides,
+        )
+        .await
+        .unwrap_or_else(|| existing_tree.doc.clone())
+    } else {
+        info!("Reusing previous root doc (clean)");
+        existing_tree.doc.clone()
+    };
+
+    // Build a new tree from updated_files
+    let mut new_root = DirdocsRoot {
+        schema_version: SCHEMA_VERSION,
+        root: root_label,
+        updated_at: Utc::now(),
+        doc: root_doc,
+        entries: Vec::new(),
+    };
+
+    for (rel_path, fe) in &updated_files {
+        insert_file_into_tree(&mut new_root.entries, rel_path, fe);
+    }
+    // Directory nodes are rebuilt from file paths; carry their docs over from the old tree
+    let mut dir_docs = HashMap::new();
+    index_dir_docs(&existing_tree.entries, "", &mut dir_docs);
+    restore_dir_docs(&mut new_root.entries, "", &dir_docs);
+
+    // Write as strict JSON (Nuon-compatible) or into the SQLite cache
+    // save_tree may wait for the cache lock; keep that off the async runtime
+    let (backend, save_root) = (args.backend, root.clone());
+    let dirdocs_path =
+        tokio::task::spawn_blocking(move || save_tree(backend, &save_root, &new_root)).await??;
+    observer.on_checkpoint(&dirdocs_path, updated_files.len());
+
+    if let Some(manifest_path) = &args.manifest {
+        let sampling = sampling_overrides.or(templates.default_prompt().sampling());
+        let mut manifest = RunManifest::new(&cfg, templates.default_template(), sampling);
+        for (rel, fe) in &updated_files {
+            let (source, (template_hash, prompt_hash)) = match sent_prompts.remove(rel) {
+                Some(hashes) => (RecordSource::Generated, hashes),
+                None => (RecordSource::Cache, (None, None)),
+            };
+            manifest.files.push(FileRecord {
+                path: rel.clone(),
+                file_hash: fe.hash.clone(),
+                source,
+                template_hash,
+                prompt_hash,
+                output_hash: output_hash(&fe.doc),
+                output_mode: output_modes.get(rel).copied(),
+            });
+        }
+        manifest.write(manifest_path)?;
+        info!(path=%manifest_path.display(), files = updated_files.len(), "Run manifest written");
+    }
+
+    report.walked = walked;
+    report.regenerated = regenerated;
+    for o in &report.context_overflows {
+        warn!(path=%o.path, prompt_tokens = o.prompt_tokens, limit_tokens = o.limit_tokens, "context_overflow");
+    }
+    info!(
+        walked = report.walked,
+        regenerated = report.regenerated,
+        context_overflows = report.context_overflows.len(),
+        deferred = report.deferred,
+        plain_json_files = report.plain_json_files,
+        "Run report"
+    );
+    observer.on_finish(&report);
+
+    let stats = RunStats {
+        finished_at: Utc::now(),
+        duration_ms: run_started.elapsed().as_millis() as u64,
+        walked: report.walked,
+        regenerated: report.regenerated,
+        backend_latency,
+    };
+    if let Err(e) = stats.write(&root) {
+        warn!(%e, "Failed to record run statistics");
+    }
+
+    info!("Done");
+    Ok(())
+}
+
+/// Summarize the top-level entries of the project for the root-level prompt.
+///
+/// Each top-level file is listed with its description (when one exists) and each
+/// top-level directory is listed once with a trailing `/`.
+///
+/// Parameters:
+/// - `files`: The flat map of relative paths to file entries produced by the walk.
+///
+/// Returns:
+/// - A newline-separated listing, sorted with directories first, then files by name.
+fn top_level_listing(files: &HashMap<String, FileEntry>) -> String {
+    use std::path::{Component, Path};
+
+    let mut dirs: BTreeSet<String> = BTreeSet::new();
+    let mut top_files: BTreeMap<String, String> = BTreeMap::new();
+    for (rel, fe) in files {
+        let comps: Vec<String> = Path::new(rel)
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(os) => Some(os.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect();
+        match comps.as_slice() {
+            [name] => {
+                top_files.insert(name.clone(), fe.doc.fileDescription.clone());
+            }
+            [dir, ..] => {
+                dirs.insert(format!("{dir}/"));
+            }
+            [] => {}
+        }
+    }
+
+    let mut lines: Vec<String> = dirs.into_iter().map(|d| format!("- {d}")).collect();
+    for (name, desc) in top_files {
+        if desc.is_empty() {
+            lines.push(format!("- {name}"));
+        } else {
+            lines.push(format!("- {name} — {desc}"));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Ask the model for a description of the project root itself.
+///
+/// Renders the `dir_docs` template as if the root directory were a file: the README
+/// snippet becomes the first chunk and the top-level listing the second, so the
+/// model can say what the project as a whole is for.
+///
+/// Parameters:
+/// - `cfg`: The Awful Jade configuration used to reach the model.
+/// - `hbs`: The Handlebars registry used to render the template.
+/// - `templates`: The run's templates; the default one describes the project.
+/// - `root`: Absolute path of the project root.
+/// - `readme`: The `(project_is_documented, snippet)` pair returned by `readme_context`.
+/// - `listing`: The top-level listing from [`top_level_listing`].
+/// - `observer`: Notified of model request retries.
+/// - `output_mode`: The run's output mode, switched to plain JSON if the endpoint rejects schemas.
+/// - `sampling_overrides`: Sampling flags from the command line, applied over the template's.
+///
+/// Returns:
+/// - `Some(Doc)` when the model produced a parseable answer, `None` otherwise.
+///
+/// Notes:
+/// - Failures are logged and never abort the run; the caller falls back to the previous root doc.
+#[allow(clippy::too_many_arguments)]
+async fn describe_root(
+    cfg: &AwfulJadeConfig,
+    hbs: &Handlebars<'_>,
+    templates: &TemplateSet,
+    root: &std::path::Path,
+    readme: (&str, &str),
+    listing: &str,
+    observer: &dyn ProgressObserver,
+    output_mode: &OutputModeState,
+    sampling_overrides: SamplingParams,
+) -> Option<Doc> {
+    let (project_is_documented, project_doc_snippet) = readme;
+
+    let name = root
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let mut extra = BTreeMap::new();
+    extra.insert("filename_re", regex::escape(name));
+    extra.insert("filename_stem_re", regex::escape(name));
+
+    let data = TplData {
+        filename: root.display().to_string(),
+        filesize: String::new(),
+        filetype: "directory".to_string(),
+        mimetype: "inode/directory".to_string(),
+        operating_system: std::env::consts::OS.to_string(),
+        project_is_documented: project_is_documented.to_string(),
+        project_documentation: project_doc_snippet.to_string(),
+        chunk_one: project_doc_snippet.to_string(),
+        chunk_two: listing.to_string(),
+        chunk_three: String::new(),
+        chunks: vec![project_doc_snippet.to_string(), listing.to_string()],
+        symbols: String::new(),
+        git: GitVars::default(),
+        image: ImageVars::default(),
+        archive: ArchiveVars::default(),
+        binary: BinaryVars::default(),
+        extra,
+    };
+
+    let tpl = match templates.default_prompt().render(hbs, &data) {
+        Ok(t) => t,
+        Err(e) => {
+            error!(%e, root=%root.display(), "Template/YAML error");
+            return None;
+        }
+    };
+
+    let t0 = Instant::now();
+    let sampling = sampling_overrides.or(templates.default_prompt().sampling());
+    match ask_with_fallback(cfg, &tpl, 5, sampling, ".", observer, output_mode).await {
+        Ok((answer, mode)) => {
+            info!(elapsed_ms = %as_ms(t0.elapsed()), ?mode, "api::ask finished (root)");
+            doc_with_correction(cfg, &tpl, &answer, mode, sampling, ".", observer).await
+        }
+        Err(e) => {
+            error!(%e, elapsed_ms = %as_ms(t0.elapsed()), root=%root.display(), "api::ask failed after retries (root)");
+            None
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Shell completion scripts for the `dirdocs`, `dls` and `dtree` binaries, or any other
+//! clap command.
+
+use clap::ValueEnum;
+use clap_complete::{Shell as Builtin, generate};
+use clap_complete_nushell::Nushell;
+use std::io;
+
+/// Shells a completion script can be generated for.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    /// Bash (`source <(dirdocs completions bash)`).
+    Bash,
+    /// Zsh (save as `_dirdocs` somewhere on `$fpath`).
+    Zsh,
+    /// Fish (save to `~/.config/fish/completions/dirdocs.fish`).
+    Fish,
+    /// Elvish.
+    Elvish,
+    /// PowerShell.
+    #[value(name = "powershell")]
+    PowerShell,
+    /// Nushell (save to a file and `source` it from `config.nu`).
+    Nushell,
+}
+
+/// Print the completion script of `cmd` for `shell` to stdout.
+///
+/// The script covers every subcommand, flag and enumerated value; arguments marked with
+/// a path value hint complete file and directory names at the prompt.
+///
+/// Parameters:
+/// - `shell`: The shell to generate for.
+/// - `cmd`: The binary's clap command (`Args::command()`).
+pub fn print(shell: Shell, cmd: &mut clap::Command) {
+    let name = cmd.get_name().to_string();
+    let out = &mut io::stdout();
+    match shell {
+        Shell::Bash => generate(Builtin::Bash, cmd, &name, out),
+        Shell::Zsh => generate(Builtin::Zsh, cmd, &name, out),
+        Shell::Fish => generate(Builtin::Fish, cmd, &name, out),
+        Shell::Elvish => generate(Builtin::Elvish, cmd, &name, out),
+        Shell::PowerShell => generate(Builtin::PowerShell, cmd, &name, out),
+        Shell::Nushell => generate(Nushell, cmd, &name, out),
+    }
+}
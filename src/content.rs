@@ -1,8 +1,38 @@
 use blake3::Hasher;
+use encoding_rs::{Encoding, UTF_8, UTF_16BE, UTF_16LE, WINDOWS_1252};
 use std::fs;
 use std::io::{self, Read};
 use std::path::Path;
 
+/// Script interpreters recognized in a shebang, with the file extension and MIME type
+/// their scripts would normally carry. Version suffixes (`python3.11`) are stripped first.
+const SHEBANG_LANGUAGES: &[(&str, &str, &str)] = &[
+    ("sh", "sh", "application/x-sh"),
+    ("bash", "sh", "application/x-sh"),
+    ("zsh", "zsh", "application/x-sh"),
+    ("dash", "sh", "application/x-sh"),
+    ("ksh", "sh", "application/x-sh"),
+    ("python", "py", "text/x-python"),
+    ("pypy", "py", "text/x-python"),
+    ("node", "js", "text/javascript"),
+    ("nodejs", "js", "text/javascript"),
+    ("deno", "ts", "text/x-typescript"),
+    ("ts-node", "ts", "text/x-typescript"),
+    ("ruby", "rb", "text/x-ruby"),
+    ("php", "php", "application/x-httpd-php"),
+    ("julia", "jl", "text/x-julia"),
+    ("ocaml", "ml", "text/x-ocaml"),
+    ("runghc", "hs", "text/x-haskell"),
+    ("runhaskell", "hs", "text/x-haskell"),
+    ("scala", "scala", "text/x-scala"),
+    ("perl", "pl", "text/x-perl"),
+    ("lua", "lua", "text/x-lua"),
+    ("elixir", "exs", "text/x-elixir"),
+    ("escript", "erl", "text/x-erlang"),
+    ("kotlin", "kts", "text/x-kotlin"),
+    ("swift", "swift", "text/x-swift"),
+];
+
 /// Handle the `readme_context` function.
 /// This function searches for a README file in various common formats (e.g., .md, .txt) under the given root directory.
 /// It loads the first valid file found, reads its contents (with a maximum length of 2 million characters),
@@ -67,12 +97,20 @@ pub(crate) fn readme_context(root: &Path) -> anyhow::Result<(String, String)> {
 /// - The MIME type is determined by checking both `mime_guess` and
 ///   `tree_magic_mini`, with a fallback to "application/octet-stream"
 ///   if no specific type is found.
+/// - Extensionless scripts take both from their shebang (see [`shebang_language`]),
+///   so `bin/deploy` starting with `#!/usr/bin/env python3` reports "py" and "text/x-python".
 /// ```
 pub(crate) fn file_meta(path: &Path) -> (String, String, String) {
     let md = fs::metadata(path);
     let size = md.as_ref().map(|m| m.len()).unwrap_or(0);
     let filesize = human_bytes(size);
 
+    if path.extension().is_none() {
+        if let Some((ext, mime)) = shebang_language(path) {
+            return (filesize, ext.to_string(), mime.to_string());
+        }
+    }
+
     let filetype = path
         .extension()
         .and_then(|e| e.to_str())
@@ -89,6 +127,34 @@ pub(crate) fn file_meta(path: &Path) -> (String, String, String) {
     (filesize, filetype, mimetype)
 }
 
+/// Detect the language of a script from its `#!` line.
+///
+/// Understands both direct interpreters (`#!/bin/bash`) and `env` indirection
+/// (`#!/usr/bin/env -S python3 -u`).
+///
+/// Parameters:
+/// - `path`: The file to inspect; only its first line is read.
+///
+/// Returns:
+/// - The extension and MIME type the script would normally carry (e.g. `("py", "text/x-python")`),
+///   or `None` when there is no shebang or the interpreter is not in [`SHEBANG_LANGUAGES`].
+pub(crate) fn shebang_language(path: &Path) -> Option<(&'static str, &'static str)> {
+    let head = read_text_lossy_limited(path, 256);
+    let line = head.lines().next()?.strip_prefix("#!")?;
+
+    let mut words = line.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|w| !w.starts_with('-') && !w.contains('='))?;
+    }
+    let name = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+    SHEBANG_LANGUAGES
+        .iter()
+        .find(|(interp, _, _)| *interp == name)
+        .map(|&(_, ext, mime)| (ext, mime))
+}
+
 /// Read up to `max_bytes` of text from a file, returning it as a UTF-8 lossy string.
 ///
 /// This function opens the specified file, reads up to `max_bytes` of content, and returns
@@ -109,13 +175,18 @@ pub(crate) fn file_meta(path: &Path) -> (String, String, String) {
 /// Notes:
 /// - The function uses `io::Read::take` to limit the number of bytes read.
 /// - If no content is available (e.g., file is empty), it returns an empty string.
+/// - UTF-16 and legacy (Windows-1252) files are transcoded to UTF-8 (see [`detect_encoding`]);
+///   a byte-order mark is dropped.
 pub(crate) fn read_text_lossy_limited(path: &Path, max_bytes: usize) -> String {
     match fs::File::open(path) {
         Ok(mut f) => {
             let mut buf = Vec::with_capacity(max_bytes.min(1_000_000));
             let mut rdr = io::BufReader::new(&mut f);
             match io::Read::take(&mut rdr, max_bytes as u64).read_to_end(&mut buf) {
-                Ok(_) => String::from_utf8_lossy(&buf).to_string(),
+                Ok(_) => {
+                    let (text, _, _) = detect_encoding(&buf).decode(&buf);
+                    text.into_owned()
+                }
                 Err(_) => String::new(),
             }
         }
@@ -123,6 +194,46 @@ pub(crate) fn read_text_lossy_limited(path: &Path, max_bytes: usize) -> String {
     }
 }
 
+/// Guess the text encoding of the start of a file.
+///
+/// Parameters:
+/// - `sample`: The first bytes of the file (a few KB is plenty).
+///
+/// Returns:
+/// - The encoding named by a byte-order mark, if there is one.
+/// - UTF-16LE/BE when most even (or odd) bytes are NUL, as in BOM-less UTF-16 ASCII text.
+/// - UTF-8 when the sample is valid UTF-8 (a sequence cut off at the end is allowed).
+/// - Windows-1252 otherwise, the usual legacy codepage for Latin-script text.
+pub(crate) fn detect_encoding(sample: &[u8]) -> &'static Encoding {
+    if let Some((enc, _)) = Encoding::for_bom(sample) {
+        return enc;
+    }
+
+    let pairs = sample.len() / 2;
+    if pairs >= 2 {
+        let even_nuls = sample.iter().step_by(2).filter(|&&b| b == 0).count();
+        let odd_nuls = sample
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .filter(|&&b| b == 0)
+            .count();
+        if odd_nuls * 10 >= pairs * 7 && even_nuls * 10 <= pairs {
+            return UTF_16LE;
+        }
+        if even_nuls * 10 >= pairs * 7 && odd_nuls * 10 <= pairs {
+            return UTF_16BE;
+        }
+    }
+
+    match std::str::from_utf8(sample) {
+        Ok(_) => UTF_8,
+        // Only an incomplete sequence at the very end: the sample was cut mid-character
+        Err(e) if e.error_len().is_none() => UTF_8,
+        Err(_) => WINDOWS_1252,
+    }
+}
+
 /// Returns the first `n` words from a string.
 ///
 /// Parameters:
@@ -203,10 +314,10 @@ pub(crate) fn hash_file(path: &Path) -> io::Result<String> {
     Ok(hasher.finalize().to_hex().to_string())
 }
 
-/// Checks if a file path contains primarily printable ASCII text.
+/// Checks if a file path contains primarily printable text.
 ///
 /// This function reads the first `limit` bytes of a file to determine if it contains
-/// mostly printable ASCII characters. It returns `true` if the file is likely to be text,
+/// mostly printable characters. It returns `true` if the file is likely to be text,
 /// based on a threshold of at least 85% printable characters.
 ///
 /// Parameters:
@@ -214,7 +325,7 @@ pub(crate) fn hash_file(path: &Path) -> io::Result<String> {
 /// - `limit`: Maximum number of bytes to examine from the start of the file
 ///
 /// Returns:
-/// - `true` if at least 85% of the examined bytes are printable ASCII characters
+/// - `true` if at least 85% of the examined bytes (or, for UTF-16, characters) are printable
 /// - `false` otherwise
 ///
 /// Errors:
@@ -225,6 +336,9 @@ pub(crate) fn hash_file(path: &Path) -> io::Result<String> {
 /// - It considers ASCII printable characters as: newline (`
 /// `), carriage return (`\r`),
 ///   tab (`	`), and any byte between 0x20 (space) and 0x7E.
+/// - UTF-16 samples (see [`detect_encoding`]) are decoded first, so their interleaved NULs
+///   don't count as binary. Bytes above 0x7F count as printable when the sample is valid
+///   UTF-8, and from 0xA0 up (Windows-1252 letters and symbols) otherwise.
 pub(crate) fn is_probably_text(path: &Path, limit: usize) -> bool {
     let mut f = match fs::File::open(path) {
         Ok(x) => x,
@@ -239,6 +353,17 @@ pub(crate) fn is_probably_text(path: &Path, limit: usize) -> bool {
         return true;
     }
     let sample = &buf[..n];
+    let encoding = detect_encoding(sample);
+
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        let (text, _, _) = encoding.decode(sample);
+        let total = text.chars().count().max(1);
+        let printable = text
+            .chars()
+            .filter(|&c| matches!(c, '\n' | '\r' | '\t') || !c.is_control())
+            .count();
+        return !text.contains('\0') && printable * 100 / total >= 85;
+    }
 
     // Any NUL => binary
     if sample.iter().any(|&b| b == 0) {
@@ -246,9 +371,17 @@ pub(crate) fn is_probably_text(path: &Path, limit: usize) -> bool {
     }
 
     // Count "printable-ish"
+    let utf8 = encoding == UTF_8;
     let printable = sample
         .iter()
-        .filter(|&&b| b == b'\n' || b == b'\r' || b == b'\t' || (b >= 0x20 && b <= 0x7E))
+        .filter(|&&b| {
+            b == b'\n'
+                || b == b'\r'
+                || b == b'\t'
+                || (b >= 0x20 && b <= 0x7E)
+                || (utf8 && b >= 0x80)
+                || b >= 0xA0
+        })
         .count();
 
     printable * 100 / n >= 85
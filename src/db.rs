@@ -0,0 +1,156 @@
+use crate::cache::insert_file_into_tree;
+use crate::types::{DirdocsRoot, Doc, FileEntry, Node, SCHEMA_VERSION};
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, params};
+use std::path::Path;
+
+/// Schema for the SQLite cache backend.
+///
+/// `meta` holds root-level fields (label, timestamp, root doc); `files` holds one row per
+/// documented file, keyed by relative path and indexed by content hash.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS meta (
+    key   TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS files (
+    path        TEXT PRIMARY KEY,
+    name        TEXT NOT NULL,
+    hash        TEXT NOT NULL,
+    updated_at  TEXT NOT NULL,
+    description TEXT NOT NULL,
+    doc         TEXT NOT NULL,
+    error       TEXT
+);
+CREATE INDEX IF NOT EXISTS files_hash ON files(hash);
+";
+
+/// Write a documentation tree to a SQLite database, replacing its previous contents.
+///
+/// Parameters:
+/// - `path`: The database file (typically `.dirdocs.db`); created if missing.
+/// - `tree`: The tree to store.
+///
+/// Returns:
+/// - `Ok(())` once the transaction has committed.
+///
+/// Errors:
+/// - SQLite errors when opening, creating the schema, or inserting rows.
+/// - JSON serialization errors for `Doc` values.
+pub(crate) fn write_tree_db(path: &Path, tree: &DirdocsRoot) -> anyhow::Result<()> {
+    let mut conn = Connection::open(path)?;
+    conn.execute_batch(SCHEMA)?;
+    if !has_error_column(&conn)? {
+        conn.execute("ALTER TABLE files ADD COLUMN error TEXT", [])?;
+    }
+
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM files", [])?;
+    tx.execute("DELETE FROM meta", [])?;
+    {
+        let mut meta = tx.prepare("INSERT INTO meta (key, value) VALUES (?1, ?2)")?;
+        meta.execute(params!["schema_version", SCHEMA_VERSION.to_string()])?;
+        meta.execute(params!["root", tree.root])?;
+        meta.execute(params!["updated_at", tree.updated_at.to_rfc3339()])?;
+        meta.execute(params!["doc", serde_json::to_string(&tree.doc)?])?;
+
+        let mut ins = tx.prepare(
+            "INSERT OR REPLACE INTO files (path, name, hash, updated_at, description, doc, error)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+        let mut stack: Vec<&Node> = tree.entries.iter().collect();
+        while let Some(n) = stack.pop() {
+            match n {
+                Node::Dir(d) => stack.extend(d.entries.iter()),
+                Node::File(f) => {
+                    ins.execute(params![
+                        f.path,
+                        f.name,
+                        f.hash,
+                        f.updated_at.to_rfc3339(),
+                        f.doc.fileDescription,
+                        serde_json::to_string(&f.doc)?,
+                        f.error.as_ref().map(serde_json::to_string).transpose()?,
+                    ])?;
+                }
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Whether `files` has the `error` column (databases written by older versions lack it).
+fn has_error_column(conn: &Connection) -> anyhow::Result<bool> {
+    Ok(conn
+        .prepare("SELECT 1 FROM pragma_table_info('files') WHERE name = 'error'")?
+        .exists([])?)
+}
+
+/// Load a documentation tree from a SQLite database written by [`write_tree_db`].
+///
+/// Parameters:
+/// - `path`: The database file.
+///
+/// Returns:
+/// - The tree rebuilt from the `files` rows, with root fields from `meta`.
+///
+/// Errors:
+/// - SQLite errors when opening or querying the database.
+/// - Timestamp or JSON parse errors for malformed rows.
+pub(crate) fn load_tree_db(path: &Path) -> anyhow::Result<DirdocsRoot> {
+    let conn = Connection::open(path)?;
+
+    let meta = |key: &str| -> Option<String> {
+        conn.query_row("SELECT value FROM meta WHERE key = ?1", [key], |r| r.get(0))
+            .ok()
+    };
+    let root = meta("root").unwrap_or_else(|| ".".to_string());
+    let updated_at = meta("updated_at")
+        .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+        .map(|d| d.with_timezone(&Utc))
+        .unwrap_or_else(Utc::now);
+    let doc: Doc = meta("doc")
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+
+    let mut tree = DirdocsRoot {
+        schema_version: SCHEMA_VERSION,
+        root,
+        updated_at,
+        doc,
+        entries: Vec::new(),
+    };
+
+    let error_col = if has_error_column(&conn)? {
+        "error"
+    } else {
+        "NULL"
+    };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT path, name, hash, updated_at, doc, {error_col} FROM files ORDER BY path"
+    ))?;
+    let rows = stmt.query_map([], |r| {
+        Ok((
+            r.get::<_, String>(0)?,
+            r.get::<_, String>(1)?,
+            r.get::<_, String>(2)?,
+            r.get::<_, String>(3)?,
+            r.get::<_, String>(4)?,
+            r.get::<_, Option<String>>(5)?,
+        ))
+    })?;
+    for row in rows {
+        let (path, name, hash, updated_at, doc, error) = row?;
+        let fe = FileEntry {
+            name,
+            hash,
+            updated_at: DateTime::parse_from_rfc3339(&updated_at)?.with_timezone(&Utc),
+            doc: serde_json::from_str(&doc).unwrap_or_default(),
+            error: error.and_then(|e| serde_json::from_str(&e).ok()),
+            path,
+        };
+        insert_file_into_tree(&mut tree.entries, &fe.path.clone(), &fe);
+    }
+    Ok(tree)
+}
@@ -0,0 +1,98 @@
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::debug;
+
+/// Well-known local OpenAI-compatible servers: (display name, `api_base`).
+pub(crate) const KNOWN_LOCAL_SERVERS: &[(&str, &str)] = &[
+    ("LM Studio", "http://localhost:1234/v1"),
+    ("Ollama", "http://localhost:11434/v1"),
+    ("llama.cpp", "http://localhost:8080/v1"),
+    ("vLLM", "http://localhost:8000/v1"),
+];
+
+/// Per-server timeout for probes; short enough for interactive paths.
+const PROBE_TIMEOUT: Duration = Duration::from_millis(800);
+
+/// A local model server that answered its model-listing endpoint.
+#[derive(Debug, Clone)]
+pub(crate) struct LocalServer {
+    /// Human-readable server name, e.g. "LM Studio".
+    pub name: &'static str,
+    /// The `api_base` to put in `config.yaml`.
+    pub api_base: String,
+    /// Model ids reported by the server.
+    pub models: Vec<String>,
+}
+
+/// Response body of an OpenAI-compatible `GET /models` request.
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    /// The listed models.
+    data: Vec<ModelInfo>,
+}
+
+/// A single entry of [`ModelList`].
+#[derive(Debug, Deserialize)]
+struct ModelInfo {
+    /// The model id to use in requests.
+    id: String,
+}
+
+/// Probe the [`KNOWN_LOCAL_SERVERS`] and return the ones that respond.
+///
+/// All servers are probed concurrently with a short timeout, so this is safe to call on
+/// an interactive path.
+///
+/// Returns:
+/// - Responding servers, in [`KNOWN_LOCAL_SERVERS`] order.
+pub(crate) async fn detect_local_servers() -> Vec<LocalServer> {
+    let mut probes = tokio::task::JoinSet::new();
+    for (idx, (name, api_base)) in KNOWN_LOCAL_SERVERS.iter().enumerate() {
+        probes.spawn(async move { (idx, *name, list_models(api_base).await) });
+    }
+
+    let mut found = Vec::new();
+    while let Some(res) = probes.join_next().await {
+        match res {
+            Ok((idx, name, Ok(models))) => found.push((
+                idx,
+                LocalServer {
+                    name,
+                    api_base: KNOWN_LOCAL_SERVERS[idx].1.to_string(),
+                    models,
+                },
+            )),
+            Ok((_, name, Err(e))) => debug!(server = name, error = %e, "Local server probe failed"),
+            Err(e) => debug!(error = %e, "Local server probe panicked"),
+        }
+    }
+    found.sort_by_key(|(idx, _)| *idx);
+    found.into_iter().map(|(_, s)| s).collect()
+}
+
+/// Ask an OpenAI-compatible server for its models (`GET {api_base}/models`).
+///
+/// Parameters:
+/// - `api_base`: The base URL, e.g. `http://localhost:1234/v1`.
+///
+/// Returns:
+/// - The model ids (possibly empty if the listing can't be parsed).
+///
+/// Errors:
+/// - A message when the server is unreachable or answers with a non-success status.
+pub(crate) async fn list_models(api_base: &str) -> Result<Vec<String>, String> {
+    let client = reqwest::Client::builder()
+        .timeout(PROBE_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let url = format!("{}/models", api_base.trim_end_matches('/'));
+    let resp = client.get(&url).send().await.map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("{url} answered {}", resp.status()));
+    }
+    Ok(resp
+        .json::<ModelList>()
+        .await
+        .map(|l| l.data.into_iter().map(|m| m.id).collect())
+        .unwrap_or_default())
+}
@@ -0,0 +1,217 @@
+//! Header metadata of compiled executables and libraries (ELF, Mach-O, PE).
+
+use goblin::Object;
+use goblin::mach::Mach;
+use regex::bytes::Regex;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// Largest binary parsed; bigger files keep the plain binary placeholder.
+const MAX_BINARY_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Most linked libraries kept.
+const MAX_LIBRARIES: usize = 30;
+
+/// Most embedded strings kept as hints.
+const MAX_STRING_HINTS: usize = 12;
+
+/// Architecture name used for machine types missing from the tables below.
+const UNKNOWN_ARCH: &str = "unknown architecture";
+
+/// What the headers of a compiled binary say about it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ExecutableInfo {
+    /// Container format: "ELF", "Mach-O" or "PE".
+    pub format: &'static str,
+    /// What the file is, e.g. "executable", "shared library" or "object file".
+    pub kind: &'static str,
+    /// CPU architectures, e.g. `["x86-64"]` (several for universal Mach-O binaries).
+    pub arch: Vec<&'static str>,
+    /// Names of the dynamically linked libraries, e.g. `libc.so.6` or `KERNEL32.dll`.
+    pub libraries: Vec<String>,
+    /// Embedded strings that hint at the binary's purpose: usage lines, versions, URLs.
+    pub strings: Vec<String>,
+}
+
+impl ExecutableInfo {
+    /// Summary used in place of binary content, e.g.
+    /// "ELF shared library, x86-64, links libc.so.6" followed by the string hints.
+    pub(crate) fn summary(&self) -> String {
+        let mut head = format!("{} {}", self.format, self.kind);
+        if !self.arch.is_empty() {
+            head.push_str(&format!(", {}", self.arch.join("/")));
+        }
+        if !self.libraries.is_empty() {
+            head.push_str(&format!(", links {}", self.libraries.join(", ")));
+        }
+        if self.strings.is_empty() {
+            head
+        } else {
+            format!("{head}\nEmbedded strings:\n{}", self.strings.join("\n"))
+        }
+    }
+}
+
+/// Read the format, architecture, linked libraries and telling strings of a binary.
+///
+/// Parameters:
+/// - `path`: The file to inspect; only files starting with an ELF, Mach-O or PE magic
+///   number (and no larger than 64 MB) are read in full.
+///
+/// Returns:
+/// - The parsed metadata, or `None` for other files and headers that don't parse.
+pub(crate) fn executable_info(path: &Path) -> Option<ExecutableInfo> {
+    let mut file = fs::File::open(path).ok()?;
+    let mut magic = [0u8; 4];
+    file.read_exact(&mut magic).ok()?;
+    let known = magic == *b"\x7fELF"
+        || magic.starts_with(b"MZ")
+        || matches!(
+            u32::from_be_bytes(magic),
+            0xFEEDFACE | 0xFEEDFACF | 0xCEFAEDFE | 0xCFFAEDFE | 0xCAFEBABE
+        );
+    if !known || file.metadata().ok()?.len() > MAX_BINARY_BYTES {
+        return None;
+    }
+    let bytes = fs::read(path).ok()?;
+
+    let mut info = match Object::parse(&bytes).ok()? {
+        Object::Elf(elf) => ExecutableInfo {
+            format: "ELF",
+            kind: match elf.header.e_type {
+                goblin::elf::header::ET_REL => "object file",
+                goblin::elf::header::ET_CORE => "core dump",
+                // PIE executables are ET_DYN too, but ask for an interpreter
+                goblin::elf::header::ET_DYN if elf.interpreter.is_none() => "shared library",
+                _ => "executable",
+            },
+            arch: vec![elf_arch(elf.header.e_machine)],
+            libraries: elf.libraries.iter().map(|l| l.to_string()).collect(),
+            ..ExecutableInfo::default()
+        },
+        Object::Mach(Mach::Binary(macho)) => ExecutableInfo {
+            format: "Mach-O",
+            kind: macho_kind(macho.header.filetype),
+            arch: vec![macho_arch(macho.header.cputype)],
+            // The first entry is the binary's own install name ("self")
+            libraries: macho.libs.iter().skip(1).map(|l| l.to_string()).collect(),
+            ..ExecutableInfo::default()
+        },
+        Object::Mach(Mach::Fat(fat)) => {
+            let arch: Vec<&'static str> = fat
+                .iter_arches()
+                .filter_map(Result::ok)
+                .map(|a| macho_arch(a.cputype))
+                .collect();
+            // Java class files share the universal-binary magic number
+            if arch.is_empty() || arch.contains(&UNKNOWN_ARCH) {
+                return None;
+            }
+            ExecutableInfo {
+                format: "Mach-O",
+                kind: "universal binary",
+                arch,
+                ..ExecutableInfo::default()
+            }
+        }
+        Object::PE(pe) => ExecutableInfo {
+            format: "PE",
+            kind: if pe.is_lib { "DLL" } else { "executable" },
+            arch: vec![pe_arch(pe.header.coff_header.machine)],
+            libraries: pe.libraries.iter().map(|l| l.to_string()).collect(),
+            ..ExecutableInfo::default()
+        },
+        _ => return None,
+    };
+    info.libraries.sort();
+    info.libraries.dedup();
+    info.libraries.truncate(MAX_LIBRARIES);
+    info.strings = string_hints(&bytes);
+    Some(info)
+}
+
+/// Printable strings that usually say what a program does: usage text, versions, URLs.
+fn string_hints(bytes: &[u8]) -> Vec<String> {
+    let Ok(runs) = Regex::new(r"[\x20-\x7E]{8,}") else {
+        return Vec::new();
+    };
+    let Ok(telling) =
+        Regex::new(r"(?i)(usage:|copyright|\(c\) |https?://|version \d|\bv?\d+\.\d+\.\d+\b)")
+    else {
+        return Vec::new();
+    };
+
+    let mut hints: Vec<String> = Vec::new();
+    for run in runs.find_iter(bytes) {
+        let s = run.as_bytes();
+        if !telling.is_match(s) {
+            continue;
+        }
+        let text = String::from_utf8_lossy(&s[..s.len().min(120)])
+            .trim()
+            .to_string();
+        if !hints.contains(&text) {
+            hints.push(text);
+        }
+        if hints.len() == MAX_STRING_HINTS {
+            break;
+        }
+    }
+    hints
+}
+
+/// Name of an ELF `e_machine` value.
+fn elf_arch(machine: u16) -> &'static str {
+    match machine {
+        3 => "x86",
+        8 => "MIPS",
+        20 => "PowerPC",
+        21 => "PowerPC64",
+        40 => "ARM",
+        62 => "x86-64",
+        183 => "AArch64",
+        243 => "RISC-V",
+        247 => "BPF",
+        258 => "LoongArch",
+        _ => UNKNOWN_ARCH,
+    }
+}
+
+/// Name of a Mach-O CPU type.
+fn macho_arch(cputype: u32) -> &'static str {
+    match cputype {
+        0x0000_0007 => "x86",
+        0x0100_0007 => "x86-64",
+        0x0000_000C => "ARM",
+        0x0100_000C => "arm64",
+        0x0200_000C => "arm64_32",
+        0x0000_0012 => "PowerPC",
+        0x0100_0012 => "PowerPC64",
+        _ => UNKNOWN_ARCH,
+    }
+}
+
+/// What a Mach-O `filetype` means.
+fn macho_kind(filetype: u32) -> &'static str {
+    match filetype {
+        1 => "object file",
+        2 => "executable",
+        4 => "core dump",
+        6 => "dynamic library",
+        8 => "bundle",
+        _ => "binary",
+    }
+}
+
+/// Name of a PE/COFF machine type.
+fn pe_arch(machine: u16) -> &'static str {
+    match machine {
+        0x014C => "x86",
+        0x8664 => "x86-64",
+        0x01C0 | 0x01C4 => "ARM",
+        0xAA64 => "ARM64",
+        0x0200 => "Itanium",
+        _ => UNKNOWN_ARCH,
+    }
+}
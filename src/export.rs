@@ -0,0 +1,82 @@
+use crate::types::{DirdocsRoot, Node};
+use std::fmt::Write as _;
+
+/// Render the documentation tree as a `FILES.md`-style Markdown document.
+///
+/// The root label becomes the title (followed by the project description, if any),
+/// and every directory becomes a nested bullet list: directories first, then files,
+/// each sorted case-insensitively. File names link to their paths relative to the root.
+///
+/// Parameters:
+/// - `tree`: The documentation tree loaded from `.dirdocs.nuon`.
+///
+/// Returns:
+/// - The Markdown document, ending with a newline.
+pub(crate) fn render_markdown(tree: &DirdocsRoot) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# {}", tree.root);
+    let root_desc = tree.doc.fileDescription.trim();
+    if !root_desc.is_empty() {
+        let _ = writeln!(out, "\n{root_desc}");
+    }
+    out.push('\n');
+    write_nodes(&mut out, &tree.entries, "", 0);
+    out
+}
+
+/// Append a bullet list for `nodes` at the given nesting `depth`.
+///
+/// `dir_path` is the path of the enclosing directory relative to the root (empty at the top).
+fn write_nodes(out: &mut String, nodes: &[Node], dir_path: &str, depth: usize) {
+    let indent = "  ".repeat(depth);
+
+    let mut sorted: Vec<&Node> = nodes.iter().collect();
+    sorted.sort_by_key(|n| match n {
+        Node::Dir(d) => (0, d.name.to_lowercase()),
+        Node::File(f) => (1, f.name.to_lowercase()),
+    });
+
+    for n in sorted {
+        match n {
+            Node::Dir(d) => {
+                let path = if dir_path.is_empty() {
+                    d.name.clone()
+                } else {
+                    format!("{dir_path}/{}", d.name)
+                };
+                let _ = writeln!(out, "{indent}- **{}/**", escape_md(&d.name));
+                write_nodes(out, &d.entries, &path, depth + 1);
+            }
+            Node::File(f) => {
+                let link = format!("[{}]({})", escape_md(&f.name), link_target(&f.path));
+                let desc = f.doc.fileDescription.trim();
+                if desc.is_empty() {
+                    let _ = writeln!(out, "{indent}- {link}");
+                } else {
+                    let _ = writeln!(out, "{indent}- {link} — {desc}");
+                }
+            }
+        }
+    }
+}
+
+/// Escape characters that would otherwise be read as Markdown emphasis or link syntax.
+fn escape_md(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '\\' | '*' | '_' | '[' | ']' | '`') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Turn a relative path into a link target, percent-encoding characters Markdown chokes on.
+fn link_target(path: &str) -> String {
+    path.replace('\\', "/")
+        .replace('%', "%25")
+        .replace(' ', "%20")
+        .replace('(', "%28")
+        .replace(')', "%29")
+}
@@ -0,0 +1,60 @@
+//! Text extraction for binary document formats, so they can be chunked like text files.
+
+use std::path::Path;
+#[cfg(feature = "pdf")]
+use tracing::warn;
+
+/// Most characters of extracted text kept, matching the cap on plain text files.
+#[cfg(feature = "pdf")]
+const MAX_EXTRACTED_CHARS: usize = 2_000_000;
+
+/// Whether text can be extracted from files of this MIME type in this build.
+///
+/// PDFs are supported when dirdocs is built with the `pdf` feature.
+pub(crate) fn is_extractable(mimetype: &str) -> bool {
+    cfg!(feature = "pdf") && mimetype == "application/pdf"
+}
+
+/// Extract the text of a document file.
+///
+/// Parameters:
+/// - `path`: The file to read.
+/// - `mimetype`: Its MIME type, as reported by `file_meta`.
+///
+/// Returns:
+/// - The extracted text (truncated to a couple of million characters), or `None` when the
+///   type isn't supported or the file can't be parsed (e.g. encrypted or scanned PDFs).
+pub(crate) fn extract_text(path: &Path, mimetype: &str) -> Option<String> {
+    if !is_extractable(mimetype) {
+        return None;
+    }
+    #[cfg(feature = "pdf")]
+    {
+        let text = pdf_text(path)?;
+        Some(text.chars().take(MAX_EXTRACTED_CHARS).collect())
+    }
+    #[cfg(not(feature = "pdf"))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// Pull the text out of a PDF, logging (rather than propagating) parse failures.
+#[cfg(feature = "pdf")]
+fn pdf_text(path: &Path) -> Option<String> {
+    // The PDF parser panics on some malformed files; treat that like any other failure
+    let result = std::panic::catch_unwind(|| pdf_extract::extract_text(path));
+    match result {
+        Ok(Ok(text)) if !text.trim().is_empty() => Some(text),
+        Ok(Ok(_)) => None,
+        Ok(Err(err)) => {
+            warn!(path=%path.display(), %err, "Could not extract PDF text");
+            None
+        }
+        Err(_) => {
+            warn!(path=%path.display(), "PDF parser panicked; treating file as binary");
+            None
+        }
+    }
+}
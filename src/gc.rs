@@ -0,0 +1,188 @@
+use crate::lock::{LOCK_FILE, is_lock_held};
+use crate::types::{DirdocsRoot, Node};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use walkdir::WalkDir;
+
+/// Name of the sidecar directory (next to `.dirdocs.nuon`) holding auxiliary artifacts.
+pub(crate) const SIDECAR_DIR: &str = ".dirdocs";
+
+/// Sidecar subdirectories whose files mirror a source file (`vectors/src/main.rs.vec`
+/// belongs to `src/main.rs`) and become orphans once that file is gone.
+pub(crate) const PER_FILE_SIDECARS: &[&str] = &["vectors", "shards"];
+
+/// Sidecar subdirectories whose files are only kept for the retention window.
+pub(crate) const RETAINED_SIDECARS: &[&str] = &["checkpoints", "logs", "history"];
+
+/// How long sidecar artifacts are allowed to live.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Retention {
+    /// Maximum age of checkpoints, logs and history entries.
+    pub keep: Duration,
+    /// Age after which a lock file is considered abandoned.
+    pub stale_lock: Duration,
+}
+
+/// What a garbage-collection pass removed (or would remove, in dry-run mode).
+#[derive(Debug, Default)]
+pub(crate) struct GcReport {
+    /// Number of file entries dropped from the tree because the file no longer exists.
+    pub pruned_entries: usize,
+    /// Sidecar files that were removed.
+    pub removed_files: Vec<PathBuf>,
+    /// Total size of the removed sidecar files, in bytes.
+    pub freed_bytes: u64,
+}
+
+/// Drop tree entries whose files no longer exist under `root`.
+///
+/// Directories left empty after pruning are dropped as well.
+///
+/// Parameters:
+/// - `tree`: The tree loaded from `.dirdocs.nuon`; modified in place.
+/// - `root`: Absolute path of the directory the tree describes.
+///
+/// Returns:
+/// - The number of file entries removed.
+pub(crate) fn prune_tree(tree: &mut DirdocsRoot, root: &Path) -> usize {
+    /// Recursively retain nodes that still exist, counting the files removed.
+    fn retain(nodes: &mut Vec<Node>, root: &Path) -> usize {
+        let mut removed = 0;
+        nodes.retain_mut(|n| match n {
+            Node::Dir(d) => {
+                removed += retain(&mut d.entries, root);
+                !d.entries.is_empty()
+            }
+            Node::File(f) => {
+                let keep = root.join(&f.path).is_file();
+                if !keep {
+                    removed += 1;
+                }
+                keep
+            }
+        });
+        removed
+    }
+
+    retain(&mut tree.entries, root)
+}
+
+/// Remove orphaned, expired, and stale sidecar artifacts under `root`.
+///
+/// - Files under [`PER_FILE_SIDECARS`] are removed when the source file they mirror is gone.
+/// - Files under [`RETAINED_SIDECARS`] are removed once older than `retention.keep`.
+/// - `*.lock` files (in the sidecar dir, or `.dirdocs.lock` at the root) are removed once
+///   older than `retention.stale_lock`, unless someone still holds them.
+///
+/// Parameters:
+/// - `root`: Absolute path of the documented directory.
+/// - `retention`: Age limits for retained artifacts and locks.
+/// - `dry_run`: When true, nothing is deleted; the report lists what would be removed.
+///
+/// Returns:
+/// - A [`GcReport`] describing the removed files and reclaimed bytes.
+///
+/// Errors:
+/// - I/O errors when deleting a file.
+pub(crate) fn sweep_sidecars(
+    root: &Path,
+    retention: Retention,
+    dry_run: bool,
+) -> anyhow::Result<GcReport> {
+    let mut report = GcReport::default();
+    let now = SystemTime::now();
+    let sidecar = root.join(SIDECAR_DIR);
+
+    let mut doomed: Vec<(PathBuf, u64)> = Vec::new();
+
+    let root_lock = root.join(LOCK_FILE);
+    if let Ok(md) = fs::metadata(&root_lock) {
+        if is_older_than(&md, now, retention.stale_lock) && !is_lock_held(&root_lock) {
+            doomed.push((root_lock, md.len()));
+        }
+    }
+
+    for entry in WalkDir::new(&sidecar).min_depth(1).into_iter().flatten() {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(md) = entry.metadata() else { continue };
+        let path = entry.path();
+        let Ok(rel) = path.strip_prefix(&sidecar) else {
+            continue;
+        };
+
+        let is_lock = path.extension().and_then(|e| e.to_str()) == Some("lock");
+        let category = rel
+            .components()
+            .next()
+            .and_then(|c| c.as_os_str().to_str())
+            .unwrap_or_default();
+
+        let remove = if is_lock {
+            is_older_than(&md, now, retention.stale_lock) && !is_lock_held(path)
+        } else if PER_FILE_SIDECARS.contains(&category) {
+            mirrored_source(root, rel).is_some_and(|src| !src.exists())
+        } else if RETAINED_SIDECARS.contains(&category) {
+            is_older_than(&md, now, retention.keep)
+        } else {
+            false
+        };
+
+        if remove {
+            doomed.push((path.to_path_buf(), md.len()));
+        }
+    }
+
+    for (path, size) in doomed {
+        if !dry_run {
+            fs::remove_file(&path)?;
+        }
+        report.freed_bytes += size;
+        report.removed_files.push(path);
+    }
+
+    if !dry_run {
+        remove_empty_dirs(&sidecar);
+    }
+
+    Ok(report)
+}
+
+/// Resolve the source file a per-file sidecar artifact belongs to.
+///
+/// `vectors/src/main.rs.vec` maps to `<root>/src/main.rs`: the category directory is
+/// stripped and the artifact's own extension removed.
+fn mirrored_source(root: &Path, rel: &Path) -> Option<PathBuf> {
+    let mut comps = rel.components();
+    comps.next()?; // category
+    let inner = comps.as_path();
+    let stem = inner.file_stem()?;
+    let parent = inner.parent().unwrap_or_else(|| Path::new(""));
+    Some(root.join(parent).join(stem))
+}
+
+/// True when the file's modification time is more than `max_age` in the past.
+fn is_older_than(md: &fs::Metadata, now: SystemTime, max_age: Duration) -> bool {
+    md.modified()
+        .ok()
+        .and_then(|m| now.duration_since(m).ok())
+        .is_some_and(|age| age > max_age)
+}
+
+/// Remove empty directories below `dir` (deepest first), keeping `dir` itself.
+fn remove_empty_dirs(dir: &Path) {
+    let dirs: Vec<PathBuf> = WalkDir::new(dir)
+        .min_depth(1)
+        .contents_first(true)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_dir())
+        .map(|e| e.into_path())
+        .collect();
+    for d in dirs {
+        // fails (harmlessly) when the directory still has contents
+        let _ = fs::remove_dir(&d);
+    }
+}
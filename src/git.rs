@@ -0,0 +1,317 @@
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// First line of the block `dirdocs install-hooks` adds to a hook script.
+const BLOCK_START: &str = "# >>> dirdocs >>>";
+
+/// Last line of that block.
+const BLOCK_END: &str = "# <<< dirdocs <<<";
+
+/// Most commits [`file_histories`] inspects, newest first.
+const MAX_HISTORY_COMMITS: usize = 5_000;
+
+/// What git knows about a file, for the prompt template.
+#[derive(Debug, Clone)]
+pub(crate) struct FileHistory {
+    /// First line of the message of the last commit that touched the file.
+    pub last_commit_message: String,
+    /// Author name of that commit.
+    pub last_author: String,
+    /// Author date of that commit.
+    pub last_commit_date: DateTime<Utc>,
+    /// Date of the oldest commit seen touching the file (its creation, within the scan limit).
+    pub first_commit_date: DateTime<Utc>,
+}
+
+/// Git hooks `dirdocs install-hooks` can manage.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GitHook {
+    /// Update descriptions before each commit and stage the cache with it.
+    PreCommit,
+    /// Update descriptions after each commit, leaving the cache to commit later.
+    PostCommit,
+}
+
+impl GitHook {
+    /// The hook's file name under the hooks directory.
+    pub(crate) fn file_name(self) -> &'static str {
+        match self {
+            GitHook::PreCommit => "pre-commit",
+            GitHook::PostCommit => "post-commit",
+        }
+    }
+}
+
+/// Files under `root` that changed since `rev`: committed, staged, unstaged or untracked.
+///
+/// Parameters:
+/// - `root`: The run root; it must be inside a git work tree.
+/// - `rev`: Any revision git understands (`HEAD`, `main`, `HEAD~3`, ...).
+///
+/// Returns:
+/// - Paths relative to `root`. Deleted files are left out.
+///
+/// Errors:
+/// - No enclosing repository, a bare repository, or an unknown revision.
+pub(crate) fn changed_since(root: &Path, rev: &str) -> anyhow::Result<HashSet<String>> {
+    let repo = git2::Repository::discover(root)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow::anyhow!("bare repositories are not supported"))?
+        .canonicalize()?;
+    let prefix = pathdiff::diff_paths(root, &workdir).unwrap_or_default();
+    let tree = repo
+        .revparse_single(rev)
+        .and_then(|o| o.peel_to_tree())
+        .map_err(|e| anyhow::anyhow!("unknown revision {rev:?}: {}", e.message()))?;
+
+    let mut opts = git2::DiffOptions::new();
+    opts.include_untracked(true).recurse_untracked_dirs(true);
+    let diff = repo.diff_tree_to_workdir_with_index(Some(&tree), Some(&mut opts))?;
+
+    let mut changed = HashSet::new();
+    for delta in diff.deltas() {
+        if delta.status() == git2::Delta::Deleted {
+            continue;
+        }
+        let Some(path) = delta.new_file().path() else {
+            continue;
+        };
+        if let Ok(rel) = path.strip_prefix(&prefix) {
+            changed.insert(rel.to_string_lossy().to_string());
+        }
+    }
+    Ok(changed)
+}
+
+/// The history of every file under `root` that was changed in the last commits on `HEAD`.
+///
+/// Walks at most [`MAX_HISTORY_COMMITS`] commits, newest first, diffing each against its
+/// first parent. Renames aren't followed, so a moved file's history starts at the move.
+///
+/// Parameters:
+/// - `root`: The run root; it must be inside a git work tree.
+///
+/// Returns:
+/// - Histories keyed by path relative to `root`.
+///
+/// Errors:
+/// - When `root` isn't inside a non-bare repository or `HEAD` can't be resolved.
+pub(crate) fn file_histories(root: &Path) -> anyhow::Result<HashMap<String, FileHistory>> {
+    let repo = git2::Repository::discover(root)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow::anyhow!("bare repositories are not supported"))?
+        .canonicalize()?;
+    let prefix = pathdiff::diff_paths(root, &workdir).unwrap_or_default();
+
+    let mut walk = repo.revwalk()?;
+    walk.push_head()?;
+    walk.set_sorting(git2::Sort::TIME)?;
+
+    let mut histories: HashMap<String, FileHistory> = HashMap::new();
+    for oid in walk.take(MAX_HISTORY_COMMITS) {
+        let commit = repo.find_commit(oid?)?;
+        let when =
+            DateTime::from_timestamp(commit.author().when().seconds(), 0).unwrap_or_default();
+        let tree = commit.tree()?;
+        let parent_tree = match commit.parent(0) {
+            Ok(p) => Some(p.tree()?),
+            Err(_) => None,
+        };
+        let diff = match repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None) {
+            Ok(d) => d,
+            Err(e) => {
+                warn!(%e, commit = %commit.id(), "Skipping commit in history scan");
+                continue;
+            }
+        };
+        for delta in diff.deltas() {
+            let Some(path) = delta.new_file().path() else {
+                continue;
+            };
+            let Ok(rel) = path.strip_prefix(&prefix) else {
+                continue;
+            };
+            histories
+                .entry(rel.to_string_lossy().to_string())
+                .and_modify(|h| h.first_commit_date = when)
+                .or_insert_with(|| FileHistory {
+                    last_commit_message: commit.summary().unwrap_or_default().to_string(),
+                    last_author: commit.author().name().unwrap_or_default().to_string(),
+                    last_commit_date: when,
+                    first_commit_date: when,
+                });
+        }
+    }
+    Ok(histories)
+}
+
+/// Install (or refresh) the dirdocs block in a git hook.
+///
+/// An existing hook script is kept: the block is appended to it, or replaced in place
+/// when a previous `install-hooks` already added one.
+///
+/// Parameters:
+/// - `root`: The documented directory; the hook runs dirdocs on it.
+/// - `hook`: Which hook to install.
+/// - `check`: Run `dirdocs check` instead of regenerating changed files.
+///
+/// Returns:
+/// - The path of the hook script.
+///
+/// Errors:
+/// - When `root` isn't inside a non-bare git repository, or the script can't be written.
+pub(crate) fn install_hook(root: &Path, hook: GitHook, check: bool) -> anyhow::Result<PathBuf> {
+    let (hooks_dir, workdir) = hooks_dir(root)?;
+    let rel = pathdiff::diff_paths(root, &workdir).unwrap_or_default();
+    let rel = match rel.to_string_lossy().as_ref() {
+        "" => ".".to_string(),
+        s => s.to_string(),
+    };
+
+    let path = hooks_dir.join(hook.file_name());
+    let existing = fs::read_to_string(&path).unwrap_or_default();
+    let kept = remove_block(&existing);
+    let mut script = if kept.trim().is_empty() {
+        "#!/bin/sh\n".to_string()
+    } else {
+        let mut s = kept;
+        if !s.ends_with('\n') {
+            s.push('\n');
+        }
+        s
+    };
+    script.push_str(&hook_block(hook, &rel, check));
+
+    fs::create_dir_all(&hooks_dir)?;
+    fs::write(&path, script)?;
+    make_executable(&path)?;
+    info!(hook = hook.file_name(), path=%path.display(), "Hook installed");
+    Ok(path)
+}
+
+/// Remove the dirdocs block from a git hook, deleting the script if nothing else is left.
+///
+/// Parameters:
+/// - `root`: Any directory inside the repository.
+/// - `hook`: Which hook to clean up.
+///
+/// Returns:
+/// - `true` when a dirdocs block was found and removed.
+///
+/// Errors:
+/// - When `root` isn't inside a non-bare git repository, or the script can't be rewritten.
+pub(crate) fn uninstall_hook(root: &Path, hook: GitHook) -> anyhow::Result<bool> {
+    let (hooks_dir, _) = hooks_dir(root)?;
+    let path = hooks_dir.join(hook.file_name());
+    let Ok(existing) = fs::read_to_string(&path) else {
+        return Ok(false);
+    };
+    if !existing.contains(BLOCK_START) {
+        return Ok(false);
+    }
+
+    let kept = remove_block(&existing);
+    let only_shebang = kept
+        .lines()
+        .all(|l| l.trim().is_empty() || l.starts_with("#!"));
+    if only_shebang {
+        fs::remove_file(&path)?;
+    } else {
+        fs::write(&path, kept)?;
+    }
+    info!(hook = hook.file_name(), path=%path.display(), "Hook removed");
+    Ok(true)
+}
+
+/// The hooks directory (honoring `core.hooksPath`) and work tree of the repository containing `root`.
+fn hooks_dir(root: &Path) -> anyhow::Result<(PathBuf, PathBuf)> {
+    let repo = git2::Repository::discover(root)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow::anyhow!("bare repositories have no hooks to install"))?
+        .canonicalize()?;
+    let configured = repo
+        .config()
+        .ok()
+        .and_then(|c| c.get_path("core.hooksPath").ok());
+    let dir = match configured {
+        Some(p) if p.is_absolute() => p,
+        Some(p) => workdir.join(p),
+        None => repo.commondir().join("hooks"),
+    };
+    Ok((dir, workdir))
+}
+
+/// The shell lines dirdocs adds to `hook`, fenced by [`BLOCK_START`] and [`BLOCK_END`].
+fn hook_block(hook: GitHook, rel: &str, check: bool) -> String {
+    let dir = sh_quote(rel);
+    let body = if check {
+        format!("dirdocs check --directory {dir}\n")
+    } else {
+        match hook {
+            GitHook::PreCommit => {
+                let cache = sh_quote(&format!("{rel}/.dirdocs.nuon"));
+                format!(
+                    "dirdocs run --directory {dir} --changed-since HEAD \\\n  \
+                     || echo \"dirdocs: run failed; descriptions not updated\" >&2\n\
+                     if [ -f {cache} ]; then git add -- {cache}; fi\n"
+                )
+            }
+            GitHook::PostCommit => format!(
+                "if git rev-parse -q --verify HEAD~1 >/dev/null; then\n  \
+                 dirdocs run --directory {dir} --changed-since HEAD~1 \\\n    \
+                 || echo \"dirdocs: run failed; descriptions not updated\" >&2\n\
+                 fi\n"
+            ),
+        }
+    };
+    format!(
+        "{BLOCK_START}\n\
+         # Added by `dirdocs install-hooks`; remove with `dirdocs uninstall-hooks`.\n\
+         {body}{BLOCK_END}\n"
+    )
+}
+
+/// `script` without the dirdocs block (if any).
+fn remove_block(script: &str) -> String {
+    let mut out = String::new();
+    let mut inside = false;
+    for line in script.lines() {
+        if line.trim() == BLOCK_START {
+            inside = true;
+        } else if line.trim() == BLOCK_END {
+            inside = false;
+        } else if !inside {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Quote `s` for a POSIX shell.
+fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Mark the hook script executable (git skips hooks that aren't).
+#[cfg(unix)]
+fn make_executable(path: &Path) -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut perms = fs::metadata(path)?.permissions();
+    perms.set_mode(perms.mode() | 0o755);
+    fs::set_permissions(path, perms)?;
+    Ok(())
+}
+
+/// Mark the hook script executable; a no-op where permissions have no execute bit.
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> anyhow::Result<()> {
+    Ok(())
+}
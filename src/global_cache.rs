@@ -0,0 +1,76 @@
+use crate::types::Doc;
+use directories::ProjectDirs;
+use std::fs;
+use std::path::PathBuf;
+use tracing::debug;
+
+/// A machine-wide, content-addressed store of file descriptions.
+///
+/// Entries live under the XDG data directory (e.g. `~/.local/share/dirdocs/descriptions`)
+/// and are keyed by the file's content hash, the raw template that produced the prompt,
+/// and the model name. The same vendored file documented in several repositories is
+/// therefore only described once per template/model pair.
+pub(crate) struct GlobalCache {
+    /// Directory holding one JSON `Doc` per key, sharded by the key's first two characters.
+    dir: PathBuf,
+    /// The model the descriptions were generated with.
+    model: String,
+}
+
+impl GlobalCache {
+    /// Open (creating if needed) the global cache for `model`.
+    ///
+    /// Errors:
+    /// - When the platform data directory cannot be determined or created.
+    pub(crate) fn open(model: &str) -> anyhow::Result<Self> {
+        let dirs = ProjectDirs::from("com", "awful-sec", "dirdocs")
+            .ok_or_else(|| anyhow::anyhow!("could not determine the user data directory"))?;
+        let dir = dirs.data_dir().join("descriptions");
+        fs::create_dir_all(&dir)
+            .map_err(|e| anyhow::anyhow!("failed to create global cache {:?}: {e}", dir))?;
+        Ok(Self {
+            dir,
+            model: model.to_string(),
+        })
+    }
+
+    /// The cache key for a file with content hash `file_hash` rendered through `raw_template`.
+    pub(crate) fn key(&self, file_hash: &str, raw_template: &str) -> String {
+        let template_hash = blake3::hash(raw_template.as_bytes());
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(file_hash.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(template_hash.to_hex().as_bytes());
+        hasher.update(b"\0");
+        hasher.update(self.model.as_bytes());
+        hasher.finalize().to_hex().to_string()
+    }
+
+    /// Path of the entry for `key`.
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(&key[..2]).join(format!("{key}.json"))
+    }
+
+    /// Look up a description; unreadable or empty entries count as misses.
+    pub(crate) fn get(&self, key: &str) -> Option<Doc> {
+        let body = fs::read_to_string(self.entry_path(key)).ok()?;
+        let doc: Doc = serde_json::from_str(&body).ok()?;
+        (!doc.fileDescription.is_empty()).then_some(doc)
+    }
+
+    /// Store a description, writing through a temp file so concurrent runs never see a partial entry.
+    ///
+    /// Errors:
+    /// - I/O or serialization errors while writing the entry.
+    pub(crate) fn put(&self, key: &str, doc: &Doc) -> anyhow::Result<()> {
+        let path = self.entry_path(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        fs::write(&tmp, serde_json::to_string(doc)?)?;
+        fs::rename(&tmp, &path)?;
+        debug!(key, "Stored description in global cache");
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use crate::types::FileEntry;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::{debug, warn};
+
+/// Run the user's post-generation hook for a freshly documented file.
+///
+/// The command runs through the platform shell (`sh -c`, or `cmd /C` on Windows) with:
+/// - `DIRDOCS_PATH`: the file path relative to the run root,
+/// - `DIRDOCS_ABS_PATH`: the absolute file path,
+/// - `DIRDOCS_DESCRIPTION`: the generated one-sentence description,
+///
+/// and the file's entry (the same JSON stored in `.dirdocs.nuon`) on stdin.
+///
+/// Parameters:
+/// - `cmd`: The hook command line, from `--hook`.
+/// - `root`: The run root, used to build `DIRDOCS_ABS_PATH`.
+/// - `entry`: The entry that was just documented.
+///
+/// Notes:
+/// - Hook failures (spawn errors, non-zero exit) are logged and never abort the run.
+pub(crate) async fn run_hook(cmd: &str, root: &Path, entry: &FileEntry) {
+    let mut command = if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.arg("/C").arg(cmd);
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c").arg(cmd);
+        c
+    };
+    command
+        .current_dir(root)
+        .env("DIRDOCS_PATH", &entry.path)
+        .env("DIRDOCS_ABS_PATH", root.join(&entry.path))
+        .env("DIRDOCS_DESCRIPTION", &entry.doc.fileDescription)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit());
+
+    let mut child = match command.spawn() {
+        Ok(c) => c,
+        Err(e) => {
+            warn!(%e, hook = cmd, "Failed to start hook");
+            return;
+        }
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        let body = serde_json::to_vec(entry).unwrap_or_default();
+        // A hook that ignores stdin may exit before reading it; that's not an error.
+        if let Err(e) = stdin.write_all(&body).await {
+            debug!(%e, "Hook did not read stdin");
+        }
+    }
+
+    match child.wait().await {
+        Ok(status) if status.success() => debug!(path = %entry.path, "Hook finished"),
+        Ok(status) => warn!(path = %entry.path, %status, hook = cmd, "Hook exited unsuccessfully"),
+        Err(e) => warn!(%e, hook = cmd, "Failed to wait for hook"),
+    }
+}
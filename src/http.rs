@@ -0,0 +1,312 @@
+use crate::cache::index_files_by_path;
+use crate::metrics::{self, RunStats};
+use crate::search::search_tree;
+use crate::types::DirdocsRoot;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+/// Largest request head accepted; everything dirdocs serves is a small GET.
+const MAX_REQUEST_BYTES: usize = 16 * 1024;
+
+/// Loads the current documentation tree for a root (re-read on every request).
+pub(crate) type TreeLoader = fn(&Path) -> anyhow::Result<DirdocsRoot>;
+
+/// Which endpoints a listener exposes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Endpoints {
+    /// The web UI and JSON API (`serve --http`).
+    Browse,
+    /// Only Prometheus metrics at `/metrics` (`serve --metrics`).
+    Metrics,
+}
+
+/// The embedded single-page UI served at `/`.
+const INDEX_HTML: &str = r#"<!doctype html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>dirdocs</title>
+<style>
+  body { font: 14px/1.5 system-ui, sans-serif; margin: 2rem auto; max-width: 60rem; color: #222; }
+  input { width: 100%; padding: .5rem; font-size: 1rem; box-sizing: border-box; }
+  ul { list-style: none; padding-left: 1.2rem; }
+  summary { cursor: pointer; font-weight: 600; }
+  .path { font-family: ui-monospace, monospace; }
+  .desc { color: #555; }
+  #root-desc { color: #555; font-style: italic; }
+</style>
+</head>
+<body>
+<h1 id="title">dirdocs</h1>
+<p id="root-desc"></p>
+<input id="q" type="search" placeholder="Search paths and descriptions…" autofocus>
+<ul id="results"></ul>
+<div id="tree"></div>
+<script>
+const el = (tag, cls, text) => { const e = document.createElement(tag); if (cls) e.className = cls; if (text) e.textContent = text; return e; };
+const fileLine = (path, name, desc) => {
+  const li = el("li");
+  li.append(el("span", "path", name), document.createTextNode(desc ? " — " : ""), el("span", "desc", desc || ""));
+  li.title = path;
+  return li;
+};
+const renderNodes = (nodes) => {
+  const ul = el("ul");
+  for (const n of nodes) {
+    if (n.kind === "dir") {
+      const li = el("li"), d = el("details"), s = el("summary", "path", n.name);
+      d.append(s, renderNodes(n.entries)); li.append(d); ul.append(li);
+    } else {
+      ul.append(fileLine(n.path, n.name, n.doc && n.doc.fileDescription));
+    }
+  }
+  return ul;
+};
+fetch("tree").then(r => r.json()).then(t => {
+  document.getElementById("title").textContent = t.root;
+  document.getElementById("root-desc").textContent = (t.doc && t.doc.fileDescription) || "";
+  document.getElementById("tree").append(renderNodes(t.entries));
+});
+let timer;
+document.getElementById("q").addEventListener("input", (ev) => {
+  clearTimeout(timer);
+  timer = setTimeout(() => {
+    const q = ev.target.value.trim(), out = document.getElementById("results");
+    out.replaceChildren();
+    document.getElementById("tree").hidden = q !== "";
+    if (!q) return;
+    fetch("search?q=" + encodeURIComponent(q)).then(r => r.json()).then(hits => {
+      for (const h of hits) out.append(fileLine(h.path, h.path, h.fileDescription));
+    });
+  }, 150);
+});
+</script>
+</body>
+</html>
+"#;
+
+/// Serve the documentation of `root` over HTTP until the process is stopped.
+///
+/// [`Endpoints::Browse`] (all `GET`):
+/// - `/`: A minimal web UI for browsing and searching the tree.
+/// - `/tree`: The whole tree as JSON.
+/// - `/file?path=<rel>`: One file's entry as JSON (404 when undocumented).
+/// - `/search?q=<text>`: Case-insensitive substring search over paths and descriptions.
+///
+/// [`Endpoints::Metrics`]:
+/// - `/metrics`: Documentation freshness and last-run statistics in Prometheus format.
+///
+/// Parameters:
+/// - `addr`: The address to bind, e.g. `127.0.0.1:7878`.
+/// - `root`: The documented root directory.
+/// - `load`: Loads the tree for `root`; called per request so new runs show up immediately.
+/// - `endpoints`: Which set of endpoints to expose.
+///
+/// Errors:
+/// - When the address cannot be bound.
+pub(crate) async fn serve_http(
+    addr: &str,
+    root: PathBuf,
+    load: TreeLoader,
+    endpoints: Endpoints,
+) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|e| anyhow::anyhow!("cannot listen on {addr}: {e}"))?;
+    info!(addr = %listener.local_addr()?, root = %root.display(), ?endpoints, "dirdocs HTTP server listening");
+
+    let root = Arc::new(root);
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let root = Arc::clone(&root);
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &root, load, endpoints).await {
+                debug!(%peer, %e, "HTTP connection error");
+            }
+        });
+    }
+}
+
+/// Read one request from `stream`, route it, and write the response.
+async fn handle_connection(
+    mut stream: TcpStream,
+    root: &Path,
+    load: TreeLoader,
+    endpoints: Endpoints,
+) -> anyhow::Result<()> {
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if buf.len() > MAX_REQUEST_BYTES {
+            return respond(&mut stream, 431, "text/plain", "request too large").await;
+        }
+    }
+
+    let head = String::from_utf8_lossy(&buf);
+    let mut parts = head.lines().next().unwrap_or_default().split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or("/"));
+    debug!(method, target, "HTTP request");
+    if method != "GET" {
+        return respond(&mut stream, 405, "text/plain", "only GET is supported").await;
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let params = parse_query(query);
+    let (path, root) = (path.to_string(), root.to_path_buf());
+    // Loading the tree (and re-hashing changed files for /metrics) is blocking I/O
+    let (status, content_type, body) = tokio::task::spawn_blocking(move || match endpoints {
+        Endpoints::Browse => route(&path, &params, &root, load),
+        Endpoints::Metrics => route_metrics(&path, &root, load),
+    })
+    .await?;
+    respond(&mut stream, status, content_type, &body).await
+}
+
+/// Map a request to `(status, content type, body)`.
+fn route(
+    path: &str,
+    params: &HashMap<String, String>,
+    root: &Path,
+    load: TreeLoader,
+) -> (u16, &'static str, String) {
+    const JSON: &str = "application/json";
+
+    if path == "/" || path == "/index.html" {
+        return (200, "text/html; charset=utf-8", INDEX_HTML.to_string());
+    }
+
+    let tree = match load(root) {
+        Ok(t) => t,
+        Err(e) => {
+            warn!(%e, "Cannot load documentation tree");
+            return (503, JSON, error_json(&e.to_string()));
+        }
+    };
+
+    match path {
+        "/tree" => (200, JSON, to_json(&tree)),
+        "/file" => {
+            let Some(rel) = params.get("path") else {
+                return (400, JSON, error_json("missing `path` query parameter"));
+            };
+            let mut by_path = HashMap::new();
+            index_files_by_path(&tree.entries, &mut by_path);
+            match by_path.get(rel.trim_start_matches("./")) {
+                Some(entry) => (200, JSON, to_json(entry)),
+                None => (404, JSON, error_json(&format!("{rel} is not documented"))),
+            }
+        }
+        "/search" => {
+            let q = params.get("q").map(String::as_str).unwrap_or_default();
+            let re = regex::RegexBuilder::new(&regex::escape(q))
+                .case_insensitive(true)
+                .build();
+            match re {
+                Ok(re) => (200, JSON, to_json(&search_tree(&tree, &re))),
+                Err(e) => (400, JSON, error_json(&e.to_string())),
+            }
+        }
+        _ => (404, JSON, error_json("not found")),
+    }
+}
+
+/// Map a request on a metrics listener to `(status, content type, body)`.
+fn route_metrics(path: &str, root: &Path, load: TreeLoader) -> (u16, &'static str, String) {
+    if path != "/metrics" {
+        return (404, "text/plain", "not found".to_string());
+    }
+    match load(root) {
+        Ok(tree) => {
+            let body = metrics::render(&tree, root, RunStats::load(root).as_ref());
+            (200, "text/plain; version=0.0.4", body)
+        }
+        Err(e) => {
+            warn!(%e, "Cannot load documentation tree");
+            (503, "text/plain", e.to_string())
+        }
+    }
+}
+
+/// Write a complete `Connection: close` response.
+async fn respond(
+    stream: &mut TcpStream,
+    status: u16,
+    content_type: &str,
+    body: &str,
+) -> anyhow::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "",
+    };
+    let head = format!(
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Serialize `v` as JSON, falling back to an error object.
+fn to_json(v: &impl serde::Serialize) -> String {
+    serde_json::to_string(v).unwrap_or_else(|e| error_json(&e.to_string()))
+}
+
+/// `{"error": msg}` as a string.
+fn error_json(msg: &str) -> String {
+    serde_json::json!({ "error": msg }).to_string()
+}
+
+/// Parse an `application/x-www-form-urlencoded` query string.
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|kv| !kv.is_empty())
+        .map(|kv| {
+            let (k, v) = kv.split_once('=').unwrap_or((kv, ""));
+            (percent_decode(k), percent_decode(v))
+        })
+        .collect()
+}
+
+/// Decode `%XX` escapes and `+` (space); invalid escapes are kept as-is.
+pub(crate) fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                match std::str::from_utf8(&bytes[i + 1..i + 3])
+                    .ok()
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                {
+                    Some(b) => {
+                        out.push(b);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
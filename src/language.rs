@@ -0,0 +1,98 @@
+use crate::types::{DirdocsRoot, Doc, Node};
+use std::collections::BTreeMap;
+
+/// Minimum detector confidence for a description to count towards a language.
+///
+/// One-sentence descriptions are short, so `whatlang` rarely flags them as "reliable";
+/// a plain confidence cut-off keeps most of them while dropping coin flips.
+const MIN_CONFIDENCE: f64 = 0.5;
+
+/// Detect the language of a description.
+///
+/// Returns:
+/// - The ISO 639-3 code (e.g. `eng`, `spa`), or `None` when the text is too short or ambiguous.
+pub(crate) fn detect_language(text: &str) -> Option<&'static str> {
+    let info = whatlang::detect(text)?;
+    (info.confidence() >= MIN_CONFIDENCE).then(|| info.lang().code())
+}
+
+/// Languages found among the cached descriptions of a tree.
+#[derive(Debug, Default)]
+pub(crate) struct LanguageReport {
+    /// Paths of the described files, grouped by detected language code.
+    pub by_language: BTreeMap<&'static str, Vec<String>>,
+    /// Number of descriptions whose language could not be detected.
+    pub undetected: usize,
+}
+
+impl LanguageReport {
+    /// The language with the most descriptions, if any were detected.
+    pub(crate) fn majority(&self) -> Option<&'static str> {
+        self.by_language
+            .iter()
+            .max_by_key(|(_, paths)| paths.len())
+            .map(|(lang, _)| *lang)
+    }
+
+    /// Whether descriptions were detected in more than one language.
+    pub(crate) fn is_mixed(&self) -> bool {
+        self.by_language.len() > 1
+    }
+}
+
+/// Group every non-empty file description in `tree` by detected language.
+pub(crate) fn language_report(tree: &DirdocsRoot) -> LanguageReport {
+    let mut report = LanguageReport::default();
+    let mut stack: Vec<&Node> = tree.entries.iter().collect();
+    while let Some(n) = stack.pop() {
+        match n {
+            Node::Dir(d) => stack.extend(d.entries.iter()),
+            Node::File(f) if !f.doc.fileDescription.is_empty() => {
+                match detect_language(&f.doc.fileDescription) {
+                    Some(lang) => report
+                        .by_language
+                        .entry(lang)
+                        .or_default()
+                        .push(f.path.clone()),
+                    None => report.undetected += 1,
+                }
+            }
+            Node::File(_) => {}
+        }
+    }
+    for paths in report.by_language.values_mut() {
+        paths.sort();
+    }
+    report
+}
+
+/// Clear the descriptions of files not written in `keep`, so the next run regenerates them.
+///
+/// Files whose language could not be detected are left untouched.
+///
+/// Parameters:
+/// - `tree`: The tree to normalize; modified in place.
+/// - `keep`: The language code to keep (usually [`LanguageReport::majority`]).
+///
+/// Returns:
+/// - The number of descriptions cleared.
+pub(crate) fn clear_other_languages(tree: &mut DirdocsRoot, keep: &str) -> usize {
+    /// Recursively clear descriptions in another language, counting them.
+    fn clear(nodes: &mut [Node], keep: &str) -> usize {
+        let mut cleared = 0;
+        for n in nodes {
+            match n {
+                Node::Dir(d) => cleared += clear(&mut d.entries, keep),
+                Node::File(f) => {
+                    if detect_language(&f.doc.fileDescription).is_some_and(|l| l != keep) {
+                        f.doc = Doc::default();
+                        cleared += 1;
+                    }
+                }
+            }
+        }
+        cleared
+    }
+
+    clear(&mut tree.entries, keep)
+}
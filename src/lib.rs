@@ -0,0 +1,171 @@
+//! Generate terse, one-sentence documentation for files.
+//!
+//! `dirdocs` walks a directory (honoring `.gitignore`), asks an OpenAI-compatible model
+//! to describe each file, and caches the results in `.dirdocs.nuon` (or `.dirdocs.db`)
+//! at the root. The `dirdocs` binary is a thin CLI over this library; other tools can
+//! reuse the same scanning, caching and generation logic:
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! # async fn demo() -> anyhow::Result<()> {
+//! let root = Path::new(".");
+//! for rel in dirdocs::scan(root) {
+//!     println!("{}", rel.display());
+//! }
+//!
+//! // Describe new or changed files under src/ (uses the Awful Jade config.yaml).
+//! for entry in dirdocs::generate(Path::new("src")).await? {
+//!     println!("{}: {}", entry.path, entry.doc.fileDescription);
+//! }
+//!
+//! let mut tree = dirdocs::load_tree(root)?;
+//! tree.doc.fileDescription = "Hand-written project summary.".to_string();
+//! dirdocs::write_tree(root, &tree)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`generate_with`] reports progress to a [`ProgressObserver`] and can be stopped with a
+//! [`CancellationToken`].
+//!
+//! - [`cli`] runs the full `dirdocs` command line.
+//! - [`completions`] prints shell completion scripts for a clap command.
+//! - [`view`] reads caches and bundles for the `dls` and `dtree` viewers.
+
+mod archive;
+mod bundle;
+mod cache;
+mod chat;
+mod chunk;
+pub mod cli;
+pub mod completions;
+mod content;
+mod db;
+mod discover;
+mod executable;
+mod export;
+mod extract;
+mod gc;
+mod git;
+mod global_cache;
+mod hook;
+mod http;
+mod language;
+mod lock;
+mod lsp;
+mod manifest;
+mod mcp;
+mod media;
+mod metrics;
+mod paths;
+mod priority;
+mod progress;
+mod prompt_llm;
+mod scan;
+mod search;
+mod serve;
+mod splitters;
+mod template;
+mod tokenizer;
+mod types;
+pub mod view;
+mod xattrs;
+
+pub use crate::progress::{
+    ContextOverflow, FileOutcome, NoopObserver, ProgressObserver, RunReport,
+};
+pub use crate::types::{DirEntry, DirdocsRoot, Doc, EntryError, FileEntry, Node, SCHEMA_VERSION};
+pub use tokio_util::sync::CancellationToken;
+
+use crate::cache::{CacheBackend, load_documented_tree, save_tree};
+use crate::paths::ConfigPaths;
+use std::path::{Path, PathBuf};
+
+/// List the files under `root` that a documentation run would describe.
+///
+/// Parameters:
+/// - `root`: The directory to walk.
+///
+/// Returns:
+/// - Paths relative to `root`, in walk order. `.gitignore`d and hidden files are skipped.
+pub fn scan(root: &Path) -> Vec<PathBuf> {
+    scan::walk_files(root, &[])
+        .into_iter()
+        .map(|(_, rel)| PathBuf::from(rel))
+        .collect()
+}
+
+/// Load the documentation tree cached at `root`.
+///
+/// Reads `.dirdocs.nuon` or `.dirdocs.db`, whichever was written last.
+///
+/// Errors:
+/// - When `root` has no cache yet.
+pub fn load_tree(root: &Path) -> anyhow::Result<DirdocsRoot> {
+    load_documented_tree(root)
+}
+
+/// Write `tree` as the documentation cache of `root`.
+///
+/// Keeps the cache format already in use at `root` (JSON by default).
+///
+/// Returns:
+/// - The path of the cache file that was written.
+///
+/// Errors:
+/// - Serialization, SQLite or I/O errors.
+pub fn write_tree(root: &Path, tree: &DirdocsRoot) -> anyhow::Result<PathBuf> {
+    let backend = CacheBackend::detect(root).unwrap_or(CacheBackend::Json);
+    save_tree(backend, root, tree)
+}
+
+/// Describe a file, or every file under a directory, and update the cache.
+///
+/// Works like `dirdocs run`: unchanged files keep their cached description and only new
+/// or modified ones are sent to the model. The cache that is updated belongs to the
+/// nearest enclosing directory that already has one; otherwise a directory becomes its
+/// own root and a file is documented within its parent directory.
+///
+/// The model settings come from the Awful Jade `config.yaml` (or `$DIRDOCS_CONFIG_DIR`),
+/// which is created with defaults on first use.
+///
+/// Parameters:
+/// - `path`: The file or directory to document.
+///
+/// Returns:
+/// - The entries for `path`, sorted by path.
+///
+/// Errors:
+/// - When `path` doesn't exist, the config can't be loaded, or the cache can't be written.
+pub async fn generate(path: &Path) -> anyhow::Result<Vec<FileEntry>> {
+    generate_with(path, &NoopObserver, CancellationToken::new()).await
+}
+
+/// Like [`generate`], reporting progress to `observer` and stopping early when `cancel`
+/// is cancelled.
+///
+/// A cancelled run still writes the cache: files described so far keep their new
+/// descriptions, the rest keep their cached ones. The file in flight is reported to
+/// `observer` as [`FileOutcome::Cancelled`].
+///
+/// Parameters:
+/// - `path`: The file or directory to document.
+/// - `observer`: Receives an event per file, each retry and checkpoint, and the final
+///   [`RunReport`], e.g. to drive a progress bar.
+/// - `cancel`: Cancel it (e.g. from a "Stop" button) to end the run after the current step.
+///
+/// Returns:
+/// - The entries for `path`, sorted by path.
+///
+/// Errors:
+/// - When `path` doesn't exist, the config can't be loaded, or the cache can't be written.
+pub async fn generate_with(
+    path: &Path,
+    observer: &dyn ProgressObserver,
+    cancel: CancellationToken,
+) -> anyhow::Result<Vec<FileEntry>> {
+    let config_dir = std::env::var_os("DIRDOCS_CONFIG_DIR").map(PathBuf::from);
+    let paths = ConfigPaths::resolve(config_dir.as_deref())?;
+    cli::generate_path(&paths, path, observer, cancel).await
+}
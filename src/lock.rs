@@ -0,0 +1,95 @@
+use fs2::FileExt;
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Advisory lock file at the root of a documented directory.
+///
+/// Writers create it and hold it exclusively while replacing the cache; viewers hold it
+/// shared while reading, but only when it already exists, so a read never leaves one
+/// behind. `dirdocs gc` removes it once it is older than `--stale-lock-minutes` and
+/// nobody holds it.
+pub(crate) const LOCK_FILE: &str = ".dirdocs.lock";
+
+/// How long to wait for the lock before going ahead without it.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often to retry a contended lock.
+const LOCK_POLL: Duration = Duration::from_millis(25);
+
+/// A held advisory lock; released when dropped.
+pub(crate) struct CacheLock {
+    /// The open lock file.
+    file: File,
+}
+
+impl Drop for CacheLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+/// Take the cache lock of `root`, shared (readers) or exclusive (writers).
+///
+/// Parameters:
+/// - `root`: The documented root directory.
+/// - `exclusive`: `true` for writers, `false` for readers.
+///
+/// Returns:
+/// - `None` when the lock file can't be created (e.g. a read-only checkout), doesn't exist
+///   yet for a reader (no writer has run there), or the lock stays contended for
+///   [`LOCK_TIMEOUT`]. The lock is advisory, so callers proceed anyway.
+pub(crate) fn lock_cache(root: &Path, exclusive: bool) -> Option<CacheLock> {
+    let path = root.join(LOCK_FILE);
+    let file = if exclusive {
+        OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+    } else {
+        File::open(path)
+    }
+    .ok()?;
+    let deadline = Instant::now() + LOCK_TIMEOUT;
+    loop {
+        let locked = if exclusive {
+            file.try_lock_exclusive()
+        } else {
+            FileExt::try_lock_shared(&file)
+        };
+        if locked.is_ok() {
+            return Some(CacheLock { file });
+        }
+        if Instant::now() >= deadline {
+            return None;
+        }
+        thread::sleep(LOCK_POLL);
+    }
+}
+
+/// Whether another process (or another handle in this one) currently holds a lock on `path`.
+///
+/// Parameters:
+/// - `path`: A lock file, such as [`LOCK_FILE`] or a `*.lock` in the sidecar directory.
+///
+/// Returns:
+/// - `true` when an exclusive lock can't be taken right now; `false` when it can, or when
+///   the file can't be opened.
+///
+/// Notes:
+/// - The lock file's mtime isn't refreshed while it's held, so age alone can't tell an
+///   abandoned lock from one a long run still holds.
+pub(crate) fn is_lock_held(path: &Path) -> bool {
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+    match file.try_lock_exclusive() {
+        Ok(()) => {
+            let _ = FileExt::unlock(&file);
+            false
+        }
+        Err(_) => true,
+    }
+}
@@ -0,0 +1,197 @@
+use crate::http::percent_decode;
+use crate::serve::{PARSE_ERROR, Request, RpcError};
+use crate::types::FileEntry;
+use serde_json::{Value, json};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, Stdin, Stdout};
+
+/// LSP `SymbolKind.File`.
+const SYMBOL_KIND_FILE: u32 = 1;
+
+/// Most symbols returned for one `workspace/symbol` query; editors filter further as you type.
+pub(crate) const MAX_WORKSPACE_SYMBOLS: usize = 200;
+
+/// Characters that may appear in a path under the cursor (quotes and brackets end it).
+fn is_path_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '/' | '\\' | '~' | '@' | '+')
+}
+
+/// LSP base protocol over stdin/stdout: each message is preceded by a `Content-Length` header.
+pub(crate) struct LspTransport {
+    /// Incoming framed messages.
+    input: BufReader<Stdin>,
+    /// Where responses are written.
+    out: Stdout,
+}
+
+impl LspTransport {
+    /// Attach to the process's stdin and stdout.
+    pub(crate) fn new() -> Self {
+        Self {
+            input: BufReader::new(tokio::io::stdin()),
+            out: tokio::io::stdout(),
+        }
+    }
+
+    /// Read the next message.
+    ///
+    /// Returns:
+    /// - `None` once stdin is closed or the framing is broken beyond recovery.
+    /// - `Some(Err(_))` for a body that is not a valid request; it should be answered with a null id.
+    pub(crate) async fn next_request(&mut self) -> Option<Result<Request, RpcError>> {
+        let mut content_length = None;
+        loop {
+            let mut header = String::new();
+            match self.input.read_line(&mut header).await {
+                Ok(0) | Err(_) => return None,
+                Ok(_) => {}
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                if content_length.is_some() {
+                    break;
+                }
+                continue;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse::<usize>().ok();
+                }
+            }
+        }
+
+        let mut body = vec![0u8; content_length?];
+        self.input.read_exact(&mut body).await.ok()?;
+        Some(serde_json::from_slice(&body).map_err(|e| RpcError::new(PARSE_ERROR, e.to_string())))
+    }
+
+    /// Write the response for request `id`.
+    ///
+    /// Errors:
+    /// - I/O errors when writing to stdout.
+    pub(crate) async fn respond(
+        &mut self,
+        id: Value,
+        result: Result<Value, RpcError>,
+    ) -> anyhow::Result<()> {
+        let msg = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": e.code, "message": e.message },
+            }),
+        };
+        let body = msg.to_string();
+        let frame = format!("Content-Length: {}\r\n\r\n{body}", body.len());
+        self.out.write_all(frame.as_bytes()).await?;
+        self.out.flush().await?;
+        Ok(())
+    }
+}
+
+/// Result of the `initialize` request: full-text sync, hover and workspace symbols.
+pub(crate) fn initialize_result() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": 1,
+            "hoverProvider": true,
+            "workspaceSymbolProvider": true,
+        },
+        "serverInfo": {
+            "name": "dirdocs",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+    })
+}
+
+/// Convert a `file://` URI to a filesystem path; `None` for other schemes.
+pub(crate) fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    // `+` is literal in URI paths, unlike in query strings.
+    let decoded = percent_decode(&rest.replace('+', "%2B"));
+    // `file:///C:/x` on Windows.
+    let decoded = match decoded.as_bytes() {
+        [b'/', _, b':', ..] => decoded[1..].to_string(),
+        _ => decoded,
+    };
+    Some(PathBuf::from(decoded))
+}
+
+/// Convert an absolute path to a `file://` URI, escaping characters URIs don't allow.
+pub(crate) fn path_to_uri(path: &Path) -> String {
+    let s = path.to_string_lossy().replace('\\', "/");
+    let prefix = if s.starts_with('/') {
+        "file://"
+    } else {
+        "file:///"
+    };
+    let mut out = String::from(prefix);
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{b:02X}")),
+        }
+    }
+    out
+}
+
+/// The path-like token around `character` (a UTF-16 offset, per LSP) on `line`, if any.
+///
+/// Notes:
+/// - Leading `./` is kept so callers can resolve relative to the document; trailing
+///   punctuation such as a sentence-ending `.` is trimmed.
+pub(crate) fn path_token_at(line: &str, character: usize) -> Option<String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut utf16 = 0;
+    let mut idx = chars.len();
+    for (i, c) in chars.iter().enumerate() {
+        if utf16 >= character {
+            idx = i;
+            break;
+        }
+        utf16 += c.len_utf16();
+    }
+    if idx >= chars.len() || !is_path_char(chars[idx]) {
+        return None;
+    }
+
+    let start = chars[..idx]
+        .iter()
+        .rposition(|c| !is_path_char(*c))
+        .map_or(0, |p| p + 1);
+    let end = chars[idx..]
+        .iter()
+        .position(|c| !is_path_char(*c))
+        .map_or(chars.len(), |p| idx + p);
+    let token: String = chars[start..end].iter().collect();
+    let token = token.trim_end_matches(['.', ',']);
+    (!token.is_empty()).then(|| token.to_string())
+}
+
+/// A hover showing a file's cached description.
+pub(crate) fn hover_result(entry: &FileEntry) -> Value {
+    let mut text = format!("**{}**", entry.path);
+    if !entry.doc.personalityEmoji.is_empty() {
+        text = format!("{} {text}", entry.doc.personalityEmoji);
+    }
+    text.push_str("\n\n");
+    text.push_str(&entry.doc.fileDescription);
+    json!({ "contents": { "kind": "markdown", "value": text } })
+}
+
+/// A `SymbolInformation` pointing at the top of a documented file.
+pub(crate) fn file_symbol(root: &Path, path: &str, description: &str) -> Value {
+    let line_start = json!({ "line": 0, "character": 0 });
+    json!({
+        "name": path,
+        "kind": SYMBOL_KIND_FILE,
+        "containerName": description,
+        "location": {
+            "uri": path_to_uri(&root.join(path)),
+            "range": { "start": line_start, "end": line_start },
+        },
+    })
+}
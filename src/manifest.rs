@@ -0,0 +1,130 @@
+use crate::chat::SamplingParams;
+use crate::prompt_llm::OutputMode;
+use crate::types::Doc;
+use awful_aj::config::AwfulJadeConfig;
+use awful_aj::template::ChatTemplate;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::Path;
+
+/// Everything needed to re-run a documentation pass and byte-compare its outputs.
+///
+/// Written by `dirdocs run --manifest <PATH>`. Teams that review generated docs can
+/// commit it next to `.dirdocs.nuon` and diff prompts and outputs between runs.
+#[derive(Debug, Serialize)]
+pub(crate) struct RunManifest {
+    /// The dirdocs version that produced the run.
+    pub dirdocs_version: &'static str,
+    /// When the run finished.
+    pub created_at: DateTime<Utc>,
+    /// The backend the prompts were sent to.
+    pub api_base: String,
+    /// The model name from `config.yaml`.
+    pub model: String,
+    /// Request parameters that influence the output.
+    pub sampling: Sampling,
+    /// blake3 of the resolved default template (after `extends:`).
+    pub template_hash: String,
+    /// One record per file, sorted by path.
+    pub files: Vec<FileRecord>,
+}
+
+/// Request parameters recorded in the manifest.
+#[derive(Debug, Serialize)]
+pub(crate) struct Sampling {
+    /// `context_max_tokens` from `config.yaml`.
+    pub context_max_tokens: u16,
+    /// `assistant_minimum_context_tokens` from `config.yaml`.
+    pub assistant_minimum_context_tokens: i32,
+    /// `stop_words` from `config.yaml`.
+    pub stop_words: Vec<String>,
+    /// Temperature, `top_p` and seed for the default template (flags over template keys).
+    #[serde(flatten)]
+    pub params: SamplingParams,
+}
+
+/// How a file's description was obtained during the run.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RecordSource {
+    /// The model was prompted.
+    Generated,
+    /// The description came from the local or global cache; no prompt was sent.
+    Cache,
+}
+
+/// Per-file reproducibility record.
+#[derive(Debug, Serialize)]
+pub(crate) struct FileRecord {
+    /// Path relative to the run root.
+    pub path: String,
+    /// Content hash of the file.
+    pub file_hash: String,
+    /// Where the description came from.
+    pub source: RecordSource,
+    /// blake3 of the raw template used for this file (variants may differ from the default).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template_hash: Option<String>,
+    /// blake3 of the rendered prompt, when one was sent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_hash: Option<String>,
+    /// blake3 of the stored doc (as JSON), when a description was produced.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_hash: Option<String>,
+    /// Whether the answer was requested with a `response_format` schema or as plain JSON.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_mode: Option<OutputMode>,
+}
+
+impl RunManifest {
+    /// Start a manifest for a run using `cfg`, the resolved default template and its sampling parameters.
+    pub(crate) fn new(
+        cfg: &AwfulJadeConfig,
+        default_template: &str,
+        params: SamplingParams,
+    ) -> Self {
+        Self {
+            dirdocs_version: env!("CARGO_PKG_VERSION"),
+            created_at: Utc::now(),
+            api_base: cfg.api_base.clone(),
+            model: cfg.model.clone(),
+            sampling: Sampling {
+                context_max_tokens: cfg.context_max_tokens,
+                assistant_minimum_context_tokens: cfg.assistant_minimum_context_tokens,
+                stop_words: cfg.stop_words.clone(),
+                params,
+            },
+            template_hash: hash_str(default_template),
+            files: Vec::new(),
+        }
+    }
+
+    /// Sort the records and write the manifest as pretty JSON to `path`.
+    ///
+    /// Errors:
+    /// - Serialization or I/O errors.
+    pub(crate) fn write(mut self, path: &Path) -> anyhow::Result<()> {
+        self.created_at = Utc::now();
+        self.files.sort_by(|a, b| a.path.cmp(&b.path));
+        std::fs::write(path, serde_json::to_string_pretty(&self)? + "\n")?;
+        Ok(())
+    }
+}
+
+/// blake3 hex digest of a string.
+pub(crate) fn hash_str(s: &str) -> String {
+    blake3::hash(s.as_bytes()).to_hex().to_string()
+}
+
+/// blake3 of a rendered prompt, hashed over its JSON form.
+pub(crate) fn prompt_hash(tpl: &ChatTemplate) -> Option<String> {
+    serde_json::to_string(tpl).ok().map(|s| hash_str(&s))
+}
+
+/// blake3 of a doc, hashed over its JSON form; `None` for an empty doc.
+pub(crate) fn output_hash(doc: &Doc) -> Option<String> {
+    if doc.fileDescription.is_empty() {
+        return None;
+    }
+    serde_json::to_string(doc).ok().map(|s| hash_str(&s))
+}
@@ -0,0 +1,106 @@
+use serde_json::{Value, json};
+
+/// MCP protocol revision this server implements.
+pub(crate) const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Resource with the whole documentation tree as JSON (the `.dirdocs.nuon` contents).
+pub(crate) const TREE_URI: &str = "dirdocs://tree";
+
+/// Resource with the tree rendered as a `FILES.md`-style Markdown document.
+pub(crate) const MARKDOWN_URI: &str = "dirdocs://files.md";
+
+/// Prefix of per-file resources: `dirdocs://file/<relative path>`.
+pub(crate) const FILE_URI_PREFIX: &str = "dirdocs://file/";
+
+/// Name of the tool that (re)generates docs for a path.
+pub(crate) const DESCRIBE_TOOL: &str = "describe_path";
+
+/// Result of the `initialize` request.
+pub(crate) fn initialize_result() -> Value {
+    json!({
+        "protocolVersion": PROTOCOL_VERSION,
+        "capabilities": {
+            "resources": {},
+            "tools": {},
+        },
+        "serverInfo": {
+            "name": "dirdocs",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+    })
+}
+
+/// Result of `resources/list`.
+pub(crate) fn resources_list() -> Value {
+    json!({
+        "resources": [
+            {
+                "uri": TREE_URI,
+                "name": "Documentation tree",
+                "description": "Every documented file with its one-sentence description, as JSON.",
+                "mimeType": "application/json",
+            },
+            {
+                "uri": MARKDOWN_URI,
+                "name": "FILES.md",
+                "description": "The documentation tree as nested Markdown lists.",
+                "mimeType": "text/markdown",
+            },
+        ]
+    })
+}
+
+/// Result of `resources/templates/list`.
+pub(crate) fn resource_templates_list() -> Value {
+    json!({
+        "resourceTemplates": [
+            {
+                "uriTemplate": format!("{FILE_URI_PREFIX}{{path}}"),
+                "name": "File description",
+                "description": "The cached entry (description, joy, emoji, hash) for one file, by path relative to the project root.",
+                "mimeType": "application/json",
+            },
+        ]
+    })
+}
+
+/// Result of `tools/list`.
+pub(crate) fn tools_list() -> Value {
+    json!({
+        "tools": [
+            {
+                "name": DESCRIBE_TOOL,
+                "description": "Regenerate dirdocs descriptions for a file, or for every file under a directory, and return the new entries.",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "description": "File or directory, absolute or relative to the project root.",
+                        },
+                    },
+                    "required": ["path"],
+                },
+            },
+        ]
+    })
+}
+
+/// A single text item for a `resources/read` response.
+pub(crate) fn resource_contents(uri: &str, mime_type: &str, text: String) -> Value {
+    json!({
+        "contents": [
+            { "uri": uri, "mimeType": mime_type, "text": text },
+        ]
+    })
+}
+
+/// A `tools/call` result carrying `text`; `is_error` marks tool-level failures.
+pub(crate) fn tool_result(text: String, is_error: bool) -> Value {
+    json!({
+        "content": [
+            { "type": "text", "text": text },
+        ],
+        "isError": is_error,
+    })
+}
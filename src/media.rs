@@ -0,0 +1,323 @@
+//! Metadata of image files (dimensions, color type, EXIF), read straight from their headers.
+
+use crate::content::read_text_lossy_limited;
+use regex::Regex;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Bytes of a binary image read when looking for its header and EXIF block.
+const HEADER_BYTES: u64 = 1024 * 1024;
+
+/// What an image's header says about it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ImageInfo {
+    /// Short format name, e.g. "PNG" or "SVG".
+    pub format: &'static str,
+    /// Width in pixels (for SVG, in user units), 0 when unknown.
+    pub width: u32,
+    /// Height in pixels (for SVG, in user units), 0 when unknown.
+    pub height: u32,
+    /// Color type, e.g. "RGBA 8-bit", "grayscale" or "indexed, 256 colors".
+    pub color: String,
+    /// Creation timestamp from EXIF or PNG text chunks, as written in the file.
+    pub created: String,
+    /// Camera make and model from EXIF, e.g. "Apple iPhone 13".
+    pub camera: String,
+}
+
+impl ImageInfo {
+    /// Dimensions as `W×H`, or an empty string when they're unknown.
+    pub(crate) fn dimensions(&self) -> String {
+        if self.width == 0 || self.height == 0 {
+            String::new()
+        } else {
+            format!("{}×{}", self.width, self.height)
+        }
+    }
+
+    /// One-line summary used in place of binary content, e.g. "PNG image, 512×512, RGBA 8-bit".
+    pub(crate) fn summary(&self) -> String {
+        let mut parts = vec![format!("{} image", self.format)];
+        for part in [self.dimensions(), self.color.clone()] {
+            if !part.is_empty() {
+                parts.push(part);
+            }
+        }
+        if !self.created.is_empty() {
+            parts.push(format!("created {}", self.created));
+        }
+        if !self.camera.is_empty() {
+            parts.push(format!("taken with {}", self.camera));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Read the metadata of a PNG, JPEG, GIF or SVG file.
+///
+/// Binary formats need an `image/*` MIME type and are then recognized by their magic bytes;
+/// SVG by its MIME type or extension.
+///
+/// Parameters:
+/// - `path`: The file to inspect.
+/// - `mimetype`: Its MIME type, as reported by `file_meta`.
+///
+/// Returns:
+/// - The parsed metadata, or `None` for other files and unreadable or truncated headers.
+pub(crate) fn image_info(path: &Path, mimetype: &str) -> Option<ImageInfo> {
+    let is_svg = mimetype.contains("svg")
+        || path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("svg"));
+    if is_svg {
+        return svg_info(&read_text_lossy_limited(path, 64 * 1024));
+    }
+    if !mimetype.starts_with("image/") {
+        return None;
+    }
+
+    let mut buf = Vec::new();
+    fs::File::open(path)
+        .and_then(|f| {
+            io::BufReader::new(f)
+                .take(HEADER_BYTES)
+                .read_to_end(&mut buf)
+        })
+        .ok()?;
+
+    if buf.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_info(&buf)
+    } else if buf.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        jpeg_info(&buf)
+    } else if buf.starts_with(b"GIF87a") || buf.starts_with(b"GIF89a") {
+        gif_info(&buf)
+    } else {
+        None
+    }
+}
+
+/// Parse the IHDR chunk of a PNG and any `Creation Time` text chunk.
+fn png_info(buf: &[u8]) -> Option<ImageInfo> {
+    // Signature (8) + IHDR length (4) + "IHDR" (4) + width, height, bit depth, color type
+    let ihdr = buf.get(16..26)?;
+    if buf.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let bit_depth = ihdr[8];
+    let color = match ihdr[9] {
+        0 => format!("grayscale {bit_depth}-bit"),
+        2 => format!("RGB {bit_depth}-bit"),
+        3 => format!("indexed {bit_depth}-bit"),
+        4 => format!("grayscale+alpha {bit_depth}-bit"),
+        6 => format!("RGBA {bit_depth}-bit"),
+        _ => String::new(),
+    };
+
+    let mut info = ImageInfo {
+        format: "PNG",
+        width: u32::from_be_bytes(ihdr[0..4].try_into().ok()?),
+        height: u32::from_be_bytes(ihdr[4..8].try_into().ok()?),
+        color,
+        ..ImageInfo::default()
+    };
+
+    let mut pos = 8;
+    while let Some(header) = buf.get(pos..pos + 8) {
+        let len = u32::from_be_bytes(header[0..4].try_into().ok()?) as usize;
+        let kind = &header[4..8];
+        let Some(data) = buf.get(pos + 8..pos + 8 + len) else {
+            break;
+        };
+        if kind == b"tEXt" {
+            if let Some(value) = data.strip_prefix(b"Creation Time\0") {
+                info.created = String::from_utf8_lossy(value).trim().to_string();
+            }
+        }
+        if kind == b"IDAT" || kind == b"IEND" {
+            break;
+        }
+        pos += 12 + len;
+    }
+    Some(info)
+}
+
+/// Walk the JPEG segments up to the image data, reading the frame header and EXIF block.
+fn jpeg_info(buf: &[u8]) -> Option<ImageInfo> {
+    let mut info = ImageInfo {
+        format: "JPEG",
+        ..ImageInfo::default()
+    };
+
+    let mut pos = 2;
+    while pos + 4 <= buf.len() {
+        if buf[pos] != 0xFF {
+            break;
+        }
+        let marker = buf[pos + 1];
+        // Fill bytes and standalone markers carry no length
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        if marker == 0x01 || (0xD0..=0xD9).contains(&marker) {
+            pos += 2;
+            continue;
+        }
+        let len = u16::from_be_bytes([buf[pos + 2], buf[pos + 3]]) as usize;
+        let Some(data) = buf.get(pos + 4..pos + 2 + len) else {
+            break;
+        };
+        match marker {
+            // Start of frame (baseline, progressive, lossless, ...); not DHT/JPG/DAC
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) && data.len() >= 6 => {
+                info.height = u16::from_be_bytes([data[1], data[2]]) as u32;
+                info.width = u16::from_be_bytes([data[3], data[4]]) as u32;
+                info.color = match data[5] {
+                    1 => "grayscale".to_string(),
+                    3 => "YCbCr color".to_string(),
+                    4 => "CMYK color".to_string(),
+                    n => format!("{n} components"),
+                };
+            }
+            0xE1 => {
+                if let Some(tiff) = data.strip_prefix(b"Exif\0\0") {
+                    read_exif(tiff, &mut info);
+                }
+            }
+            // Start of scan: the compressed image data follows
+            0xDA => break,
+            _ => {}
+        }
+        pos += 2 + len;
+    }
+
+    (info.width > 0).then_some(info)
+}
+
+/// Copy the capture date and camera out of a TIFF-structured EXIF block.
+fn read_exif(tiff: &[u8], info: &mut ImageInfo) {
+    let little = match tiff.get(0..2) {
+        Some(b"II") => true,
+        Some(b"MM") => false,
+        _ => return,
+    };
+    let u16_at = |at: usize| -> Option<u16> {
+        let b: [u8; 2] = tiff.get(at..at + 2)?.try_into().ok()?;
+        Some(if little {
+            u16::from_le_bytes(b)
+        } else {
+            u16::from_be_bytes(b)
+        })
+    };
+    let u32_at = |at: usize| -> Option<u32> {
+        let b: [u8; 4] = tiff.get(at..at + 4)?.try_into().ok()?;
+        Some(if little {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        })
+    };
+    // ASCII values of up to four bytes are stored inline, longer ones at an offset
+    let ascii_at = |entry: usize| -> Option<String> {
+        let count = u32_at(entry + 4)? as usize;
+        let start = if count <= 4 {
+            entry + 8
+        } else {
+            u32_at(entry + 8)? as usize
+        };
+        let raw = tiff.get(start..start + count)?;
+        let text = String::from_utf8_lossy(raw);
+        Some(text.trim_end_matches('\0').trim().to_string())
+    };
+    let entries = |ifd: usize| -> Vec<(u16, usize)> {
+        let n = u16_at(ifd).unwrap_or(0) as usize;
+        (0..n)
+            .map(|i| ifd + 2 + i * 12)
+            .filter_map(|entry| Some((u16_at(entry)?, entry)))
+            .collect()
+    };
+
+    let Some(ifd0) = u32_at(4) else {
+        return;
+    };
+    let (mut make, mut model, mut date, mut original) = (None, None, None, None);
+    for (tag, entry) in entries(ifd0 as usize) {
+        match tag {
+            0x010F => make = ascii_at(entry),
+            0x0110 => model = ascii_at(entry),
+            0x0132 => date = ascii_at(entry),
+            0x8769 => {
+                let Some(sub) = u32_at(entry + 8) else {
+                    continue;
+                };
+                for (tag, entry) in entries(sub as usize) {
+                    if tag == 0x9003 {
+                        original = ascii_at(entry);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    info.created = original.or(date).unwrap_or_default();
+    let make = make.unwrap_or_default();
+    let model = model.unwrap_or_default();
+    // Models often repeat the make ("Canon" / "Canon EOS R5")
+    info.camera = if model.starts_with(&make) {
+        model
+    } else {
+        format!("{make} {model}").trim().to_string()
+    };
+}
+
+/// Parse a GIF's logical screen descriptor.
+fn gif_info(buf: &[u8]) -> Option<ImageInfo> {
+    let screen = buf.get(6..11)?;
+    let packed = screen[4];
+    let mut color = if packed & 0x80 != 0 {
+        format!("indexed, {} colors", 1u32 << ((packed & 0x07) + 1))
+    } else {
+        "indexed".to_string()
+    };
+    if buf.windows(11).any(|w| w == b"NETSCAPE2.0") {
+        color.push_str(", animated");
+    }
+    Some(ImageInfo {
+        format: "GIF",
+        width: u16::from_le_bytes([screen[0], screen[1]]) as u32,
+        height: u16::from_le_bytes([screen[2], screen[3]]) as u32,
+        color,
+        ..ImageInfo::default()
+    })
+}
+
+/// Read the size of an SVG from the `width`/`height` or `viewBox` of its root element.
+fn svg_info(text: &str) -> Option<ImageInfo> {
+    let root = Regex::new(r"(?s)<svg\b[^>]*>").ok()?.find(text)?.as_str();
+    let attr = |name: &str| -> Option<f64> {
+        let re = Regex::new(&format!(r#"\s{name}\s*=\s*["']\s*([0-9.]+)"#)).ok()?;
+        re.captures(root)?[1].parse().ok()
+    };
+    let view_box = Regex::new(r#"\sviewBox\s*=\s*["']([^"']*)["']"#)
+        .ok()?
+        .captures(root)
+        .map(|c| {
+            c[1].split([' ', ','])
+                .filter_map(|v| v.parse::<f64>().ok())
+                .collect::<Vec<_>>()
+        })
+        .filter(|v| v.len() == 4);
+
+    let width = attr("width").or_else(|| view_box.as_ref().map(|v| v[2]));
+    let height = attr("height").or_else(|| view_box.as_ref().map(|v| v[3]));
+    Some(ImageInfo {
+        format: "SVG",
+        width: width.unwrap_or(0.0).round() as u32,
+        height: height.unwrap_or(0.0).round() as u32,
+        color: "vector".to_string(),
+        ..ImageInfo::default()
+    })
+}
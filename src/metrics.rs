@@ -0,0 +1,204 @@
+use crate::content::hash_file;
+use crate::gc::SIDECAR_DIR;
+use crate::types::{DirdocsRoot, Node};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// File (inside the `.dirdocs/` sidecar) recording the most recent run's statistics.
+pub(crate) const LAST_RUN_FILE: &str = "last_run.json";
+
+/// Upper bounds, in seconds, of the backend latency histogram buckets.
+pub(crate) const LATENCY_BUCKETS_SECS: &[f64] = &[0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+
+/// A Prometheus-style histogram with fixed [`LATENCY_BUCKETS_SECS`] bounds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Histogram {
+    /// Observations per bucket (not cumulative); index matches [`LATENCY_BUCKETS_SECS`].
+    pub buckets: Vec<u64>,
+    /// Sum of all observations, in seconds.
+    pub sum: f64,
+    /// Number of observations, including those above the largest bound.
+    pub count: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: vec![0; LATENCY_BUCKETS_SECS.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+}
+
+impl Histogram {
+    /// Record one observation.
+    pub(crate) fn observe(&mut self, d: Duration) {
+        let secs = d.as_secs_f64();
+        if let Some(i) = LATENCY_BUCKETS_SECS.iter().position(|b| secs <= *b) {
+            self.buckets[i] += 1;
+        }
+        self.sum += secs;
+        self.count += 1;
+    }
+}
+
+/// Statistics of the most recent `dirdocs run`, persisted for the metrics endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct RunStats {
+    /// When the run finished.
+    pub finished_at: DateTime<Utc>,
+    /// Wall-clock duration of the run, in milliseconds.
+    pub duration_ms: u64,
+    /// Files visited by the walk.
+    pub walked: usize,
+    /// Files (re)described rather than reused.
+    pub regenerated: usize,
+    /// Latency of each model request made during the run.
+    pub backend_latency: Histogram,
+}
+
+impl RunStats {
+    /// Path of the stats file for `root`.
+    pub(crate) fn path(root: &Path) -> PathBuf {
+        root.join(SIDECAR_DIR).join(LAST_RUN_FILE)
+    }
+
+    /// Write the stats for `root`, creating the sidecar directory if needed.
+    ///
+    /// Errors:
+    /// - Serialization or I/O errors.
+    pub(crate) fn write(&self, root: &Path) -> anyhow::Result<()> {
+        let path = Self::path(root);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+
+    /// Read the stats for `root`; `None` when no run has recorded any yet.
+    pub(crate) fn load(root: &Path) -> Option<Self> {
+        let s = fs::read_to_string(Self::path(root)).ok()?;
+        serde_json::from_str(&s).ok()
+    }
+}
+
+/// Render documentation freshness for `root` in the Prometheus text exposition format.
+///
+/// Parameters:
+/// - `tree`: The current documentation tree.
+/// - `root`: The documented directory; files are re-hashed to count stale entries.
+/// - `last_run`: Statistics of the last run, when available.
+///
+/// Returns:
+/// - The metrics page body.
+///
+/// Notes:
+/// - Only files modified after their entry's `updated_at` are re-hashed, so a scrape of an
+///   unchanged tree costs one `stat` per file. This still blocks; call it off the async runtime.
+pub(crate) fn render(tree: &DirdocsRoot, root: &Path, last_run: Option<&RunStats>) -> String {
+    /// Count documented, stale and failed files.
+    fn visit(nodes: &[Node], root: &Path, counts: &mut (u64, u64, u64)) {
+        for n in nodes {
+            match n {
+                Node::Dir(d) => visit(&d.entries, root, counts),
+                Node::File(f) => {
+                    if f.doc.fileDescription.is_empty() {
+                        counts.2 += 1;
+                    } else {
+                        counts.0 += 1;
+                    }
+                    let full = root.join(&f.path);
+                    let fresh = match fs::metadata(&full).and_then(|m| m.modified()) {
+                        Ok(mtime) if DateTime::<Utc>::from(mtime) <= f.updated_at => true,
+                        Ok(_) => hash_file(&full).is_ok_and(|h| h == f.hash),
+                        Err(_) => false,
+                    };
+                    if !fresh {
+                        counts.1 += 1;
+                    }
+                }
+            }
+        }
+    }
+    let mut counts = (0, 0, 0);
+    visit(&tree.entries, root, &mut counts);
+    let (documented, stale, failed) = counts;
+
+    let mut out = String::new();
+    gauge(
+        &mut out,
+        "dirdocs_files_documented",
+        "Files with a cached description.",
+        documented as f64,
+    );
+    gauge(
+        &mut out,
+        "dirdocs_files_stale",
+        "Cached files whose content changed or that no longer exist.",
+        stale as f64,
+    );
+    gauge(
+        &mut out,
+        "dirdocs_files_failed",
+        "Cached files without a description (backend failures, context overflows).",
+        failed as f64,
+    );
+    gauge(
+        &mut out,
+        "dirdocs_tree_updated_timestamp_seconds",
+        "When the documentation tree was last written.",
+        tree.updated_at.timestamp() as f64,
+    );
+
+    let Some(run) = last_run else {
+        return out;
+    };
+    gauge(
+        &mut out,
+        "dirdocs_last_run_timestamp_seconds",
+        "When the last dirdocs run finished.",
+        run.finished_at.timestamp() as f64,
+    );
+    gauge(
+        &mut out,
+        "dirdocs_last_run_duration_seconds",
+        "Wall-clock duration of the last dirdocs run.",
+        run.duration_ms as f64 / 1000.0,
+    );
+    gauge(
+        &mut out,
+        "dirdocs_last_run_regenerated_files",
+        "Files described (rather than reused) by the last run.",
+        run.regenerated as f64,
+    );
+
+    let name = "dirdocs_backend_request_duration_seconds";
+    let _ = writeln!(
+        out,
+        "# HELP {name} Latency of model requests during the last run."
+    );
+    let _ = writeln!(out, "# TYPE {name} histogram");
+    let h = &run.backend_latency;
+    let mut cumulative = 0;
+    for (bound, n) in LATENCY_BUCKETS_SECS.iter().zip(&h.buckets) {
+        cumulative += n;
+        let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {cumulative}");
+    }
+    let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {}", h.count);
+    let _ = writeln!(out, "{name}_sum {}", h.sum);
+    let _ = writeln!(out, "{name}_count {}", h.count);
+    out
+}
+
+/// Append one gauge with its `HELP` and `TYPE` lines.
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} gauge");
+    let _ = writeln!(out, "{name} {value}");
+}
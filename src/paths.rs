@@ -0,0 +1,98 @@
+use crate::gc::SIDECAR_DIR;
+use crate::priority::PRIORITY_FILE;
+use crate::splitters::SPLITTERS_FILE;
+use crate::template::{DEFAULT_TEMPLATE_FILE, PARTIALS_DIR};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Name of the documentation cache written at the root of a documented directory.
+pub(crate) const CACHE_FILE: &str = ".dirdocs.nuon";
+
+/// Name of the SQLite cache written by `--backend sqlite`.
+pub(crate) const DB_FILE: &str = ".dirdocs.db";
+
+/// Where dirdocs reads its configuration and templates from.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ConfigPaths {
+    /// The Awful Jade config directory.
+    pub config_dir: PathBuf,
+    /// `config.yaml` inside [`Self::config_dir`].
+    pub config_file: PathBuf,
+    /// The templates directory.
+    pub templates_dir: PathBuf,
+    /// The default `dir_docs.yaml` template.
+    pub template_file: PathBuf,
+    /// Directory whose files are registered as Handlebars partials.
+    pub partials_dir: PathBuf,
+    /// `priority.yaml`: weighting rules for the order files are described in.
+    pub priority_file: PathBuf,
+    /// `splitters.yaml`: per-glob overrides of how files are chunked.
+    pub splitters_file: PathBuf,
+}
+
+impl ConfigPaths {
+    /// Resolve the config paths, preferring an explicit override over the Awful Jade config directory.
+    ///
+    /// Parameters:
+    /// - `override_dir`: From `--config-dir` / `DIRDOCS_CONFIG_DIR`; isolates config,
+    ///   templates and partials per invocation (sandboxes, tests, multi-tenant servers).
+    ///
+    /// Errors:
+    /// - When no override is given and the platform config directory cannot be determined.
+    pub(crate) fn resolve(override_dir: Option<&Path>) -> anyhow::Result<Self> {
+        let config_dir = match override_dir {
+            Some(dir) => dir.to_path_buf(),
+            None => {
+                awful_aj::config_dir().map_err(|e| anyhow::anyhow!("config_dir() failed: {e}"))?
+            }
+        };
+        Ok(Self::under(config_dir))
+    }
+
+    /// Lay out the config paths under an explicit config directory.
+    pub(crate) fn under(config_dir: PathBuf) -> Self {
+        let templates_dir = config_dir.join("templates");
+        Self {
+            config_file: config_dir.join("config.yaml"),
+            priority_file: config_dir.join(PRIORITY_FILE),
+            splitters_file: config_dir.join(SPLITTERS_FILE),
+            template_file: templates_dir.join(DEFAULT_TEMPLATE_FILE),
+            partials_dir: templates_dir.join(PARTIALS_DIR),
+            templates_dir,
+            config_dir,
+        }
+    }
+}
+
+/// Where dirdocs keeps its cache for a documented directory.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct CachePaths {
+    /// The documented root directory.
+    pub root: PathBuf,
+    /// `.dirdocs.nuon` at the root.
+    pub cache_file: PathBuf,
+    /// Whether [`Self::cache_file`] exists.
+    pub cache_exists: bool,
+    /// `.dirdocs.db` at the root (SQLite backend).
+    pub db_file: PathBuf,
+    /// Whether [`Self::db_file`] exists.
+    pub db_exists: bool,
+    /// The `.dirdocs/` sidecar directory.
+    pub sidecar_dir: PathBuf,
+}
+
+impl CachePaths {
+    /// Lay out the cache paths for `root`.
+    pub(crate) fn for_root(root: &Path) -> Self {
+        let cache_file = root.join(CACHE_FILE);
+        let db_file = root.join(DB_FILE);
+        Self {
+            root: root.to_path_buf(),
+            cache_exists: cache_file.exists(),
+            cache_file,
+            db_exists: db_file.exists(),
+            db_file,
+            sidecar_dir: root.join(SIDECAR_DIR),
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tracing::{debug, warn};
+
+/// File name of the prioritization rules inside the config directory.
+pub(crate) const PRIORITY_FILE: &str = "priority.yaml";
+
+/// Most commits inspected when measuring git churn, so huge histories stay fast.
+const MAX_CHURN_COMMITS: usize = 500;
+
+/// Written by `dirdocs init`; documents the format and mirrors [`PriorityRules::default`].
+pub(crate) const DEFAULT_PRIORITY_YAML: &str = r#"# Files with a higher score are described first, which matters when a run is
+# limited with `--max-generate` or interrupted. A file's score is the sum of the
+# weights of every rule whose glob matches its path (relative to the run root),
+# plus `churn_weight` for each commit that touched it in the last `churn_days`.
+rules:
+  # Entry points
+  - glob: "**/{main,lib,mod,index,app,__init__,__main__,server,cli}.*"
+    weight: 10
+  # Public APIs and interface definitions
+  - glob: "**/{api,include,public,proto}/**"
+    weight: 5
+  - glob: "**/*.{proto,graphql,d.ts,h,hpp}"
+    weight: 5
+  # Build manifests
+  - glob: "**/{Cargo.toml,package.json,pyproject.toml,go.mod,Makefile,Dockerfile}"
+    weight: 3
+  # Tests, fixtures and generated code matter least
+  - glob: "**/{test,tests,__tests__,spec,fixtures,testdata}/**"
+    weight: -5
+  - glob: "**/*.{lock,min.js,map,snap}"
+    weight: -5
+churn_weight: 1.0
+churn_days: 90
+"#;
+
+/// One weighting rule from `priority.yaml`.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Rule {
+    /// Glob matched against the path relative to the run root.
+    pub glob: String,
+    /// Added to the score of every matching file (may be negative).
+    pub weight: f64,
+}
+
+/// User-tunable weighting rules for ordering files within a run.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct PriorityRules {
+    /// Path rules; every matching rule contributes its weight.
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    /// Score added per recent commit that touched a file; `0` disables git churn.
+    #[serde(default = "default_churn_weight")]
+    pub churn_weight: f64,
+    /// How far back commits count as "recent".
+    #[serde(default = "default_churn_days")]
+    pub churn_days: u32,
+}
+
+/// Default for [`PriorityRules::churn_weight`].
+fn default_churn_weight() -> f64 {
+    1.0
+}
+
+/// Default for [`PriorityRules::churn_days`].
+fn default_churn_days() -> u32 {
+    90
+}
+
+impl Default for PriorityRules {
+    fn default() -> Self {
+        serde_yaml::from_str(DEFAULT_PRIORITY_YAML).expect("built-in priority rules are valid")
+    }
+}
+
+impl PriorityRules {
+    /// Load rules from `path`, falling back to the built-in defaults when it doesn't exist.
+    ///
+    /// Errors:
+    /// - I/O errors other than a missing file, or invalid YAML.
+    pub(crate) fn load(path: &Path) -> anyhow::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(s) => serde_yaml::from_str(&s)
+                .map_err(|e| anyhow::anyhow!("invalid priority rules {:?}: {e}", path)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(anyhow::anyhow!("failed to read {:?}: {e}", path)),
+        }
+    }
+}
+
+/// Scores files so the most valuable ones are described first.
+pub(crate) struct Planner {
+    /// Compiled rule globs, in the same order as `weights`.
+    globs: GlobSet,
+    /// Weight of each glob.
+    weights: Vec<f64>,
+    /// Score per recent commit.
+    churn_weight: f64,
+    /// Recent commit count per root-relative path.
+    churn: HashMap<String, usize>,
+}
+
+impl Planner {
+    /// Compile `rules` and measure git churn under `root`.
+    ///
+    /// Parameters:
+    /// - `rules`: The loaded weighting rules.
+    /// - `root`: The run root; churn is skipped when it isn't inside a git repository.
+    ///
+    /// Errors:
+    /// - When a rule's glob is invalid.
+    pub(crate) fn new(rules: &PriorityRules, root: &Path) -> anyhow::Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for rule in &rules.rules {
+            let glob = Glob::new(&rule.glob)
+                .map_err(|e| anyhow::anyhow!("invalid priority glob {:?}: {e}", rule.glob))?;
+            builder.add(glob);
+        }
+        let churn = if rules.churn_weight != 0.0 {
+            git_churn(root, rules.churn_days).unwrap_or_else(|e| {
+                debug!(%e, "No git churn available");
+                HashMap::new()
+            })
+        } else {
+            HashMap::new()
+        };
+        debug!(
+            rules = rules.rules.len(),
+            churned_files = churn.len(),
+            "Priority planner ready"
+        );
+        Ok(Self {
+            globs: builder.build()?,
+            weights: rules.rules.iter().map(|r| r.weight).collect(),
+            churn_weight: rules.churn_weight,
+            churn,
+        })
+    }
+
+    /// The score of a root-relative path; higher is described earlier.
+    pub(crate) fn score(&self, rel: &str) -> f64 {
+        let rules: f64 = self
+            .globs
+            .matches(rel)
+            .into_iter()
+            .map(|i| self.weights[i])
+            .sum();
+        let churn = self.churn.get(rel).copied().unwrap_or(0) as f64;
+        rules + self.churn_weight * churn
+    }
+}
+
+/// Count, per root-relative path, the commits reachable from `HEAD` in the last `days`
+/// days that changed it (at most [`MAX_CHURN_COMMITS`] commits are inspected).
+///
+/// Errors:
+/// - When `root` isn't inside a git work tree or the history can't be read.
+fn git_churn(root: &Path, days: u32) -> anyhow::Result<HashMap<String, usize>> {
+    let repo = git2::Repository::discover(root)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow::anyhow!("bare repository"))?
+        .canonicalize()?;
+    let prefix = pathdiff::diff_paths(root, &workdir).unwrap_or_default();
+    let since = chrono::Utc::now().timestamp() - i64::from(days) * 86_400;
+
+    let mut walk = repo.revwalk()?;
+    walk.push_head()?;
+    walk.set_sorting(git2::Sort::TIME)?;
+
+    let mut churn = HashMap::new();
+    for oid in walk.take(MAX_CHURN_COMMITS) {
+        let commit = repo.find_commit(oid?)?;
+        if commit.time().seconds() < since {
+            break;
+        }
+        let tree = commit.tree()?;
+        let parent_tree = match commit.parent(0) {
+            Ok(p) => Some(p.tree()?),
+            Err(_) => None,
+        };
+        let diff = match repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None) {
+            Ok(d) => d,
+            Err(e) => {
+                warn!(%e, commit = %commit.id(), "Skipping commit in churn scan");
+                continue;
+            }
+        };
+        for delta in diff.deltas() {
+            let Some(path) = delta.new_file().path() else {
+                continue;
+            };
+            if let Ok(rel) = path.strip_prefix(&prefix) {
+                *churn
+                    .entry(rel.to_string_lossy().to_string())
+                    .or_insert(0usize) += 1;
+            }
+        }
+    }
+    Ok(churn)
+}
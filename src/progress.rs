@@ -0,0 +1,231 @@
+use chrono::Utc;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// How a single file was handled during a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileOutcome {
+    /// The cached description was still valid and reused as-is.
+    Reused,
+    /// A fresh description was generated by the model.
+    Generated,
+    /// The model was asked but no usable description came back.
+    Failed,
+    /// The file could not be read or hashed and was left out of the tree.
+    Skipped,
+    /// The run was cancelled while the model was describing the file; its cached entry,
+    /// if any, is kept.
+    Cancelled,
+}
+
+impl FileOutcome {
+    /// Lower-case name of the outcome, as used in NDJSON events.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FileOutcome::Reused => "reused",
+            FileOutcome::Generated => "generated",
+            FileOutcome::Failed => "failed",
+            FileOutcome::Skipped => "skipped",
+            FileOutcome::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// A file whose prompt did not fit the backend's context window, even after shrinking.
+#[derive(Debug, Clone)]
+pub struct ContextOverflow {
+    /// Path of the file relative to the run root.
+    pub path: String,
+    /// Estimated prompt tokens at the smallest chunk size tried.
+    pub prompt_tokens: usize,
+    /// Tokens available for the prompt.
+    pub limit_tokens: usize,
+}
+
+/// Summary of a finished run.
+#[derive(Debug, Clone, Default)]
+pub struct RunReport {
+    /// Number of files visited by the walk.
+    pub walked: usize,
+    /// Number of files that were (re)described rather than reused from the local cache.
+    pub regenerated: usize,
+    /// Files recorded with a `context_overflow` error.
+    pub context_overflows: Vec<ContextOverflow>,
+    /// Files left for a later run because `--max-generate` was reached.
+    pub deferred: usize,
+    /// Files described through the plain-text fallback because the endpoint rejected `response_format`.
+    pub plain_json_files: usize,
+}
+
+/// Receives progress events from a documentation run.
+///
+/// Embedders (GUI wrappers, editor plugins) implement this to drive their own progress
+/// UI instead of scraping log output. Every method has a no-op default, so implementors
+/// only override the events they care about.
+pub trait ProgressObserver: Send + Sync {
+    /// Called before a file is hashed and (possibly) described.
+    ///
+    /// - `rel_path`: Path of the file relative to the run root.
+    fn on_file_start(&self, _rel_path: &str) {}
+
+    /// Called once a file has been handled.
+    ///
+    /// - `rel_path`: Path of the file relative to the run root.
+    /// - `outcome`: Whether the doc was reused, generated, failed, the file skipped, or the
+    ///   run cancelled while it was in flight.
+    fn on_file_done(&self, _rel_path: &str, _outcome: FileOutcome) {}
+
+    /// Called once the prompt for a file has been rendered, before the model is asked.
+    ///
+    /// - `rel_path`: Path of the file relative to the run root.
+    /// - `prompt_tokens`: Estimated size of the rendered prompt.
+    fn on_prompt(&self, _rel_path: &str, _prompt_tokens: usize) {}
+
+    /// Called when a model request failed and is about to be retried.
+    ///
+    /// - `attempt`: The attempt number that just failed (starting at 1).
+    /// - `error`: The error message from the failed attempt.
+    fn on_retry(&self, _attempt: usize, _error: &str) {}
+
+    /// Called while a streamed answer arrives (`should_stream: true`), at most every 250ms
+    /// and once more when the stream ends.
+    ///
+    /// - `rel_path`: Path of the file being described (`.` for the project description).
+    /// - `received_chars`: Characters of the answer received so far.
+    fn on_typing(&self, _rel_path: &str, _received_chars: usize) {}
+
+    /// Called after the documentation tree has been written to disk.
+    ///
+    /// - `path`: The file that was written (e.g. `.dirdocs.nuon`).
+    /// - `files`: Number of file entries in the written tree.
+    fn on_checkpoint(&self, _path: &Path, _files: usize) {}
+
+    /// Called once at the end of a run, after the final checkpoint.
+    ///
+    /// - `report`: Counts for the run plus any files that hit `context_overflow`.
+    fn on_finish(&self, _report: &RunReport) {}
+}
+
+/// A [`ProgressObserver`] that ignores every event; the CLI relies on tracing logs instead.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopObserver;
+
+impl ProgressObserver for NoopObserver {}
+
+/// A [`ProgressObserver`] that writes one JSON object per event (NDJSON).
+///
+/// Every event carries `event` and an RFC 3339 `ts`. File events add `path`; a file's
+/// final event (`reused`, `generated`, `failed` or `skipped`) also carries `elapsed_ms`
+/// since its `started` event and, when a prompt was rendered, `prompt_tokens`.
+pub struct NdjsonObserver {
+    /// Where events are written (stdout or a file).
+    out: Mutex<Box<dyn Write + Send>>,
+    /// Start time of files that are in progress.
+    started: Mutex<HashMap<String, Instant>>,
+    /// Prompt size of files that are in progress.
+    tokens: Mutex<HashMap<String, usize>>,
+}
+
+impl NdjsonObserver {
+    /// Create an observer writing events to `out`.
+    pub fn new(out: Box<dyn Write + Send>) -> Self {
+        Self {
+            out: Mutex::new(out),
+            started: Mutex::new(HashMap::new()),
+            tokens: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Write `event` as one line, stamping it with the event name and time.
+    fn emit(&self, event: &str, mut fields: serde_json::Value) {
+        if let Some(obj) = fields.as_object_mut() {
+            obj.insert("event".into(), event.into());
+            obj.insert("ts".into(), Utc::now().to_rfc3339().into());
+        }
+        if let Ok(mut out) = self.out.lock() {
+            // Progress events are best-effort; a closed pipe must not fail the run.
+            let _ = writeln!(out, "{fields}").and_then(|_| out.flush());
+        }
+    }
+}
+
+impl ProgressObserver for NdjsonObserver {
+    fn on_file_start(&self, rel_path: &str) {
+        if let Ok(mut started) = self.started.lock() {
+            started.insert(rel_path.to_string(), Instant::now());
+        }
+        self.emit("started", serde_json::json!({ "path": rel_path }));
+    }
+
+    fn on_prompt(&self, rel_path: &str, prompt_tokens: usize) {
+        if let Ok(mut tokens) = self.tokens.lock() {
+            tokens.insert(rel_path.to_string(), prompt_tokens);
+        }
+    }
+
+    fn on_file_done(&self, rel_path: &str, outcome: FileOutcome) {
+        let elapsed_ms = self
+            .started
+            .lock()
+            .ok()
+            .and_then(|mut s| s.remove(rel_path))
+            .map(|t| t.elapsed().as_millis() as u64);
+        let prompt_tokens = self.tokens.lock().ok().and_then(|mut t| t.remove(rel_path));
+        self.emit(
+            outcome.as_str(),
+            serde_json::json!({
+                "path": rel_path,
+                "elapsed_ms": elapsed_ms,
+                "prompt_tokens": prompt_tokens,
+            }),
+        );
+    }
+
+    fn on_retry(&self, attempt: usize, error: &str) {
+        self.emit(
+            "retry",
+            serde_json::json!({ "attempt": attempt, "error": error }),
+        );
+    }
+
+    fn on_typing(&self, rel_path: &str, received_chars: usize) {
+        self.emit(
+            "typing",
+            serde_json::json!({ "path": rel_path, "received_chars": received_chars }),
+        );
+    }
+
+    fn on_checkpoint(&self, path: &Path, files: usize) {
+        self.emit(
+            "checkpoint",
+            serde_json::json!({ "path": path.display().to_string(), "files": files }),
+        );
+    }
+
+    fn on_finish(&self, report: &RunReport) {
+        let overflows: Vec<_> = report
+            .context_overflows
+            .iter()
+            .map(|o| {
+                serde_json::json!({
+                    "path": o.path,
+                    "prompt_tokens": o.prompt_tokens,
+                    "limit_tokens": o.limit_tokens,
+                })
+            })
+            .collect();
+        self.emit(
+            "finished",
+            serde_json::json!({
+                "walked": report.walked,
+                "regenerated": report.regenerated,
+                "context_overflows": overflows,
+                "deferred": report.deferred,
+                "plain_json_files": report.plain_json_files,
+            }),
+        );
+    }
+}
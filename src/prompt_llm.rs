@@ -1,11 +1,149 @@
+use crate::chat::{ApiStatusError, SamplingParams, ask_completion, ask_streaming};
 use crate::content::truncate;
-use anyhow::Context;
+use crate::progress::ProgressObserver;
+use crate::tokenizer::Tokenizer;
+use crate::types::Doc;
 use awful_aj::{api, config::AwfulJadeConfig, template::ChatTemplate};
-use handlebars::Handlebars;
-use serde::Deserialize;
-use serde_yaml as yaml;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::time::{Duration, sleep};
-use tracing::{info, warn};
+use tracing::{debug, error, info, warn};
+
+/// Lower-cased phrases with which servers refuse a parameter they don't support.
+const UNSUPPORTED_PARAMETER: &[&str] = &[
+    "not supported",
+    "unsupported",
+    "not implemented",
+    "not allowed",
+    "not permitted",
+    "unknown",
+    "unrecognized",
+    "extra inputs",
+    "invalid",
+];
+
+/// How the model was asked for its JSON answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum OutputMode {
+    /// The template's `response_format` JSON schema was sent (constrained decoding).
+    Structured,
+    /// No `response_format`; the prompt asks for raw JSON and the first object in the reply is parsed.
+    PlainJson,
+}
+
+/// Remembers, for the rest of a run, that the endpoint rejects structured output.
+#[derive(Debug, Default)]
+pub(crate) struct OutputModeState {
+    /// Set once a request with `response_format` was refused.
+    plain: AtomicBool,
+}
+
+impl OutputModeState {
+    /// The mode the next request starts with.
+    pub(crate) fn current(&self) -> OutputMode {
+        if self.plain.load(Ordering::Relaxed) {
+            OutputMode::PlainJson
+        } else {
+            OutputMode::Structured
+        }
+    }
+}
+
+/// The server refused the request's `response_format`; retrying it unchanged is pointless.
+#[derive(Debug)]
+pub(crate) struct StructuredOutputRejected(pub String);
+
+impl std::fmt::Display for StructuredOutputRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "structured output not supported: {}", self.0)
+    }
+}
+
+impl std::error::Error for StructuredOutputRejected {}
+
+/// Whether `error` is the server refusing the request's `response_format`.
+///
+/// Only a 4xx answer (other than 429) counts, and only when its error object names
+/// `response_format` as the offending `param`, or its message names `response_format` as
+/// unsupported. Rate limits, server errors, timeouts and other refusals are retried as usual.
+pub(crate) fn rejects_structured_output(error: &anyhow::Error) -> bool {
+    let Some(err) = error.downcast_ref::<ApiStatusError>() else {
+        return false;
+    };
+    if !err.status.is_client_error() || err.status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return false;
+    }
+    let Ok(body) = serde_json::from_str::<serde_json::Value>(&err.body) else {
+        return false;
+    };
+    // OpenAI-style `{"error": {"message", "param"}}`, or a bare `{"error": "..."}`/`{"detail": "..."}`
+    let detail = &body["error"];
+    if detail["param"]
+        .as_str()
+        .is_some_and(|p| p.starts_with("response_format"))
+    {
+        return true;
+    }
+    let message = detail["message"]
+        .as_str()
+        .or(detail.as_str())
+        .or(body["detail"].as_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    message.contains("response_format") && UNSUPPORTED_PARAMETER.iter().any(|p| message.contains(p))
+}
+
+/// A copy of `tpl` without `response_format` that spells the schema out in the system prompt instead.
+pub(crate) fn plain_json_template(tpl: &ChatTemplate) -> ChatTemplate {
+    let mut plain = tpl.clone();
+    let schema = plain.response_format.take().and_then(|f| f.schema);
+    plain.system_prompt.push_str(
+        "\n\nRespond with a single JSON object and nothing else: no prose, no Markdown code fences.",
+    );
+    if let Some(schema) = schema {
+        plain.system_prompt.push_str(&format!(
+            " The object must match this JSON schema:\n{schema}"
+        ));
+    }
+    plain
+}
+
+/// The first balanced `{...}` in `text` that parses as JSON, e.g. inside a code fence or after prose.
+fn first_json_object(text: &str) -> Option<&str> {
+    for (start, _) in text.match_indices('{') {
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+        for (i, c) in text[start..].char_indices() {
+            if in_string {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match c {
+                '"' => in_string = true,
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let candidate = &text[start..start + i + 1];
+                        if serde_json::from_str::<serde_json::Value>(candidate).is_ok() {
+                            return Some(candidate);
+                        }
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    None
+}
 
 /// ModelResp represents a response from the LLM about a file, including its description, joy level, and personality emoji.
 #[allow(non_snake_case)]
@@ -23,67 +161,138 @@ pub(crate) struct ModelResp {
     pub personalityEmoji: String,
 }
 
-/// Sanitizes a string for safe YAML serialization by filtering out control characters
-/// and replacing certain Unicode line breaks with spaces. This is useful for
-/// ensuring that strings can be safely written to YAML files without corruption.
+/// Parse a raw model answer into a [`Doc`], cleaning up the description.
 ///
 /// Parameters:
-/// - `s`: The input string to sanitize.
+/// - `answer`: The raw JSON text returned by the model.
 ///
 /// Returns:
-/// A new `String`` with sanitized content, containing only valid YAML characters
-/// and appropriate replacements for control sequences.
-///
-/// Errors:
-/// - This function does not return any errors directly; it handles all logic at the
-///   call site.
-///
-/// Notes:
-/// - This function is intended for use in contexts where strings may contain
-///   special characters that need to be safely represented.
-/// - The `is_control` method is used internally for character classification.
-///
-/// The implementation filters out newlines (`
-/// `), carriage returns (`\r`),
-/// and tabs (`	`). It replaces Unicode line breaks (U+2028, U+2029) and
-/// the zero-width space (U+FEFF) with spaces. All other control characters
-/// are removed, and non-control characters are retained unchanged.
-///
-/// The `filter_map` method is used to process each character, and the
-/// `collect()` function combines all valid characters into a final String.
-pub(crate) fn sanitize_for_yaml(s: &str) -> String {
-    s.chars()
-        .filter_map(|c| match c {
-            '\n' | '\r' | '\t' => Some(c),
-            _ if c.is_control() => None,
-            '\u{2028}' | '\u{2029}' | '\u{FEFF}' => Some(' '),
-            _ => Some(c),
-        })
-        .collect()
+/// - `Some(Doc)` if the answer is non-empty and parses as a [`ModelResp`], either as a
+///   whole or (for plain-text answers) from the first JSON object inside it.
+/// - `None` otherwise; parse failures are logged with a preview of the answer.
+pub(crate) fn doc_from_answer(answer: &str) -> Option<Doc> {
+    if answer.is_empty() {
+        return None;
+    }
+    match parse_answer(answer) {
+        Ok(doc) => Some(doc),
+        Err(e) => {
+            error!(%e, raw_preview=%truncate(answer, 400), "Response JSON parse error");
+            None
+        }
+    }
+}
+
+/// Parse a raw model answer like [`doc_from_answer`], returning the parse error instead of logging it.
+fn parse_answer(answer: &str) -> Result<Doc, serde_json::Error> {
+    let parsed = serde_json::from_str::<ModelResp>(answer).or_else(|e| {
+        let object = first_json_object(answer).ok_or(e)?;
+        debug!("Parsed the first JSON object out of a non-JSON answer");
+        serde_json::from_str::<ModelResp>(object)
+    })?;
+    Ok(Doc {
+        fileDescription: sanitize_description(&parsed.fileDescription),
+        joyThisFileBrings: parsed.joyThisFileBrings,
+        personalityEmoji: parsed.personalityEmoji,
+    })
 }
 
-/// Indents a string by `n` spaces, with each line in the string indented.
+/// Parse a model answer, re-prompting once with the parse error when it isn't valid.
 ///
-/// # Parameters:
-/// - `s`: The input string to indent.
-/// - `n`: The number of spaces to add as indentation.
+/// The corrective request replays the original prompt, then the model's invalid answer
+/// as its own turn, then a user message quoting the error and asking for the JSON object
+/// alone. Models usually repair a truncated or chatty answer this way.
 ///
-/// # Returns:
-/// A new `String` with each line indented by `n` spaces.
+/// Parameters:
+/// - `cfg`: A reference to an [`AwfulJadeConfig`] instance.
+/// - `tpl`: The rendered template the answer was requested with.
+/// - `answer`: The answer to parse; empty when the request itself failed.
+/// - `mode`: How the answer was requested, so the correction uses the same output mode.
+/// - `sampling`: Temperature, `top_p` and seed to request.
+/// - `rel_path`: The file being described (`.` for the project description).
+/// - `observer`: Notified of retries of the corrective request.
 ///
-/// # Notes:
-/// - If the input string is empty, it returns an empty string.
-/// - The function handles line breaks and indents each line individually.
-pub(crate) fn indent_for_yaml(s: &str, n: usize) -> String {
-    if s.is_empty() {
-        return String::new();
+/// Returns:
+/// - `Some(Doc)` from the answer or its correction, `None` when neither parses or the
+///   original request failed (there's nothing to correct then).
+pub(crate) async fn doc_with_correction(
+    cfg: &AwfulJadeConfig,
+    tpl: &ChatTemplate,
+    answer: &str,
+    mode: OutputMode,
+    sampling: SamplingParams,
+    rel_path: &str,
+    observer: &dyn ProgressObserver,
+) -> Option<Doc> {
+    if answer.is_empty() {
+        return None;
+    }
+    let err = match parse_answer(answer) {
+        Ok(doc) => return Some(doc),
+        Err(e) => e,
+    };
+    warn!(path = rel_path, %err, raw_preview=%truncate(answer, 400), "Invalid JSON answer; asking the model to correct it");
+
+    let base = if mode == OutputMode::PlainJson && tpl.response_format.is_some() {
+        plain_json_template(tpl)
+    } else {
+        tpl.clone()
+    };
+    let corrective = match corrective_template(&base, answer, &err.to_string()) {
+        Ok(t) => t,
+        Err(e) => {
+            error!(%e, "Could not build the corrective prompt");
+            return None;
+        }
+    };
+    match ask_with_retry(cfg, "", &corrective, 2, sampling, rel_path, observer).await {
+        Ok(fixed) => {
+            let doc = doc_from_answer(&fixed);
+            if doc.is_some() {
+                info!(path = rel_path, "Corrected answer parsed");
+            }
+            doc
+        }
+        Err(e) => {
+            error!(%e, path = rel_path, "Corrective request failed");
+            None
+        }
     }
-    let pad = " ".repeat(n);
-    s.lines()
-        .map(|line| format!("{pad}{line}"))
-        .collect::<Vec<_>>()
-        .join("\n")
 }
+
+/// `tpl` extended with the original question, the invalid `answer` and a request to fix `error`.
+///
+/// Errors:
+/// - When a message can't be represented as a chat message (never for string content).
+fn corrective_template(
+    tpl: &ChatTemplate,
+    answer: &str,
+    error: &str,
+) -> anyhow::Result<ChatTemplate> {
+    // The user turn `api::ask` built for the original request (its prompt is always empty).
+    let mut question = String::new();
+    if let Some(pre) = &tpl.pre_user_message_content {
+        question = format!("{pre} {question}");
+    }
+    if let Some(post) = &tpl.post_user_message_content {
+        question = format!("{question} {post}");
+    }
+
+    let user = serde_json::from_value(serde_json::json!({ "role": "user", "content": question }))?;
+    let assistant =
+        serde_json::from_value(serde_json::json!({ "role": "assistant", "content": answer }))?;
+
+    let mut fixed = tpl.clone();
+    fixed.messages.extend([user, assistant]);
+    fixed.pre_user_message_content = Some(format!(
+        "Your answer could not be parsed: {error}. Reply again with only the corrected JSON \
+         object (keys `fileDescription`, `joyThisFileBrings` and `personalityEmoji`), with no \
+         prose and no Markdown code fences."
+    ));
+    fixed.post_user_message_content = None;
+    Ok(fixed)
+}
+
 /// Returns a string representation for suppressed binary content.
 ///
 /// This function returns the literal "[[binary content suppressed]]" as a `String`.
@@ -94,46 +303,27 @@ pub(crate) fn suppressed_block() -> String {
     String::from("[[binary content suppressed]]")
 }
 
-/// Handlebars-rendered chat template.
+/// Estimate how many tokens a rendered template sends to the backend.
 ///
-/// Renders a raw text template using the given `Handlebars` instance and data,
-/// serializes the result to YAML, then maps it back into a `ChatTemplate`.
+/// The whole template (system prompt, seed messages and response schema) is serialized
+/// and counted with the run's tokenizer. JSON punctuation makes this a slight
+/// overestimate, which is the safe side for a context-size check.
 ///
 /// Parameters:
-/// - `hbs`: A reference to the Handlebars instance.
-/// - `raw_template`: The raw text template as a string slice.
-/// - `data`: A reference to data that implements the `serde::Serialize` trait.
+/// - `tpl`: The rendered chat template.
+/// - `tokenizer`: The tokenizer of the configured model.
 ///
 /// Returns:
-/// - A serialized `ChatTemplate`, or an error if serializing, rendering,
-///   or parsing fails.
-///
-/// Errors:
-/// - All errors from `Handlebars::render_template`, `yaml::from_str`, and
-///   `anyhow::anyhow` are returned.
-///
-/// Safety:
-/// - This function is safe to call as it only handles references and
-///   does not perform unsafe operations.
-///
-/// Notes:
-/// - If the rendered output is too long, it will be truncated and logged
-///   with an error message.
-pub(crate) fn render_chat_template(
-    hbs: &Handlebars<'_>,
-    raw_template: &str,
-    data: &impl serde::Serialize,
-) -> anyhow::Result<ChatTemplate> {
-    let rendered = hbs
-        .render_template(raw_template, data)
-        .context("Handlebars render failed")?;
-    let tpl: ChatTemplate = yaml::from_str(&rendered).map_err(|e| {
-        anyhow::anyhow!(
-            "YAML -> ChatTemplate error: {e}\n{}",
-            truncate(&rendered, 400)
-        )
-    })?;
-    Ok(tpl)
+/// - The estimated prompt size in tokens, or `None` if the template can't be serialized.
+pub(crate) fn prompt_tokens(tpl: &ChatTemplate, tokenizer: &Tokenizer) -> Option<usize> {
+    let body = serde_json::to_string(tpl).ok()?;
+    Some(tokenizer.count(&body))
+}
+
+/// Tokens available for the prompt: `context_max_tokens` minus the tokens reserved for the answer.
+pub(crate) fn prompt_budget(cfg: &AwfulJadeConfig) -> usize {
+    let reserved = cfg.assistant_minimum_context_tokens.max(0) as usize;
+    (cfg.context_max_tokens as usize).saturating_sub(reserved)
 }
 
 /// Exponential backoff + jitter around `api::ask`.
@@ -145,6 +335,9 @@ pub(crate) fn render_chat_template(
 /// - `prompt`: The user input string to send via the API.
 /// - `tpl`: A reference to a [`ChatTemplate`] used for formatting the request.
 /// - `max_attempts`: The maximum number of retry attempts allowed (including the initial call).
+/// - `sampling`: Temperature, `top_p` and seed to request; unset fields use the server's defaults.
+/// - `rel_path`: The file being described (`.` for the project description).
+/// - `observer`: Notified through `on_retry` before each retry, and `on_typing` while streaming.
 ///
 /// Returns:
 /// - `Ok(String)`: The response from `api::ask` if successful.
@@ -163,17 +356,32 @@ pub(crate) fn render_chat_template(
 /// - The backoff delay increases exponentially, capped at 8 seconds.
 /// - Jitter (0–250ms) is added to prevent repeated retries with identical delays.
 /// - The initial call (attempt 1) does not have a delay, and subsequent errors trigger retries.
+/// - With `should_stream: true` the answer is streamed through [`ask_streaming`] instead, and
+///   when sampling parameters or a `response_format` are set it is requested through
+///   [`ask_completion`], whose errors keep the HTTP status for [`rejects_structured_output`].
 pub(crate) async fn ask_with_retry(
     cfg: &AwfulJadeConfig,
     prompt: &str,
     tpl: &ChatTemplate,
     max_attempts: usize,
+    sampling: SamplingParams,
+    rel_path: &str,
+    observer: &dyn ProgressObserver,
 ) -> anyhow::Result<String> {
     let base = Duration::from_millis(300);
     let cap = Duration::from_secs(8);
 
     for attempt in 1..=max_attempts {
-        match api::ask(cfg, prompt.to_string(), tpl, None, None).await {
+        let res = if cfg.should_stream == Some(true) {
+            ask_streaming(cfg, prompt, tpl, sampling, rel_path, observer).await
+        } else if !sampling.is_unset() || tpl.response_format.is_some() {
+            ask_completion(cfg, prompt, tpl, sampling).await
+        } else {
+            api::ask(cfg, prompt.to_string(), tpl, None, None)
+                .await
+                .map_err(|e| anyhow::anyhow!(e.to_string()))
+        };
+        match res {
             Ok(answer) => {
                 if attempt > 1 {
                     info!(attempt, "api::ask succeeded after retries");
@@ -184,9 +392,13 @@ pub(crate) async fn ask_with_retry(
                 let emsg = e.to_string();
                 let is_last = attempt == max_attempts;
                 warn!(attempt, error=%emsg, "api::ask failed");
+                if tpl.response_format.is_some() && rejects_structured_output(&e) {
+                    return Err(StructuredOutputRejected(emsg).into());
+                }
                 if is_last {
                     return Err(anyhow::anyhow!(emsg));
                 }
+                observer.on_retry(attempt, &emsg);
 
                 // backoff = min(base * 2^(attempt-1), cap) + jitter
                 let exp: u32 = ((attempt - 1) as u32).min(16);
@@ -209,6 +421,55 @@ pub(crate) async fn ask_with_retry(
     Err(anyhow::anyhow!("ask_with_retry: exhausted attempts"))
 }
 
+/// Ask the model like [`ask_with_retry`], falling back to a plain-text prompt when the
+/// endpoint rejects structured output.
+///
+/// The first rejection switches `state` to [`OutputMode::PlainJson`], so later requests
+/// in the same run skip the doomed structured attempt.
+///
+/// Parameters:
+/// - `cfg`: A reference to an [`AwfulJadeConfig`] instance.
+/// - `tpl`: The rendered template, usually carrying a `response_format` schema.
+/// - `max_attempts`: The maximum number of attempts per mode.
+/// - `sampling`: Temperature, `top_p` and seed to request; unset fields use the server's defaults.
+/// - `rel_path`: The file being described (`.` for the project description).
+/// - `observer`: Notified through `on_retry` before each retry, and `on_typing` while streaming.
+/// - `state`: The run's output mode, shared by every request.
+///
+/// Returns:
+/// - The answer and the mode that produced it.
+///
+/// Errors:
+/// - The last error once every attempt has failed.
+pub(crate) async fn ask_with_fallback(
+    cfg: &AwfulJadeConfig,
+    tpl: &ChatTemplate,
+    max_attempts: usize,
+    sampling: SamplingParams,
+    rel_path: &str,
+    observer: &dyn ProgressObserver,
+    state: &OutputModeState,
+) -> anyhow::Result<(String, OutputMode)> {
+    if tpl.response_format.is_none() {
+        return ask_with_retry(cfg, "", tpl, max_attempts, sampling, rel_path, observer)
+            .await
+            .map(|a| (a, OutputMode::PlainJson));
+    }
+    if state.current() == OutputMode::Structured {
+        match ask_with_retry(cfg, "", tpl, max_attempts, sampling, rel_path, observer).await {
+            Err(e) if e.downcast_ref::<StructuredOutputRejected>().is_some() => {
+                warn!(error=%e, "Endpoint rejects response_format; using plain JSON prompts for the rest of the run");
+                state.plain.store(true, Ordering::Relaxed);
+            }
+            res => return res.map(|a| (a, OutputMode::Structured)),
+        }
+    }
+    let plain = plain_json_template(tpl);
+    ask_with_retry(cfg, "", &plain, max_attempts, sampling, rel_path, observer)
+        .await
+        .map(|a| (a, OutputMode::PlainJson))
+}
+
 /// Adds a random jitter between 0 and 250 milliseconds to the current system time.
 /// This function calculates a duration based on subsecond nanoseconds of the current timestamp, applying modulo to ensure it falls within 0-250ms.
 /// The result is returned as a `Duration`.
@@ -0,0 +1,60 @@
+use ignore::WalkBuilder;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// List the files under `root` that a documentation run would visit.
+///
+/// The walk honors `.gitignore`, `.git/info/exclude` and the global git excludes, and
+/// skips hidden files and directories (which also keeps `.dirdocs/` and `.git/` out).
+///
+/// Parameters:
+/// - `root`: The directory to walk.
+/// - `ignore`: Extra directory names to skip anywhere in the tree (`--ignore`).
+///
+/// Returns:
+/// - `(absolute path, path relative to root)` pairs, in walk order.
+pub(crate) fn walk_files(root: &Path, ignore: &[String]) -> Vec<(PathBuf, String)> {
+    let ignore_set: HashSet<String> = ignore.iter().cloned().collect();
+    info!(?ignore_set, "Initializing walker (git + hidden rules)");
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .git_ignore(true)
+        .git_exclude(true)
+        .git_global(true)
+        .hidden(true);
+    builder.filter_entry(move |e| {
+        if e.depth() == 0 {
+            return true;
+        }
+        if let Some(ft) = e.file_type() {
+            if ft.is_dir() {
+                if let Some(name) = e.file_name().to_str() {
+                    if ignore_set.contains(name) {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    });
+
+    let mut files = Vec::new();
+    for entry in builder.build() {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(err) => {
+                warn!(%err, "Walk error");
+                continue;
+            }
+        };
+        if entry.depth() == 0 || !entry.file_type().map(|ft| ft.is_file()).unwrap_or(false) {
+            continue;
+        }
+        let path = entry.into_path();
+        let rel_path = pathdiff::diff_paths(&path, root).unwrap_or_else(|| path.clone());
+        let rel_str = rel_path.to_string_lossy().to_string();
+        files.push((path, rel_str));
+    }
+    files
+}
@@ -0,0 +1,80 @@
+use crate::types::{DirdocsRoot, Node};
+use nu_ansi_term::{Color, Style};
+use regex::Regex;
+use serde::Serialize;
+
+/// A file entry whose path or description matched a search pattern.
+#[allow(non_snake_case)]
+#[derive(Debug, Serialize)]
+pub(crate) struct SearchHit {
+    /// Path of the file relative to the documented root.
+    pub path: String,
+    /// The file's description.
+    pub fileDescription: String,
+    /// The joy level of the file.
+    pub joyThisFileBrings: serde_json::Value,
+    /// The personality emoji.
+    pub personalityEmoji: String,
+}
+
+/// Collect every file entry whose path or description matches `re`.
+///
+/// Parameters:
+/// - `tree`: The documentation tree loaded from `.dirdocs.nuon`.
+/// - `re`: The compiled pattern to match against each entry.
+///
+/// Returns:
+/// - The matching entries, sorted by path.
+pub(crate) fn search_tree(tree: &DirdocsRoot, re: &Regex) -> Vec<SearchHit> {
+    /// Recursively visit nodes, pushing matching files into `out`.
+    fn visit(nodes: &[Node], re: &Regex, out: &mut Vec<SearchHit>) {
+        for n in nodes {
+            match n {
+                Node::Dir(d) => visit(&d.entries, re, out),
+                Node::File(f) => {
+                    if re.is_match(&f.path) || re.is_match(&f.doc.fileDescription) {
+                        out.push(SearchHit {
+                            path: f.path.clone(),
+                            fileDescription: f.doc.fileDescription.clone(),
+                            joyThisFileBrings: f.doc.joyThisFileBrings.clone(),
+                            personalityEmoji: f.doc.personalityEmoji.clone(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    visit(&tree.entries, re, &mut out);
+    out.sort_by(|a, b| a.path.cmp(&b.path));
+    out
+}
+
+/// Paint every match of `re` in `s`, leaving the rest of the text untouched.
+///
+/// Parameters:
+/// - `s`: The text to highlight.
+/// - `re`: The pattern whose matches are highlighted.
+/// - `color`: When false, `s` is returned unchanged.
+///
+/// Returns:
+/// - The text with matches wrapped in bold red ANSI escapes.
+pub(crate) fn highlight_matches(s: &str, re: &Regex, color: bool) -> String {
+    if !color {
+        return s.to_string();
+    }
+    let style = Style::new().fg(Color::Red).bold();
+    let mut out = String::with_capacity(s.len());
+    let mut last = 0;
+    for m in re.find_iter(s) {
+        if m.is_empty() {
+            continue;
+        }
+        out.push_str(&s[last..m.start()]);
+        out.push_str(&style.paint(m.as_str()).to_string());
+        last = m.end();
+    }
+    out.push_str(&s[last..]);
+    out
+}
@@ -0,0 +1,123 @@
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines, Stdin, Stdout};
+
+/// JSON-RPC error code for a request that is not valid JSON.
+pub(crate) const PARSE_ERROR: i64 = -32700;
+/// JSON-RPC error code for an unknown method.
+pub(crate) const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC error code for missing or malformed parameters.
+pub(crate) const INVALID_PARAMS: i64 = -32602;
+/// JSON-RPC error code for failures while handling a valid request.
+pub(crate) const SERVER_ERROR: i64 = -32000;
+
+/// A JSON-RPC 2.0 request or notification.
+#[derive(Debug, Deserialize)]
+pub(crate) struct Request {
+    /// Request id; `None` for notifications, which get no response.
+    #[serde(default)]
+    pub id: Option<Value>,
+    /// The method name, e.g. `getDescription`.
+    pub method: String,
+    /// Method parameters (an object for every dirdocs method).
+    #[serde(default)]
+    pub params: Value,
+}
+
+/// A JSON-RPC error object.
+#[derive(Debug)]
+pub(crate) struct RpcError {
+    /// One of the JSON-RPC error codes above.
+    pub code: i64,
+    /// Human-readable message.
+    pub message: String,
+}
+
+impl RpcError {
+    /// Build an error with `code` and `message`.
+    pub(crate) fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::new(SERVER_ERROR, e.to_string())
+    }
+}
+
+/// Newline-delimited JSON-RPC over stdin/stdout: one request per line, one response per line.
+pub(crate) struct StdioTransport {
+    /// Incoming request lines.
+    lines: Lines<BufReader<Stdin>>,
+    /// Where responses are written.
+    out: Stdout,
+}
+
+impl StdioTransport {
+    /// Attach to the process's stdin and stdout.
+    pub(crate) fn new() -> Self {
+        Self {
+            lines: BufReader::new(tokio::io::stdin()).lines(),
+            out: tokio::io::stdout(),
+        }
+    }
+
+    /// Read the next request, skipping blank lines.
+    ///
+    /// Returns:
+    /// - `None` once stdin is closed.
+    /// - `Some(Err(_))` for a line that is not a valid request; it should be answered with a null id.
+    pub(crate) async fn next_request(&mut self) -> Option<Result<Request, RpcError>> {
+        loop {
+            let line = match self.lines.next_line().await {
+                Ok(Some(l)) => l,
+                Ok(None) | Err(_) => return None,
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Some(
+                serde_json::from_str(&line).map_err(|e| RpcError::new(PARSE_ERROR, e.to_string())),
+            );
+        }
+    }
+
+    /// Write the response for request `id`.
+    ///
+    /// Errors:
+    /// - I/O errors when writing to stdout.
+    pub(crate) async fn respond(
+        &mut self,
+        id: Value,
+        result: Result<Value, RpcError>,
+    ) -> anyhow::Result<()> {
+        let msg = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": e.code, "message": e.message },
+            }),
+        };
+        let mut line = msg.to_string();
+        line.push('\n');
+        self.out.write_all(line.as_bytes()).await?;
+        self.out.flush().await?;
+        Ok(())
+    }
+}
+
+/// Read the string parameter `name` from a request's params object.
+///
+/// Errors:
+/// - An `INVALID_PARAMS` error when the parameter is missing or not a string.
+pub(crate) fn str_param<'a>(params: &'a Value, name: &str) -> Result<&'a str, RpcError> {
+    params
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("missing string param `{name}`")))
+}
@@ -0,0 +1,107 @@
+use crate::chunk::{SplitterKind, guess_tree_sitter_language};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use tracing::debug;
+
+/// File name of the splitter overrides inside the config directory.
+pub(crate) const SPLITTERS_FILE: &str = "splitters.yaml";
+
+/// Written by `dirdocs init`; documents the format. No overrides are active by default.
+pub(crate) const DEFAULT_SPLITTERS_YAML: &str = r#"# Force how files are chunked before their contents are put into the prompt.
+# Globs match paths relative to the run root (`*` also crosses directories);
+# the first matching override wins. `splitter` is one of:
+#   markdown     - split on headings, lists and paragraphs
+#   text         - split on paragraphs and sentences
+#   code:<ext>   - parse with the Tree-sitter grammar used for `.<ext>` files
+overrides: []
+#  - glob: "*.tpl"
+#    splitter: markdown
+#  - glob: "*.rules"
+#    splitter: text
+#  - glob: "**/BUILD"
+#    splitter: code:py
+"#;
+
+/// One override from `splitters.yaml`.
+#[derive(Debug, Clone, Deserialize)]
+struct SplitterRule {
+    /// Glob matched against the path relative to the run root.
+    glob: String,
+    /// `markdown`, `text` or `code:<ext>`.
+    splitter: String,
+}
+
+/// The file layout of `splitters.yaml`.
+#[derive(Debug, Default, Deserialize)]
+struct SplitterRules {
+    #[serde(default)]
+    overrides: Vec<SplitterRule>,
+}
+
+/// Compiled splitter overrides, consulted before the built-in MIME/extension guess.
+pub(crate) struct SplitterOverrides {
+    /// Override globs, in file order.
+    globs: GlobSet,
+    /// Splitter of each glob.
+    kinds: Vec<SplitterKind>,
+}
+
+impl SplitterOverrides {
+    /// Load and compile the overrides in `path`; a missing file means no overrides.
+    ///
+    /// Errors:
+    /// - I/O errors other than a missing file, invalid YAML, an invalid glob, or a
+    ///   splitter that isn't `markdown`, `text` or `code:<ext>` with a compiled-in grammar.
+    pub(crate) fn load(path: &Path) -> anyhow::Result<Self> {
+        let rules: SplitterRules = match fs::read_to_string(path) {
+            Ok(s) => serde_yaml::from_str(&s)
+                .map_err(|e| anyhow::anyhow!("invalid splitter overrides {:?}: {e}", path))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => SplitterRules::default(),
+            Err(e) => return Err(anyhow::anyhow!("failed to read {:?}: {e}", path)),
+        };
+
+        let mut builder = GlobSetBuilder::new();
+        let mut kinds = Vec::with_capacity(rules.overrides.len());
+        for rule in &rules.overrides {
+            let glob = Glob::new(&rule.glob)
+                .map_err(|e| anyhow::anyhow!("invalid splitter glob {:?}: {e}", rule.glob))?;
+            builder.add(glob);
+            kinds.push(parse_splitter(&rule.splitter).map_err(|e| {
+                anyhow::anyhow!("invalid splitter for {:?} in {:?}: {e}", rule.glob, path)
+            })?);
+        }
+        debug!(overrides = kinds.len(), "Splitter overrides loaded");
+        Ok(Self {
+            globs: builder.build()?,
+            kinds,
+        })
+    }
+
+    /// The forced splitter for a root-relative path, if any override matches it.
+    pub(crate) fn kind_for(&self, rel: &str) -> Option<SplitterKind> {
+        let first = self.globs.matches(rel).into_iter().min()?;
+        Some(self.kinds[first].clone())
+    }
+}
+
+/// Parse a `splitter:` value.
+///
+/// Errors:
+/// - An unknown splitter name, or `code:<ext>` for an extension without a grammar.
+fn parse_splitter(s: &str) -> anyhow::Result<SplitterKind> {
+    match s.trim() {
+        "markdown" => Ok(SplitterKind::Markdown),
+        "text" => Ok(SplitterKind::Text),
+        other => {
+            let Some(ext) = other.strip_prefix("code:") else {
+                anyhow::bail!("expected `markdown`, `text` or `code:<ext>`, got {other:?}");
+            };
+            let probe = Path::new("file").with_extension(ext.trim_start_matches('.'));
+            guess_tree_sitter_language("", &probe)
+                .map(SplitterKind::Code)
+                .ok_or_else(|| anyhow::anyhow!("no Tree-sitter grammar for `.{ext}` in this build"))
+        }
+    }
+}
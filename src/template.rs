@@ -0,0 +1,614 @@
+use crate::chat::SamplingParams;
+use crate::chunk::ChunkSettings;
+use crate::content::shebang_language;
+use awful_aj::template::ChatTemplate;
+use handlebars::{Handlebars, handlebars_helper};
+use serde_yaml::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+/// File name of the default prompt template inside the templates directory.
+pub(crate) const DEFAULT_TEMPLATE_FILE: &str = "dir_docs.yaml";
+
+/// Subdirectory of the templates directory whose files are registered as Handlebars partials.
+pub(crate) const PARTIALS_DIR: &str = "partials";
+
+/// Placeholders a template must render; without them the model never sees the file.
+const REQUIRED_PLACEHOLDERS: &[&str] = &["filename", "chunk_one"];
+
+/// Sample values for the per-file metadata fields `dirdocs run` passes to templates.
+///
+/// Validation renders with these so helpers that reject a missing value (e.g.
+/// `{{truncate git_last_commit_message 80}}`) see the same keys a real run provides.
+const SAMPLE_FIELDS: &[(&str, &str)] = &[
+    ("git_last_commit_message", "Fix the parser"),
+    ("git_last_author", "Jane Doe"),
+    ("git_last_commit_date", "2024-05-01"),
+    ("git_file_age_days", "412"),
+    ("image_format", "PNG"),
+    ("image_width", "512"),
+    ("image_height", "512"),
+    ("image_dimensions", "512×512"),
+    ("image_color", "RGBA 8-bit"),
+    ("image_created", "2023:06:01 14:02:11"),
+    ("image_camera", "Apple iPhone 13"),
+    ("archive_format", "zip"),
+    ("archive_members", "128"),
+    ("archive_listing", "assets/icon.png (12.0 KB)"),
+    ("binary_format", "ELF"),
+    ("binary_kind", "shared library"),
+    ("binary_arch", "x86-64"),
+    ("binary_libraries", "libc.so.6, libm.so.6"),
+    ("binary_strings", "usage: tool [OPTIONS]"),
+];
+
+/// Keys the answer schema must declare (or an alias of them), mirroring `ModelResp`.
+const REQUIRED_SCHEMA_KEYS: &[&[&str]] = &[
+    &["fileDescription"],
+    &["joyThisFileBrings", "howMuchJoyDoesThisFileBringYou"],
+    &["personalityEmoji", "emojiThatExpressesThisFilesPersonality"],
+];
+
+/// Register every file in `<templates_dir>/partials/` as a Handlebars partial.
+///
+/// Each partial is named after its file stem, so `partials/rules.hbs` can be used as
+/// `{{> rules}}` inside any string of a template.
+///
+/// Parameters:
+/// - `hbs`: The registry to register partials on.
+/// - `templates_dir`: The templates directory (e.g. `~/.config/com.awful-sec.aj/templates`).
+///
+/// Returns:
+/// - The number of partials registered (0 if the directory does not exist).
+///
+/// Errors:
+/// - I/O errors when reading a partial, or Handlebars errors for an invalid partial.
+pub(crate) fn register_partials(
+    hbs: &mut Handlebars<'_>,
+    templates_dir: &Path,
+) -> anyhow::Result<usize> {
+    let dir = templates_dir.join(PARTIALS_DIR);
+    let Ok(rd) = fs::read_dir(&dir) else {
+        return Ok(0);
+    };
+
+    let mut count = 0;
+    for entry in rd.flatten() {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let body = fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("failed to read partial {:?}: {e}", path))?;
+        hbs.register_partial(name, body)
+            .map_err(|e| anyhow::anyhow!("invalid partial {:?}: {e}", path))?;
+        debug!(partial = name, "Registered template partial");
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Register the helpers dirdocs offers to templates.
+///
+/// - `{{truncate s 500}}`: the first 500 characters of `s`, ending in `…` when cut.
+/// - `{{json-escape s}}`: `s` escaped for use inside a JSON string (without the quotes).
+/// - `{{upper s}}`: `s` in upper case.
+/// - `{{basename path}}`: the last component of `path`.
+///
+/// Parameters:
+/// - `hbs`: The registry to register the helpers on.
+pub(crate) fn register_helpers(hbs: &mut Handlebars<'_>) {
+    handlebars_helper!(truncate: |s: str, max: u64| {
+        let max = max as usize;
+        match s.char_indices().nth(max) {
+            Some((cut, _)) => format!("{}…", &s[..cut]),
+            None => s.to_string(),
+        }
+    });
+    handlebars_helper!(json_escape: |s: str| {
+        let quoted = serde_json::to_string(s).unwrap_or_default();
+        quoted[1..quoted.len() - 1].to_string()
+    });
+    handlebars_helper!(upper: |s: str| s.to_uppercase());
+    handlebars_helper!(basename: |path: str| {
+        Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    });
+
+    hbs.register_helper("truncate", Box::new(truncate));
+    hbs.register_helper("json-escape", Box::new(json_escape));
+    hbs.register_helper("upper", Box::new(upper));
+    hbs.register_helper("basename", Box::new(basename));
+}
+
+/// Load a template from `templates_dir`, resolving any `extends:` chain.
+///
+/// A template may start with `extends: <base>` (the `.yaml` extension is optional).
+/// Its top-level keys then replace the base template's keys of the same name, and keys
+/// the base lacks are appended. Merging is textual, per top-level key, so block scalars
+/// and Handlebars expressions are carried over untouched.
+///
+/// Parameters:
+/// - `templates_dir`: The templates directory.
+/// - `file_name`: The template file to load (e.g. `dir_docs.rs.yaml`).
+///
+/// Returns:
+/// - The fully merged raw template, ready for Handlebars rendering.
+///
+/// Errors:
+/// - I/O errors when reading a template in the chain.
+/// - A cycle in the `extends:` chain.
+pub(crate) fn load_template(templates_dir: &Path, file_name: &str) -> anyhow::Result<String> {
+    let mut seen = HashSet::new();
+    load_template_inner(templates_dir, file_name, &mut seen)
+}
+
+/// Recursive worker for [`load_template`], tracking visited files to detect cycles.
+fn load_template_inner(
+    templates_dir: &Path,
+    file_name: &str,
+    seen: &mut HashSet<PathBuf>,
+) -> anyhow::Result<String> {
+    let path = templates_dir.join(file_name);
+    if !seen.insert(path.clone()) {
+        anyhow::bail!("template inheritance cycle at {:?}", path);
+    }
+    let raw = fs::read_to_string(&path)
+        .map_err(|e| anyhow::anyhow!("failed to read template {:?}: {e}", path))?;
+
+    let blocks = split_top_level(&raw);
+    let Some(base) = blocks
+        .iter()
+        .find(|b| b.key.as_deref() == Some("extends"))
+        .and_then(|b| b.scalar_value())
+    else {
+        return Ok(raw);
+    };
+
+    let base_file = if Path::new(&base).extension().is_some() {
+        base
+    } else {
+        format!("{base}.yaml")
+    };
+    debug!(template = file_name, base = %base_file, "Resolving template inheritance");
+    let base_raw = load_template_inner(templates_dir, &base_file, seen)?;
+    Ok(merge_blocks(&split_top_level(&base_raw), &blocks))
+}
+
+/// Check every prompt template in `templates_dir` before a run starts.
+///
+/// The default template and each per-extension variant are rendered with placeholder
+/// data and parsed like a real prompt, so mistakes surface once, up front, instead of as a
+/// YAML error on every file.
+///
+/// Parameters:
+/// - `hbs`: The registry the run renders with (partials already registered).
+/// - `templates_dir`: The templates directory.
+///
+/// Returns:
+/// - The number of templates checked.
+///
+/// Errors:
+/// - One message listing every problem found: unreadable files, Handlebars or YAML errors,
+///   placeholders in [`REQUIRED_PLACEHOLDERS`] that never render, or a `response_format`
+///   schema without the answer keys dirdocs parses.
+pub(crate) fn validate_templates(
+    hbs: &Handlebars<'_>,
+    templates_dir: &Path,
+) -> anyhow::Result<usize> {
+    let mut files = vec![DEFAULT_TEMPLATE_FILE.to_string()];
+    if let Ok(rd) = fs::read_dir(templates_dir) {
+        let mut variants: Vec<String> = rd
+            .flatten()
+            .filter_map(|e| e.file_name().to_str().map(str::to_string))
+            .filter(|n| {
+                n.starts_with("dir_docs.") && n.ends_with(".yaml") && n != DEFAULT_TEMPLATE_FILE
+            })
+            .collect();
+        variants.sort();
+        files.extend(variants);
+    }
+
+    let mut problems = Vec::new();
+    for file in &files {
+        let result =
+            load_template(templates_dir, file).and_then(|raw| validate_template(hbs, &raw));
+        if let Err(e) = result {
+            problems.push(format!("{file}: {e}"));
+        }
+    }
+    if !problems.is_empty() {
+        anyhow::bail!(
+            "invalid prompt template in {}:\n  {}\nFix the template, or move it aside and run `dirdocs init` to restore the default.",
+            templates_dir.display(),
+            problems.join("\n  ")
+        );
+    }
+    debug!(templates = files.len(), "Templates validated");
+    Ok(files.len())
+}
+
+/// Render `raw_template` with marker values and check the resulting prompt.
+///
+/// Errors:
+/// - The first problem found, as described in [`validate_templates`].
+fn validate_template(hbs: &Handlebars<'_>, raw_template: &str) -> anyhow::Result<()> {
+    let marker = |name: &str| format!("@@dirdocs:{name}@@");
+    let mut data = serde_json::json!({
+        "filename": marker("filename"),
+        "filesize": "1 KiB",
+        "filetype": "text",
+        "mimetype": "text/plain",
+        "operating_system": std::env::consts::OS,
+        "project_is_documented": "false",
+        "project_documentation": "",
+        "chunk_one": marker("chunk_one"),
+        "chunk_two": "",
+        "chunk_three": "",
+        "chunks": [marker("chunk_one")],
+        "symbols": "",
+        "filename_re": "",
+        "filename_stem_re": "",
+    });
+    for (key, value) in SAMPLE_FIELDS {
+        data[*key] = serde_json::Value::from(*value);
+    }
+    let tpl = PromptTemplate::parse(raw_template)?.render(hbs, &data)?;
+
+    let prompt = serde_json::to_string(&tpl)?;
+    let missing: Vec<&str> = REQUIRED_PLACEHOLDERS
+        .iter()
+        .copied()
+        .filter(|name| !prompt.contains(&marker(name)))
+        .collect();
+    if !missing.is_empty() {
+        anyhow::bail!(
+            "the prompt never includes {}",
+            missing
+                .iter()
+                .map(|n| format!("{{{{{n}}}}}"))
+                .collect::<Vec<_>>()
+                .join(" or ")
+        );
+    }
+
+    let Some(format) = &tpl.response_format else {
+        warn!("Template has no response_format; answers are parsed as plain JSON");
+        return Ok(());
+    };
+    let Some(schema) = &format.schema else {
+        return Ok(());
+    };
+    let properties = schema.get("properties").and_then(|p| p.as_object());
+    let Some(properties) = properties else {
+        anyhow::bail!("response_format.schema has no `properties`");
+    };
+    for keys in REQUIRED_SCHEMA_KEYS {
+        if !keys.iter().any(|k| properties.contains_key(*k)) {
+            anyhow::bail!("response_format.schema doesn't declare `{}`", keys[0]);
+        }
+    }
+    Ok(())
+}
+
+/// A prompt template parsed once, with its Handlebars expressions still inside its strings.
+///
+/// Rendering fills in each string on its own and builds the [`ChatTemplate`] from the
+/// parsed document, so file contents are plain string values: they are never spliced
+/// into YAML text and need no escaping or indentation.
+#[derive(Debug, Clone)]
+pub(crate) struct PromptTemplate {
+    /// The template's YAML document.
+    doc: Value,
+}
+
+impl PromptTemplate {
+    /// Parse a resolved template (see [`load_template`]).
+    ///
+    /// Errors:
+    /// - When the template isn't valid YAML, typically because a Handlebars expression
+    ///   sits outside a string (e.g. `system_prompt: {{foo}}` instead of a quoted or block value).
+    pub(crate) fn parse(raw_template: &str) -> anyhow::Result<Self> {
+        let doc: Value = serde_yaml::from_str(raw_template).map_err(|e| {
+            anyhow::anyhow!(
+                "template is not valid YAML: {e} (Handlebars expressions must be inside quoted or `|` block strings)"
+            )
+        })?;
+        if !doc.is_mapping() {
+            anyhow::bail!("template must be a YAML mapping");
+        }
+        Ok(Self { doc })
+    }
+
+    /// Render every string of the template with `data` and build the chat request from it.
+    ///
+    /// Parameters:
+    /// - `hbs`: The registry to render with (partials registered, HTML escaping off).
+    /// - `data`: The template variables.
+    ///
+    /// Returns:
+    /// - The rendered [`ChatTemplate`].
+    ///
+    /// Errors:
+    /// - Handlebars errors, or a document that doesn't have the [`ChatTemplate`] shape.
+    pub(crate) fn render(
+        &self,
+        hbs: &Handlebars<'_>,
+        data: &impl serde::Serialize,
+    ) -> anyhow::Result<ChatTemplate> {
+        /// Replace every string scalar under `value` by its rendering.
+        fn render_strings(
+            value: &mut Value,
+            hbs: &Handlebars<'_>,
+            data: &serde_json::Value,
+        ) -> anyhow::Result<()> {
+            match value {
+                Value::String(s) if s.contains("{{") => {
+                    *s = hbs
+                        .render_template(s, data)
+                        .map_err(|e| anyhow::anyhow!("Handlebars render failed: {e}"))?;
+                }
+                Value::Sequence(items) => {
+                    for item in items {
+                        render_strings(item, hbs, data)?;
+                    }
+                }
+                Value::Mapping(map) => {
+                    for (_, v) in map.iter_mut() {
+                        render_strings(v, hbs, data)?;
+                    }
+                }
+                Value::Tagged(tagged) => render_strings(&mut tagged.value, hbs, data)?,
+                _ => {}
+            }
+            Ok(())
+        }
+
+        let data = serde_json::to_value(data)?;
+        let mut doc = self.doc.clone();
+        render_strings(&mut doc, hbs, &data)?;
+        serde_yaml::from_value(doc).map_err(|e| anyhow::anyhow!("YAML -> ChatTemplate error: {e}"))
+    }
+
+    /// Sampling parameters set by the template's top-level `temperature`, `top_p` and
+    /// `seed` keys (inherited through `extends:` like any other key).
+    ///
+    /// Returns:
+    /// - The parameters found; values that aren't numbers are ignored with a warning.
+    pub(crate) fn sampling(&self) -> SamplingParams {
+        SamplingParams {
+            temperature: self.top_level_value("temperature"),
+            top_p: self.top_level_value("top_p"),
+            seed: self.top_level_value("seed"),
+        }
+    }
+
+    /// Chunk settings set by the template's top-level `chunk_count`, `chunk_tokens` and
+    /// `chunk_strategy` keys (read like [`PromptTemplate::sampling`]).
+    ///
+    /// Returns:
+    /// - The settings found; invalid values are ignored with a warning.
+    pub(crate) fn chunking(&self) -> ChunkSettings {
+        ChunkSettings {
+            count: self.top_level_value("chunk_count"),
+            tokens: self.top_level_value("chunk_tokens"),
+            strategy: self.top_level_value("chunk_strategy"),
+        }
+    }
+
+    /// Parse the scalar value of the top-level `key`, if the template sets it.
+    fn top_level_value<T: std::str::FromStr>(&self, key: &str) -> Option<T> {
+        let raw = match self.doc.get(key)? {
+            Value::Null => return None,
+            Value::Number(n) => n.to_string(),
+            Value::String(s) => s.trim().to_string(),
+            other => format!("{other:?}"),
+        };
+        let parsed = raw.parse().ok();
+        if parsed.is_none() {
+            warn!(key, value = %raw, "Ignoring invalid value for template key");
+        }
+        parsed
+    }
+}
+
+/// A run of lines belonging to one top-level YAML key (or leading comments when `key` is `None`).
+#[derive(Debug, Clone)]
+struct Block {
+    /// The top-level key, or `None` for text before the first key.
+    key: Option<String>,
+    /// The block's lines, including the `key:` line itself.
+    text: String,
+}
+
+impl Block {
+    /// The inline value of a `key: value` line, trimmed of whitespace and quotes.
+    fn scalar_value(&self) -> Option<String> {
+        let first = self.text.lines().next()?;
+        let (_, v) = first.split_once(':')?;
+        let v = v.trim().trim_matches(['"', '\'']);
+        (!v.is_empty()).then(|| v.to_string())
+    }
+}
+
+/// Split a YAML mapping document into top-level key blocks, preserving the original text.
+fn split_top_level(yaml: &str) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut cur = Block {
+        key: None,
+        text: String::new(),
+    };
+
+    for line in yaml.split_inclusive('\n') {
+        let starts_key = line
+            .chars()
+            .next()
+            .is_some_and(|c| !c.is_whitespace() && c != '#' && c != '-')
+            && line.contains(':');
+        if starts_key {
+            if cur.key.is_some() || !cur.text.is_empty() {
+                blocks.push(cur);
+            }
+            let key = line
+                .split(':')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            cur = Block {
+                key: Some(key),
+                text: String::new(),
+            };
+        }
+        cur.text.push_str(line);
+    }
+    if cur.key.is_some() || !cur.text.is_empty() {
+        blocks.push(cur);
+    }
+    blocks
+}
+
+/// Overlay `child` blocks onto `base` blocks, dropping the `extends` key.
+fn merge_blocks(base: &[Block], child: &[Block]) -> String {
+    let child_by_key: HashMap<&str, &Block> = child
+        .iter()
+        .filter_map(|b| b.key.as_deref().map(|k| (k, b)))
+        .collect();
+
+    let mut out = String::new();
+    let mut used: HashSet<&str> = HashSet::new();
+    for b in base {
+        let text = match b.key.as_deref() {
+            Some(k) => match child_by_key.get(k) {
+                Some(c) => {
+                    used.insert(k);
+                    &c.text
+                }
+                None => &b.text,
+            },
+            None => &b.text,
+        };
+        push_block(&mut out, text);
+    }
+    for c in child {
+        match c.key.as_deref() {
+            Some("extends") => {}
+            Some(k) if used.contains(k) => {}
+            Some(_) => push_block(&mut out, &c.text),
+            None => {}
+        }
+    }
+    out
+}
+
+/// Append a block, making sure it starts on a fresh line.
+fn push_block(out: &mut String, text: &str) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out.push_str(text);
+}
+
+/// A resolved template: its text (hashed into cache keys) and its parsed form.
+struct Resolved {
+    /// The merged raw template.
+    raw: String,
+    /// The parsed template, rendered once per prompt.
+    prompt: PromptTemplate,
+}
+
+impl Resolved {
+    /// Load and parse `file_name` from `dir`.
+    fn load(dir: &Path, file_name: &str) -> anyhow::Result<Self> {
+        let raw = load_template(dir, file_name)?;
+        let prompt = PromptTemplate::parse(&raw)?;
+        Ok(Self { raw, prompt })
+    }
+}
+
+/// Resolved templates for a run, with optional per-extension variants.
+///
+/// For a file with extension `ext`, `dir_docs.<ext>.yaml` is used when it exists in the
+/// templates directory; otherwise the default `dir_docs.yaml` applies.
+pub(crate) struct TemplateSet {
+    /// The templates directory.
+    dir: PathBuf,
+    /// The resolved default template.
+    default: Resolved,
+    /// Resolved per-extension variants (`None` when no variant exists).
+    by_ext: HashMap<String, Option<Resolved>>,
+}
+
+impl TemplateSet {
+    /// Load and parse the default template from `dir`, resolving `extends:`.
+    ///
+    /// Errors:
+    /// - Any error from [`load_template`] or [`PromptTemplate::parse`] for the default template.
+    pub(crate) fn load(dir: &Path) -> anyhow::Result<Self> {
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            default: Resolved::load(dir, DEFAULT_TEMPLATE_FILE)?,
+            by_ext: HashMap::new(),
+        })
+    }
+
+    /// The raw template used for `path`, loading the per-extension variant on first use.
+    ///
+    /// Errors:
+    /// - Any error from [`load_template`] or [`PromptTemplate::parse`] for an existing variant.
+    pub(crate) fn for_path(&mut self, path: &Path) -> anyhow::Result<&str> {
+        Ok(&self.resolve(path)?.raw)
+    }
+
+    /// The parsed template to render for `path`, loading the per-extension variant on first use.
+    ///
+    /// Errors:
+    /// - Same as [`TemplateSet::for_path`].
+    pub(crate) fn prompt_for_path(&mut self, path: &Path) -> anyhow::Result<&PromptTemplate> {
+        Ok(&self.resolve(path)?.prompt)
+    }
+
+    /// The resolved template for `path`: its extension's variant if one exists, else the default.
+    ///
+    /// Extensionless scripts use the variant of their shebang's language (`dir_docs.py.yaml`
+    /// for `#!/usr/bin/env python3`).
+    fn resolve(&mut self, path: &Path) -> anyhow::Result<&Resolved> {
+        let ext = match path.extension() {
+            Some(e) => e.to_str().map(|e| e.to_ascii_lowercase()),
+            None => shebang_language(path).map(|(e, _)| e.to_string()),
+        };
+        let Some(ext) = ext else {
+            return Ok(&self.default);
+        };
+
+        if !self.by_ext.contains_key(&ext) {
+            let file = format!("dir_docs.{ext}.yaml");
+            let variant = if self.dir.join(&file).is_file() {
+                info!(template = %file, "Using per-extension template variant");
+                Some(Resolved::load(&self.dir, &file)?)
+            } else {
+                None
+            };
+            self.by_ext.insert(ext.clone(), variant);
+        }
+
+        Ok(self.by_ext[&ext].as_ref().unwrap_or(&self.default))
+    }
+
+    /// The default template.
+    pub(crate) fn default_template(&self) -> &str {
+        &self.default.raw
+    }
+
+    /// The parsed default template.
+    pub(crate) fn default_prompt(&self) -> &PromptTemplate {
+        &self.default.prompt
+    }
+}
@@ -0,0 +1,103 @@
+use std::path::Path;
+use text_splitter::ChunkSizer;
+use tiktoken_rs::CoreBPE;
+use tracing::{debug, info};
+
+/// Built-in tiktoken encodings accepted by `--tokenizer`.
+pub(crate) const TIKTOKEN_ENCODINGS: &[&str] = &[
+    "cl100k_base",
+    "o200k_base",
+    "p50k_base",
+    "p50k_edit",
+    "r50k_base",
+];
+
+/// Counts tokens the way the configured model does, for chunk sizes and the context check.
+pub(crate) enum Tokenizer {
+    /// An OpenAI tiktoken encoding.
+    Tiktoken(Box<CoreBPE>),
+    /// A Hugging Face `tokenizer.json` (Llama, Qwen, Mistral, ...).
+    #[cfg(feature = "hf-tokenizer")]
+    HuggingFace(Box<tokenizers::Tokenizer>),
+}
+
+impl Tokenizer {
+    /// Load the tokenizer named by `spec`, or pick one for `model` when it's unset.
+    ///
+    /// Parameters:
+    /// - `spec`: A tiktoken encoding name (see [`TIKTOKEN_ENCODINGS`]) or the path of a
+    ///   Hugging Face `tokenizer.json` (needs the `hf-tokenizer` feature).
+    /// - `model`: The model from `config.yaml`; OpenAI model names map to their encoding,
+    ///   anything else falls back to `cl100k_base`.
+    ///
+    /// Errors:
+    /// - An unknown encoding name, a tokenizer file that can't be loaded, or a
+    ///   `tokenizer.json` in a build without the `hf-tokenizer` feature.
+    pub(crate) fn load(spec: Option<&str>, model: &str) -> anyhow::Result<Self> {
+        let Some(spec) = spec.map(str::trim).filter(|s| !s.is_empty()) else {
+            return Ok(match tiktoken_rs::get_bpe_from_model(model) {
+                Ok(bpe) => {
+                    debug!(%model, "Using the model's tiktoken encoding");
+                    Self::Tiktoken(Box::new(bpe))
+                }
+                Err(_) => Self::tiktoken("cl100k_base")?,
+            });
+        };
+        if TIKTOKEN_ENCODINGS.contains(&spec) {
+            info!(tokenizer = %spec, "Using tiktoken encoding");
+            return Self::tiktoken(spec);
+        }
+        Self::from_file(Path::new(spec))
+    }
+
+    /// One of the built-in tiktoken encodings.
+    fn tiktoken(name: &str) -> anyhow::Result<Self> {
+        let bpe = match name {
+            "o200k_base" => tiktoken_rs::o200k_base(),
+            "p50k_base" => tiktoken_rs::p50k_base(),
+            "p50k_edit" => tiktoken_rs::p50k_edit(),
+            "r50k_base" => tiktoken_rs::r50k_base(),
+            _ => tiktoken_rs::cl100k_base(),
+        }
+        .map_err(|e| anyhow::anyhow!("failed to load tiktoken encoding {name}: {e}"))?;
+        Ok(Self::Tiktoken(Box::new(bpe)))
+    }
+
+    /// A Hugging Face `tokenizer.json`.
+    #[cfg(feature = "hf-tokenizer")]
+    fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let tok = tokenizers::Tokenizer::from_file(path)
+            .map_err(|e| anyhow::anyhow!("failed to load tokenizer {:?}: {e}", path))?;
+        info!(tokenizer = %path.display(), "Using Hugging Face tokenizer");
+        Ok(Self::HuggingFace(Box::new(tok)))
+    }
+
+    /// Without the `hf-tokenizer` feature, only tiktoken names are accepted.
+    #[cfg(not(feature = "hf-tokenizer"))]
+    fn from_file(path: &Path) -> anyhow::Result<Self> {
+        anyhow::bail!(
+            "unknown tokenizer {:?}: expected one of {} (tokenizer.json files need the `hf-tokenizer` feature)",
+            path,
+            TIKTOKEN_ENCODINGS.join(", ")
+        )
+    }
+
+    /// Number of tokens in `text`.
+    pub(crate) fn count(&self, text: &str) -> usize {
+        match self {
+            Self::Tiktoken(bpe) => bpe.encode_ordinary(text).len(),
+            #[cfg(feature = "hf-tokenizer")]
+            Self::HuggingFace(tok) => tok
+                .encode(text, false)
+                .map(|e| e.len())
+                // Fall back to a rough characters-per-token estimate
+                .unwrap_or(text.len() / 4),
+        }
+    }
+}
+
+impl ChunkSizer for Tokenizer {
+    fn size(&self, chunk: &str) -> usize {
+        self.count(chunk)
+    }
+}
@@ -1,29 +1,35 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+/// A node in the documentation tree, tagged by `kind` (`"dir"` or `"file"`).
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "kind", rename_all = "lowercase")]
-pub(crate) enum Node {
+pub enum Node {
+    /// A directory and its children.
     Dir(DirEntry),
+    /// A documented file.
     File(FileEntry),
 }
 
 /// A representation of a directory entry with metadata and nested nodes.
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct DirEntry {
+pub struct DirEntry {
     /// Name of the file/directory (with possible trailing "/").
     pub name: String,
     /// Full path to the file/directory (with possible leading "/").
     pub path: String,
     /// Time of last update for the file/directory; UTC time in ISO format.
     pub updated_at: DateTime<Utc>,
+    /// Description of the directory itself, when it was documented.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<Doc>,
     /// Nested entries of this directory (child nodes).
     pub entries: Vec<Node>,
 }
 
 /// Represents a file entry with metadata. This struct stores basic file information and an optional model response.
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct FileEntry {
+pub struct FileEntry {
     /// The file's name (e.g., 'example.txt').
     pub name: String,
     /// Absolute file path (e.g., '/users/aj/example.txt').
@@ -35,12 +41,28 @@ pub(crate) struct FileEntry {
     /// The model's response, if any (default is empty).
     #[serde(default)]
     pub doc: Doc,
+    /// Why no description could be produced, when the failure is known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<EntryError>,
+}
+
+/// A structured reason for a file having no description.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EntryError {
+    /// The rendered prompt did not fit the backend's context window, even with the smallest chunks.
+    ContextOverflow {
+        /// Estimated prompt tokens at the smallest chunk size tried.
+        prompt_tokens: usize,
+        /// Tokens available for the prompt (`context_max_tokens` minus the reserved answer budget).
+        limit_tokens: usize,
+    },
 }
 
 /// The fundamental unit that describes a file's characteristics. It stores information about the file's description, joy level, and personality emoji.
 #[allow(non_snake_case)]
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
-pub(crate) struct Doc {
+pub struct Doc {
     /// The file's description.
     pub fileDescription: String,
 
@@ -53,15 +75,27 @@ pub(crate) struct Doc {
     pub personalityEmoji: String,
 }
 
+/// Version of the cache layout written by this build.
+///
+/// Bump when a change would confuse older readers (new node kinds, renamed fields), so
+/// `dls`/`dtree` from an older release can warn instead of silently misreading the cache.
+pub const SCHEMA_VERSION: u32 = 1;
+
 /// Represents a directory root with metadata and child nodes.
 ///
 /// This struct is used to store the state of a directory structure, including its path.
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub(crate) struct DirdocsRoot {
+pub struct DirdocsRoot {
+    /// Cache layout version ([`SCHEMA_VERSION`] when written; 0 for caches predating it).
+    #[serde(default)]
+    pub schema_version: u32,
     /// The absolute path to the directory root.
     pub root: String,
     /// A UTC DateTime indicating when the directory was last updated.
     pub updated_at: DateTime<Utc>,
+    /// The model's description of the project as a whole, synthesized from the README and top-level entries.
+    #[serde(default)]
+    pub doc: Doc,
     /// A list of child nodes in the directory.
     pub entries: Vec<Node>,
 }
@@ -0,0 +1,849 @@
+//! Reading dirdocs caches for display: the supported API behind the `dls` and `dtree`
+//! viewers, for other tools that list or render documented trees.
+//!
+//! - Finding and reading a cache: [`find_project_root`], [`load_descriptions`] (either
+//!   cache format, keyed by relative path), [`read_cache`] (consistent reads while
+//!   `dirdocs run` writes), [`read_cache_at`] (a git ref) and [`read_bundle`].
+//! - Parsing leniently: [`parse_descriptions`] and the [`DirdocsRoot`] types skip entry
+//!   kinds from newer dirdocs versions with a warning instead of failing.
+//! - Theming: [`Theme`] and [`try_load_nu_theme`] pick up the Nushell color config.
+
+use crate::bundle::{BUNDLE_FORMAT, BUNDLE_VERSION};
+use crate::content::hash_file;
+use crate::lock::lock_cache;
+use crate::xattrs::XATTR_NAME;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use chrono_humanize::{Accuracy, HumanTime, Tense};
+use directories::ProjectDirs;
+use nu_ansi_term::{Color, Style};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+/// How many times a failed read is retried before giving up.
+const READ_RETRIES: u32 = 5;
+
+/// Delay before the first retry; doubled after each attempt.
+const READ_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Read the cache of `root` consistently while a `dirdocs run` may be rewriting it.
+///
+/// Takes the shared advisory lock (so a writer's replace happens before or after the
+/// read, never during it) and retries `read` with backoff when it fails, e.g. on a
+/// half-written file from an older writer or a busy SQLite database.
+///
+/// Parameters:
+/// - `root`: The documented root directory.
+/// - `read`: Loads and parses the cache under `root`.
+///
+/// Returns:
+/// - The first successful result of `read`.
+///
+/// Errors:
+/// - The last error of `read` once every retry has failed.
+pub fn read_cache<T>(root: &Path, read: impl Fn(&Path) -> anyhow::Result<T>) -> anyhow::Result<T> {
+    let mut backoff = READ_BACKOFF;
+    let mut attempt = 0;
+    loop {
+        let result = {
+            let _lock = lock_cache(root, false);
+            read(root)
+        };
+        match result {
+            Ok(v) => return Ok(v),
+            Err(e) if attempt >= READ_RETRIES => return Err(e),
+            Err(_) => {
+                attempt += 1;
+                thread::sleep(backoff);
+                backoff *= 2;
+            }
+        }
+    }
+}
+
+/// Read a bundle written by `dirdocs bundle`.
+///
+/// A bundle is a `.dirdocs.nuon` tree with every child cache merged in, plus a few header
+/// fields, so viewers parse it with the same types they use for a cache. Paths inside it
+/// are relative to the bundled root.
+///
+/// Parameters:
+/// - `path`: The bundle file.
+///
+/// Returns:
+/// - The bundle's JSON text.
+///
+/// Errors:
+/// - I/O errors, a file that isn't a dirdocs bundle, or a bundle layout newer than this build.
+pub fn read_bundle(path: &Path) -> anyhow::Result<String> {
+    /// The header fields that identify a bundle.
+    #[derive(Deserialize)]
+    struct Header {
+        /// Must equal [`BUNDLE_FORMAT`].
+        #[serde(default)]
+        format: String,
+        /// Bundle layout version.
+        #[serde(default)]
+        bundle_version: u32,
+    }
+
+    let s = fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+    let header: Header = serde_json::from_str(&s)
+        .with_context(|| format!("{} is not a dirdocs bundle", path.display()))?;
+    if header.format != BUNDLE_FORMAT {
+        anyhow::bail!("{} is not a dirdocs bundle", path.display());
+    }
+    if header.bundle_version > BUNDLE_VERSION {
+        anyhow::bail!(
+            "{} uses bundle format v{}; this build reads up to v{BUNDLE_VERSION}",
+            path.display(),
+            header.bundle_version
+        );
+    }
+    Ok(s)
+}
+
+/// Read the entry `dirdocs run --backend xattr` stored on `path`.
+///
+/// Returns:
+/// - The entry as JSON (the same shape as a file node in `.dirdocs.nuon`; on a documented
+///   root directory, an object with its `doc`), or `None` when the attribute is missing or
+///   the platform has no extended attributes.
+pub fn read_xattr(path: &Path) -> Option<Vec<u8>> {
+    xattr::get(path, XATTR_NAME).ok().flatten()
+}
+
+/// Hash a file the way `dirdocs run` does, to compare with the `hash` of its cache entry.
+///
+/// Errors:
+/// - I/O errors when opening or reading the file.
+pub fn file_hash(path: &Path) -> io::Result<String> {
+    hash_file(path)
+}
+
+/// A time as "3 days ago", or `—` when it's unknown.
+pub fn humanize(t: Option<DateTime<Utc>>) -> String {
+    t.map(|dt| HumanTime::from(Utc::now() - dt).to_text_en(Accuracy::Rough, Tense::Past))
+        .unwrap_or_else(|| "—".to_string())
+}
+
+/// Wrap `text` in an OSC 8 terminal hyperlink to the `file://` URL of `path`.
+///
+/// Relative paths (entries of a bundle, which aren't on disk) are left unlinked.
+pub fn hyperlink(path: &Path, text: &str) -> String {
+    if !path.is_absolute() {
+        return text.to_string();
+    }
+    let path = path.to_string_lossy().replace('\\', "/");
+    // Windows paths (`C:/...`) still need the slash that starts the URL path
+    let mut url = String::from(if path.starts_with('/') {
+        "file://"
+    } else {
+        "file:///"
+    });
+    for b in path.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'.' | b'_' | b'~' | b':' => {
+                url.push(b as char)
+            }
+            _ => {
+                let _ = write!(url, "%{b:02X}");
+            }
+        }
+    }
+    format!("\x1b]8;;{url}\x1b\\{text}\x1b]8;;\x1b\\")
+}
+
+/// Quote a string for NUON, escaping quotes, backslashes and control characters.
+pub fn nuon_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// File name of the viewer theme inside the dirdocs config directory (`~/.config/dirdocs` on Linux).
+pub const THEME_FILE: &str = "theme.toml";
+
+/// File name of the cached Nushell colors inside the dirdocs cache directory.
+const NU_COLORS_FILE: &str = "nu-color-config.json";
+
+/// How long the cached Nushell colors are reused before `nu` is asked again.
+const NU_COLORS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The colors for `dls` and `dtree`, keyed like Nushell's `$env.config.color_config`.
+///
+/// Sources, first found wins:
+/// 1. `theme.toml` in the dirdocs config directory, e.g. `header = "green_bold"` or
+///    `[shape_directory]` with `fg`, `bg` and `attr` keys.
+/// 2. Nushell's colors cached by an earlier call, when less than a day old.
+/// 3. `nu -c '$env.config.color_config | to json'`; its output is cached for the next call.
+///
+/// Returns:
+/// - The color map, or `None` when there's no theme file and `nu` isn't available.
+///
+/// Errors:
+/// - A theme file that can't be read or isn't valid TOML.
+pub fn color_config() -> anyhow::Result<Option<serde_json::Value>> {
+    if let Some(theme) = read_config(THEME_FILE)? {
+        return Ok(Some(theme));
+    }
+    let cache = ProjectDirs::from("com", "awful-sec", "dirdocs")
+        .map(|dirs| dirs.cache_dir().join(NU_COLORS_FILE));
+    Ok(nu_color_config(cache.as_deref()))
+}
+
+/// Read a viewer config file, such as [`THEME_FILE`] or `dls.toml`, from the dirdocs
+/// config directory (`~/.config/dirdocs` on Linux).
+///
+/// Returns:
+/// - The parsed file, or `None` when it doesn't exist.
+///
+/// Errors:
+/// - A file that can't be read or isn't valid TOML for `T`.
+pub fn read_config<T: DeserializeOwned>(file: &str) -> anyhow::Result<Option<T>> {
+    let Some(dirs) = ProjectDirs::from("com", "awful-sec", "dirdocs") else {
+        return Ok(None);
+    };
+    let path = dirs.config_dir().join(file);
+    match fs::read_to_string(&path) {
+        Ok(s) => toml::from_str(&s)
+            .map(Some)
+            .with_context(|| format!("invalid {:?}", path)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("failed to read {:?}", path)),
+    }
+}
+
+/// Nushell's color map, from a fresh `cache` file or by running `nu` (refreshing `cache`).
+fn nu_color_config(cache: Option<&Path>) -> Option<serde_json::Value> {
+    if let Some(cache) = cache {
+        let fresh = fs::metadata(cache)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.elapsed().ok())
+            .is_some_and(|age| age < NU_COLORS_TTL);
+        if fresh {
+            let cached = fs::read(cache).ok();
+            if let Some(value) = cached.and_then(|b| serde_json::from_slice(&b).ok()) {
+                return Some(value);
+            }
+        }
+    }
+
+    let out = Command::new("nu")
+        .args(["-c", "$env.config.color_config | to json"])
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    let value = serde_json::from_slice::<serde_json::Value>(&out.stdout).ok()?;
+
+    // Caching is best-effort; the colors are already in hand
+    if let Some(cache) = cache {
+        if let Some(dir) = cache.parent() {
+            let _ = fs::create_dir_all(dir).and_then(|_| fs::write(cache, &out.stdout));
+        }
+    }
+    Some(value)
+}
+
+/// Newest cache layout (`schema_version`) the viewers understand.
+///
+/// Newer caches are still read; unknown node kinds are skipped and a warning is printed once.
+pub const SUPPORTED_SCHEMA_VERSION: u32 = 1;
+
+/// A node of a cache tree as the viewers read it.
+///
+/// Unlike [`crate::Node`], every field is optional and unknown kinds are kept as
+/// [`Node::Unknown`], so caches and bundles from other dirdocs versions still load.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Node {
+    /// A directory and its children.
+    Dir(DirEntry),
+    /// A documented file.
+    File(FileEntry),
+    /// A node kind written by a newer dirdocs; skipped.
+    #[serde(other)]
+    Unknown,
+}
+
+/// A directory of a cache tree.
+#[derive(Debug, Default, Deserialize)]
+pub struct DirEntry {
+    /// The directory's own name.
+    #[serde(default)]
+    pub name: String,
+    /// Its description, when the directory itself was documented.
+    #[serde(default)]
+    pub doc: Doc,
+    /// Its children.
+    #[serde(default)]
+    pub entries: Vec<Node>,
+}
+
+/// A file of a cache tree.
+#[derive(Debug, Default, Deserialize)]
+pub struct FileEntry {
+    /// Path relative to the cache root.
+    #[serde(default)]
+    pub path: String,
+    /// Hash of the file's contents when it was described.
+    #[serde(default)]
+    pub hash: String,
+    /// When the entry was written.
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+    /// What the model said about the file.
+    #[serde(default)]
+    pub doc: Doc,
+}
+
+/// What the model said about a file, directory or project.
+#[allow(non_snake_case)]
+#[derive(Debug, Default, Deserialize)]
+pub struct Doc {
+    /// The one-sentence description.
+    #[serde(default)]
+    pub fileDescription: String,
+    /// The joy rating, usually a number from 1 to 10.
+    #[serde(default, alias = "howMuchJoyDoesThisFileBringYou")]
+    pub joyThisFileBrings: serde_json::Value,
+    /// The personality emoji.
+    #[serde(default, alias = "emojiThatExpressesThisFilesPersonality")]
+    pub personalityEmoji: String,
+}
+
+/// The top of a `.dirdocs.nuon` cache or a bundle.
+#[derive(Debug, Default, Deserialize)]
+pub struct DirdocsRoot {
+    /// Cache layout version (0 for caches written before versioning).
+    #[serde(default)]
+    pub schema_version: u32,
+    /// The documented root; bundles store the bundled root's name here.
+    #[serde(default)]
+    pub root: String,
+    /// Description of the project root itself.
+    #[serde(default)]
+    pub doc: Doc,
+    /// The nodes under the root.
+    pub entries: Vec<Node>,
+}
+
+/// A cache entry as the viewers show it.
+#[derive(Debug, Default, Clone)]
+pub struct FileDocInfo {
+    /// The one-sentence description, trimmed.
+    pub description: String,
+    /// The personality emoji.
+    pub personality: String,
+    /// The joy rating as written in the cache (`7`, `"7/10"`, ...).
+    pub joy: String,
+    /// Hash of the file's contents when it was described (empty for directories and
+    /// when unknown).
+    pub hash: String,
+    /// When the cache entry was written.
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Find the nearest directory at or above `start` that holds a `.dirdocs.nuon` or
+/// `.dirdocs.db` cache.
+///
+/// Returns:
+/// - The project root, or `None` when the filesystem root is reached without finding one.
+pub fn find_project_root(start: &Path) -> Option<PathBuf> {
+    let mut cur = start.to_path_buf();
+    loop {
+        if cur.join(".dirdocs.nuon").exists() || cur.join(".dirdocs.db").exists() {
+            return Some(cur);
+        }
+        let parent = cur.parent()?.to_path_buf();
+        if parent == cur {
+            return None;
+        }
+        cur = parent;
+    }
+}
+
+/// Load the cache of `root` as display info keyed by relative path.
+///
+/// Reads `.dirdocs.db` when it's newer than `.dirdocs.nuon`, the JSON cache otherwise.
+/// The root's own description is stored under the empty key, which is what [`rel_str`]
+/// yields for the root itself; described directories under their relative path, like files.
+///
+/// Parameters:
+/// - `root`: The project root, as found by [`find_project_root`].
+/// - `bin`: Name of the viewer, used as the prefix of warnings.
+///
+/// Errors:
+/// - I/O, JSON or SQLite errors.
+pub fn load_descriptions(root: &Path, bin: &str) -> anyhow::Result<HashMap<String, FileDocInfo>> {
+    if prefer_db(root) {
+        return load_descriptions_db(&root.join(".dirdocs.db"), bin);
+    }
+    parse_descriptions(&fs::read_to_string(root.join(".dirdocs.nuon"))?, bin)
+}
+
+/// Parse the contents of a `.dirdocs.nuon` cache (or a bundle) into display info keyed by
+/// relative path, warning once when it was written by a newer dirdocs.
+///
+/// Parameters:
+/// - `s`: The cache's JSON text.
+/// - `bin`: Name of the viewer, used as the prefix of warnings.
+///
+/// Errors:
+/// - JSON errors when the text is not a dirdocs tree.
+pub fn parse_descriptions(s: &str, bin: &str) -> anyhow::Result<HashMap<String, FileDocInfo>> {
+    /// Collect the entries below `nodes`, the directory at `parent`, returning how many nodes
+    /// of unknown kinds were skipped.
+    fn visit(nodes: &[Node], parent: &str, out: &mut HashMap<String, FileDocInfo>) -> usize {
+        let mut skipped = 0;
+        for n in nodes {
+            match n {
+                Node::Dir(d) => {
+                    // Directories are keyed by their position in the tree, like the root
+                    let path = if parent.is_empty() {
+                        d.name.clone()
+                    } else {
+                        format!("{parent}/{}", d.name)
+                    };
+                    if let Some(info) = doc_info(&d.doc) {
+                        out.insert(path.clone(), info);
+                    }
+                    skipped += visit(&d.entries, &path, out);
+                }
+                Node::File(f) if !f.path.is_empty() => {
+                    if let Some(info) = doc_info(&f.doc) {
+                        let info = FileDocInfo {
+                            hash: f.hash.clone(),
+                            updated_at: f.updated_at,
+                            ..info
+                        };
+                        out.insert(f.path.clone(), info);
+                    }
+                }
+                Node::File(_) => {}
+                Node::Unknown => skipped += 1,
+            }
+        }
+        skipped
+    }
+
+    let parsed: DirdocsRoot = serde_json::from_str(s)?;
+    let mut map = HashMap::new();
+    if let Some(info) = doc_info(&parsed.doc) {
+        map.insert(String::new(), info);
+    }
+    let skipped = visit(&parsed.entries, "", &mut map);
+    warn_if_newer(bin, parsed.schema_version, skipped);
+    Ok(map)
+}
+
+/// Load the SQLite cache written by `dirdocs run --backend sqlite` as display info keyed
+/// by relative path, like [`parse_descriptions`].
+///
+/// Parameters:
+/// - `db_path`: Path to `.dirdocs.db`.
+/// - `bin`: Name of the viewer, used as the prefix of warnings.
+///
+/// Errors:
+/// - SQLite errors when opening or querying the database.
+pub fn load_descriptions_db(
+    db_path: &Path,
+    bin: &str,
+) -> anyhow::Result<HashMap<String, FileDocInfo>> {
+    let conn =
+        rusqlite::Connection::open_with_flags(db_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let meta = |key: &str| -> Option<String> {
+        conn.query_row("SELECT value FROM meta WHERE key = ?1", [key], |r| r.get(0))
+            .ok()
+    };
+    let schema_version = meta("schema_version")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_default();
+    warn_if_newer(bin, schema_version, 0);
+
+    let mut map = HashMap::new();
+    let root_doc = meta("doc").and_then(|d| serde_json::from_str::<Doc>(&d).ok());
+    if let Some(info) = root_doc.as_ref().and_then(doc_info) {
+        map.insert(String::new(), info);
+    }
+
+    let mut stmt = conn.prepare("SELECT path, hash, updated_at, doc FROM files")?;
+    let rows = stmt.query_map([], |r| {
+        Ok((
+            r.get::<_, String>(0)?,
+            r.get::<_, String>(1)?,
+            r.get::<_, String>(2)?,
+            r.get::<_, String>(3)?,
+        ))
+    })?;
+    for row in rows {
+        let (path, hash, updated_at, doc) = row?;
+        let doc: Doc = serde_json::from_str(&doc).unwrap_or_default();
+        if let Some(info) = doc_info(&doc) {
+            let info = FileDocInfo {
+                hash,
+                updated_at: updated_at.parse().ok(),
+                ..info
+            };
+            map.insert(path, info);
+        }
+    }
+    Ok(map)
+}
+
+/// Whether `.dirdocs.db` should be read instead of `.dirdocs.nuon`.
+///
+/// True when the database exists and is newer than the JSON cache (or the JSON cache is missing).
+pub fn prefer_db(root: &Path) -> bool {
+    let mtime = |name: &str| {
+        fs::metadata(root.join(name))
+            .and_then(|m| m.modified())
+            .ok()
+    };
+    match (mtime(".dirdocs.db"), mtime(".dirdocs.nuon")) {
+        (Some(db), Some(json)) => db > json,
+        (Some(_), None) => true,
+        _ => false,
+    }
+}
+
+/// Warn once on stderr when the cache was written by a newer dirdocs.
+///
+/// Parameters:
+/// - `bin`: Name of the viewer, used as the message prefix.
+/// - `schema_version`: The cache's `schema_version`.
+/// - `skipped`: Number of entries of unknown kinds that were left out.
+pub fn warn_if_newer(bin: &str, schema_version: u32, skipped: usize) {
+    if schema_version > SUPPORTED_SCHEMA_VERSION || skipped > 0 {
+        eprintln!(
+            "{bin}: warning: cache schema v{schema_version} is newer than this build understands \
+             (v{SUPPORTED_SCHEMA_VERSION}); showing what it can ({skipped} entries skipped). \
+             Upgrade dirdocs for full support."
+        );
+    }
+}
+
+/// Read `.dirdocs.nuon` as it was committed at `git_ref`.
+///
+/// Starting at `start`, walks up to the repository's work tree looking for the nearest
+/// directory whose `.dirdocs.nuon` exists in that commit.
+///
+/// Parameters:
+/// - `start`: The (canonicalized) directory being listed.
+/// - `git_ref`: Any revision git understands (`HEAD~3`, a tag, a commit id, ...).
+///
+/// Returns:
+/// - The project root and the cache contents at that commit.
+///
+/// Errors:
+/// - No enclosing git repository, an unknown ref, or no `.dirdocs.nuon` at that ref.
+pub fn read_cache_at(start: &Path, git_ref: &str) -> anyhow::Result<(PathBuf, String)> {
+    let repo = git2::Repository::discover(start)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow::anyhow!("bare repositories are not supported"))?
+        .canonicalize()?;
+    let tree = repo.revparse_single(git_ref)?.peel_to_tree()?;
+
+    let mut cur = start.to_path_buf();
+    while let Ok(rel) = cur.strip_prefix(&workdir) {
+        if let Ok(entry) = tree.get_path(&rel.join(".dirdocs.nuon")) {
+            let blob = entry.to_object(&repo)?.peel_to_blob()?;
+            return Ok((cur, String::from_utf8_lossy(blob.content()).into_owned()));
+        }
+        match cur.parent() {
+            Some(parent) => cur = parent.to_path_buf(),
+            None => break,
+        }
+    }
+    anyhow::bail!(
+        "no .dirdocs.nuon at {git_ref} in or above {}",
+        start.display()
+    )
+}
+
+/// Display info from the `user.dirdocs` extended attribute of `path`, if it has one.
+pub fn xattr_info(path: &Path) -> Option<FileDocInfo> {
+    let entry: FileEntry = serde_json::from_slice(&read_xattr(path)?).ok()?;
+    let info = doc_info(&entry.doc)?;
+    Some(FileDocInfo {
+        hash: entry.hash,
+        updated_at: entry.updated_at,
+        ..info
+    })
+}
+
+/// Convert a parsed [`Doc`] into display info, or `None` when every field is empty.
+fn doc_info(doc: &Doc) -> Option<FileDocInfo> {
+    let description = doc.fileDescription.trim().to_string();
+    let personality = doc.personalityEmoji.trim().to_string();
+    let joy = match &doc.joyThisFileBrings {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(s) => s.clone(),
+        // numbers, bools, arrays, objects – compact string
+        other => other.to_string(),
+    };
+    if description.is_empty() && personality.is_empty() && joy.is_empty() {
+        return None;
+    }
+    Some(FileDocInfo {
+        description,
+        personality,
+        joy,
+        ..FileDocInfo::default()
+    })
+}
+
+/// Rebuild a cache tree from the file paths of `.dirdocs.db`, which stores no directories.
+///
+/// The empty path (the project root's own entry) is skipped.
+pub fn tree_from_paths<'a>(paths: impl Iterator<Item = &'a String>) -> Vec<Node> {
+    let mut root = DirEntry::default();
+    for path in paths.filter(|p| !p.is_empty()) {
+        let mut dir = &mut root;
+        let mut names: Vec<&str> = path.split('/').collect();
+        names.pop();
+        for name in names {
+            let i = match dir
+                .entries
+                .iter()
+                .position(|n| matches!(n, Node::Dir(d) if d.name == name))
+            {
+                Some(i) => i,
+                None => {
+                    dir.entries.push(Node::Dir(DirEntry {
+                        name: name.to_string(),
+                        ..DirEntry::default()
+                    }));
+                    dir.entries.len() - 1
+                }
+            };
+            let Node::Dir(child) = &mut dir.entries[i] else {
+                unreachable!("entry {i} was just found or pushed as a directory");
+            };
+            dir = child;
+        }
+        dir.entries.push(Node::File(FileEntry {
+            path: path.clone(),
+            ..FileEntry::default()
+        }));
+    }
+    root.entries
+}
+
+/// `p` relative to `base` as a (lossy) string, or `p` itself when there is no relative path.
+pub fn rel_str(p: &Path, base: &Path) -> String {
+    pathdiff::diff_paths(p, base)
+        .unwrap_or_else(|| p.to_path_buf())
+        .to_string_lossy()
+        .into()
+}
+
+/// Convert a string to its emoji presentation form.
+///
+/// A single codepoint without a variation selector gets VS16 (`U+FE0F`) appended so that
+/// terminals draw it as an emoji; anything else is returned unchanged.
+pub fn as_emoji_presentation(s: &str) -> String {
+    if s.chars().count() == 1 && !s.contains('\u{FE0F}') {
+        format!("{s}\u{FE0F}")
+    } else {
+        s.to_string()
+    }
+}
+
+/// The styles the viewers paint with, resolved from [`color_config`].
+#[derive(Clone)]
+pub struct Theme {
+    /// Table headers.
+    pub header: Style,
+    /// Directory names, when LS_COLORS doesn't style them.
+    pub dir: Style,
+    /// File names, when LS_COLORS doesn't style them.
+    pub file: Style,
+    /// Sizes.
+    pub filesize: Style,
+    /// Dates and times.
+    pub date: Style,
+    /// Row numbers.
+    pub index: Style,
+    /// Whether colors are on at all.
+    pub enabled: bool,
+}
+
+impl Theme {
+    /// The built-in colors, used when no color map is found.
+    ///
+    /// Parameters:
+    /// - `enabled`: Whether colors are on; with `false`, callers print plain text.
+    pub fn default_enabled(enabled: bool) -> Self {
+        Self {
+            header: Style::new().fg(Color::Green).bold(),
+            dir: Style::new().fg(Color::Cyan),
+            file: Style::new(),
+            filesize: Style::new().fg(Color::Cyan),
+            date: Style::new().fg(Color::Purple),
+            index: Style::new(),
+            enabled,
+        }
+    }
+}
+
+/// Load the theme from [`color_config`], falling back to the built-in style for each
+/// key the color map lacks.
+///
+/// Parameters:
+/// - `bin`: Name of the viewer, used as the prefix of warnings.
+///
+/// Returns:
+/// - The theme, or `None` when there's no theme file and `nu` isn't available, or the
+///   theme file is invalid (reported as a warning).
+pub fn try_load_nu_theme(bin: &str) -> Option<Theme> {
+    let json = match color_config() {
+        Ok(json) => json?,
+        Err(e) => {
+            eprintln!("{bin}: warning: {e:#}");
+            return None;
+        }
+    };
+
+    let resolve = |key: &str| -> Option<Style> { value_to_style(json.get(key)?) };
+
+    let header = resolve("header").or_else(|| resolve("shape_table"));
+    let dir = resolve("shape_directory")
+        .or_else(|| resolve("shape_filepath"))
+        .or_else(|| resolve("string"));
+    let file = resolve("shape_filepath")
+        .or_else(|| resolve("string"))
+        .or_else(|| resolve("nothing"));
+    let filesize = resolve("filesize").or_else(|| resolve("int"));
+    let date = resolve("date").or_else(|| resolve("shape_datetime"));
+    let index = resolve("row_index").or_else(|| header.clone());
+
+    let default = Theme::default_enabled(true);
+    Some(Theme {
+        header: header.unwrap_or(default.header),
+        dir: dir.unwrap_or(default.dir),
+        file: file.unwrap_or(default.file),
+        filesize: filesize.unwrap_or(default.filesize),
+        date: date.unwrap_or(default.date),
+        index: index.unwrap_or(default.index),
+        enabled: true,
+    })
+}
+
+/// Convert a Nushell color value, either a string like `"green_bold"` or a table with
+/// `fg`, `bg` and `attr` keys, into a `Style`.
+///
+/// Returns:
+/// - The style, or `None` for values of other types.
+fn value_to_style(v: &serde_json::Value) -> Option<Style> {
+    match v {
+        serde_json::Value::String(s) => parse_style_string(s),
+        serde_json::Value::Object(map) => {
+            let mut st = Style::new();
+            if let Some(c) = map.get("fg").and_then(|x| x.as_str()).and_then(parse_color) {
+                st = st.fg(c);
+            }
+            if let Some(c) = map.get("bg").and_then(|x| x.as_str()).and_then(parse_color) {
+                st = st.on(c);
+            }
+            if let Some(attr) = map.get("attr").and_then(|x| x.as_str()) {
+                let a = attr.to_lowercase();
+                if a.contains('b') {
+                    st = st.bold();
+                }
+                if a.contains('u') {
+                    st = st.underline();
+                }
+                if a.contains('r') {
+                    st = st.reverse();
+                }
+            }
+            Some(st)
+        }
+        _ => None,
+    }
+}
+
+/// Parse a Nushell style string: a `#rrggbb` color, or `_`-separated color names and
+/// `bold`, `underline` (`u`) or `reverse` (`r`), case-insensitive. Unknown words are ignored.
+fn parse_style_string(s: &str) -> Option<Style> {
+    let s = s.trim().to_lowercase();
+    if let Some(rgb) = parse_hex_rgb(&s) {
+        return Some(Style::new().fg(rgb));
+    }
+    let mut st = Style::new();
+    for p in s.split('_') {
+        match p {
+            "bold" => st = st.bold(),
+            "underline" | "u" => st = st.underline(),
+            "reverse" | "r" => st = st.reverse(),
+            other => {
+                if let Some(c) = parse_color(other) {
+                    st = st.fg(c);
+                }
+            }
+        }
+    }
+    Some(st)
+}
+
+/// Parse a `#rrggbb` color.
+fn parse_hex_rgb(s: &str) -> Option<Color> {
+    let hex = s.strip_prefix('#').filter(|h| h.len() == 6)?;
+    let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
+    let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
+    let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
+    Some(Color::Rgb(r, g, b))
+}
+
+/// The color for a Nushell color name such as `cyan`, `light_red` or `dark_gray`.
+fn parse_color(name: &str) -> Option<Color> {
+    use Color::*;
+    Some(match name {
+        "black" => Black,
+        "red" => Red,
+        "green" => Green,
+        "yellow" => Yellow,
+        "blue" => Blue,
+        "purple" | "magenta" => Purple,
+        "cyan" => Cyan,
+        "white" => White,
+
+        "dark_gray" | "grey" | "gray" => Rgb(128, 128, 128),
+        "light_black" => Rgb(96, 96, 96),
+        "light_white" => Rgb(240, 240, 240),
+
+        "light_red" => LightRed,
+        "light_green" => LightGreen,
+        "light_yellow" => LightYellow,
+        "light_blue" => LightBlue,
+        "light_purple" | "light_magenta" => LightPurple,
+        "light_cyan" => LightCyan,
+
+        _ => return None,
+    })
+}
@@ -0,0 +1,131 @@
+use crate::cache::insert_file_into_tree;
+use crate::scan::walk_files;
+use crate::types::{DirdocsRoot, Doc, FileEntry, Node, SCHEMA_VERSION};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::{debug, info, warn};
+
+/// Extended attribute holding a file's entry (or, on the root directory, the root fields).
+pub(crate) const XATTR_NAME: &str = "user.dirdocs";
+
+/// Root-level fields, stored in the root directory's own attribute.
+#[derive(Debug, Serialize, Deserialize)]
+struct RootAttr {
+    /// Cache layout version.
+    #[serde(default)]
+    schema_version: u32,
+    /// Label of the root.
+    #[serde(default)]
+    root: String,
+    /// When the tree was last written.
+    updated_at: DateTime<Utc>,
+    /// The project description.
+    #[serde(default)]
+    doc: Doc,
+}
+
+/// Whether `root` carries a tree written by the xattr backend.
+pub(crate) fn has_xattr_tree(root: &Path) -> bool {
+    matches!(xattr::get(root, XATTR_NAME), Ok(Some(_)))
+}
+
+/// Store a documentation tree in extended attributes instead of a cache file.
+///
+/// Each file's entry (the same JSON as in `.dirdocs.nuon`) goes into its own
+/// [`XATTR_NAME`] attribute; the root fields go onto the root directory.
+///
+/// Parameters:
+/// - `root`: The documented root directory.
+/// - `tree`: The tree to store.
+///
+/// Returns:
+/// - `Ok(())` once every attribute that could be set has been written.
+///
+/// Errors:
+/// - When the platform or the root's filesystem doesn't support user extended attributes.
+///
+/// Notes:
+/// - Files that vanished meanwhile, or whose attribute is rejected (e.g. too large for
+///   the filesystem), are skipped with a warning; the next run describes them again.
+pub(crate) fn write_tree_xattr(root: &Path, tree: &DirdocsRoot) -> anyhow::Result<()> {
+    if !xattr::SUPPORTED_PLATFORM {
+        anyhow::bail!("extended attributes are not supported on this platform");
+    }
+    let meta = RootAttr {
+        schema_version: SCHEMA_VERSION,
+        root: tree.root.clone(),
+        updated_at: tree.updated_at,
+        doc: tree.doc.clone(),
+    };
+    xattr::set(root, XATTR_NAME, &serde_json::to_vec(&meta)?)
+        .map_err(|e| anyhow::anyhow!("cannot set {XATTR_NAME} on {}: {e}", root.display()))?;
+
+    /// Set the attribute of every file under `nodes`; returns how many were written.
+    fn visit(nodes: &[Node], root: &Path) -> anyhow::Result<usize> {
+        let mut written = 0;
+        for n in nodes {
+            match n {
+                Node::Dir(d) => written += visit(&d.entries, root)?,
+                Node::File(f) => {
+                    let path = root.join(&f.path);
+                    match xattr::set(&path, XATTR_NAME, &serde_json::to_vec(f)?) {
+                        Ok(()) => written += 1,
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                            debug!(path=%f.path, "File gone; not storing its entry");
+                        }
+                        Err(e) => warn!(path=%f.path, %e, "Could not store entry in xattr"),
+                    }
+                }
+            }
+        }
+        Ok(written)
+    }
+
+    let written = visit(&tree.entries, root)?;
+    info!(
+        written,
+        attr = XATTR_NAME,
+        "Entries stored in extended attributes"
+    );
+    Ok(())
+}
+
+/// Rebuild a documentation tree from the extended attributes under `root`.
+///
+/// Parameters:
+/// - `root`: The documented root directory.
+///
+/// Returns:
+/// - The tree; entries take their path from where the file is now, so moved files
+///   keep their description (and are re-described only if their content changed).
+///
+/// Errors:
+/// - When `root` has no [`XATTR_NAME`] attribute, or it isn't valid JSON.
+pub(crate) fn load_tree_xattr(root: &Path) -> anyhow::Result<DirdocsRoot> {
+    let raw = xattr::get(root, XATTR_NAME)?
+        .ok_or_else(|| anyhow::anyhow!("no {XATTR_NAME} attribute on {}", root.display()))?;
+    let meta: RootAttr = serde_json::from_slice(&raw)?;
+
+    let mut entries = Vec::new();
+    for (path, rel) in walk_files(root, &[]) {
+        let Ok(Some(raw)) = xattr::get(&path, XATTR_NAME) else {
+            continue;
+        };
+        match serde_json::from_slice::<FileEntry>(&raw) {
+            Ok(mut fe) => {
+                fe.path = rel.clone();
+                insert_file_into_tree(&mut entries, &rel, &fe);
+            }
+            Err(e) => debug!(path=%rel, %e, "Ignoring malformed xattr entry"),
+        }
+    }
+
+    Ok(DirdocsRoot {
+        schema_version: meta.schema_version,
+        root: meta.root,
+        updated_at: meta.updated_at,
+        doc: meta.doc,
+        entries,
+    })
+}